@@ -1,3 +1,5 @@
+#![cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+
 use std::convert::Infallible;
 
 use anyhow::Context;
@@ -37,10 +39,7 @@ async fn example() -> anyhow::Result<()> {
         .run::<_, std::io::Error>(async move |t| {
             let data = t.object_store("store")?.get_all(Some(1)).await?;
             if data.len() != 1 {
-                Err(std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    "Unexpected data length",
-                ))?;
+                Err(std::io::Error::other("Unexpected data length"))?;
             }
             Ok(())
         })
@@ -54,10 +53,7 @@ async fn example() -> anyhow::Result<()> {
             store.add(&JsString::from("quux")).await?;
             if store.count().await? > 3 {
                 // Oops! In this example, we have 4 items by this point
-                Err(std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    "Too many objects in store",
-                ))?;
+                Err(std::io::Error::other("Too many objects in store"))?;
             }
             Ok(())
         })
@@ -0,0 +1,555 @@
+//! Traits abstracting over the storage engine behind the API of this crate
+//!
+//! The types at the root of this crate are generic over a [`Backend`], which defaults to [`WebSys`], the
+//! backend that talks to IndexedDB through `web_sys`. Other engines, like the `memory` and `sqlite` ones,
+//! plug their own storage behind the same types. Code that is generic over the backend can be written once,
+//! and then run on any of them:
+//!
+//! ```rust
+//! # use indexed_db::{backend::Backend, Database};
+//! async fn count_users<B: Backend>(db: &Database<B>) -> indexed_db::Result<usize, ()> {
+//!     db.transaction(&["users"])
+//!         .run(async |t| t.object_store("users")?.count().await)
+//!         .await
+//! }
+//! ```
+//!
+//! The traits of this module are what a backend implements, and are not meant to be called directly. All
+//! the backends use [`Key`] for the keys, but each one has its own value type, see [`Backend::Value`].
+
+use crate::{CursorDirection, DatabaseInfo, Durability, Key, KeyPath};
+use std::{borrow::Borrow, convert::Infallible, fmt::Debug, future::Future, ops::RangeBounds};
+use web_sys::wasm_bindgen::JsValue;
+
+/// A storage engine, that provides the implementation of all the types of the API
+pub trait Backend: Sized + 'static {
+    /// The values of the records, like [`JsValue`] for [`WebSys`]
+    type Value: BackendValue;
+
+    /// See [`crate::Factory`]
+    type Factory: BackendFactory<Self> + Clone + Debug;
+
+    /// See [`crate::Database`]
+    type Database: BackendDatabase<Self> + Clone + Debug;
+
+    /// See [`crate::VersionChangeEvent`]
+    type VersionChangeEvent<Err>: BackendVersionChangeEvent<Self, Err>;
+
+    /// See [`crate::TransactionBuilder`]
+    type TransactionBuilder: BackendTransactionBuilder<Self>;
+
+    /// See [`crate::Transaction`]
+    type Transaction<Err>: BackendTransaction<Self, Err> + Clone;
+
+    /// See [`crate::ObjectStore`]
+    type ObjectStore<Err>: BackendObjectStore<Self, Err>;
+
+    /// See [`crate::Index`]
+    type Index<Err>: BackendIndex<Self, Err>;
+
+    /// See [`crate::CursorBuilder`]
+    type CursorBuilder<Err>: BackendCursorBuilder<Self, Err>;
+
+    /// See [`crate::Cursor`]
+    type Cursor<Err>: BackendCursor<Self, Err>;
+
+    /// See [`crate::KeyCursor`]
+    type KeyCursor<Err>: BackendKeyCursor<Self, Err>;
+}
+
+/// The values of the records of a [`Backend`]
+pub trait BackendValue: Clone {
+    /// Convert `key` into a value
+    fn from_key(key: &Key) -> Self;
+
+    /// Convert this value into a key, or `None` if it is not a valid key
+    fn to_key(&self) -> Option<Key>;
+
+    /// Push the JSON encoding of this value to `out`, as written by [`crate::Database::export`], or return
+    /// why it cannot be exported
+    fn push_json(&self, out: &mut String) -> Result<(), String>;
+
+    /// Convert `value` into a value, for [`crate::TypedObjectStore`]
+    #[cfg(feature = "serde")]
+    fn serialize<T: serde::Serialize>(value: &T) -> Result<Self, String>;
+
+    /// Convert this value back into a `T`, for [`crate::TypedObjectStore`]
+    #[cfg(feature = "serde")]
+    fn deserialize<T: serde::de::DeserializeOwned>(self) -> Result<T, String>;
+}
+
+/// The factory of a [`Backend`], see [`crate::Factory`]
+pub trait BackendFactory<B: Backend> {
+    /// See [`crate::Factory::open`]
+    fn open<Err: 'static>(
+        &self,
+        name: &str,
+        version: u32,
+        on_upgrade_needed: impl AsyncFnOnce(B::VersionChangeEvent<Err>) -> crate::Result<(), Err>,
+    ) -> impl Future<Output = crate::Result<B::Database, Err>>;
+
+    /// See [`crate::Factory::open_latest_version`]
+    fn open_latest_version(
+        &self,
+        name: &str,
+    ) -> impl Future<Output = crate::Result<B::Database, Infallible>>;
+
+    /// See [`crate::Factory::delete_database`]
+    fn delete_database(&self, name: &str) -> impl Future<Output = crate::Result<(), Infallible>>;
+
+    /// See [`crate::Factory::databases`]
+    fn databases(&self) -> impl Future<Output = crate::Result<Vec<DatabaseInfo>, Infallible>>;
+}
+
+/// A database connection of a [`Backend`], see [`crate::Database`]
+pub trait BackendDatabase<B: Backend> {
+    /// See [`crate::Database::name`]
+    fn name(&self) -> String;
+
+    /// See [`crate::Database::version`]
+    fn version(&self) -> u32;
+
+    /// See [`crate::Database::object_store_names`]
+    fn object_store_names(&self) -> Vec<String>;
+
+    /// See [`crate::Database::transaction`]
+    fn transaction(&self, stores: &[&str]) -> B::TransactionBuilder;
+
+    /// See [`crate::Database::close`]
+    fn close(&self);
+}
+
+/// The event passed to the `on_upgrade_needed` callback of a [`Backend`], see [`crate::VersionChangeEvent`]
+pub trait BackendVersionChangeEvent<B: Backend, Err> {
+    /// See [`crate::VersionChangeEvent::old_version`]
+    fn old_version(&self) -> u32;
+
+    /// See [`crate::VersionChangeEvent::new_version`]
+    fn new_version(&self) -> u32;
+
+    /// See [`crate::VersionChangeEvent::database`]
+    fn database(&self) -> &B::Database;
+
+    /// Create the object store `name`, see [`crate::ObjectStoreBuilder::create`]
+    fn create_object_store(
+        &self,
+        name: &str,
+        key_path: Option<&KeyPath>,
+        auto_increment: bool,
+    ) -> crate::Result<B::ObjectStore<Err>, Err>;
+
+    /// See [`crate::VersionChangeEvent::delete_object_store`]
+    fn delete_object_store(&self, name: &str) -> crate::Result<(), Err>;
+
+    /// See [`crate::VersionChangeEvent::transaction`]
+    fn transaction(&self) -> &B::Transaction<Err>;
+}
+
+/// Helper to build a transaction of a [`Backend`], see [`crate::TransactionBuilder`]
+pub trait BackendTransactionBuilder<B: Backend>: Sized {
+    /// See [`crate::TransactionBuilder::rw`]
+    fn rw(self) -> Self;
+
+    /// See [`crate::TransactionBuilder::durability`]
+    fn durability(self, durability: Durability) -> Self;
+
+    /// See [`crate::TransactionBuilder::run`]
+    fn run<Ret, Err>(
+        self,
+        transaction: impl AsyncFnOnce(B::Transaction<Err>) -> crate::Result<Ret, Err>,
+    ) -> impl Future<Output = crate::Result<Ret, Err>>;
+
+    /// See [`crate::TransactionBuilder::run_no_wait`]
+    fn run_no_wait<Ret, Err>(
+        self,
+        transaction: impl AsyncFnOnce(B::Transaction<Err>) -> crate::Result<Ret, Err>,
+    ) -> impl Future<Output = crate::Result<Ret, Err>>;
+}
+
+/// A transaction of a [`Backend`], see [`crate::Transaction`]
+pub trait BackendTransaction<B: Backend, Err> {
+    /// See [`crate::Transaction::object_store`]
+    fn object_store(&self, name: &str) -> crate::Result<B::ObjectStore<Err>, Err>;
+
+    /// See [`crate::Transaction::commit`]
+    fn commit(&self) -> crate::Result<(), Err>;
+
+    /// See [`crate::Transaction::abort`]
+    fn abort(&self) -> crate::Result<(), Err>;
+}
+
+/// An object store of a [`Backend`], see [`crate::ObjectStore`]
+pub trait BackendObjectStore<B: Backend, Err> {
+    /// See [`crate::ObjectStore::name`]
+    fn name(&self) -> String;
+
+    /// See [`crate::ObjectStore::key_path`]
+    fn key_path(&self) -> crate::Result<Option<KeyPath>, Err>;
+
+    /// See [`crate::ObjectStore::auto_increment`]
+    fn auto_increment(&self) -> bool;
+
+    /// See [`crate::ObjectStore::index_names`]
+    fn index_names(&self) -> Vec<String>;
+
+    /// Create the index `name`, see [`crate::IndexBuilder::create`]
+    fn create_index(
+        &self,
+        name: &str,
+        key_path: &KeyPath,
+        unique: bool,
+        multi_entry: bool,
+    ) -> crate::Result<(), Err>;
+
+    /// See [`crate::ObjectStore::delete_index`]
+    fn delete_index(&self, name: &str) -> crate::Result<(), Err>;
+
+    /// See [`crate::ObjectStore::add`]
+    fn add(
+        &self,
+        value: &B::Value,
+    ) -> impl Future<Output = crate::Result<Key, Err>> + use<Self, B, Err>;
+
+    /// See [`crate::ObjectStore::add_kv`]
+    fn add_kv(
+        &self,
+        key: &Key,
+        value: &B::Value,
+    ) -> impl Future<Output = crate::Result<(), Err>> + use<Self, B, Err>;
+
+    /// See [`crate::ObjectStore::put`]
+    fn put(
+        &self,
+        value: &B::Value,
+    ) -> impl Future<Output = crate::Result<Key, Err>> + use<Self, B, Err>;
+
+    /// See [`crate::ObjectStore::put_kv`]
+    fn put_kv(
+        &self,
+        key: &Key,
+        value: &B::Value,
+    ) -> impl Future<Output = crate::Result<(), Err>> + use<Self, B, Err>;
+
+    /// See [`crate::ObjectStore::add_many`]
+    fn add_many<I: IntoIterator<Item = V>, V: Borrow<B::Value>>(
+        &self,
+        values: I,
+    ) -> impl Future<Output = crate::Result<Vec<Key>, Err>> + use<Self, B, Err, I, V>;
+
+    /// See [`crate::ObjectStore::add_kv_many`]
+    fn add_kv_many<I: IntoIterator<Item = (Key, V)>, V: Borrow<B::Value>>(
+        &self,
+        entries: I,
+    ) -> impl Future<Output = crate::Result<(), Err>> + use<Self, B, Err, I, V>;
+
+    /// See [`crate::ObjectStore::put_many`]
+    fn put_many<I: IntoIterator<Item = V>, V: Borrow<B::Value>>(
+        &self,
+        values: I,
+    ) -> impl Future<Output = crate::Result<Vec<Key>, Err>> + use<Self, B, Err, I, V>;
+
+    /// See [`crate::ObjectStore::put_kv_many`]
+    fn put_kv_many<I: IntoIterator<Item = (Key, V)>, V: Borrow<B::Value>>(
+        &self,
+        entries: I,
+    ) -> impl Future<Output = crate::Result<(), Err>> + use<Self, B, Err, I, V>;
+
+    /// See [`crate::ObjectStore::update_with`]
+    #[allow(clippy::type_complexity)]
+    fn update_with<F: FnOnce(Option<B::Value>) -> Option<B::Value>>(
+        &self,
+        key: &Key,
+        f: F,
+    ) -> impl Future<Output = crate::Result<(Option<B::Value>, Option<B::Value>), Err>>
+           + use<Self, B, Err, F>;
+
+    /// See [`crate::ObjectStore::compare_and_swap`]
+    #[allow(clippy::type_complexity)]
+    fn compare_and_swap(
+        &self,
+        key: &Key,
+        expected: Option<&B::Value>,
+        new: Option<&B::Value>,
+    ) -> impl Future<Output = crate::Result<(Option<B::Value>, Option<B::Value>), Err>> + use<Self, B, Err>;
+
+    /// See [`crate::ObjectStore::clear`]
+    fn clear(&self) -> impl Future<Output = crate::Result<(), Err>> + use<Self, B, Err>;
+
+    /// See [`crate::ObjectStore::count`]
+    fn count(&self) -> impl Future<Output = crate::Result<usize, Err>> + use<Self, B, Err>;
+
+    /// See [`crate::ObjectStore::contains`]
+    fn contains(
+        &self,
+        key: &Key,
+    ) -> impl Future<Output = crate::Result<bool, Err>> + use<Self, B, Err>;
+
+    /// See [`crate::ObjectStore::count_in`]
+    fn count_in<R: RangeBounds<Key>>(
+        &self,
+        range: R,
+    ) -> impl Future<Output = crate::Result<usize, Err>> + use<Self, B, Err, R>;
+
+    /// See [`crate::ObjectStore::delete`]
+    fn delete(&self, key: &Key)
+        -> impl Future<Output = crate::Result<(), Err>> + use<Self, B, Err>;
+
+    /// See [`crate::ObjectStore::delete_range`]
+    fn delete_range<R: RangeBounds<Key>>(
+        &self,
+        range: R,
+    ) -> impl Future<Output = crate::Result<(), Err>> + use<Self, B, Err, R>;
+
+    /// See [`crate::ObjectStore::delete_many`]
+    fn delete_many<I: IntoIterator<Item = Key>>(
+        &self,
+        keys: I,
+    ) -> impl Future<Output = crate::Result<(), Err>> + use<Self, B, Err, I>;
+
+    /// See [`crate::ObjectStore::get`]
+    fn get(
+        &self,
+        key: &Key,
+    ) -> impl Future<Output = crate::Result<Option<B::Value>, Err>> + use<Self, B, Err>;
+
+    /// See [`crate::ObjectStore::get_first_in`]
+    fn get_first_in<R: RangeBounds<Key>>(
+        &self,
+        range: R,
+    ) -> impl Future<Output = crate::Result<Option<B::Value>, Err>> + use<Self, B, Err, R>;
+
+    /// See [`crate::ObjectStore::get_all`]
+    fn get_all(
+        &self,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<B::Value>, Err>> + use<Self, B, Err>;
+
+    /// See [`crate::ObjectStore::get_all_in`]
+    fn get_all_in<R: RangeBounds<Key>>(
+        &self,
+        range: R,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<B::Value>, Err>> + use<Self, B, Err, R>;
+
+    /// See [`crate::ObjectStore::get_first_key_in`]
+    fn get_first_key_in<R: RangeBounds<Key>>(
+        &self,
+        range: R,
+    ) -> impl Future<Output = crate::Result<Option<Key>, Err>> + use<Self, B, Err, R>;
+
+    /// See [`crate::ObjectStore::get_all_keys`]
+    fn get_all_keys(
+        &self,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<Key>, Err>> + use<Self, B, Err>;
+
+    /// See [`crate::ObjectStore::get_all_keys_in`]
+    fn get_all_keys_in<R: RangeBounds<Key>>(
+        &self,
+        range: R,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<Key>, Err>> + use<Self, B, Err, R>;
+
+    /// See [`crate::ObjectStore::index`]
+    fn index(&self, name: &str) -> crate::Result<B::Index<Err>, Err>;
+
+    /// See [`crate::ObjectStore::cursor`]
+    fn cursor(&self) -> B::CursorBuilder<Err>;
+}
+
+/// An index of a [`Backend`], see [`crate::Index`]
+pub trait BackendIndex<B: Backend, Err> {
+    /// See [`crate::Index::name`]
+    fn name(&self) -> String;
+
+    /// See [`crate::Index::key_path`]
+    fn key_path(&self) -> crate::Result<KeyPath, Err>;
+
+    /// See [`crate::Index::is_unique`]
+    fn is_unique(&self) -> bool;
+
+    /// See [`crate::Index::is_multi_entry`]
+    fn is_multi_entry(&self) -> bool;
+
+    /// See [`crate::Index::contains`]
+    fn contains(
+        &self,
+        key: &Key,
+    ) -> impl Future<Output = crate::Result<bool, Err>> + use<Self, B, Err>;
+
+    /// See [`crate::Index::count_in`]
+    fn count_in<R: RangeBounds<Key>>(
+        &self,
+        range: R,
+    ) -> impl Future<Output = crate::Result<usize, Err>> + use<Self, B, Err, R>;
+
+    /// See [`crate::Index::get`]
+    fn get(
+        &self,
+        key: &Key,
+    ) -> impl Future<Output = crate::Result<Option<B::Value>, Err>> + use<Self, B, Err>;
+
+    /// See [`crate::Index::get_first_in`]
+    fn get_first_in<R: RangeBounds<Key>>(
+        &self,
+        range: R,
+    ) -> impl Future<Output = crate::Result<Option<B::Value>, Err>> + use<Self, B, Err, R>;
+
+    /// See [`crate::Index::get_all`]
+    fn get_all(
+        &self,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<B::Value>, Err>> + use<Self, B, Err>;
+
+    /// See [`crate::Index::get_all_in`]
+    fn get_all_in<R: RangeBounds<Key>>(
+        &self,
+        range: R,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<B::Value>, Err>> + use<Self, B, Err, R>;
+
+    /// See [`crate::Index::get_first_key_in`]
+    fn get_first_key_in<R: RangeBounds<Key>>(
+        &self,
+        range: R,
+    ) -> impl Future<Output = crate::Result<Option<Key>, Err>> + use<Self, B, Err, R>;
+
+    /// See [`crate::Index::get_all_keys`]
+    fn get_all_keys(
+        &self,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<Key>, Err>> + use<Self, B, Err>;
+
+    /// See [`crate::Index::get_all_keys_in`]
+    fn get_all_keys_in<R: RangeBounds<Key>>(
+        &self,
+        range: R,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<Key>, Err>> + use<Self, B, Err, R>;
+
+    /// See [`crate::Index::cursor`]
+    fn cursor(&self) -> B::CursorBuilder<Err>;
+}
+
+/// Helper to build cursors of a [`Backend`], see [`crate::CursorBuilder`]
+pub trait BackendCursorBuilder<B: Backend, Err>: Sized {
+    /// See [`crate::CursorBuilder::open`]
+    fn open(self) -> impl Future<Output = crate::Result<B::Cursor<Err>, Err>>;
+
+    /// See [`crate::CursorBuilder::open_key`]
+    fn open_key(self) -> impl Future<Output = crate::Result<B::KeyCursor<Err>, Err>>;
+
+    /// See [`crate::CursorBuilder::range`]
+    fn range(self, range: impl RangeBounds<Key>) -> crate::Result<Self, Err>;
+
+    /// See [`crate::CursorBuilder::direction`]
+    fn direction(self, direction: CursorDirection) -> Self;
+}
+
+/// A cursor of a [`Backend`], see [`crate::Cursor`]
+pub trait BackendCursor<B: Backend, Err> {
+    /// See [`crate::Cursor::key`]
+    fn key(&self) -> Option<Key>;
+
+    /// See [`crate::Cursor::primary_key`]
+    fn primary_key(&self) -> Option<Key>;
+
+    /// See [`crate::Cursor::value`]
+    fn value(&self) -> Option<B::Value>;
+
+    /// See [`crate::Cursor::advance`]
+    fn advance(&mut self, count: u32) -> impl Future<Output = crate::Result<(), Err>>;
+
+    /// See [`crate::Cursor::advance_until`]
+    fn advance_until(&mut self, key: &Key) -> impl Future<Output = crate::Result<(), Err>>;
+
+    /// See [`crate::Cursor::advance_until_primary_key`]
+    fn advance_until_primary_key(
+        &mut self,
+        index_key: &Key,
+        primary_key: &Key,
+    ) -> impl Future<Output = crate::Result<(), Err>>;
+
+    /// See [`crate::Cursor::delete`]
+    fn delete(&self) -> impl Future<Output = crate::Result<(), Err>>;
+
+    /// See [`crate::Cursor::update`]
+    fn update(&self, value: &B::Value) -> impl Future<Output = crate::Result<(), Err>>;
+}
+
+/// A key-only cursor of a [`Backend`], see [`crate::KeyCursor`]
+pub trait BackendKeyCursor<B: Backend, Err> {
+    /// See [`crate::KeyCursor::key`]
+    fn key(&self) -> Option<Key>;
+
+    /// See [`crate::KeyCursor::primary_key`]
+    fn primary_key(&self) -> Option<Key>;
+
+    /// See [`crate::KeyCursor::advance`]
+    fn advance(&mut self, count: u32) -> impl Future<Output = crate::Result<(), Err>>;
+
+    /// See [`crate::KeyCursor::advance_until`]
+    fn advance_until(&mut self, key: &Key) -> impl Future<Output = crate::Result<(), Err>>;
+
+    /// See [`crate::KeyCursor::advance_until_primary_key`]
+    fn advance_until_primary_key(
+        &mut self,
+        index_key: &Key,
+        primary_key: &Key,
+    ) -> impl Future<Output = crate::Result<(), Err>>;
+}
+
+/// The backend that uses IndexedDB through `web_sys`, and the default backend of the types of this crate
+#[derive(Clone, Copy, Debug)]
+pub struct WebSys;
+
+impl BackendValue for JsValue {
+    fn from_key(key: &Key) -> Self {
+        key.to_js()
+    }
+
+    fn to_key(&self) -> Option<Key> {
+        Key::from_js(self)
+    }
+
+    fn push_json(&self, out: &mut String) -> Result<(), String> {
+        crate::export::ExportValue::push_json(self, out)
+    }
+
+    #[cfg(feature = "serde")]
+    fn serialize<T: serde::Serialize>(value: &T) -> Result<Self, String> {
+        value
+            .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+            .map_err(|e| e.to_string())
+    }
+
+    #[cfg(feature = "serde")]
+    fn deserialize<T: serde::de::DeserializeOwned>(self) -> Result<T, String> {
+        serde_wasm_bindgen::from_value(self).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(feature = "memory")]
+impl BackendValue for crate::memory::Value {
+    fn from_key(key: &Key) -> Self {
+        Self::from(key)
+    }
+
+    fn to_key(&self) -> Option<Key> {
+        crate::memory::Value::to_key(self)
+    }
+
+    fn push_json(&self, out: &mut String) -> Result<(), String> {
+        crate::export::ExportValue::push_json(self, out)
+    }
+
+    #[cfg(feature = "serde")]
+    fn serialize<T: serde::Serialize>(value: &T) -> Result<Self, String> {
+        crate::memory::serde::to_value(value)
+    }
+
+    #[cfg(feature = "serde")]
+    fn deserialize<T: serde::de::DeserializeOwned>(self) -> Result<T, String> {
+        crate::memory::serde::from_value(self)
+    }
+}
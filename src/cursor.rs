@@ -1,24 +1,19 @@
 use crate::{
-    transaction::transaction_request,
-    utils::{
-        make_key_range, map_cursor_advance_err, map_cursor_advance_until_err,
-        map_cursor_advance_until_primary_key_err, map_cursor_delete_err, map_cursor_update_err,
-        map_open_cursor_err,
-    },
+    backend::{Backend, BackendCursor, BackendCursorBuilder, BackendKeyCursor, WebSys},
+    Key,
 };
-use futures_util::future::Either;
-use std::{future::Future, marker::PhantomData, ops::RangeBounds};
-use web_sys::{
-    wasm_bindgen::{JsCast, JsValue},
-    IdbCursor, IdbCursorDirection, IdbCursorWithValue, IdbIndex, IdbObjectStore, IdbRequest,
+use futures_util::{
+    future::{Either, FutureExt},
+    stream, Stream,
 };
+use std::ops::RangeBounds;
+use web_sys::{wasm_bindgen::JsValue, IdbCursorDirection};
 
 #[cfg(doc)]
 use crate::{Index, ObjectStore};
-#[cfg(doc)]
-use web_sys::js_sys::Array;
 
 /// The direction for a cursor
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum CursorDirection {
     /// Advance one by one
     Next,
@@ -34,7 +29,7 @@ pub enum CursorDirection {
 }
 
 impl CursorDirection {
-    pub(crate) fn to_sys(&self) -> IdbCursorDirection {
+    pub(crate) fn to_sys(self) -> IdbCursorDirection {
         match self {
             CursorDirection::Next => IdbCursorDirection::Next,
             CursorDirection::NextUnique => IdbCursorDirection::Nextunique,
@@ -45,178 +40,133 @@ impl CursorDirection {
 }
 
 /// Helper to build cursors over [`ObjectStore`]s
-pub struct CursorBuilder<Err> {
-    source: Either<IdbObjectStore, IdbIndex>,
-    query: JsValue,
-    direction: IdbCursorDirection,
-    _phantom: PhantomData<Err>,
+pub struct CursorBuilder<Err, B: Backend = WebSys> {
+    inner: B::CursorBuilder<Err>,
 }
 
-impl<Err> CursorBuilder<Err> {
-    pub(crate) fn from_store(store: IdbObjectStore) -> CursorBuilder<Err> {
-        CursorBuilder {
-            source: Either::Left(store),
-            query: JsValue::UNDEFINED,
-            direction: IdbCursorDirection::Next,
-            _phantom: PhantomData,
-        }
-    }
-
-    pub(crate) fn from_index(index: IdbIndex) -> CursorBuilder<Err> {
-        CursorBuilder {
-            source: Either::Right(index),
-            query: JsValue::UNDEFINED,
-            direction: IdbCursorDirection::Next,
-            _phantom: PhantomData,
-        }
+impl<Err, B: Backend> CursorBuilder<Err, B> {
+    pub(crate) fn from_backend(inner: B::CursorBuilder<Err>) -> CursorBuilder<Err, B> {
+        CursorBuilder { inner }
     }
 
     /// Open the cursor
-    ///
-    /// Internally, this uses [`IDBObjectStore::openCursor`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/openCursor).
-    pub fn open(self) -> impl Future<Output = crate::Result<Cursor<Err>, Err>> {
-        let req = match self.source {
-            Either::Left(store) => {
-                store.open_cursor_with_range_and_direction(&self.query, self.direction)
-            }
-            Either::Right(index) => {
-                index.open_cursor_with_range_and_direction(&self.query, self.direction)
-            }
-        };
-        match req {
-            Ok(open_req) => Either::Right(Cursor::from(open_req)),
-            Err(err) => Either::Left(std::future::ready(Err(map_open_cursor_err(err)))),
-        }
+    pub async fn open(self) -> crate::Result<Cursor<Err, B>, Err> {
+        Ok(Cursor {
+            inner: self.inner.open().await?,
+        })
     }
 
     /// Open the cursor as a key-only cursor
+    pub async fn open_key(self) -> crate::Result<KeyCursor<Err, B>, Err> {
+        Ok(KeyCursor {
+            inner: self.inner.open_key().await?,
+        })
+    }
+
+    /// Open the cursor as a [`Stream`] of its entries
     ///
-    /// Internally, this uses [`IDBObjectStore::openKeyCursor`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/openKeyCursor).
-    pub fn open_key(self) -> impl Future<Output = crate::Result<Cursor<Err>, Err>> {
-        let req = match self.source {
-            Either::Left(store) => {
-                store.open_key_cursor_with_range_and_direction(&self.query, self.direction)
-            }
-            Either::Right(index) => {
-                index.open_key_cursor_with_range_and_direction(&self.query, self.direction)
-            }
-        };
-        match req {
-            Ok(open_req) => Either::Right(Cursor::from(open_req)),
-            Err(err) => Either::Left(std::future::ready(Err(map_open_cursor_err(err)))),
-        }
+    /// This is a shortcut for calling [`Cursor::into_stream`] on the result of [`CursorBuilder::open`]. If
+    /// opening the cursor fails, the stream yields the error and then ends.
+    pub fn stream(self) -> impl Stream<Item = crate::Result<CursorEntry<B::Value>, Err>> {
+        self.open()
+            .map(|res| match res {
+                Ok(cursor) => Either::Left(cursor.into_stream()),
+                Err(err) => Either::Right(stream::once(std::future::ready(Err(err)))),
+            })
+            .flatten_stream()
     }
 
     /// Limit the range of the cursor
-    ///
-    /// Internally, this sets [this property](https://developer.mozilla.org/en-US/docs/Web/API/IDBIndex/openCursor#range).
-    pub fn range(mut self, range: impl RangeBounds<JsValue>) -> crate::Result<Self, Err> {
-        self.query = make_key_range(range)?;
-        Ok(self)
+    pub fn range(self, range: impl RangeBounds<Key>) -> crate::Result<Self, Err> {
+        Ok(CursorBuilder {
+            inner: self.inner.range(range)?,
+        })
     }
 
     /// Define the direction of the cursor
-    ///
-    /// Internally, this sets [this property](https://developer.mozilla.org/en-US/docs/Web/API/IDBIndex/openCursor#direction).
-    pub fn direction(mut self, direction: CursorDirection) -> Self {
-        self.direction = direction.to_sys();
-        self
+    pub fn direction(self, direction: CursorDirection) -> Self {
+        CursorBuilder {
+            inner: self.inner.direction(direction),
+        }
     }
 }
 
-/// Wrapper for [`IDBCursorWithValue`](https://developer.mozilla.org/en-US/docs/Web/API/IDBCursorWithValue)
-pub struct Cursor<Err> {
-    sys: Option<IdbCursor>,
-    req: IdbRequest,
-    _phantom: PhantomData<Err>,
+/// An entry yielded by [`Cursor::into_stream`]
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct CursorEntry<V = JsValue> {
+    /// The key of the entry, as per [`Cursor::key`]
+    pub key: Key,
+
+    /// The primary key of the entry, as per [`Cursor::primary_key`]
+    pub primary_key: Key,
+
+    /// The value of the entry, as per [`Cursor::value`]
+    pub value: V,
 }
 
-impl<Err> Cursor<Err> {
-    pub(crate) async fn from(req: IdbRequest) -> crate::Result<Cursor<Err>, Err> {
-        let res = transaction_request(req.clone())
-            .await
-            .map_err(map_open_cursor_err)?;
-        let is_already_over = res.is_null();
-        let sys = (!is_already_over).then(|| {
-            res.dyn_into::<IdbCursor>()
-                .expect("Cursor-returning request did not return an IDBCursor")
-        });
-        Ok(Cursor {
-            sys,
-            req,
-            _phantom: PhantomData,
-        })
-    }
+/// An entry yielded by [`KeyCursor::into_stream`]
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct KeyCursorEntry {
+    /// The key of the entry, as per [`KeyCursor::key`]
+    pub key: Key,
 
+    /// The primary key of the entry, as per [`KeyCursor::primary_key`]
+    pub primary_key: Key,
+}
+
+/// A cursor over the entries of an object store or index
+pub struct Cursor<Err, B: Backend = WebSys> {
+    inner: B::Cursor<Err>,
+}
+
+impl<Err, B: Backend> Cursor<Err, B> {
     /// Retrieve the value this [`Cursor`] is currently pointing at, or `None` if the cursor is completed
-    ///
-    /// If this cursor was opened as a key-only cursor, then trying to call this method will panic.
-    ///
-    /// Internally, this uses the [`IDBCursorWithValue::value`](https://developer.mozilla.org/en-US/docs/Web/API/IDBCursorWithValue/value) property.
-    pub fn value(&self) -> Option<JsValue> {
-        self.sys.as_ref().map(|sys| {
-            sys.dyn_ref::<IdbCursorWithValue>()
-                .expect("Called Cursor::value on a key-only cursor")
-                .value()
-                .expect("Unable to retrieve value from known-good cursor")
-        })
+    pub fn value(&self) -> Option<B::Value> {
+        self.inner.value()
     }
 
     /// Retrieve the key this [`Cursor`] is currently pointing at, or `None` if the cursor is completed
-    ///
-    /// Internally, this uses the [`IDBCursor::key`](https://developer.mozilla.org/en-US/docs/Web/API/IDBCursor/key) property.
-    pub fn key(&self) -> Option<JsValue> {
-        self.sys.as_ref().map(|sys| {
-            sys.key()
-                .expect("Failed retrieving key from known-good cursor")
-        })
+    pub fn key(&self) -> Option<Key> {
+        self.inner.key()
     }
 
     /// Retrieve the primary key this [`Cursor`] is currently pointing at, or `None` if the cursor is completed
+    pub fn primary_key(&self) -> Option<Key> {
+        self.inner.primary_key()
+    }
+
+    /// Convert this [`Cursor`] into a [`Stream`] of its entries, starting with the current one
     ///
-    /// Internally, this uses the [`IDBCursor::primaryKey`](https://developer.mozilla.org/en-US/docs/Web/API/IDBCursor/key) property.
-    pub fn primary_key(&self) -> Option<JsValue> {
-        self.sys.as_ref().map(|sys| {
-            sys.primary_key()
-                .expect("Failed retrieving primary key from known-good cursor")
+    /// The cursor is only advanced when the next entry is requested, so stopping early (eg. with
+    /// [`StreamExt::take`](futures_util::StreamExt::take)) does not issue any useless request. The stream
+    /// ends after the cursor completes, or after yielding the first error.
+    pub fn into_stream(self) -> impl Stream<Item = crate::Result<CursorEntry<B::Value>, Err>> {
+        stream::unfold(Some((self, false)), move |state| async move {
+            let (mut cursor, started) = state?;
+            if started {
+                if let Err(err) = cursor.advance(1).await {
+                    return Some((Err(err), None));
+                }
+            }
+            let entry = CursorEntry {
+                key: cursor.key()?,
+                primary_key: cursor.primary_key()?,
+                value: cursor.value()?,
+            };
+            Some((Ok(entry), Some((cursor, true))))
         })
     }
 
     /// Advance this [`Cursor`] by `count` elements
-    ///
-    /// Internally, this uses [`IDBCursor::advance`](https://developer.mozilla.org/en-US/docs/Web/API/IDBCursor/advance).
     pub async fn advance(&mut self, count: u32) -> crate::Result<(), Err> {
-        let Some(sys) = &self.sys else {
-            return Err(crate::Error::CursorCompleted);
-        };
-        sys.advance(count).map_err(map_cursor_advance_err)?;
-        if transaction_request(self.req.clone())
-            .await
-            .map_err(map_cursor_advance_err)?
-            .is_null()
-        {
-            self.sys = None;
-        }
-        Ok(())
+        self.inner.advance(count).await
     }
 
     /// Advance this [`Cursor`] until the provided key
-    ///
-    /// Internally, this uses [`IDBCursor::continue`](https://developer.mozilla.org/en-US/docs/Web/API/IDBCursor/continue).
-    pub async fn advance_until(&mut self, key: &JsValue) -> crate::Result<(), Err> {
-        let Some(sys) = &self.sys else {
-            return Err(crate::Error::CursorCompleted);
-        };
-        sys.continue_with_key(key)
-            .map_err(map_cursor_advance_until_err)?;
-        if transaction_request(self.req.clone())
-            .await
-            .map_err(map_cursor_advance_until_err)?
-            .is_null()
-        {
-            self.sys = None;
-        }
-        Ok(())
+    pub async fn advance_until(&mut self, key: impl Into<Key>) -> crate::Result<(), Err> {
+        self.inner.advance_until(&key.into()).await
     }
 
     /// Advance this [`Cursor`] until the provided primary key
@@ -227,57 +177,86 @@ impl<Err> Cursor<Err> {
     ///
     /// Note that this method does not work on cursors over object stores, nor on cursors
     /// which are set with a direction of anything other than `Next` or `Prev`.
-    ///
-    /// Internally, this uses [`IDBCursor::continuePrimaryKey`](https://developer.mozilla.org/en-US/docs/Web/API/IDBCursor/continuePrimaryKey).
     pub async fn advance_until_primary_key(
         &mut self,
-        index_key: &JsValue,
-        primary_key: &JsValue,
+        index_key: impl Into<Key>,
+        primary_key: impl Into<Key>,
     ) -> crate::Result<(), Err> {
-        let Some(sys) = &self.sys else {
-            return Err(crate::Error::CursorCompleted);
-        };
-        sys.continue_primary_key(&index_key, primary_key)
-            .map_err(map_cursor_advance_until_primary_key_err)?;
-        if transaction_request(self.req.clone())
+        self.inner
+            .advance_until_primary_key(&index_key.into(), &primary_key.into())
             .await
-            .map_err(map_cursor_advance_until_primary_key_err)?
-            .is_null()
-        {
-            self.sys = None;
-        }
-        Ok(())
     }
 
     /// Deletes the value currently pointed by this [`Cursor`]
-    ///
-    /// Note that this method does not work on key-only cursors over indexes.
-    ///
-    /// Internally, this uses [`IDBCursor::delete`](https://developer.mozilla.org/en-US/docs/Web/API/IDBCursor/delete).
     pub async fn delete(&self) -> crate::Result<(), Err> {
-        let Some(sys) = &self.sys else {
-            return Err(crate::Error::CursorCompleted);
-        };
-        let req = sys.delete().map_err(map_cursor_delete_err)?;
-        transaction_request(req)
-            .await
-            .map_err(map_cursor_delete_err)?;
-        Ok(())
+        self.inner.delete().await
     }
 
     /// Update the value currently pointed by this [`Cursor`] to `value`
+    pub async fn update(&self, value: &B::Value) -> crate::Result<(), Err> {
+        self.inner.update(value).await
+    }
+}
+
+/// A key-only cursor over the entries of an object store or index
+///
+/// Unlike [`Cursor`], this does not load the values, and thus provides neither `value`, nor `update`
+/// nor `delete`, as modifications through key-only cursors are rejected, be they over object stores
+/// or indexes.
+pub struct KeyCursor<Err, B: Backend = WebSys> {
+    inner: B::KeyCursor<Err>,
+}
+
+impl<Err, B: Backend> KeyCursor<Err, B> {
+    /// Retrieve the key this [`KeyCursor`] is currently pointing at, or `None` if the cursor is completed
+    pub fn key(&self) -> Option<Key> {
+        self.inner.key()
+    }
+
+    /// Retrieve the primary key this [`KeyCursor`] is currently pointing at, or `None` if the cursor is completed
+    pub fn primary_key(&self) -> Option<Key> {
+        self.inner.primary_key()
+    }
+
+    /// Convert this [`KeyCursor`] into a [`Stream`] of its entries, starting with the current one
     ///
-    /// Note that this method does not work on key-only cursors over indexes.
+    /// See [`Cursor::into_stream`] for more details.
+    pub fn into_stream(self) -> impl Stream<Item = crate::Result<KeyCursorEntry, Err>> {
+        stream::unfold(Some((self, false)), move |state| async move {
+            let (mut cursor, started) = state?;
+            if started {
+                if let Err(err) = cursor.advance(1).await {
+                    return Some((Err(err), None));
+                }
+            }
+            let entry = KeyCursorEntry {
+                key: cursor.key()?,
+                primary_key: cursor.primary_key()?,
+            };
+            Some((Ok(entry), Some((cursor, true))))
+        })
+    }
+
+    /// Advance this [`KeyCursor`] by `count` elements
+    pub async fn advance(&mut self, count: u32) -> crate::Result<(), Err> {
+        self.inner.advance(count).await
+    }
+
+    /// Advance this [`KeyCursor`] until the provided key
+    pub async fn advance_until(&mut self, key: impl Into<Key>) -> crate::Result<(), Err> {
+        self.inner.advance_until(&key.into()).await
+    }
+
+    /// Advance this [`KeyCursor`] until the provided primary key
     ///
-    /// Internally, this uses [`IDBCursor::update`](https://developer.mozilla.org/en-US/docs/Web/API/IDBCursor/update).
-    pub async fn update(&self, value: &JsValue) -> crate::Result<(), Err> {
-        let Some(sys) = &self.sys else {
-            return Err(crate::Error::CursorCompleted);
-        };
-        let req = sys.update(value).map_err(map_cursor_update_err)?;
-        transaction_request(req)
+    /// See [`Cursor::advance_until_primary_key`] for more details.
+    pub async fn advance_until_primary_key(
+        &mut self,
+        index_key: impl Into<Key>,
+        primary_key: impl Into<Key>,
+    ) -> crate::Result<(), Err> {
+        self.inner
+            .advance_until_primary_key(&index_key.into(), &primary_key.into())
             .await
-            .map_err(map_cursor_update_err)?;
-        Ok(())
     }
 }
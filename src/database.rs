@@ -1,27 +1,33 @@
-use crate::transaction::TransactionBuilder;
-use web_sys::IdbDatabase;
+use crate::{
+    backend::{Backend, BackendDatabase, WebSys},
+    idb::{EventStream, VersionChange},
+    ExportFormat, SchemaSnapshot, TransactionBuilder,
+};
+use std::convert::Infallible;
 
-/// Wrapper for [`IDBDatabase`](https://developer.mozilla.org/en-US/docs/Web/API/IDBDatabase)
+#[cfg(doc)]
+use crate::ObjectStore;
+
+/// A database connection that gets closed when dropped
 ///
-/// Note that dropping this wrapper automatically calls [`IDBDatabase::close`](https://developer.mozilla.org/en-US/docs/Web/API/IDBDatabase/close)
+/// Note that dropping this wrapper automatically calls [`Database::close`]
 /// to request the underlying database connection to be closed (the actual database close
 /// occuring asynchronously with no way for the client to identify when this happens).
-#[derive(Debug)]
-pub struct OwnedDatabase {
+pub struct OwnedDatabase<B: Backend = WebSys> {
     /// This field only switches to `None` to prevent database close on drop when
     /// `OwnedDatabase::into_manual_close` is used.
-    db: Option<Database>,
+    db: Option<Database<B>>,
 }
 
-impl OwnedDatabase {
-    pub(crate) fn make_auto_close(db: Database) -> OwnedDatabase {
+impl<B: Backend> OwnedDatabase<B> {
+    pub(crate) fn make_auto_close(db: Database<B>) -> OwnedDatabase<B> {
         OwnedDatabase { db: Some(db) }
     }
 
     /// Convert this into a [`Database`] that does not automatically close the connection when dropped.
     ///
     /// The resulting [`Database`] is `Clone` without requiring reference-counting, which can be more convenient than refcounting [`OwnedDatabase`]
-    pub fn into_manual_close(mut self) -> Database {
+    pub fn into_manual_close(mut self) -> Database<B> {
         self.db.take().expect("Database already taken")
     }
 
@@ -34,90 +40,176 @@ impl OwnedDatabase {
     }
 }
 
-impl std::ops::Deref for OwnedDatabase {
-    type Target = Database;
+impl<B: Backend> std::fmt::Debug for OwnedDatabase<B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OwnedDatabase")
+            .field("db", &self.db)
+            .finish()
+    }
+}
+
+impl<B: Backend> std::ops::Deref for OwnedDatabase<B> {
+    type Target = Database<B>;
 
     fn deref(&self) -> &Self::Target {
         self.db.as_ref().expect("Database already taken")
     }
 }
 
-impl Drop for OwnedDatabase {
+impl<B: Backend> Drop for OwnedDatabase<B> {
     fn drop(&mut self) {
-        match self.db.take() {
-            Some(db) => db.close(),
-            None => {} // Database was taken with `into_manual_close`
+        // `db` is `None` if the database was taken with `into_manual_close`
+        if let Some(db) = self.db.take() {
+            db.close();
         }
     }
 }
 
-/// Wrapper for [`IDBDatabase`](https://developer.mozilla.org/en-US/docs/Web/API/IDBDatabase)
+/// A database connection
 ///
 /// Unlike[``OwnedDatabase`], this does not automatically close the database connection when dropped.
 ///
 /// Note that failing to close the database connection prior to dropping this wrapper will let the connection
 /// remain open until the Javascript garbage collector kicks in, which typically can take tens of seconds
 /// during which any new attempt to e.g. delete or open with upgrade the database will hang.
-#[derive(Debug)]
-pub struct Database {
-    sys: IdbDatabase,
+pub struct Database<B: Backend = WebSys> {
+    inner: B::Database,
 }
 
-impl Database {
-    pub(crate) fn from_sys(sys: IdbDatabase) -> Database {
-        Database { sys }
+impl<B: Backend> Clone for Database<B> {
+    fn clone(&self) -> Self {
+        Database {
+            inner: self.inner.clone(),
+        }
     }
+}
 
-    pub(crate) fn as_sys(&self) -> &IdbDatabase {
-        &self.sys
+impl<B: Backend> std::fmt::Debug for Database<B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Database")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl<B: Backend> Database<B> {
+    pub(crate) fn from_backend(inner: B::Database) -> Database<B> {
+        Database { inner }
     }
 
     /// The name of this database
-    ///
-    /// Internally, this uses [`IDBDatabase::name`](https://developer.mozilla.org/en-US/docs/Web/API/IDBDatabase/name).
     pub fn name(&self) -> String {
-        self.sys.name()
+        self.inner.name()
     }
 
     /// The version of this database, clamped at `u32::MAX`
-    ///
-    /// Internally, this uses [`IDBDatabase::version`](https://developer.mozilla.org/en-US/docs/Web/API/IDBDatabase/version).
     pub fn version(&self) -> u32 {
-        self.sys.version() as u32
+        self.inner.version()
     }
 
     /// The names of all [`ObjectStore`]s in this [`Database`]
-    ///
-    /// Internally, this uses [`IDBDatabase::objectStoreNames`](https://developer.mozilla.org/en-US/docs/Web/API/IDBDatabase/objectStoreNames).
     pub fn object_store_names(&self) -> Vec<String> {
-        let names = self.sys.object_store_names();
-        let len = names.length();
-        let mut res = Vec::with_capacity(usize::try_from(len).unwrap());
-        for i in 0..len {
-            res.push(
-                names
-                    .get(i)
-                    .expect("DOMStringList did not contain as many elements as its length"),
-            );
+        self.inner.object_store_names()
+    }
+
+    /// Take a snapshot of the current object stores and indexes of this [`Database`]
+    ///
+    /// This runs a read-only transaction over all the object stores, so it waits for any ongoing read-write
+    /// transaction on them to complete.
+    pub async fn schema_snapshot(&self) -> crate::Result<SchemaSnapshot, Infallible> {
+        let names = self.object_store_names();
+        if names.is_empty() {
+            return Ok(SchemaSnapshot {
+                version: self.version(),
+                stores: Vec::new(),
+            });
         }
-        res
+        let names = names.iter().map(|n| n as &str).collect::<Vec<_>>();
+        self.transaction(&names)
+            .run(async |t| SchemaSnapshot::from_transaction(self, &t))
+            .await
+    }
+
+    /// Write the schema and all the records of this [`Database`] to `writer`, in `format`
+    ///
+    /// This walks all the object stores with cursors, in a single read-only transaction, so the export is
+    /// consistent even if other connections are writing to the database. Keys and values are encoded such
+    /// that no information is lost, see [`ExportFormat`] for the details.
+    ///
+    /// Errors of `writer` are returned as [`Error::User`](crate::Error::User), and values that cannot be
+    /// exported, like `Blob`s, make this fail with [`Error::Serialization`](crate::Error::Serialization).
+    pub async fn export(
+        &self,
+        writer: impl std::io::Write,
+        format: ExportFormat,
+    ) -> crate::Result<(), std::io::Error> {
+        crate::export::export(self, writer, format).await
     }
 
     /// Run a transaction
     ///
     /// This will open the object stores identified by `stores`. See the methods of [`TransactionBuilder`]
     /// for more details about how transactions actually happen.
-    pub fn transaction(&self, stores: &[&str]) -> TransactionBuilder {
-        TransactionBuilder::from_names(self.sys.clone(), stores)
+    pub fn transaction(&self, stores: &[&str]) -> TransactionBuilder<B> {
+        TransactionBuilder::from_backend(self.inner.transaction(stores))
     }
 
     /// Closes this database connection
     ///
     /// Note that the closing will actually happen asynchronously with no way for the client to
     /// identify when the database was closed.
-    ///
-    /// Internally, this uses [`IDBDatabase::close`](https://developer.mozilla.org/en-US/docs/Web/API/IDBDatabase/close).
     pub fn close(&self) {
-        self.sys.close();
+        self.inner.close()
+    }
+}
+
+impl Database<WebSys> {
+    /// Stream of the `versionchange` events fired on this connection
+    ///
+    /// Such an event is fired when another connection (eg. from another tab) requests to upgrade or delete the
+    /// database. That other connection stays blocked until this connection is closed, so a long-lived
+    /// connection should usually react by closing itself.
+    ///
+    /// The event listener is removed when the returned stream is dropped.
+    ///
+    /// See the [`versionchange` event](https://developer.mozilla.org/en-US/docs/Web/API/IDBDatabase/versionchange_event) on MDN.
+    pub fn on_version_change(&self) -> EventStream<VersionChange> {
+        self.inner.on_version_change()
+    }
+
+    /// Stream of the `close` events fired on this connection
+    ///
+    /// Such an event is only fired when the connection was closed unexpectedly by the browser, eg. because the
+    /// database was deleted from the browser settings. It is not fired when calling [`Database::close`].
+    ///
+    /// The event listener is removed when the returned stream is dropped.
+    ///
+    /// See the [`close` event](https://developer.mozilla.org/en-US/docs/Web/API/IDBDatabase/close_event) on MDN.
+    pub fn on_close(&self) -> EventStream<()> {
+        self.inner.on_close()
+    }
+
+    /// Stream of the `abort` events bubbling up from the transactions of this connection
+    ///
+    /// Each item is the error that caused the transaction to abort, or [`Error::Aborted`](crate::Error::Aborted)
+    /// if the transaction was aborted explicitly, eg. by returning an error from [`TransactionBuilder::run`].
+    ///
+    /// The event listener is removed when the returned stream is dropped.
+    ///
+    /// See the [`abort` event](https://developer.mozilla.org/en-US/docs/Web/API/IDBDatabase/abort_event) on MDN.
+    pub fn on_abort(&self) -> EventStream<crate::Error<Infallible>> {
+        self.inner.on_abort()
+    }
+
+    /// Stream of the `error` events bubbling up from the requests of this connection
+    ///
+    /// Each item is the error of the failed request. Errors that were already handled by this crate, eg. by
+    /// returning them from the request's future, are still reported here.
+    ///
+    /// The event listener is removed when the returned stream is dropped.
+    ///
+    /// See the [`error` event](https://developer.mozilla.org/en-US/docs/Web/API/IDBDatabase/error_event) on MDN.
+    pub fn on_error(&self) -> EventStream<crate::Error<Infallible>> {
+        self.inner.on_error()
     }
 }
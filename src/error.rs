@@ -1,7 +1,7 @@
-use crate::utils::err_from_event;
+use crate::{utils::err_from_event, Key};
 use web_sys::{
     wasm_bindgen::{JsCast, JsValue},
-    DomException,
+    DomException, IdbIndex, IdbObjectStore,
 };
 
 /// Type alias for convenience
@@ -11,6 +11,13 @@ pub type Result<T, E> = std::result::Result<T, Error<E>>;
 ///
 /// The `E` generic argument is used for user-defined error types, eg. when
 /// the user provides a callback.
+///
+/// Errors reported by the browser carry an [`ErrorContext`], with the browser's message and what
+/// was being done, see [`Error::context`].
+///
+/// Since 0.5, the variants that carry an [`ErrorContext`] are tuple variants, so they are matched
+/// with eg. `Error::AlreadyExists(_)` instead of `Error::AlreadyExists`. This is an intentional
+/// break, as there is no way to keep unit variants while also reporting the context.
 #[derive(Clone, Debug, thiserror::Error)]
 #[non_exhaustive]
 pub enum Error<E> {
@@ -23,60 +30,131 @@ pub enum Error<E> {
     IndexedDbDisabled,
 
     /// Operation is not supported by the browser
-    #[error("Operation is not supported by the browser")]
-    OperationNotSupported,
+    #[error("Operation is not supported by the browser{0}")]
+    OperationNotSupported(ErrorContext),
 
     /// Operation is not allowed by the user agent
-    #[error("Operation is not allowed by the user agent")]
-    OperationNotAllowed,
+    #[error("Operation is not allowed by the user agent{0}")]
+    OperationNotAllowed(ErrorContext),
 
     /// Provided key is not valid
-    #[error("Provided key is not valid")]
-    InvalidKey,
+    #[error("Provided key is not valid{0}")]
+    InvalidKey(ErrorContext),
 
     /// Version must not be zero
     #[error("Version must not be zero")]
     VersionMustNotBeZero,
 
     /// Requested version is older than existing version
-    #[error("Requested version is older than existing version")]
-    VersionTooOld,
+    #[error("Requested version is older than existing version{0}")]
+    VersionTooOld(ErrorContext),
 
     /// The requested function cannot be called from this context
-    #[error("The requested function cannot be called from this context")]
-    InvalidCall,
+    #[error("The requested function cannot be called from this context{0}")]
+    InvalidCall(ErrorContext),
 
     /// The provided arguments are invalid
-    #[error("The provided arguments are invalid")]
-    InvalidArgument,
+    #[error("The provided arguments are invalid{0}")]
+    InvalidArgument(ErrorContext),
 
     /// Cannot create something that already exists
-    #[error("Cannot create something that already exists")]
-    AlreadyExists,
+    #[error("Cannot create something that already exists{0}")]
+    AlreadyExists(ErrorContext),
 
     /// Cannot change something that does not exists
-    #[error("Cannot change something that does not exists")]
-    DoesNotExist,
+    #[error("Cannot change something that does not exists{0}")]
+    DoesNotExist(ErrorContext),
+
+    /// Database opening or deletion stayed blocked by other connections for too long
+    #[error("Database opening or deletion stayed blocked by other connections for too long")]
+    Blocked,
 
     /// Database is closed
-    #[error("Database is closed")]
-    DatabaseIsClosed,
+    #[error("Database is closed{0}")]
+    DatabaseIsClosed(ErrorContext),
 
     /// Object store was removed
-    #[error("Object store was removed")]
-    ObjectStoreWasRemoved,
+    #[error("Object store was removed{0}")]
+    ObjectStoreWasRemoved(ErrorContext),
 
     /// Transaction is read-only
-    #[error("Transaction is read-only")]
-    ReadOnly,
+    #[error("Transaction is read-only{0}")]
+    ReadOnly(ErrorContext),
+
+    /// Transaction was aborted by the browser instead of committing
+    #[error("Transaction was aborted{}", reason.as_ref().map(|r| format!(": {r}")).unwrap_or_default())]
+    TransactionAborted {
+        /// The error that caused the transaction to abort, if known
+        reason: Option<String>,
+    },
+
+    /// Transaction was already explicitly committed or aborted
+    #[error("Transaction was already explicitly committed or aborted{0}")]
+    TransactionFinished(ErrorContext),
+
+    /// Transaction was inactive, because it auto-committed after awaiting on something other than a request
+    #[error("Transaction was inactive, it probably awaited on something other than a request{0}")]
+    TransactionInactive(ErrorContext),
+
+    /// Future passed to [`Transaction::run_local`](crate::Transaction::run_local) needed the event loop
+    #[error("Future passed to `Transaction::run_local` needed the event loop to complete")]
+    LocalFutureNotReady,
 
     /// Unable to clone
-    #[error("Unable to clone")]
-    FailedClone,
+    #[error("Unable to clone{0}")]
+    FailedClone(ErrorContext),
 
     /// Invalid range
-    #[error("Invalid range")]
-    InvalidRange,
+    ///
+    /// This is never returned anymore: `..` now selects the whole object store or index instead of being
+    /// rejected.
+    #[deprecated(note = "Unbounded ranges are now accepted, so this error is never returned")]
+    #[error("Invalid range{0}")]
+    InvalidRange(ErrorContext),
+
+    /// Database schema does not match the expected schema
+    #[error("Database schema does not match the expected schema")]
+    SchemaMismatch,
+
+    /// Failed converting a value from or to Javascript
+    #[error("Failed converting a value from or to Javascript: {0}")]
+    Serialization(String),
+
+    /// Storage quota was exceeded
+    #[error("Storage quota was exceeded{0}")]
+    QuotaExceeded(ErrorContext),
+
+    /// Operation failed for transient reasons unrelated to the database or the request, like a disk I/O error
+    #[error("Operation failed for reasons unrelated to the database or the request{0}")]
+    Unknown(ErrorContext),
+
+    /// Request was aborted, usually because its transaction was aborted
+    #[error("Request was aborted{0}")]
+    Aborted(ErrorContext),
+
+    /// Operation timed out
+    #[error("Operation timed out{0}")]
+    Timeout(ErrorContext),
+
+    /// Any other error reported by the browser
+    #[error("{name}{context}")]
+    Other {
+        /// Name of the error, like `"InvalidStateError"`
+        name: String,
+
+        /// Details about the error, including the message provided by the browser
+        context: ErrorContext,
+    },
+
+    /// Operation number `index` of a batch failed, like the value at `index` in [`ObjectStore::put_many`](crate::ObjectStore::put_many)
+    #[error("Operation {index} of the batch failed: {}", &**error)]
+    Batch {
+        /// Position of the failed operation in the batch
+        index: usize,
+
+        /// Error of the failed operation
+        error: Box<Error<E>>,
+    },
 
     /// Cursor finished its range
     #[error("Cursor finished its range")]
@@ -87,21 +165,282 @@ pub enum Error<E> {
     User(#[from] E),
 }
 
+/// Details about an [`Error`] reported by the browser
+///
+/// This holds the message of the original `DOMException`, as well as what was being done when the
+/// error happened, when it is known.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ErrorContext(Box<ContextDetails>);
+
+// Boxed to keep `Error` small, as it is returned by most functions
+#[derive(Clone, Debug, Default, PartialEq)]
+struct ContextDetails {
+    message: Option<String>,
+    operation: Option<&'static str>,
+    store: Option<String>,
+    index: Option<String>,
+    key: Option<Key>,
+}
+
+impl ErrorContext {
+    pub(crate) fn from_js(err: &JsValue) -> ErrorContext {
+        let message = if let Some(err) = err.dyn_ref::<DomException>() {
+            Some(err.message())
+        } else {
+            err.dyn_ref::<web_sys::js_sys::Error>()
+                .map(|err| String::from(err.message()))
+        };
+        ErrorContext(Box::new(ContextDetails {
+            message: message.filter(|m| !m.is_empty()),
+            ..ContextDetails::default()
+        }))
+    }
+
+    /// Context for errors that do not come from the browser, like the ones of the in-memory backend
+    pub(crate) fn new(message: &str) -> ErrorContext {
+        ErrorContext(Box::new(ContextDetails {
+            message: Some(message.to_string()),
+            ..ContextDetails::default()
+        }))
+    }
+
+    /// The message provided by the browser
+    pub fn message(&self) -> Option<&str> {
+        self.0.message.as_deref()
+    }
+
+    /// The operation that failed, like `"ObjectStore::add"`
+    pub fn operation(&self) -> Option<&str> {
+        self.0.operation
+    }
+
+    /// The name of the object store the operation was running on
+    pub fn store(&self) -> Option<&str> {
+        self.0.store.as_deref()
+    }
+
+    /// The name of the index the operation was running on
+    pub fn index(&self) -> Option<&str> {
+        self.0.index.as_deref()
+    }
+
+    /// The key the operation was running on
+    pub fn key(&self) -> Option<&Key> {
+        self.0.key.as_ref()
+    }
+}
+
+impl std::fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut location = Vec::new();
+        if let Some(operation) = self.0.operation {
+            location.push(format!("in `{operation}`"));
+        }
+        if let Some(store) = &self.0.store {
+            location.push(format!("on store `{store}`"));
+        }
+        if let Some(index) = &self.0.index {
+            location.push(format!("on index `{index}`"));
+        }
+        if let Some(key) = &self.0.key {
+            location.push(format!("with key {key:?}"));
+        }
+        if !location.is_empty() {
+            write!(f, " ({})", location.join(", "))?;
+        }
+        if let Some(message) = &self.0.message {
+            write!(f, ": {message}")?;
+        }
+        Ok(())
+    }
+}
+
+/// What a request was about, used to fill the [`ErrorContext`] of its errors
+pub(crate) struct RequestContext {
+    operation: &'static str,
+    store: Option<IdbObjectStore>,
+    store_name: Option<String>,
+    index: Option<IdbIndex>,
+    index_name: Option<String>,
+    key: Option<Key>,
+}
+
+impl RequestContext {
+    pub(crate) fn new(operation: &'static str) -> RequestContext {
+        RequestContext {
+            operation,
+            store: None,
+            store_name: None,
+            index: None,
+            index_name: None,
+            key: None,
+        }
+    }
+
+    pub(crate) fn store(mut self, store: &IdbObjectStore) -> RequestContext {
+        self.store = Some(store.clone());
+        self
+    }
+
+    /// Set the name of the store, for when it cannot be retrieved, eg. because it does not exist
+    pub(crate) fn store_name(mut self, name: &str) -> RequestContext {
+        self.store_name = Some(name.to_string());
+        self
+    }
+
+    pub(crate) fn index(mut self, index: &IdbIndex) -> RequestContext {
+        self.index = Some(index.clone());
+        self
+    }
+
+    /// Set the name of the index, for when it cannot be retrieved, eg. because it does not exist
+    pub(crate) fn index_name(mut self, name: &str) -> RequestContext {
+        self.index_name = Some(name.to_string());
+        self
+    }
+
+    /// Set the store or index from the source of a cursor
+    pub(crate) fn source(self, source: &JsValue) -> RequestContext {
+        if let Some(index) = source.dyn_ref::<IdbIndex>() {
+            self.index(index)
+        } else if let Some(store) = source.dyn_ref::<IdbObjectStore>() {
+            self.store(store)
+        } else {
+            self
+        }
+    }
+
+    pub(crate) fn key(mut self, key: &Key) -> RequestContext {
+        self.key = Some(key.clone());
+        self
+    }
+
+    /// Add this context to `err`, only computing the names upon error
+    pub(crate) fn apply<Err>(&self, mut err: Error<Err>) -> Error<Err> {
+        if let Some(context) = err.context_mut() {
+            context.0.operation = Some(self.operation);
+            let store = match &self.index {
+                Some(index) => Some(index.object_store()),
+                None => self.store.clone(),
+            };
+            context.0.store = store.map(|s| s.name()).or_else(|| self.store_name.clone());
+            context.0.index =
+                (self.index.as_ref().map(|i| i.name())).or_else(|| self.index_name.clone());
+            context.0.key = self.key.clone();
+        }
+        err
+    }
+}
+
+macro_rules! with_context {
+    ($err:expr, $context:ident => $res:expr, $default:expr) => {
+        match $err {
+            Error::OperationNotSupported($context)
+            | Error::OperationNotAllowed($context)
+            | Error::InvalidKey($context)
+            | Error::VersionTooOld($context)
+            | Error::InvalidCall($context)
+            | Error::InvalidArgument($context)
+            | Error::AlreadyExists($context)
+            | Error::DoesNotExist($context)
+            | Error::DatabaseIsClosed($context)
+            | Error::ObjectStoreWasRemoved($context)
+            | Error::ReadOnly($context)
+            | Error::TransactionFinished($context)
+            | Error::TransactionInactive($context)
+            | Error::FailedClone($context)
+            | Error::InvalidRange($context)
+            | Error::QuotaExceeded($context)
+            | Error::Unknown($context)
+            | Error::Aborted($context)
+            | Error::Timeout($context)
+            | Error::Other {
+                context: $context, ..
+            } => $res,
+            _ => $default,
+        }
+    };
+}
+
 impl<Err> Error<Err> {
+    /// Details about this error, like the message provided by the browser and the failed operation
+    ///
+    /// This is `None` for errors that do not come from the browser.
+    #[allow(deprecated)] // Error::InvalidRange
+    pub fn context(&self) -> Option<&ErrorContext> {
+        if let Error::Batch { error, .. } = self {
+            return error.context();
+        }
+        with_context!(self, context => Some(context), None)
+    }
+
+    #[allow(deprecated)] // Error::InvalidRange
+    fn context_mut(&mut self) -> Option<&mut ErrorContext> {
+        if let Error::Batch { error, .. } = self {
+            return error.context_mut();
+        }
+        with_context!(self, context => Some(context), None)
+    }
+
+    /// Set what was being done, for errors that do not go through a [`RequestContext`]
+    #[cfg(feature = "memory")]
+    pub(crate) fn located(
+        mut self,
+        operation: &'static str,
+        store: Option<&str>,
+        index: Option<&str>,
+        key: Option<&Key>,
+    ) -> Error<Err> {
+        if let Some(context) = self.context_mut() {
+            context.0.operation = Some(operation);
+            context.0.store = store.map(str::to_string);
+            context.0.index = index.map(str::to_string);
+            context.0.key = key.cloned();
+        }
+        self
+    }
+
     pub(crate) fn from_dom_exception(err: DomException) -> Error<Err> {
+        let context = ErrorContext::from_js(&err);
         match &err.name() as &str {
-            "NotSupportedError" => crate::Error::OperationNotSupported,
-            "NotAllowedError" => crate::Error::OperationNotAllowed,
-            "VersionError" => crate::Error::VersionTooOld,
-            _ => panic!("Unexpected error: {err:?}"),
+            "NotSupportedError" => crate::Error::OperationNotSupported(context),
+            "NotAllowedError" => crate::Error::OperationNotAllowed(context),
+            "VersionError" => crate::Error::VersionTooOld(context),
+            "AbortError" | "InvalidStateError" | "TransactionInactiveError"
+                if crate::idb::transaction::is_explicitly_finished() =>
+            {
+                crate::Error::TransactionFinished(context)
+            }
+            "TransactionInactiveError" => crate::utils::transaction_inactive_err(
+                context,
+                "Tried making a request while the transaction was inactive",
+            ),
+            "QuotaExceededError" => crate::Error::QuotaExceeded(context),
+            "UnknownError" => crate::Error::Unknown(context),
+            "AbortError" => crate::Error::Aborted(context),
+            "TimeoutError" => crate::Error::Timeout(context),
+            name => crate::Error::Other {
+                name: name.to_string(),
+                context,
+            },
         }
     }
 
     pub(crate) fn from_js_value(v: JsValue) -> Error<Err> {
-        let err = v
-            .dyn_into::<web_sys::DomException>()
-            .expect("Trying to parse indexed_db::Error from value that is not a DomException");
-        Error::from_dom_exception(err)
+        let context = ErrorContext::from_js(&v);
+        match v.dyn_into::<DomException>() {
+            Ok(err) => Error::from_dom_exception(err),
+            Err(v) => match v.dyn_into::<web_sys::js_sys::Error>() {
+                Ok(err) => crate::Error::Other {
+                    name: String::from(err.name()),
+                    context,
+                },
+                Err(v) => crate::Error::Other {
+                    name: String::from("Error"),
+                    context: ErrorContext::new(&format!("{v:?}")),
+                },
+            },
+        }
     }
 
     pub(crate) fn from_js_event(evt: web_sys::Event) -> Error<Err> {
@@ -0,0 +1,467 @@
+use crate::{
+    backend::{Backend, BackendValue},
+    Database, Key, KeyPath, StoreSchema,
+};
+use std::{fmt::Write as _, io::Write};
+use web_sys::{
+    js_sys::{Array, ArrayBuffer, BigInt, Date, Map, Object, Set, Uint8Array},
+    wasm_bindgen::{JsCast, JsValue},
+};
+
+/// The format written by [`Database::export`](crate::Database::export)
+///
+/// Both formats hold the same data, with the same field names:
+/// - The database, as its `name` and `version`
+/// - Each object store, as its `name`, `key_path` (`null` for out-of-line keys), `auto_increment` and
+///   `indexes`, each index having a `name`, `key_path`, `unique` and `multi_entry`
+/// - Each record, as its `key` and `value`
+///
+/// Keys and values are written as JSON when JSON can represent them, ie. for `null`, booleans, finite
+/// numbers, strings, arrays and objects. The other values are written as an object with a `$type` field,
+/// and usually a `value` field:
+/// - `{"$type": "undefined"}`
+/// - `{"$type": "Number", "value": "NaN"}`, and the same with `"Infinity"` and `"-Infinity"`
+/// - `{"$type": "Date", "value": 1700000000000}`, with the number of milliseconds since the Unix epoch, or
+///   `null` for invalid dates
+/// - `{"$type": "ArrayBuffer", "value": "AQID"}`, with the base64 of the bytes, and the same for typed arrays
+///   and `DataView`s, with eg. `"Uint8Array"` or `"Float64Array"` as `$type`
+/// - `{"$type": "BigInt", "value": "123"}`
+/// - `{"$type": "Map", "value": [[key, value], ...]}` and `{"$type": "Set", "value": [value, ...]}`
+/// - `{"$type": "Object", "value": {...}}`, for the objects that have a `$type` field themselves
+///
+/// Values that cannot be written this way, like `Blob`s, make the export fail with [`Error::Serialization`](crate::Error::Serialization).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// A single JSON object, with the `name` and `version` of the database, and its `stores`, each with
+    /// its schema and its `records`
+    Json,
+
+    /// Newline-delimited JSON, with one object per line, and a `type` field to tell them apart
+    ///
+    /// The first line is the `"database"`, followed by one line per `"store"`, then one line per `"record"`,
+    /// with the name of its `store`. This makes it possible to stream through large exports.
+    Ndjson,
+}
+
+/// A key or a value that can be written to an export
+pub(crate) trait ExportValue {
+    /// Push the JSON encoding of this value to `out`, or return why it cannot be exported
+    fn push_json(&self, out: &mut String) -> Result<(), String>;
+}
+
+/// Export `db` to `writer`, reading all its object stores in a single transaction
+pub(crate) async fn export<B: Backend>(
+    db: &Database<B>,
+    writer: impl Write,
+    format: ExportFormat,
+) -> crate::Result<(), std::io::Error> {
+    let mut exporter = Exporter {
+        writer,
+        format,
+        stores: 0,
+        records: 0,
+    };
+    let (name, version, stores) = (db.name(), db.version(), db.object_store_names());
+    if stores.is_empty() {
+        exporter.database(&name, version, &[])?;
+        return exporter.end();
+    }
+    let names = stores.iter().map(|n| n as &str).collect::<Vec<_>>();
+    db.transaction(&names)
+        .run(async |t| {
+            let schemas = stores
+                .iter()
+                .map(|name| StoreSchema::from_store(&t.object_store(name)?))
+                .collect::<crate::Result<Vec<_>, std::io::Error>>()?;
+            exporter.database(&name, version, &schemas)?;
+            for schema in &schemas {
+                exporter.begin_store(schema)?;
+                let store = t.object_store(&schema.name)?;
+                let mut cursor = store.cursor().open().await?;
+                while let (Some(key), Some(value)) = (cursor.primary_key(), cursor.value()) {
+                    exporter.record(&schema.name, &key, &value)?;
+                    cursor.advance(1).await?;
+                }
+                exporter.end_store()?;
+            }
+            exporter.end()
+        })
+        .await
+}
+
+struct Exporter<W> {
+    writer: W,
+    format: ExportFormat,
+    /// The number of stores started so far
+    stores: usize,
+    /// The number of records written so far for the current store
+    records: usize,
+}
+
+impl<W: Write> Exporter<W> {
+    fn database(
+        &mut self,
+        name: &str,
+        version: u32,
+        stores: &[StoreSchema],
+    ) -> crate::Result<(), std::io::Error> {
+        let mut out = String::new();
+        match self.format {
+            ExportFormat::Json => {
+                out.push_str("{\"name\":");
+                push_string(&mut out, name);
+                let _ = write!(out, ",\"version\":{version},\"stores\":[");
+            }
+            ExportFormat::Ndjson => {
+                out.push_str("{\"type\":\"database\",\"name\":");
+                push_string(&mut out, name);
+                let _ = writeln!(out, ",\"version\":{version}}}");
+                for store in stores {
+                    out.push_str("{\"type\":\"store\",");
+                    push_store_fields(&mut out, store);
+                    out.push_str("}\n");
+                }
+            }
+        }
+        self.write(&out)
+    }
+
+    fn begin_store(&mut self, store: &StoreSchema) -> crate::Result<(), std::io::Error> {
+        self.stores += 1;
+        self.records = 0;
+        if self.format == ExportFormat::Ndjson {
+            return Ok(());
+        }
+        let mut out = String::from(if self.stores > 1 { ",{" } else { "{" });
+        push_store_fields(&mut out, store);
+        out.push_str(",\"records\":[");
+        self.write(&out)
+    }
+
+    fn record(
+        &mut self,
+        store: &str,
+        key: &impl ExportValue,
+        value: &impl BackendValue,
+    ) -> crate::Result<(), std::io::Error> {
+        let mut out = String::new();
+        match self.format {
+            ExportFormat::Json if self.records > 0 => out.push_str(",{"),
+            ExportFormat::Json => out.push('{'),
+            ExportFormat::Ndjson => {
+                out.push_str("{\"type\":\"record\",\"store\":");
+                push_string(&mut out, store);
+                out.push(',');
+            }
+        }
+        out.push_str("\"key\":");
+        key.push_json(&mut out)
+            .and_then(|()| {
+                out.push_str(",\"value\":");
+                value.push_json(&mut out)
+            })
+            .map_err(|err| {
+                crate::Error::Serialization(format!(
+                    "Failed exporting a record of object store `{store}`: {err}"
+                ))
+            })?;
+        out.push('}');
+        if self.format == ExportFormat::Ndjson {
+            out.push('\n');
+        }
+        self.records += 1;
+        self.write(&out)
+    }
+
+    fn end_store(&mut self) -> crate::Result<(), std::io::Error> {
+        match self.format {
+            ExportFormat::Json => self.write("]}"),
+            ExportFormat::Ndjson => Ok(()),
+        }
+    }
+
+    fn end(mut self) -> crate::Result<(), std::io::Error> {
+        if self.format == ExportFormat::Json {
+            self.write("]}\n")?;
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    fn write(&mut self, out: &str) -> crate::Result<(), std::io::Error> {
+        self.writer.write_all(out.as_bytes())?;
+        Ok(())
+    }
+}
+
+fn push_store_fields(out: &mut String, store: &StoreSchema) {
+    out.push_str("\"name\":");
+    push_string(out, &store.name);
+    out.push_str(",\"key_path\":");
+    match &store.key_path {
+        Some(key_path) => push_key_path(out, key_path),
+        None => out.push_str("null"),
+    }
+    let _ = write!(
+        out,
+        ",\"auto_increment\":{},\"indexes\":[",
+        store.auto_increment
+    );
+    for (i, index) in store.indexes.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push_str("{\"name\":");
+        push_string(out, &index.name);
+        out.push_str(",\"key_path\":");
+        push_key_path(out, &index.key_path);
+        let _ = write!(
+            out,
+            ",\"unique\":{},\"multi_entry\":{}}}",
+            index.unique, index.multi_entry
+        );
+    }
+    out.push(']');
+}
+
+fn push_key_path(out: &mut String, key_path: &KeyPath) {
+    match key_path {
+        KeyPath::Single(path) => push_string(out, path),
+        KeyPath::Compound(paths) => {
+            out.push('[');
+            for (i, path) in paths.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                push_string(out, path);
+            }
+            out.push(']');
+        }
+    }
+}
+
+fn push_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c < ' ' => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn push_number(out: &mut String, n: f64) {
+    if n.is_nan() {
+        out.push_str("{\"$type\":\"Number\",\"value\":\"NaN\"}");
+    } else if n == f64::INFINITY {
+        out.push_str("{\"$type\":\"Number\",\"value\":\"Infinity\"}");
+    } else if n == f64::NEG_INFINITY {
+        out.push_str("{\"$type\":\"Number\",\"value\":\"-Infinity\"}");
+    } else if n == 0.0 || (1e-6..1e21).contains(&n.abs()) {
+        // Like Javascript, only use the exponent notation for very small or large numbers
+        let _ = write!(out, "{n}");
+    } else {
+        let _ = write!(out, "{n:e}");
+    }
+}
+
+fn push_date(out: &mut String, t: f64) {
+    out.push_str("{\"$type\":\"Date\",\"value\":");
+    if t.is_nan() {
+        out.push_str("null");
+    } else {
+        push_number(out, t);
+    }
+    out.push('}');
+}
+
+fn push_binary(out: &mut String, kind: &str, bytes: &[u8]) {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    out.push_str("{\"$type\":");
+    push_string(out, kind);
+    out.push_str(",\"value\":\"");
+    for chunk in bytes.chunks(3) {
+        let bits = u32::from_be_bytes([
+            0,
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(bits >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out.push_str("\"}");
+}
+
+fn push_array<'a, T: 'a + ExportValue>(
+    out: &mut String,
+    items: impl IntoIterator<Item = &'a T>,
+) -> Result<(), String> {
+    out.push('[');
+    for (i, item) in items.into_iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        item.push_json(out)?;
+    }
+    out.push(']');
+    Ok(())
+}
+
+/// Push an object, wrapping it if one of its fields could be confused with the `$type` of special values
+fn push_object<'a, T: 'a + ExportValue>(
+    out: &mut String,
+    fields: impl Clone + IntoIterator<Item = (&'a str, &'a T)>,
+) -> Result<(), String> {
+    let wrapped = fields.clone().into_iter().any(|(name, _)| name == "$type");
+    if wrapped {
+        out.push_str("{\"$type\":\"Object\",\"value\":");
+    }
+    out.push('{');
+    for (i, (name, value)) in fields.into_iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        push_string(out, name);
+        out.push(':');
+        value.push_json(out)?;
+    }
+    out.push('}');
+    if wrapped {
+        out.push('}');
+    }
+    Ok(())
+}
+
+impl ExportValue for Key {
+    fn push_json(&self, out: &mut String) -> Result<(), String> {
+        match self {
+            Key::Number(n) => push_number(out, *n),
+            Key::Date(t) => push_date(out, *t),
+            Key::String(s) => push_string(out, s),
+            Key::Binary(b) => push_binary(out, "ArrayBuffer", b),
+            Key::Array(a) => push_array(out, a)?,
+        }
+        Ok(())
+    }
+}
+
+impl ExportValue for JsValue {
+    fn push_json(&self, out: &mut String) -> Result<(), String> {
+        if self.is_undefined() {
+            out.push_str("{\"$type\":\"undefined\"}");
+        } else if self.is_null() {
+            out.push_str("null");
+        } else if let Some(b) = self.as_bool() {
+            out.push_str(if b { "true" } else { "false" });
+        } else if let Some(n) = self.as_f64() {
+            push_number(out, n);
+        } else if let Some(s) = self.as_string() {
+            push_string(out, &s);
+        } else if self.is_bigint() {
+            let value = self
+                .unchecked_ref::<BigInt>()
+                .to_string(10)
+                .map_err(|_| String::from("BigInt could not be formatted"))?;
+            out.push_str("{\"$type\":\"BigInt\",\"value\":");
+            push_string(out, &String::from(value));
+            out.push('}');
+        } else if let Some(d) = self.dyn_ref::<Date>() {
+            push_date(out, d.get_time());
+        } else if let Some(b) = self.dyn_ref::<ArrayBuffer>() {
+            push_binary(out, "ArrayBuffer", &Uint8Array::new(b).to_vec());
+        } else if ArrayBuffer::is_view(self) {
+            let view = self.unchecked_ref::<Uint8Array>();
+            let bytes = Uint8Array::new_with_byte_offset_and_length(
+                &view.buffer(),
+                view.byte_offset(),
+                view.byte_length(),
+            );
+            push_binary(out, &constructor_name(self), &bytes.to_vec());
+        } else if Array::is_array(self) {
+            push_array(out, &self.unchecked_ref::<Array>().to_vec())?;
+        } else if let Some(map) = self.dyn_ref::<Map>() {
+            let mut entries = Vec::new();
+            map.for_each(&mut |value, key| entries.push(Array::of2(&key, &value).into()));
+            out.push_str("{\"$type\":\"Map\",\"value\":");
+            push_array::<JsValue>(out, &entries)?;
+            out.push('}');
+        } else if let Some(set) = self.dyn_ref::<Set>() {
+            let mut values = Vec::new();
+            set.for_each(&mut |value, _, _| values.push(value));
+            out.push_str("{\"$type\":\"Set\",\"value\":");
+            push_array(out, &values)?;
+            out.push('}');
+        } else if is_plain_object(self) {
+            let fields = Object::entries(self.unchecked_ref())
+                .iter()
+                .map(|entry| {
+                    let entry = entry.unchecked_into::<Array>();
+                    (entry.get(0).as_string().unwrap_or_default(), entry.get(1))
+                })
+                .collect::<Vec<_>>();
+            push_object(
+                out,
+                fields.iter().map(|(name, value)| (name as &str, value)),
+            )?;
+        } else {
+            return Err(format!(
+                "{} values cannot be exported",
+                constructor_name(self)
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Whether `v` is an object without a prototype other than `Object.prototype`, like the structured clone
+/// algorithm returns for all the objects it does not special-case
+fn is_plain_object(v: &JsValue) -> bool {
+    if !v.is_object() {
+        return false;
+    }
+    let proto = Object::get_prototype_of(v);
+    proto.is_null() || Object::is(&proto, &Object::get_prototype_of(&Object::new()))
+}
+
+fn constructor_name(v: &JsValue) -> String {
+    match v.dyn_ref::<Object>() {
+        Some(object) => String::from(object.constructor().name()),
+        None => v.js_typeof().as_string().unwrap_or_default(),
+    }
+}
+
+#[cfg(feature = "memory")]
+impl ExportValue for crate::memory::Value {
+    fn push_json(&self, out: &mut String) -> Result<(), String> {
+        use crate::memory::Value;
+        match self {
+            Value::Undefined => out.push_str("{\"$type\":\"undefined\"}"),
+            Value::Null => out.push_str("null"),
+            Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+            Value::Number(n) => push_number(out, *n),
+            Value::String(s) => push_string(out, s),
+            Value::Date(t) => push_date(out, *t),
+            Value::Binary(b) => push_binary(out, "ArrayBuffer", b),
+            Value::Array(a) => push_array(out, a)?,
+            Value::Object(fields) => push_object(
+                out,
+                fields.iter().map(|(name, value)| (name as &str, value)),
+            )?,
+        }
+        Ok(())
+    }
+}
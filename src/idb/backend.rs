@@ -0,0 +1,510 @@
+use super::{
+    Cursor, CursorBuilder, Database, Factory, Index, KeyCursor, ObjectStore, Transaction,
+    TransactionBuilder, VersionChangeEvent,
+};
+use crate::{
+    backend::{
+        Backend, BackendCursor, BackendCursorBuilder, BackendDatabase, BackendFactory,
+        BackendIndex, BackendKeyCursor, BackendObjectStore, BackendTransaction,
+        BackendTransactionBuilder, BackendVersionChangeEvent, WebSys,
+    },
+    CursorDirection, DatabaseInfo, Durability, Key, KeyPath,
+};
+use std::{borrow::Borrow, convert::Infallible, future::Future, ops::RangeBounds};
+use web_sys::wasm_bindgen::JsValue;
+
+impl Backend for WebSys {
+    type Value = JsValue;
+    type Factory = Factory;
+    type Database = Database;
+    type VersionChangeEvent<Err> = VersionChangeEvent<Err>;
+    type TransactionBuilder = TransactionBuilder;
+    type Transaction<Err> = Transaction<Err>;
+    type ObjectStore<Err> = ObjectStore<Err>;
+    type Index<Err> = Index<Err>;
+    type CursorBuilder<Err> = CursorBuilder<Err>;
+    type Cursor<Err> = Cursor<Err>;
+    type KeyCursor<Err> = KeyCursor<Err>;
+}
+
+impl BackendFactory<WebSys> for Factory {
+    fn open<Err: 'static>(
+        &self,
+        name: &str,
+        version: u32,
+        on_upgrade_needed: impl AsyncFnOnce(VersionChangeEvent<Err>) -> crate::Result<(), Err>,
+    ) -> impl Future<Output = crate::Result<Database, Err>> {
+        self.open(name, version, on_upgrade_needed)
+    }
+
+    fn open_latest_version(
+        &self,
+        name: &str,
+    ) -> impl Future<Output = crate::Result<Database, Infallible>> {
+        self.open_latest_version(name)
+    }
+
+    fn delete_database(&self, name: &str) -> impl Future<Output = crate::Result<(), Infallible>> {
+        self.delete_database(name)
+    }
+
+    fn databases(&self) -> impl Future<Output = crate::Result<Vec<DatabaseInfo>, Infallible>> {
+        self.databases()
+    }
+}
+
+impl BackendDatabase<WebSys> for Database {
+    fn name(&self) -> String {
+        self.name()
+    }
+
+    fn version(&self) -> u32 {
+        self.version()
+    }
+
+    fn object_store_names(&self) -> Vec<String> {
+        self.object_store_names()
+    }
+
+    fn transaction(&self, stores: &[&str]) -> TransactionBuilder {
+        self.transaction(stores)
+    }
+
+    fn close(&self) {
+        self.close()
+    }
+}
+
+impl<Err> BackendVersionChangeEvent<WebSys, Err> for VersionChangeEvent<Err> {
+    fn old_version(&self) -> u32 {
+        self.old_version()
+    }
+
+    fn new_version(&self) -> u32 {
+        self.new_version()
+    }
+
+    fn database(&self) -> &Database {
+        self.database()
+    }
+
+    fn create_object_store(
+        &self,
+        name: &str,
+        key_path: Option<&KeyPath>,
+        auto_increment: bool,
+    ) -> crate::Result<ObjectStore<Err>, Err> {
+        self.create_object_store(name, key_path, auto_increment)
+    }
+
+    fn delete_object_store(&self, name: &str) -> crate::Result<(), Err> {
+        self.delete_object_store(name)
+    }
+
+    fn transaction(&self) -> &Transaction<Err> {
+        self.transaction()
+    }
+}
+
+impl BackendTransactionBuilder<WebSys> for TransactionBuilder {
+    fn rw(self) -> Self {
+        self.rw()
+    }
+
+    fn durability(self, durability: Durability) -> Self {
+        self.durability(durability)
+    }
+
+    fn run<Ret, Err>(
+        self,
+        transaction: impl AsyncFnOnce(Transaction<Err>) -> crate::Result<Ret, Err>,
+    ) -> impl Future<Output = crate::Result<Ret, Err>> {
+        self.run(transaction)
+    }
+
+    fn run_no_wait<Ret, Err>(
+        self,
+        transaction: impl AsyncFnOnce(Transaction<Err>) -> crate::Result<Ret, Err>,
+    ) -> impl Future<Output = crate::Result<Ret, Err>> {
+        self.run_no_wait(transaction)
+    }
+}
+
+impl<Err> BackendTransaction<WebSys, Err> for Transaction<Err> {
+    fn object_store(&self, name: &str) -> crate::Result<ObjectStore<Err>, Err> {
+        self.object_store(name)
+    }
+
+    fn commit(&self) -> crate::Result<(), Err> {
+        self.commit()
+    }
+
+    fn abort(&self) -> crate::Result<(), Err> {
+        self.abort()
+    }
+}
+
+impl<Err> BackendObjectStore<WebSys, Err> for ObjectStore<Err> {
+    fn name(&self) -> String {
+        self.name()
+    }
+
+    fn key_path(&self) -> crate::Result<Option<KeyPath>, Err> {
+        self.key_path()
+    }
+
+    fn auto_increment(&self) -> bool {
+        self.auto_increment()
+    }
+
+    fn index_names(&self) -> Vec<String> {
+        self.index_names()
+    }
+
+    fn create_index(
+        &self,
+        name: &str,
+        key_path: &KeyPath,
+        unique: bool,
+        multi_entry: bool,
+    ) -> crate::Result<(), Err> {
+        self.create_index(name, key_path, unique, multi_entry)
+    }
+
+    fn delete_index(&self, name: &str) -> crate::Result<(), Err> {
+        self.delete_index(name)
+    }
+
+    fn add(&self, value: &JsValue) -> impl Future<Output = crate::Result<Key, Err>> + use<Err> {
+        self.add(value)
+    }
+
+    fn add_kv(
+        &self,
+        key: &Key,
+        value: &JsValue,
+    ) -> impl Future<Output = crate::Result<(), Err>> + use<Err> {
+        self.add_kv(key.clone(), value)
+    }
+
+    fn put(&self, value: &JsValue) -> impl Future<Output = crate::Result<Key, Err>> + use<Err> {
+        self.put(value)
+    }
+
+    fn put_kv(
+        &self,
+        key: &Key,
+        value: &JsValue,
+    ) -> impl Future<Output = crate::Result<(), Err>> + use<Err> {
+        self.put_kv(key.clone(), value)
+    }
+
+    fn add_many<I: IntoIterator<Item = V>, V: Borrow<JsValue>>(
+        &self,
+        values: I,
+    ) -> impl Future<Output = crate::Result<Vec<Key>, Err>> + use<Err, I, V> {
+        self.add_many(values)
+    }
+
+    fn add_kv_many<I: IntoIterator<Item = (Key, V)>, V: Borrow<JsValue>>(
+        &self,
+        entries: I,
+    ) -> impl Future<Output = crate::Result<(), Err>> + use<Err, I, V> {
+        self.add_kv_many(entries)
+    }
+
+    fn put_many<I: IntoIterator<Item = V>, V: Borrow<JsValue>>(
+        &self,
+        values: I,
+    ) -> impl Future<Output = crate::Result<Vec<Key>, Err>> + use<Err, I, V> {
+        self.put_many(values)
+    }
+
+    fn put_kv_many<I: IntoIterator<Item = (Key, V)>, V: Borrow<JsValue>>(
+        &self,
+        entries: I,
+    ) -> impl Future<Output = crate::Result<(), Err>> + use<Err, I, V> {
+        self.put_kv_many(entries)
+    }
+
+    fn update_with<F: FnOnce(Option<JsValue>) -> Option<JsValue>>(
+        &self,
+        key: &Key,
+        f: F,
+    ) -> impl Future<Output = crate::Result<(Option<JsValue>, Option<JsValue>), Err>> + use<Err, F>
+    {
+        self.update_with(key.clone(), f)
+    }
+
+    fn compare_and_swap(
+        &self,
+        key: &Key,
+        expected: Option<&JsValue>,
+        new: Option<&JsValue>,
+    ) -> impl Future<Output = crate::Result<(Option<JsValue>, Option<JsValue>), Err>> + use<Err>
+    {
+        self.compare_and_swap(key.clone(), expected, new)
+    }
+
+    fn clear(&self) -> impl Future<Output = crate::Result<(), Err>> + use<Err> {
+        self.clear()
+    }
+
+    fn count(&self) -> impl Future<Output = crate::Result<usize, Err>> + use<Err> {
+        self.count()
+    }
+
+    fn contains(&self, key: &Key) -> impl Future<Output = crate::Result<bool, Err>> + use<Err> {
+        self.contains(key.clone())
+    }
+
+    fn count_in<R: RangeBounds<Key>>(
+        &self,
+        range: R,
+    ) -> impl Future<Output = crate::Result<usize, Err>> + use<Err, R> {
+        self.count_in(range)
+    }
+
+    fn delete(&self, key: &Key) -> impl Future<Output = crate::Result<(), Err>> + use<Err> {
+        self.delete(key.clone())
+    }
+
+    fn delete_range<R: RangeBounds<Key>>(
+        &self,
+        range: R,
+    ) -> impl Future<Output = crate::Result<(), Err>> + use<Err, R> {
+        self.delete_range(range)
+    }
+
+    fn delete_many<I: IntoIterator<Item = Key>>(
+        &self,
+        keys: I,
+    ) -> impl Future<Output = crate::Result<(), Err>> + use<Err, I> {
+        self.delete_many(keys)
+    }
+
+    fn get(
+        &self,
+        key: &Key,
+    ) -> impl Future<Output = crate::Result<Option<JsValue>, Err>> + use<Err> {
+        self.get(key.clone())
+    }
+
+    fn get_first_in<R: RangeBounds<Key>>(
+        &self,
+        range: R,
+    ) -> impl Future<Output = crate::Result<Option<JsValue>, Err>> + use<Err, R> {
+        self.get_first_in(range)
+    }
+
+    fn get_all(
+        &self,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<JsValue>, Err>> + use<Err> {
+        self.get_all(limit)
+    }
+
+    fn get_all_in<R: RangeBounds<Key>>(
+        &self,
+        range: R,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<JsValue>, Err>> + use<Err, R> {
+        self.get_all_in(range, limit)
+    }
+
+    fn get_first_key_in<R: RangeBounds<Key>>(
+        &self,
+        range: R,
+    ) -> impl Future<Output = crate::Result<Option<Key>, Err>> + use<Err, R> {
+        self.get_first_key_in(range)
+    }
+
+    fn get_all_keys(
+        &self,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<Key>, Err>> + use<Err> {
+        self.get_all_keys(limit)
+    }
+
+    fn get_all_keys_in<R: RangeBounds<Key>>(
+        &self,
+        range: R,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<Key>, Err>> + use<Err, R> {
+        self.get_all_keys_in(range, limit)
+    }
+
+    fn index(&self, name: &str) -> crate::Result<Index<Err>, Err> {
+        self.index(name)
+    }
+
+    fn cursor(&self) -> CursorBuilder<Err> {
+        self.cursor()
+    }
+}
+
+impl<Err> BackendIndex<WebSys, Err> for Index<Err> {
+    fn name(&self) -> String {
+        self.name()
+    }
+
+    fn key_path(&self) -> crate::Result<KeyPath, Err> {
+        self.key_path()
+    }
+
+    fn is_unique(&self) -> bool {
+        self.is_unique()
+    }
+
+    fn is_multi_entry(&self) -> bool {
+        self.is_multi_entry()
+    }
+
+    fn contains(&self, key: &Key) -> impl Future<Output = crate::Result<bool, Err>> + use<Err> {
+        self.contains(key.clone())
+    }
+
+    fn count_in<R: RangeBounds<Key>>(
+        &self,
+        range: R,
+    ) -> impl Future<Output = crate::Result<usize, Err>> + use<Err, R> {
+        self.count_in(range)
+    }
+
+    fn get(
+        &self,
+        key: &Key,
+    ) -> impl Future<Output = crate::Result<Option<JsValue>, Err>> + use<Err> {
+        self.get(key.clone())
+    }
+
+    fn get_first_in<R: RangeBounds<Key>>(
+        &self,
+        range: R,
+    ) -> impl Future<Output = crate::Result<Option<JsValue>, Err>> + use<Err, R> {
+        self.get_first_in(range)
+    }
+
+    fn get_all(
+        &self,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<JsValue>, Err>> + use<Err> {
+        self.get_all(limit)
+    }
+
+    fn get_all_in<R: RangeBounds<Key>>(
+        &self,
+        range: R,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<JsValue>, Err>> + use<Err, R> {
+        self.get_all_in(range, limit)
+    }
+
+    fn get_first_key_in<R: RangeBounds<Key>>(
+        &self,
+        range: R,
+    ) -> impl Future<Output = crate::Result<Option<Key>, Err>> + use<Err, R> {
+        self.get_first_key_in(range)
+    }
+
+    fn get_all_keys(
+        &self,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<Key>, Err>> + use<Err> {
+        self.get_all_keys(limit)
+    }
+
+    fn get_all_keys_in<R: RangeBounds<Key>>(
+        &self,
+        range: R,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<Key>, Err>> + use<Err, R> {
+        self.get_all_keys_in(range, limit)
+    }
+
+    fn cursor(&self) -> CursorBuilder<Err> {
+        self.cursor()
+    }
+}
+
+impl<Err> BackendCursorBuilder<WebSys, Err> for CursorBuilder<Err> {
+    fn open(self) -> impl Future<Output = crate::Result<Cursor<Err>, Err>> {
+        self.open()
+    }
+
+    fn open_key(self) -> impl Future<Output = crate::Result<KeyCursor<Err>, Err>> {
+        self.open_key()
+    }
+
+    fn range(self, range: impl RangeBounds<Key>) -> crate::Result<Self, Err> {
+        self.range(range)
+    }
+
+    fn direction(self, direction: CursorDirection) -> Self {
+        self.direction(direction)
+    }
+}
+
+impl<Err> BackendCursor<WebSys, Err> for Cursor<Err> {
+    fn key(&self) -> Option<Key> {
+        self.key()
+    }
+
+    fn primary_key(&self) -> Option<Key> {
+        self.primary_key()
+    }
+
+    fn value(&self) -> Option<JsValue> {
+        self.value()
+    }
+
+    fn advance(&mut self, count: u32) -> impl Future<Output = crate::Result<(), Err>> {
+        self.advance(count)
+    }
+
+    fn advance_until(&mut self, key: &Key) -> impl Future<Output = crate::Result<(), Err>> {
+        self.advance_until(key)
+    }
+
+    fn advance_until_primary_key(
+        &mut self,
+        index_key: &Key,
+        primary_key: &Key,
+    ) -> impl Future<Output = crate::Result<(), Err>> {
+        self.advance_until_primary_key(index_key, primary_key)
+    }
+
+    fn delete(&self) -> impl Future<Output = crate::Result<(), Err>> {
+        self.delete()
+    }
+
+    fn update(&self, value: &JsValue) -> impl Future<Output = crate::Result<(), Err>> {
+        self.update(value)
+    }
+}
+
+impl<Err> BackendKeyCursor<WebSys, Err> for KeyCursor<Err> {
+    fn key(&self) -> Option<Key> {
+        self.key()
+    }
+
+    fn primary_key(&self) -> Option<Key> {
+        self.primary_key()
+    }
+
+    fn advance(&mut self, count: u32) -> impl Future<Output = crate::Result<(), Err>> {
+        self.advance(count)
+    }
+
+    fn advance_until(&mut self, key: &Key) -> impl Future<Output = crate::Result<(), Err>> {
+        self.advance_until(key)
+    }
+
+    fn advance_until_primary_key(
+        &mut self,
+        index_key: &Key,
+        primary_key: &Key,
+    ) -> impl Future<Output = crate::Result<(), Err>> {
+        self.advance_until_primary_key(index_key, primary_key)
+    }
+}
@@ -0,0 +1,413 @@
+use super::transaction::transaction_request;
+use crate::{
+    error::RequestContext,
+    utils::{
+        make_key_range, map_cursor_advance_err, map_cursor_advance_until_err,
+        map_cursor_advance_until_primary_key_err, map_cursor_delete_err, map_cursor_update_err,
+        map_open_cursor_err,
+    },
+    CursorDirection, Key,
+};
+use futures_util::future::Either;
+use std::{future::Future, marker::PhantomData, ops::RangeBounds};
+use web_sys::{
+    wasm_bindgen::{JsCast, JsValue},
+    IdbCursor, IdbCursorDirection, IdbCursorWithValue, IdbIndex, IdbObjectStore, IdbRequest,
+};
+
+#[cfg(doc)]
+use super::{Index, ObjectStore};
+
+/// Helper to build cursors over [`ObjectStore`]s
+pub struct CursorBuilder<Err> {
+    source: Either<IdbObjectStore, IdbIndex>,
+    query: JsValue,
+    direction: IdbCursorDirection,
+    _phantom: PhantomData<Err>,
+}
+
+impl<Err> CursorBuilder<Err> {
+    pub(crate) fn from_store(store: IdbObjectStore) -> CursorBuilder<Err> {
+        CursorBuilder {
+            source: Either::Left(store),
+            query: JsValue::UNDEFINED,
+            direction: IdbCursorDirection::Next,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub(crate) fn from_index(index: IdbIndex) -> CursorBuilder<Err> {
+        CursorBuilder {
+            source: Either::Right(index),
+            query: JsValue::UNDEFINED,
+            direction: IdbCursorDirection::Next,
+            _phantom: PhantomData,
+        }
+    }
+
+    fn context(&self, operation: &'static str) -> RequestContext {
+        match &self.source {
+            Either::Left(store) => RequestContext::new(operation).store(store),
+            Either::Right(index) => RequestContext::new(operation).index(index),
+        }
+    }
+
+    /// Open the cursor
+    ///
+    /// See [`crate::CursorBuilder::open`].
+    ///
+    /// Internally, this uses [`IDBObjectStore::openCursor`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/openCursor).
+    pub fn open(self) -> impl Future<Output = crate::Result<Cursor<Err>, Err>> {
+        let ctx = self.context("CursorBuilder::open");
+        let req = match self.source {
+            Either::Left(store) => {
+                store.open_cursor_with_range_and_direction(&self.query, self.direction)
+            }
+            Either::Right(index) => {
+                index.open_cursor_with_range_and_direction(&self.query, self.direction)
+            }
+        };
+        match req {
+            Ok(open_req) => Either::Right(Cursor::from(open_req, ctx)),
+            Err(err) => Either::Left(std::future::ready(Err(ctx.apply(map_open_cursor_err(err))))),
+        }
+    }
+
+    /// Open the cursor as a key-only cursor
+    ///
+    /// See [`crate::CursorBuilder::open_key`].
+    ///
+    /// Internally, this uses [`IDBObjectStore::openKeyCursor`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/openKeyCursor).
+    pub fn open_key(self) -> impl Future<Output = crate::Result<KeyCursor<Err>, Err>> {
+        let ctx = self.context("CursorBuilder::open_key");
+        let req = match self.source {
+            Either::Left(store) => {
+                store.open_key_cursor_with_range_and_direction(&self.query, self.direction)
+            }
+            Either::Right(index) => {
+                index.open_key_cursor_with_range_and_direction(&self.query, self.direction)
+            }
+        };
+        match req {
+            Ok(open_req) => Either::Right(KeyCursor::from(open_req, ctx)),
+            Err(err) => Either::Left(std::future::ready(Err(ctx.apply(map_open_cursor_err(err))))),
+        }
+    }
+
+    /// Limit the range of the cursor
+    ///
+    /// See [`crate::CursorBuilder::range`].
+    ///
+    /// Internally, this sets [this property](https://developer.mozilla.org/en-US/docs/Web/API/IDBIndex/openCursor#range).
+    pub fn range(mut self, range: impl RangeBounds<Key>) -> crate::Result<Self, Err> {
+        self.query = make_key_range(range)?;
+        Ok(self)
+    }
+
+    /// Define the direction of the cursor
+    ///
+    /// See [`crate::CursorBuilder::direction`].
+    ///
+    /// Internally, this sets [this property](https://developer.mozilla.org/en-US/docs/Web/API/IDBIndex/openCursor#direction).
+    pub fn direction(mut self, direction: CursorDirection) -> Self {
+        self.direction = direction.to_sys();
+        self
+    }
+}
+
+/// Common implementation of [`Cursor`] and [`KeyCursor`]
+struct RawCursor<Err> {
+    sys: Option<IdbCursor>,
+    req: IdbRequest,
+    _phantom: PhantomData<Err>,
+}
+
+impl<Err> RawCursor<Err> {
+    async fn from(req: IdbRequest, ctx: RequestContext) -> crate::Result<RawCursor<Err>, Err> {
+        let res = transaction_request(req.clone())
+            .await
+            .map_err(|err| ctx.apply(map_open_cursor_err(err)))?;
+        let is_already_over = res.is_null();
+        let sys = (!is_already_over).then(|| {
+            res.dyn_into::<IdbCursor>()
+                .expect("Cursor-returning request did not return an IDBCursor")
+        });
+        Ok(RawCursor {
+            sys,
+            req,
+            _phantom: PhantomData,
+        })
+    }
+
+    fn key(&self) -> Option<Key> {
+        self.sys.as_ref().map(cursor_key)
+    }
+
+    fn primary_key(&self) -> Option<Key> {
+        self.sys.as_ref().map(cursor_primary_key)
+    }
+
+    async fn advance(&mut self, count: u32) -> crate::Result<(), Err> {
+        let Some(sys) = &self.sys else {
+            return Err(crate::Error::CursorCompleted);
+        };
+        let ctx = cursor_context(sys, "Cursor::advance");
+        sys.advance(count)
+            .map_err(|err| ctx.apply(map_cursor_advance_err(err)))?;
+        if transaction_request(self.req.clone())
+            .await
+            .map_err(|err| ctx.apply(map_cursor_advance_err(err)))?
+            .is_null()
+        {
+            self.sys = None;
+        }
+        Ok(())
+    }
+
+    async fn advance_until(&mut self, key: &Key) -> crate::Result<(), Err> {
+        let Some(sys) = &self.sys else {
+            return Err(crate::Error::CursorCompleted);
+        };
+        let ctx = cursor_context(sys, "Cursor::advance_until").key(key);
+        sys.continue_with_key(&key.to_js())
+            .map_err(|err| ctx.apply(map_cursor_advance_until_err(err)))?;
+        if transaction_request(self.req.clone())
+            .await
+            .map_err(|err| ctx.apply(map_cursor_advance_until_err(err)))?
+            .is_null()
+        {
+            self.sys = None;
+        }
+        Ok(())
+    }
+
+    async fn advance_until_primary_key(
+        &mut self,
+        index_key: &Key,
+        primary_key: &Key,
+    ) -> crate::Result<(), Err> {
+        let Some(sys) = &self.sys else {
+            return Err(crate::Error::CursorCompleted);
+        };
+        let ctx = cursor_context(sys, "Cursor::advance_until_primary_key").key(primary_key);
+        sys.continue_primary_key(&index_key.to_js(), &primary_key.to_js())
+            .map_err(|err| ctx.apply(map_cursor_advance_until_primary_key_err(err)))?;
+        if transaction_request(self.req.clone())
+            .await
+            .map_err(|err| ctx.apply(map_cursor_advance_until_primary_key_err(err)))?
+            .is_null()
+        {
+            self.sys = None;
+        }
+        Ok(())
+    }
+}
+
+fn cursor_context(sys: &IdbCursor, operation: &'static str) -> RequestContext {
+    RequestContext::new(operation).source(&sys.source())
+}
+
+fn cursor_key(sys: &IdbCursor) -> Key {
+    let key = sys
+        .key()
+        .expect("Failed retrieving key from known-good cursor");
+    Key::from_js(&key).expect("Known-good cursor returned an invalid key")
+}
+
+fn cursor_primary_key(sys: &IdbCursor) -> Key {
+    let key = sys
+        .primary_key()
+        .expect("Failed retrieving primary key from known-good cursor");
+    Key::from_js(&key).expect("Known-good cursor returned an invalid primary key")
+}
+
+fn cursor_value(sys: &IdbCursor) -> JsValue {
+    sys.unchecked_ref::<IdbCursorWithValue>()
+        .value()
+        .expect("Unable to retrieve value from known-good cursor")
+}
+
+/// Wrapper for [`IDBCursorWithValue`](https://developer.mozilla.org/en-US/docs/Web/API/IDBCursorWithValue)
+pub struct Cursor<Err> {
+    raw: RawCursor<Err>,
+}
+
+impl<Err> Cursor<Err> {
+    pub(crate) async fn from(
+        req: IdbRequest,
+        ctx: RequestContext,
+    ) -> crate::Result<Cursor<Err>, Err> {
+        Ok(Cursor {
+            raw: RawCursor::from(req, ctx).await?,
+        })
+    }
+
+    /// Retrieve the value this [`Cursor`] is currently pointing at, or `None` if the cursor is completed
+    ///
+    /// See [`crate::Cursor::value`].
+    ///
+    /// Internally, this uses the [`IDBCursorWithValue::value`](https://developer.mozilla.org/en-US/docs/Web/API/IDBCursorWithValue/value) property.
+    pub fn value(&self) -> Option<JsValue> {
+        self.raw.sys.as_ref().map(cursor_value)
+    }
+
+    /// Retrieve the key this [`Cursor`] is currently pointing at, or `None` if the cursor is completed
+    ///
+    /// See [`crate::Cursor::key`].
+    ///
+    /// Internally, this uses the [`IDBCursor::key`](https://developer.mozilla.org/en-US/docs/Web/API/IDBCursor/key) property.
+    pub fn key(&self) -> Option<Key> {
+        self.raw.key()
+    }
+
+    /// Retrieve the primary key this [`Cursor`] is currently pointing at, or `None` if the cursor is completed
+    ///
+    /// See [`crate::Cursor::primary_key`].
+    ///
+    /// Internally, this uses the [`IDBCursor::primaryKey`](https://developer.mozilla.org/en-US/docs/Web/API/IDBCursor/key) property.
+    pub fn primary_key(&self) -> Option<Key> {
+        self.raw.primary_key()
+    }
+
+    /// Advance this [`Cursor`] by `count` elements
+    ///
+    /// See [`crate::Cursor::advance`].
+    ///
+    /// Internally, this uses [`IDBCursor::advance`](https://developer.mozilla.org/en-US/docs/Web/API/IDBCursor/advance).
+    pub async fn advance(&mut self, count: u32) -> crate::Result<(), Err> {
+        self.raw.advance(count).await
+    }
+
+    /// Advance this [`Cursor`] until the provided key
+    ///
+    /// See [`crate::Cursor::advance_until`].
+    ///
+    /// Internally, this uses [`IDBCursor::continue`](https://developer.mozilla.org/en-US/docs/Web/API/IDBCursor/continue).
+    pub async fn advance_until(&mut self, key: impl Into<Key>) -> crate::Result<(), Err> {
+        self.raw.advance_until(&key.into()).await
+    }
+
+    /// Advance this [`Cursor`] until the provided primary key
+    ///
+    /// See [`crate::Cursor::advance_until_primary_key`].
+    ///
+    /// Internally, this uses [`IDBCursor::continuePrimaryKey`](https://developer.mozilla.org/en-US/docs/Web/API/IDBCursor/continuePrimaryKey).
+    pub async fn advance_until_primary_key(
+        &mut self,
+        index_key: impl Into<Key>,
+        primary_key: impl Into<Key>,
+    ) -> crate::Result<(), Err> {
+        self.raw
+            .advance_until_primary_key(&index_key.into(), &primary_key.into())
+            .await
+    }
+
+    /// Deletes the value currently pointed by this [`Cursor`]
+    ///
+    /// See [`crate::Cursor::delete`].
+    ///
+    /// Internally, this uses [`IDBCursor::delete`](https://developer.mozilla.org/en-US/docs/Web/API/IDBCursor/delete).
+    pub async fn delete(&self) -> crate::Result<(), Err> {
+        let Some(sys) = &self.raw.sys else {
+            return Err(crate::Error::CursorCompleted);
+        };
+        let ctx = cursor_context(sys, "Cursor::delete").key(&cursor_primary_key(sys));
+        let req = sys
+            .delete()
+            .map_err(|err| ctx.apply(map_cursor_delete_err(err)))?;
+        transaction_request(req)
+            .await
+            .map_err(|err| ctx.apply(map_cursor_delete_err(err)))?;
+        Ok(())
+    }
+
+    /// Update the value currently pointed by this [`Cursor`] to `value`
+    ///
+    /// See [`crate::Cursor::update`].
+    ///
+    /// Internally, this uses [`IDBCursor::update`](https://developer.mozilla.org/en-US/docs/Web/API/IDBCursor/update).
+    pub async fn update(&self, value: &JsValue) -> crate::Result<(), Err> {
+        let Some(sys) = &self.raw.sys else {
+            return Err(crate::Error::CursorCompleted);
+        };
+        let ctx = cursor_context(sys, "Cursor::update").key(&cursor_primary_key(sys));
+        let req = sys
+            .update(value)
+            .map_err(|err| ctx.apply(map_cursor_update_err(err)))?;
+        transaction_request(req)
+            .await
+            .map_err(|err| ctx.apply(map_cursor_update_err(err)))?;
+        Ok(())
+    }
+}
+
+/// Wrapper for a key-only [`IDBCursor`](https://developer.mozilla.org/en-US/docs/Web/API/IDBCursor)
+///
+/// Unlike [`Cursor`], this does not load the values, and thus provides neither `value`, nor `update`
+/// nor `delete`, as IndexedDB rejects modifications through key-only cursors, be they over object
+/// stores or indexes.
+pub struct KeyCursor<Err> {
+    raw: RawCursor<Err>,
+}
+
+impl<Err> KeyCursor<Err> {
+    pub(crate) async fn from(
+        req: IdbRequest,
+        ctx: RequestContext,
+    ) -> crate::Result<KeyCursor<Err>, Err> {
+        Ok(KeyCursor {
+            raw: RawCursor::from(req, ctx).await?,
+        })
+    }
+
+    /// Retrieve the key this [`KeyCursor`] is currently pointing at, or `None` if the cursor is completed
+    ///
+    /// See [`crate::KeyCursor::key`].
+    ///
+    /// Internally, this uses the [`IDBCursor::key`](https://developer.mozilla.org/en-US/docs/Web/API/IDBCursor/key) property.
+    pub fn key(&self) -> Option<Key> {
+        self.raw.key()
+    }
+
+    /// Retrieve the primary key this [`KeyCursor`] is currently pointing at, or `None` if the cursor is completed
+    ///
+    /// See [`crate::KeyCursor::primary_key`].
+    ///
+    /// Internally, this uses the [`IDBCursor::primaryKey`](https://developer.mozilla.org/en-US/docs/Web/API/IDBCursor/key) property.
+    pub fn primary_key(&self) -> Option<Key> {
+        self.raw.primary_key()
+    }
+
+    /// Advance this [`KeyCursor`] by `count` elements
+    ///
+    /// See [`crate::KeyCursor::advance`].
+    ///
+    /// Internally, this uses [`IDBCursor::advance`](https://developer.mozilla.org/en-US/docs/Web/API/IDBCursor/advance).
+    pub async fn advance(&mut self, count: u32) -> crate::Result<(), Err> {
+        self.raw.advance(count).await
+    }
+
+    /// Advance this [`KeyCursor`] until the provided key
+    ///
+    /// See [`crate::KeyCursor::advance_until`].
+    ///
+    /// Internally, this uses [`IDBCursor::continue`](https://developer.mozilla.org/en-US/docs/Web/API/IDBCursor/continue).
+    pub async fn advance_until(&mut self, key: impl Into<Key>) -> crate::Result<(), Err> {
+        self.raw.advance_until(&key.into()).await
+    }
+
+    /// Advance this [`KeyCursor`] until the provided primary key
+    ///
+    /// See [`crate::KeyCursor::advance_until_primary_key`].
+    ///
+    /// Internally, this uses [`IDBCursor::continuePrimaryKey`](https://developer.mozilla.org/en-US/docs/Web/API/IDBCursor/continuePrimaryKey).
+    pub async fn advance_until_primary_key(
+        &mut self,
+        index_key: impl Into<Key>,
+        primary_key: impl Into<Key>,
+    ) -> crate::Result<(), Err> {
+        self.raw
+            .advance_until_primary_key(&index_key.into(), &primary_key.into())
+            .await
+    }
+}
@@ -0,0 +1,200 @@
+use super::TransactionBuilder;
+use crate::{utils::dom_string_list_to_vec, ErrorContext};
+use futures_channel::mpsc;
+use futures_util::{Stream, StreamExt};
+use std::{
+    convert::Infallible,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use web_sys::{
+    wasm_bindgen::{closure::Closure, JsCast},
+    IdbDatabase, IdbRequest, IdbTransaction, IdbVersionChangeEvent,
+};
+
+/// Wrapper for [`IDBDatabase`](https://developer.mozilla.org/en-US/docs/Web/API/IDBDatabase)
+///
+/// This does not close the connection when dropped, [`crate::OwnedDatabase`] takes care of it.
+#[derive(Clone, Debug)]
+pub struct Database {
+    sys: IdbDatabase,
+}
+
+impl Database {
+    pub(crate) fn from_sys(sys: IdbDatabase) -> Database {
+        Database { sys }
+    }
+
+    pub(crate) fn as_sys(&self) -> &IdbDatabase {
+        &self.sys
+    }
+
+    /// The name of this database
+    ///
+    /// See [`crate::Database::name`].
+    ///
+    /// Internally, this uses [`IDBDatabase::name`](https://developer.mozilla.org/en-US/docs/Web/API/IDBDatabase/name).
+    pub fn name(&self) -> String {
+        self.sys.name()
+    }
+
+    /// The version of this database, clamped at `u32::MAX`
+    ///
+    /// See [`crate::Database::version`].
+    ///
+    /// Internally, this uses [`IDBDatabase::version`](https://developer.mozilla.org/en-US/docs/Web/API/IDBDatabase/version).
+    pub fn version(&self) -> u32 {
+        self.sys.version() as u32
+    }
+
+    /// The names of all [`ObjectStore`]s in this [`Database`]
+    ///
+    /// See [`crate::Database::object_store_names`].
+    ///
+    /// Internally, this uses [`IDBDatabase::objectStoreNames`](https://developer.mozilla.org/en-US/docs/Web/API/IDBDatabase/objectStoreNames).
+    pub fn object_store_names(&self) -> Vec<String> {
+        dom_string_list_to_vec(self.sys.object_store_names())
+    }
+
+    /// Stream of the `versionchange` events fired on this connection
+    ///
+    /// See [`crate::Database::on_version_change`].
+    ///
+    /// Internally, this uses [`IDBDatabase::versionchange`](https://developer.mozilla.org/en-US/docs/Web/API/IDBDatabase/versionchange_event).
+    pub fn on_version_change(&self) -> EventStream<VersionChange> {
+        EventStream::new(&self.sys, "versionchange", |evt| {
+            let evt = evt
+                .dyn_into::<IdbVersionChangeEvent>()
+                .expect("versionchange event was not an IDBVersionChangeEvent");
+            VersionChange {
+                old_version: evt.old_version() as u32,
+                new_version: evt.new_version().map(|v| v as u32),
+            }
+        })
+    }
+
+    /// Stream of the `close` events fired on this connection
+    ///
+    /// See [`crate::Database::on_close`].
+    ///
+    /// Internally, this uses [`IDBDatabase::close`](https://developer.mozilla.org/en-US/docs/Web/API/IDBDatabase/close_event).
+    pub fn on_close(&self) -> EventStream<()> {
+        EventStream::new(&self.sys, "close", |_| ())
+    }
+
+    /// Stream of the `abort` events bubbling up from the transactions of this connection
+    ///
+    /// See [`crate::Database::on_abort`].
+    ///
+    /// Internally, this uses [`IDBDatabase::abort`](https://developer.mozilla.org/en-US/docs/Web/API/IDBDatabase/abort_event).
+    pub fn on_abort(&self) -> EventStream<crate::Error<Infallible>> {
+        EventStream::new(&self.sys, "abort", |evt| {
+            event_error(&evt).unwrap_or_else(|| {
+                crate::Error::Aborted(ErrorContext::new("The transaction was aborted"))
+            })
+        })
+    }
+
+    /// Stream of the `error` events bubbling up from the requests of this connection
+    ///
+    /// See [`crate::Database::on_error`].
+    ///
+    /// Internally, this uses [`IDBDatabase::error`](https://developer.mozilla.org/en-US/docs/Web/API/IDBDatabase/error_event).
+    pub fn on_error(&self) -> EventStream<crate::Error<Infallible>> {
+        EventStream::new(&self.sys, "error", |evt| {
+            event_error(&evt).unwrap_or_else(|| {
+                crate::Error::Unknown(ErrorContext::new("A request failed without an error"))
+            })
+        })
+    }
+
+    /// Run a transaction
+    ///
+    /// See [`crate::Database::transaction`].
+    pub fn transaction(&self, stores: &[&str]) -> TransactionBuilder {
+        TransactionBuilder::from_names(self.sys.clone(), stores)
+    }
+
+    /// Closes this database connection
+    ///
+    /// See [`crate::Database::close`].
+    ///
+    /// Internally, this uses [`IDBDatabase::close`](https://developer.mozilla.org/en-US/docs/Web/API/IDBDatabase/close).
+    pub fn close(&self) {
+        self.sys.close();
+    }
+}
+
+/// Details about a `versionchange` event, as returned by [`Database::on_version_change`](crate::Database::on_version_change)
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct VersionChange {
+    /// The current version of the database, clamped to `u32::MAX`
+    pub old_version: u32,
+
+    /// The version requested by the other connection, clamped to `u32::MAX`, or `None` if the database is
+    /// being deleted
+    pub new_version: Option<u32>,
+}
+
+/// Error carried by an `abort` or `error` event, whether it was fired by a request or a transaction
+///
+/// Unlike `utils::err_from_event`, this does not prevent the default action of the event.
+fn event_error(evt: &web_sys::Event) -> Option<crate::Error<Infallible>> {
+    let target = evt.target()?;
+    let err = if let Some(req) = target.dyn_ref::<IdbRequest>() {
+        req.error().ok().flatten()
+    } else {
+        target.dyn_ref::<IdbTransaction>()?.error()
+    };
+    err.map(crate::Error::from_dom_exception)
+}
+
+/// Stream of events fired on a [`Database`](crate::Database)
+///
+/// The underlying event listener is removed when this stream is dropped.
+pub struct EventStream<T> {
+    target: IdbDatabase,
+    event: &'static str,
+    listener: Closure<dyn FnMut(web_sys::Event)>,
+    events: mpsc::UnboundedReceiver<T>,
+}
+
+impl<T: 'static> EventStream<T> {
+    fn new(
+        target: &IdbDatabase,
+        event: &'static str,
+        parse: impl 'static + Fn(web_sys::Event) -> T,
+    ) -> EventStream<T> {
+        let (tx, events) = mpsc::unbounded();
+        let listener = Closure::<dyn FnMut(_)>::new(move |evt| {
+            let _ = tx.unbounded_send(parse(evt));
+        });
+        target
+            .add_event_listener_with_callback(event, listener.as_ref().unchecked_ref())
+            .expect("Failed adding an event listener to an IDBDatabase");
+        EventStream {
+            target: target.clone(),
+            event,
+            listener,
+            events,
+        }
+    }
+}
+
+impl<T> Stream for EventStream<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.events.poll_next_unpin(cx)
+    }
+}
+
+impl<T> Drop for EventStream<T> {
+    fn drop(&mut self) {
+        let _ = self.target.remove_event_listener_with_callback(
+            self.event,
+            self.listener.as_ref().unchecked_ref(),
+        );
+    }
+}
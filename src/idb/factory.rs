@@ -0,0 +1,475 @@
+use super::{
+    transaction::{unsafe_jar, RunnableTransaction, TransactionResult},
+    Database, ObjectStore, Transaction,
+};
+use crate::{
+    error::RequestContext,
+    utils::{
+        array_to_vec, await_promise, non_transaction_request, open_db_request,
+        transaction_blocked_err, transaction_inactive_err, BlockedOptions,
+    },
+    DatabaseInfo, ErrorContext, KeyPath,
+};
+use futures_util::{pin_mut, FutureExt};
+use std::{
+    cell::{Cell, RefCell},
+    convert::Infallible,
+    time::Duration,
+};
+use wasm_bindgen::prelude::wasm_bindgen;
+use web_sys::{
+    js_sys::{self, Function, JsString, Promise, Reflect, TypeError},
+    wasm_bindgen::{closure::Closure, JsCast, JsValue},
+    IdbDatabase, IdbFactory, IdbObjectStoreParameters, IdbOpenDbRequest, IdbTransaction,
+    IdbVersionChangeEvent, WorkerGlobalScope,
+};
+
+/// Wrapper for [`IDBFactory`](https://developer.mozilla.org/en-US/docs/Web/API/IDBFactory)
+#[derive(Clone, Debug)]
+pub struct Factory {
+    sys: IdbFactory,
+}
+
+impl Factory {
+    /// Retrieve the global `Factory` from the browser
+    ///
+    /// See [`crate::Factory::get`].
+    ///
+    /// This internally uses [`indexedDB`](https://developer.mozilla.org/en-US/docs/Web/API/indexedDB).
+    pub fn get() -> crate::Result<Factory, Infallible> {
+        let indexed_db = if let Some(window) = web_sys::window() {
+            window.indexed_db()
+        } else if let Ok(worker_scope) = js_sys::global().dyn_into::<WorkerGlobalScope>() {
+            worker_scope.indexed_db()
+        } else {
+            return Err(crate::Error::NotInBrowser);
+        };
+
+        let sys = indexed_db
+            .map_err(|_| crate::Error::IndexedDbDisabled)?
+            .ok_or(crate::Error::IndexedDbDisabled)?;
+
+        Ok(Factory { sys })
+    }
+
+    /// Compare two keys for ordering
+    ///
+    /// See [`crate::Factory::cmp`].
+    ///
+    /// This internally uses [`IDBFactory::cmp`](https://developer.mozilla.org/en-US/docs/Web/API/IDBFactory/cmp).
+    pub fn cmp(
+        &self,
+        lhs: &JsValue,
+        rhs: &JsValue,
+    ) -> crate::Result<std::cmp::Ordering, Infallible> {
+        use std::cmp::Ordering::*;
+        self.sys
+            .cmp(lhs, rhs)
+            .map(|v| match v {
+                -1 => Less,
+                0 => Equal,
+                1 => Greater,
+                v => panic!("Unexpected result of IDBFactory::cmp: {v}"),
+            })
+            .map_err(|e| match error_name!(&e) {
+                Some("DataError") => crate::Error::InvalidKey(ErrorContext::from_js(&e)),
+                _ => crate::Error::from_js_value(e),
+            })
+    }
+
+    /// List the databases that currently exist
+    ///
+    /// See [`crate::Factory::databases`].
+    ///
+    /// Returns [`Error::OperationNotSupported`](crate::Error::OperationNotSupported) if the browser does
+    /// not support enumerating databases.
+    ///
+    /// This internally uses [`IDBFactory::databases`](https://developer.mozilla.org/en-US/docs/Web/API/IDBFactory/databases).
+    pub async fn databases(&self) -> crate::Result<Vec<DatabaseInfo>, Infallible> {
+        let promise = self
+            .sys
+            .unchecked_ref::<IdbFactoryWithDatabases>()
+            .databases()
+            .map_err(|err| match error_name!(&err) {
+                None if err.has_type::<TypeError>() => {
+                    crate::Error::OperationNotSupported(ErrorContext::from_js(&err))
+                }
+                _ => crate::Error::from_js_value(err),
+            })?;
+        let databases = await_promise(promise)
+            .await
+            .map_err(crate::Error::from_js_value)?;
+        array_to_vec(databases)
+            .into_iter()
+            .map(database_info)
+            .collect()
+    }
+
+    /// Delete a database
+    ///
+    /// See [`crate::Factory::delete_database`].
+    ///
+    /// This internally uses [`IDBFactory::deleteDatabase`](https://developer.mozilla.org/en-US/docs/Web/API/IDBFactory/deleteDatabase)
+    pub async fn delete_database(&self, name: &str) -> crate::Result<(), Infallible> {
+        self.build_delete_database(name).delete().await
+    }
+
+    /// Build a database deletion
+    ///
+    /// See [`crate::Factory::build_delete_database`].
+    pub fn build_delete_database<'a>(&'a self, name: &'a str) -> DeleteDatabaseBuilder<'a> {
+        DeleteDatabaseBuilder {
+            factory: &self.sys,
+            name,
+            blocked: BlockedOptions::new(),
+        }
+    }
+
+    /// Open a database
+    ///
+    /// Returns an error if something failed while opening or upgrading the database.
+    /// Blocks until it can actually open the database. Use [`Factory::build_open`] for more control
+    /// over what happens while the opening is blocked.
+    ///
+    /// Note that `version` must be at least `1`. `on_upgrade_needed` will be called when `version` is higher
+    /// than the previous database version, or upon database creation.
+    ///
+    /// This internally uses [`IDBFactory::open`](https://developer.mozilla.org/en-US/docs/Web/API/IDBFactory/open)
+    /// as well as the methods from [`IDBOpenDBRequest`](https://developer.mozilla.org/en-US/docs/Web/API/IDBOpenDBRequest)
+    // TODO: once the try_trait_v2 feature is stabilized, we can finally stop carrying any `Err` generic
+    pub async fn open<Err: 'static>(
+        &self,
+        name: &str,
+        version: u32,
+        on_upgrade_needed: impl AsyncFnOnce(VersionChangeEvent<Err>) -> crate::Result<(), Err>,
+    ) -> crate::Result<Database, Err> {
+        self.build_open(name, version).open(on_upgrade_needed).await
+    }
+
+    /// Build a database opening
+    ///
+    /// See [`crate::Factory::build_open`].
+    pub fn build_open<'a>(&'a self, name: &'a str, version: u32) -> OpenBuilder<'a> {
+        OpenBuilder {
+            factory: &self.sys,
+            name,
+            version,
+            blocked: BlockedOptions::new(),
+        }
+    }
+
+    /// Open a database at the latest version
+    ///
+    /// See [`crate::Factory::open_latest_version`].
+    ///
+    /// This internally uses [`IDBFactory::open`](https://developer.mozilla.org/en-US/docs/Web/API/IDBFactory/open)
+    /// as well as the methods from [`IDBOpenDBRequest`](https://developer.mozilla.org/en-US/docs/Web/API/IDBOpenDBRequest)
+    pub async fn open_latest_version(&self, name: &str) -> crate::Result<Database, Infallible> {
+        let open_req = self.sys.open(name).map_err(crate::Error::from_js_value)?;
+
+        let completion_fut = non_transaction_request(open_req.clone().into())
+            .map(|res| res.map_err(crate::Error::from_js_event));
+        pin_mut!(completion_fut);
+
+        completion_fut.await?;
+
+        let db = open_req
+            .result()
+            .map_err(crate::Error::from_js_value)?
+            .dyn_into::<IdbDatabase>()
+            .expect("Result of successful IDBOpenDBRequest is not an IDBDatabase");
+
+        Ok(Database::from_sys(db))
+    }
+}
+
+/// Helper to open a database
+pub struct OpenBuilder<'a> {
+    factory: &'a IdbFactory,
+    name: &'a str,
+    version: u32,
+    blocked: BlockedOptions<'a>,
+}
+
+impl<'a> OpenBuilder<'a> {
+    /// Open the database
+    ///
+    /// See [`crate::OpenBuilder::open`].
+    ///
+    /// This internally uses [`IDBFactory::open`](https://developer.mozilla.org/en-US/docs/Web/API/IDBFactory/open)
+    /// as well as the methods from [`IDBOpenDBRequest`](https://developer.mozilla.org/en-US/docs/Web/API/IDBOpenDBRequest)
+    pub async fn open<Err: 'static>(
+        self,
+        on_upgrade_needed: impl AsyncFnOnce(VersionChangeEvent<Err>) -> crate::Result<(), Err>,
+    ) -> crate::Result<Database, Err> {
+        if self.version == 0 {
+            return Err(crate::Error::VersionMustNotBeZero);
+        }
+
+        let open_req = self
+            .factory
+            .open_with_u32(self.name, self.version)
+            .map_err(crate::Error::from_js_value)?;
+
+        let result = RefCell::new(None);
+        let result = &result;
+        let (finished_tx, finished_rx) = futures_channel::oneshot::channel();
+        let ran_upgrade_cb = Cell::new(false);
+        let ran_upgrade_cb = &ran_upgrade_cb;
+
+        unsafe_jar::extend_lifetime_to_scope_and_run(
+            Box::new(
+                move |(transaction, event): (IdbTransaction, VersionChangeEvent<Err>)| {
+                    let fut = async move {
+                        ran_upgrade_cb.set(true);
+                        on_upgrade_needed(event).await
+                    };
+                    RunnableTransaction::new(transaction, fut, result, finished_tx)
+                },
+            ),
+            async move |s| {
+                // Separate variable to keep the closure alive until opening completed
+                let on_upgrade_needed = Closure::once(move |evt: IdbVersionChangeEvent| {
+                    let evt = VersionChangeEvent::from_sys(evt);
+                    let transaction = evt.transaction().as_sys().clone();
+                    s.run((transaction, evt))
+                });
+                open_req.set_onupgradeneeded(Some(
+                    on_upgrade_needed.as_ref().dyn_ref::<Function>().unwrap(),
+                ));
+
+                let Some(completion_res) = open_db_request(&open_req, self.blocked).await else {
+                    return Err(crate::Error::Blocked);
+                };
+                if ran_upgrade_cb.get() {
+                    // The upgrade callback was run, so we need to wait for its result to reach us
+                    let _ = finished_rx.await;
+                    let result = result
+                        .borrow_mut()
+                        .take()
+                        .expect("Finished was called without the result being available");
+                    match result {
+                        TransactionResult::PolledForbiddenThing => {
+                            return Err(transaction_blocked_err())
+                        }
+                        TransactionResult::Done(upgrade_res) => upgrade_res?,
+                    }
+                }
+                completion_res.map_err(crate::Error::from_js_event)?;
+
+                let db = open_req
+                    .result()
+                    .map_err(crate::Error::from_js_value)?
+                    .dyn_into::<IdbDatabase>()
+                    .expect("Result of successful IDBOpenDBRequest is not an IDBDatabase");
+
+                Ok(Database::from_sys(db))
+            },
+        )
+        .await
+    }
+
+    /// Call `on_blocked` whenever other connections prevent the database from being upgraded
+    ///
+    /// See [`crate::OpenBuilder::on_blocked`].
+    ///
+    /// Internally, this uses [`IDBOpenDBRequest::blocked`](https://developer.mozilla.org/en-US/docs/Web/API/IDBOpenDBRequest/blocked_event).
+    pub fn on_blocked(mut self, on_blocked: impl 'a + FnMut(u32, Option<u32>)) -> Self {
+        self.blocked.on_blocked = Some(Box::new(on_blocked));
+        self
+    }
+
+    /// Fail with [`Error::Blocked`](crate::Error::Blocked) if the opening stays blocked for longer than `timeout`
+    ///
+    /// See [`crate::OpenBuilder::blocked_timeout`].
+    pub fn blocked_timeout(mut self, timeout: Duration) -> Self {
+        self.blocked.timeout = Some(timeout);
+        self
+    }
+}
+
+/// Helper to delete a database
+pub struct DeleteDatabaseBuilder<'a> {
+    factory: &'a IdbFactory,
+    name: &'a str,
+    blocked: BlockedOptions<'a>,
+}
+
+impl<'a> DeleteDatabaseBuilder<'a> {
+    /// Delete the database
+    ///
+    /// Returns an error if something failed during the deletion. Note that trying to delete
+    /// a database that does not exist will result in a successful result.
+    ///
+    /// This internally uses [`IDBFactory::deleteDatabase`](https://developer.mozilla.org/en-US/docs/Web/API/IDBFactory/deleteDatabase)
+    pub async fn delete(self) -> crate::Result<(), Infallible> {
+        let req = self
+            .factory
+            .delete_database(self.name)
+            .map_err(crate::Error::from_js_value)?;
+        open_db_request(&req, self.blocked)
+            .await
+            .ok_or(crate::Error::Blocked)?
+            .map(|_| ())
+            .map_err(crate::Error::from_js_event)
+    }
+
+    /// Call `on_blocked` whenever other connections prevent the database from being deleted
+    ///
+    /// `on_blocked` receives the current version of the database, and `None` as the requested version.
+    /// This is typically used to ask the user to close other tabs of the application.
+    ///
+    /// Internally, this uses [`IDBOpenDBRequest::blocked`](https://developer.mozilla.org/en-US/docs/Web/API/IDBOpenDBRequest/blocked_event).
+    pub fn on_blocked(mut self, on_blocked: impl 'a + FnMut(u32, Option<u32>)) -> Self {
+        self.blocked.on_blocked = Some(Box::new(on_blocked));
+        self
+    }
+
+    /// Fail with [`Error::Blocked`](crate::Error::Blocked) if the deletion stays blocked for longer than `timeout`
+    ///
+    /// The timeout starts when the deletion first gets blocked. Without this, deletion waits for as long as
+    /// other connections stay open.
+    pub fn blocked_timeout(mut self, timeout: Duration) -> Self {
+        self.blocked.timeout = Some(timeout);
+        self
+    }
+}
+
+#[wasm_bindgen]
+extern "C" {
+    // TODO: remove once web-sys has `IDBFactory::databases`
+    #[wasm_bindgen(extends = IdbFactory)]
+    type IdbFactoryWithDatabases;
+
+    #[wasm_bindgen(method, catch)]
+    fn databases(this: &IdbFactoryWithDatabases) -> Result<Promise, JsValue>;
+}
+
+/// Parse an entry of the list returned by `IDBFactory::databases`
+fn database_info<Err>(sys: JsValue) -> crate::Result<DatabaseInfo, Err> {
+    let field = |name: &str| Reflect::get(&sys, &JsString::from(name)).ok();
+    let (Some(name), Some(version)) = (
+        field("name").and_then(|n| n.as_string()),
+        field("version").and_then(|v| v.as_f64()),
+    ) else {
+        return Err(crate::Error::Unknown(ErrorContext::new(
+            "The browser listed a database without a name or a version",
+        )));
+    };
+    Ok(DatabaseInfo {
+        name,
+        version: version as u32,
+    })
+}
+
+/// Wrapper for [`IDBVersionChangeEvent`](https://developer.mozilla.org/en-US/docs/Web/API/IDBVersionChangeEvent)
+#[derive(Debug)]
+pub struct VersionChangeEvent<Err> {
+    sys: IdbVersionChangeEvent,
+    db: Database,
+    transaction: Transaction<Err>,
+}
+
+impl<Err> VersionChangeEvent<Err> {
+    fn from_sys(sys: IdbVersionChangeEvent) -> VersionChangeEvent<Err> {
+        let db_req = sys
+            .target()
+            .expect("IDBVersionChangeEvent had no target")
+            .dyn_into::<IdbOpenDbRequest>()
+            .expect("IDBVersionChangeEvent target was not an IDBOpenDBRequest");
+        let db_sys = db_req
+            .result()
+            .expect("IDBOpenDBRequest had no result in its on_upgrade_needed handler")
+            .dyn_into::<IdbDatabase>()
+            .expect("IDBOpenDBRequest result was not an IDBDatabase");
+        let transaction_sys = db_req
+            .transaction()
+            .expect("IDBOpenDBRequest had no associated transaction");
+        let db = Database::from_sys(db_sys);
+        let transaction = Transaction::from_sys(transaction_sys);
+        VersionChangeEvent {
+            sys,
+            db,
+            transaction,
+        }
+    }
+
+    /// The version before the database upgrade, clamped to `u32::MAX`
+    ///
+    /// See [`crate::VersionChangeEvent::old_version`].
+    ///
+    /// Internally, this uses [`IDBVersionChangeEvent::oldVersion`](https://developer.mozilla.org/en-US/docs/Web/API/IDBVersionChangeEvent/oldVersion)
+    pub fn old_version(&self) -> u32 {
+        self.sys.old_version() as u32
+    }
+
+    /// The version after the database upgrade, clamped to `u32::MAX`
+    ///
+    /// See [`crate::VersionChangeEvent::new_version`].
+    ///
+    /// Internally, this uses [`IDBVersionChangeEvent::newVersion`](https://developer.mozilla.org/en-US/docs/Web/API/IDBVersionChangeEvent/newVersion)
+    pub fn new_version(&self) -> u32 {
+        self.sys
+            .new_version()
+            .expect("IDBVersionChangeEvent did not provide a new version") as u32
+    }
+
+    /// The database under creation
+    ///
+    /// See [`crate::VersionChangeEvent::database`].
+    pub fn database(&self) -> &Database {
+        &self.db
+    }
+
+    /// Create an [`ObjectStore`]
+    ///
+    /// Internally, this uses [`IDBDatabase::createObjectStore`](https://developer.mozilla.org/en-US/docs/Web/API/IDBDatabase/createObjectStore).
+    pub fn create_object_store(
+        &self,
+        name: &str,
+        key_path: Option<&KeyPath>,
+        auto_increment: bool,
+    ) -> crate::Result<ObjectStore<Err>, Err> {
+        let ctx = RequestContext::new("ObjectStoreBuilder::create").store_name(name);
+        let options = IdbObjectStoreParameters::new();
+        if let Some(key_path) = key_path {
+            options.set_key_path(&key_path.to_sys());
+        }
+        options.set_auto_increment(auto_increment);
+        self.db
+            .as_sys()
+            .create_object_store_with_optional_parameters(name, &options)
+            .map_err(
+                |err| ctx.apply(match error_name!(&err) {
+                    Some("InvalidStateError") => crate::Error::InvalidCall(ErrorContext::from_js(&err)),
+                    Some("TransactionInactiveError") => transaction_inactive_err(ErrorContext::from_js(&err), "Tried to create an object store with the `versionchange` transaction having already aborted"),
+                    Some("ConstraintError") => crate::Error::AlreadyExists(ErrorContext::from_js(&err)),
+                    Some("InvalidAccessError") => crate::Error::InvalidArgument(ErrorContext::from_js(&err)),
+                    _ => crate::Error::from_js_value(err),
+                }),
+            )
+            .map(ObjectStore::from_sys)
+    }
+
+    /// Deletes an [`ObjectStore`]
+    ///
+    /// See [`crate::VersionChangeEvent::delete_object_store`].
+    ///
+    /// Internally, this uses [`IDBDatabase::deleteObjectStore`](https://developer.mozilla.org/en-US/docs/Web/API/IDBDatabase/deleteObjectStore).
+    pub fn delete_object_store(&self, name: &str) -> crate::Result<(), Err> {
+        let ctx = RequestContext::new("VersionChangeEvent::delete_object_store").store_name(name);
+        self.db.as_sys().delete_object_store(name).map_err(|err| ctx.apply(match error_name!(&err) {
+                Some("InvalidStateError") => crate::Error::InvalidCall(ErrorContext::from_js(&err)),
+                Some("TransactionInactiveError") => transaction_inactive_err(ErrorContext::from_js(&err), "Tried to delete an object store with the `versionchange` transaction having already aborted"),
+                Some("NotFoundError") => crate::Error::DoesNotExist(ErrorContext::from_js(&err)),
+                _ => crate::Error::from_js_value(err),
+            }))
+    }
+
+    /// The `versionchange` transaction that triggered this event
+    ///
+    /// See [`crate::VersionChangeEvent::transaction`].
+    pub fn transaction(&self) -> &Transaction<Err> {
+        &self.transaction
+    }
+}
@@ -0,0 +1,300 @@
+use super::{transaction::transaction_request, CursorBuilder};
+use crate::{
+    error::RequestContext,
+    utils::{
+        array_to_keys, array_to_vec, key_from_js, make_defined_key_range, make_key_range,
+        map_count_err, map_count_res, map_get_err, none_if_undefined,
+    },
+    ErrorContext, Key, KeyPath,
+};
+use futures_util::future::{Either, FutureExt};
+use std::{future::Future, marker::PhantomData, ops::RangeBounds};
+use web_sys::{wasm_bindgen::JsValue, IdbIndex};
+
+#[cfg(doc)]
+use super::Cursor;
+
+/// Wrapper for [`IDBIndex`](https://developer.mozilla.org/en-US/docs/Web/API/IDBIndex),
+/// for use in transactions
+///
+/// Most of the functions here take a [`Key`] as the key(s) to use in the index. If the index was
+/// built with a compound key, then you should use eg. `Key::from((key_1, key_2))` as the key.
+pub struct Index<Err> {
+    sys: IdbIndex,
+    _phantom: PhantomData<Err>,
+}
+
+impl<Err> Index<Err> {
+    pub(crate) fn from_sys(sys: IdbIndex) -> Index<Err> {
+        Index {
+            sys,
+            _phantom: PhantomData,
+        }
+    }
+
+    fn context(&self, operation: &'static str) -> RequestContext {
+        RequestContext::new(operation).index(&self.sys)
+    }
+
+    /// The name of this index
+    ///
+    /// See [`crate::Index::name`].
+    ///
+    /// Internally, this uses [`IDBIndex::name`](https://developer.mozilla.org/en-US/docs/Web/API/IDBIndex/name).
+    pub fn name(&self) -> String {
+        self.sys.name()
+    }
+
+    /// The key path of this index
+    ///
+    /// See [`crate::Index::key_path`].
+    ///
+    /// Internally, this uses [`IDBIndex::keyPath`](https://developer.mozilla.org/en-US/docs/Web/API/IDBIndex/keyPath).
+    pub fn key_path(&self) -> crate::Result<KeyPath, Err> {
+        let sys = self.sys.key_path().map_err(crate::Error::from_js_value)?;
+        KeyPath::from_sys(sys)?.ok_or_else(|| {
+            crate::Error::Unknown(ErrorContext::new(
+                "The browser returned an index without a key path",
+            ))
+        })
+    }
+
+    /// Whether this index enforces unicity of its keys
+    ///
+    /// See [`crate::Index::is_unique`].
+    ///
+    /// Internally, this uses [`IDBIndex::unique`](https://developer.mozilla.org/en-US/docs/Web/API/IDBIndex/unique).
+    pub fn is_unique(&self) -> bool {
+        self.sys.unique()
+    }
+
+    /// Whether this index adds one entry per element when its key path resolves to an array
+    ///
+    /// See [`crate::Index::is_multi_entry`].
+    ///
+    /// Internally, this uses [`IDBIndex::multiEntry`](https://developer.mozilla.org/en-US/docs/Web/API/IDBIndex/multiEntry).
+    pub fn is_multi_entry(&self) -> bool {
+        self.sys.multi_entry()
+    }
+
+    /// Checks whether the provided key (for this index) already exists
+    ///
+    /// See [`crate::Index::contains`].
+    ///
+    /// Internally, this uses [`IDBIndex::count`](https://developer.mozilla.org/en-US/docs/Web/API/IDBIndex/count).
+    pub fn contains(&self, key: impl Into<Key>) -> impl Future<Output = crate::Result<bool, Err>> {
+        let key = key.into();
+        let ctx = self.context("Index::contains").key(&key);
+        match self.sys.count_with_key(&key.to_js()) {
+            Ok(count_req) => Either::Right(transaction_request(count_req).map(move |res| {
+                res.map_err(|e| ctx.apply(map_count_err(e)))
+                    .map(|n| map_count_res(n) != 0)
+            })),
+            Err(e) => Either::Left(std::future::ready(Err(ctx.apply(map_count_err(e))))),
+        }
+    }
+
+    /// Count all the keys (for this index) in the provided range
+    ///
+    /// See [`crate::Index::count_in`].
+    ///
+    /// Internally, this uses [`IDBIndex::count`](https://developer.mozilla.org/en-US/docs/Web/API/IDBIndex/count).
+    pub fn count_in(
+        &self,
+        range: impl RangeBounds<Key>,
+    ) -> impl Future<Output = crate::Result<usize, Err>> {
+        let ctx = self.context("Index::count_in");
+        let range = match make_key_range(range) {
+            Ok(range) => range,
+            Err(e) => return Either::Left(std::future::ready(Err(ctx.apply(e)))),
+        };
+        match self.sys.count_with_key(&range) {
+            Ok(count_req) => Either::Right(transaction_request(count_req).map(move |res| {
+                res.map_err(|e| ctx.apply(map_count_err(e)))
+                    .map(map_count_res)
+            })),
+            Err(e) => Either::Left(std::future::ready(Err(ctx.apply(map_count_err(e))))),
+        }
+    }
+
+    /// Get the object with key `key` for this index
+    ///
+    /// See [`crate::Index::get`].
+    ///
+    /// Internally, this uses [`IDBIndex::get`](https://developer.mozilla.org/en-US/docs/Web/API/IDBIndex/get).
+    pub fn get(
+        &self,
+        key: impl Into<Key>,
+    ) -> impl Future<Output = crate::Result<Option<JsValue>, Err>> {
+        let key = key.into();
+        let ctx = self.context("Index::get").key(&key);
+        match self.sys.get(&key.to_js()) {
+            Ok(get_req) => Either::Right(transaction_request(get_req).map(move |res| {
+                res.map_err(|e| ctx.apply(map_get_err(e)))
+                    .map(none_if_undefined)
+            })),
+            Err(err) => Either::Left(std::future::ready(Err(ctx.apply(map_get_err(err))))),
+        }
+    }
+
+    /// Get the first value with a key (for this index) in `range`, ordered by key (for this index)
+    ///
+    /// See [`crate::Index::get_first_in`].
+    ///
+    /// Internally, this uses [`IDBIndex::get`](https://developer.mozilla.org/en-US/docs/Web/API/IDBIndex/get).
+    pub fn get_first_in(
+        &self,
+        range: impl RangeBounds<Key>,
+    ) -> impl Future<Output = crate::Result<Option<JsValue>, Err>> {
+        let ctx = self.context("Index::get_first_in");
+        let range = match make_defined_key_range(range) {
+            Ok(range) => range,
+            Err(e) => return Either::Left(std::future::ready(Err(ctx.apply(e)))),
+        };
+        match self.sys.get(&range) {
+            Ok(get_req) => Either::Right(transaction_request(get_req).map(move |res| {
+                res.map_err(|e| ctx.apply(map_get_err(e)))
+                    .map(none_if_undefined)
+            })),
+            Err(e) => Either::Left(std::future::ready(Err(ctx.apply(map_get_err(e))))),
+        }
+    }
+
+    /// Get all the objects in the store, ordered by this index, with a maximum number of results of `limit`
+    ///
+    /// See [`crate::Index::get_all`].
+    ///
+    /// Internally, this uses [`IDBIndex::getAll`](https://developer.mozilla.org/en-US/docs/Web/API/IDBIndex/getAll).
+    pub fn get_all(
+        &self,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<JsValue>, Err>> {
+        let ctx = self.context("Index::get_all");
+        let get_req = match limit {
+            None => self.sys.get_all(),
+            Some(limit) => self
+                .sys
+                .get_all_with_key_and_limit(&JsValue::UNDEFINED, limit),
+        };
+        match get_req {
+            Ok(get_req) => Either::Right(
+                transaction_request(get_req)
+                    .map(move |res| res.map_err(|e| ctx.apply(map_get_err(e))).map(array_to_vec)),
+            ),
+            Err(err) => Either::Left(std::future::ready(Err(ctx.apply(map_get_err(err))))),
+        }
+    }
+
+    /// Get all the objects with a key (for this index) in the provided range, with a maximum number of
+    /// results of `limit`, ordered by this index
+    ///
+    /// See [`crate::Index::get_all_in`].
+    ///
+    /// Internally, this uses [`IDBIndex::getAll`](https://developer.mozilla.org/en-US/docs/Web/API/IDBIndex/getAll).
+    pub fn get_all_in(
+        &self,
+        range: impl RangeBounds<Key>,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<JsValue>, Err>> {
+        let ctx = self.context("Index::get_all_in");
+        let range = match make_key_range(range) {
+            Ok(range) => range,
+            Err(e) => return Either::Left(std::future::ready(Err(ctx.apply(e)))),
+        };
+        let get_req = match limit {
+            None => self.sys.get_all_with_key(&range),
+            Some(limit) => self.sys.get_all_with_key_and_limit(&range, limit),
+        };
+        match get_req {
+            Ok(get_req) => Either::Right(
+                transaction_request(get_req)
+                    .map(move |res| res.map_err(|e| ctx.apply(map_get_err(e))).map(array_to_vec)),
+            ),
+            Err(err) => Either::Left(std::future::ready(Err(ctx.apply(map_get_err(err))))),
+        }
+    }
+
+    /// Get the first existing primary key for an object that has a key (for this index) in the provided range
+    ///
+    /// See [`crate::Index::get_first_key_in`].
+    ///
+    /// Internally, this uses [`IDBIndex::getKey`](https://developer.mozilla.org/en-US/docs/Web/API/IDBIndex/getKey).
+    pub fn get_first_key_in(
+        &self,
+        range: impl RangeBounds<Key>,
+    ) -> impl Future<Output = crate::Result<Option<Key>, Err>> {
+        let ctx = self.context("Index::get_first_key_in");
+        let range = match make_defined_key_range(range) {
+            Ok(range) => range,
+            Err(e) => return Either::Left(std::future::ready(Err(ctx.apply(e)))),
+        };
+        match self.sys.get_key(&range) {
+            Ok(get_req) => Either::Right(transaction_request(get_req).map(move |res| {
+                res.map_err(|e| ctx.apply(map_get_err(e)))
+                    .and_then(|key| none_if_undefined(key).map(|k| key_from_js(&k)).transpose())
+            })),
+            Err(err) => Either::Left(std::future::ready(Err(ctx.apply(map_get_err(err))))),
+        }
+    }
+
+    /// List all the primary keys in the object store, with a maximum number of results of `limit`, ordered by this index
+    ///
+    /// See [`crate::Index::get_all_keys`].
+    ///
+    /// Internally, this uses [`IDBIndex::getAllKeys`](https://developer.mozilla.org/en-US/docs/Web/API/IDBIndex/getAllKeys).
+    pub fn get_all_keys(
+        &self,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<Key>, Err>> {
+        let ctx = self.context("Index::get_all_keys");
+        let get_req = match limit {
+            None => self.sys.get_all_keys(),
+            Some(limit) => self
+                .sys
+                .get_all_keys_with_key_and_limit(&JsValue::UNDEFINED, limit),
+        };
+        match get_req {
+            Ok(get_req) => Either::Right(transaction_request(get_req).map(move |res| {
+                res.map_err(|e| ctx.apply(map_get_err(e)))
+                    .and_then(array_to_keys)
+            })),
+            Err(err) => Either::Left(std::future::ready(Err(ctx.apply(map_get_err(err))))),
+        }
+    }
+
+    /// List all the primary keys of objects with a key (for this index)in the provided range, with a maximum number
+    /// of results of `limit`, ordered by this index
+    ///
+    /// See [`crate::Index::get_all_keys_in`].
+    ///
+    /// Internally, this uses [`IDBIndex::getAllKeys`](https://developer.mozilla.org/en-US/docs/Web/API/IDBIndex/getAllKeys).
+    pub fn get_all_keys_in(
+        &self,
+        range: impl RangeBounds<Key>,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<Key>, Err>> {
+        let ctx = self.context("Index::get_all_keys_in");
+        let range = match make_key_range(range) {
+            Ok(range) => range,
+            Err(e) => return Either::Left(std::future::ready(Err(ctx.apply(e)))),
+        };
+        let get_req = match limit {
+            None => self.sys.get_all_keys_with_key(&range),
+            Some(limit) => self.sys.get_all_keys_with_key_and_limit(&range, limit),
+        };
+        match get_req {
+            Ok(get_req) => Either::Right(transaction_request(get_req).map(move |res| {
+                res.map_err(|e| ctx.apply(map_get_err(e)))
+                    .and_then(array_to_keys)
+            })),
+            Err(err) => Either::Left(std::future::ready(Err(ctx.apply(map_get_err(err))))),
+        }
+    }
+
+    /// Open a [`Cursor`] on this index
+    ///
+    /// See [`crate::Index::cursor`].
+    pub fn cursor(&self) -> CursorBuilder<Err> {
+        CursorBuilder::from_index(self.sys.clone())
+    }
+}
@@ -0,0 +1,16 @@
+//! The [`WebSys`](crate::backend::WebSys) backend, that talks to IndexedDB through `web_sys`
+
+mod backend;
+mod cursor;
+mod database;
+mod factory;
+mod index;
+mod object_store;
+pub(crate) mod transaction;
+
+pub use cursor::{Cursor, CursorBuilder, KeyCursor};
+pub use database::{Database, EventStream, VersionChange};
+pub use factory::{DeleteDatabaseBuilder, Factory, OpenBuilder, VersionChangeEvent};
+pub use index::Index;
+pub use object_store::ObjectStore;
+pub use transaction::{set_panic_on_inactive_transaction, Transaction, TransactionBuilder};
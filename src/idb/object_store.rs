@@ -0,0 +1,748 @@
+use super::{
+    transaction::{batch_requests, transaction_request},
+    CursorBuilder, Index,
+};
+use crate::{
+    error::RequestContext,
+    utils::{
+        array_to_keys, array_to_vec, dom_string_list_to_vec, evaluate_key_path, js_deep_equal,
+        key_from_js, make_defined_key_range, make_key_range, map_add_err, map_clear_err,
+        map_count_err, map_count_res, map_delete_err, map_get_err, none_if_undefined,
+    },
+    ErrorContext, Key, KeyPath,
+};
+use futures_util::future::{Either, FutureExt};
+use std::{borrow::Borrow, future::Future, marker::PhantomData, ops::RangeBounds};
+use web_sys::{
+    wasm_bindgen::JsValue, IdbIndexParameters, IdbObjectStore, IdbRequest, IdbTransactionMode,
+};
+
+#[cfg(doc)]
+use super::Cursor;
+
+/// Wrapper for [`IDBObjectStore`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore),
+/// for use in transactions
+#[derive(Debug)]
+pub struct ObjectStore<Err> {
+    sys: IdbObjectStore,
+    _phantom: PhantomData<Err>,
+}
+
+impl<Err> ObjectStore<Err> {
+    pub(crate) fn from_sys(sys: IdbObjectStore) -> ObjectStore<Err> {
+        ObjectStore {
+            sys,
+            _phantom: PhantomData,
+        }
+    }
+
+    fn context(&self, operation: &'static str) -> RequestContext {
+        RequestContext::new(operation).store(&self.sys)
+    }
+
+    /// The name of this object store
+    ///
+    /// See [`crate::ObjectStore::name`].
+    ///
+    /// Internally, this uses [`IDBObjectStore::name`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/name).
+    pub fn name(&self) -> String {
+        self.sys.name()
+    }
+
+    /// The key path of this object store, or `None` if it uses out-of-line keys
+    ///
+    /// See [`crate::ObjectStore::key_path`].
+    ///
+    /// Internally, this uses [`IDBObjectStore::keyPath`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/keyPath).
+    pub fn key_path(&self) -> crate::Result<Option<KeyPath>, Err> {
+        let sys = self.sys.key_path().map_err(crate::Error::from_js_value)?;
+        KeyPath::from_sys(sys)
+    }
+
+    /// Whether the keys of this object store are auto-incremented
+    ///
+    /// See [`crate::ObjectStore::auto_increment`].
+    ///
+    /// Internally, this uses [`IDBObjectStore::autoIncrement`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/autoIncrement).
+    pub fn auto_increment(&self) -> bool {
+        self.sys.auto_increment()
+    }
+
+    /// The names of all [`Index`]es on this object store
+    ///
+    /// See [`crate::ObjectStore::index_names`].
+    ///
+    /// Internally, this uses [`IDBObjectStore::indexNames`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/indexNames).
+    pub fn index_names(&self) -> Vec<String> {
+        dom_string_list_to_vec(self.sys.index_names())
+    }
+
+    /// Create an index over this object store
+    ///
+    /// Note that this method can only be called from within an `on_upgrade_needed` callback.
+    ///
+    /// Internally, this uses [`IDBObjectStore::createIndex`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/createIndex).
+    pub fn create_index(
+        &self,
+        name: &str,
+        key_path: &KeyPath,
+        unique: bool,
+        multi_entry: bool,
+    ) -> crate::Result<(), Err> {
+        let ctx = self.context("IndexBuilder::create").index_name(name);
+        let options = IdbIndexParameters::new();
+        options.set_unique(unique);
+        options.set_multi_entry(multi_entry);
+        self.sys
+            .create_index_with_str_sequence_and_optional_parameters(
+                name,
+                &key_path.to_sys(),
+                &options,
+            )
+            .map_err(|err| {
+                ctx.apply(match error_name!(&err) {
+                    Some("ConstraintError") => {
+                        crate::Error::AlreadyExists(ErrorContext::from_js(&err))
+                    }
+                    Some("InvalidAccessError") => {
+                        crate::Error::InvalidArgument(ErrorContext::from_js(&err))
+                    }
+                    Some("InvalidStateError") => {
+                        crate::Error::ObjectStoreWasRemoved(ErrorContext::from_js(&err))
+                    }
+                    Some("SyntaxError") => crate::Error::InvalidKey(ErrorContext::from_js(&err)),
+                    _ => crate::Error::from_js_value(err),
+                })
+            })
+            .map(|_| ())
+    }
+
+    /// Delete an index from this object store
+    ///
+    /// See [`crate::ObjectStore::delete_index`].
+    ///
+    /// Internally, this uses [`IDBObjectStore::deleteIndex`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/deleteIndex).
+    pub fn delete_index(&self, name: &str) -> crate::Result<(), Err> {
+        let ctx = self.context("ObjectStore::delete_index").index_name(name);
+        self.sys.delete_index(name).map_err(|err| {
+            ctx.apply(match error_name!(&err) {
+                Some("InvalidStateError") => {
+                    crate::Error::ObjectStoreWasRemoved(ErrorContext::from_js(&err))
+                }
+                Some("NotFoundError") => crate::Error::DoesNotExist(ErrorContext::from_js(&err)),
+                _ => crate::Error::from_js_value(err),
+            })
+        })
+    }
+
+    /// Add the value `value` to this object store, and return its auto-computed key
+    ///
+    /// See [`crate::ObjectStore::add`].
+    ///
+    /// Internally, this uses [`IDBObjectStore::add`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/add).
+    pub fn add(&self, value: &JsValue) -> impl Future<Output = crate::Result<Key, Err>> {
+        let ctx = self.context("ObjectStore::add");
+        match self.sys.add(value) {
+            Ok(add_req) => Either::Left(transaction_request(add_req).map(move |res| {
+                res.map_err(|e| ctx.apply(map_add_err(e)))
+                    .and_then(|key| key_from_js(&key))
+            })),
+            Err(e) => Either::Right(std::future::ready(Err(ctx.apply(map_add_err(e))))),
+        }
+    }
+
+    /// Add the value `value` to this object store, with key `key`
+    ///
+    /// See [`crate::ObjectStore::add_kv`].
+    ///
+    /// Internally, this uses [`IDBObjectStore::add`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/add).
+    pub fn add_kv(
+        &self,
+        key: impl Into<Key>,
+        value: &JsValue,
+    ) -> impl Future<Output = crate::Result<(), Err>> {
+        let key = key.into();
+        let ctx = self.context("ObjectStore::add_kv").key(&key);
+        match self.sys.add_with_key(value, &key.to_js()) {
+            Ok(add_req) => Either::Left(
+                transaction_request(add_req)
+                    .map(move |res| res.map_err(|e| ctx.apply(map_add_err(e))).map(|_| ())),
+            ),
+            Err(e) => Either::Right(std::future::ready(Err(ctx.apply(map_add_err(e))))),
+        }
+    }
+
+    /// Add the value `value` to this object store, and return its auto-computed key
+    ///
+    /// See [`crate::ObjectStore::put`].
+    ///
+    /// Internally, this uses [`IDBObjectStore::add`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/add).
+    pub fn put(&self, value: &JsValue) -> impl Future<Output = crate::Result<Key, Err>> {
+        let ctx = self.context("ObjectStore::put");
+        match self.sys.put(value) {
+            Ok(add_req) => Either::Left(transaction_request(add_req).map(move |res| {
+                res.map_err(|e| ctx.apply(map_add_err(e)))
+                    .and_then(|key| key_from_js(&key))
+            })),
+            Err(e) => Either::Right(std::future::ready(Err(ctx.apply(map_add_err(e))))),
+        }
+    }
+
+    /// Add the value `value` to this object store, with key `key`
+    ///
+    /// See [`crate::ObjectStore::put_kv`].
+    ///
+    /// Internally, this uses [`IDBObjectStore::add`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/add).
+    pub fn put_kv(
+        &self,
+        key: impl Into<Key>,
+        value: &JsValue,
+    ) -> impl Future<Output = crate::Result<(), Err>> {
+        let key = key.into();
+        let ctx = self.context("ObjectStore::put_kv").key(&key);
+        match self.sys.put_with_key(value, &key.to_js()) {
+            Ok(add_req) => Either::Left(
+                transaction_request(add_req)
+                    .map(move |res| res.map_err(|e| ctx.apply(map_add_err(e))).map(|_| ())),
+            ),
+            Err(e) => Either::Right(std::future::ready(Err(ctx.apply(map_add_err(e))))),
+        }
+    }
+
+    /// Add all the values of `values` to this object store, and return their auto-computed keys
+    ///
+    /// See [`crate::ObjectStore::add_many`].
+    ///
+    /// Internally, this uses [`IDBObjectStore::add`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/add).
+    pub fn add_many(
+        &self,
+        values: impl IntoIterator<Item = impl Borrow<JsValue>>,
+    ) -> impl Future<Output = crate::Result<Vec<Key>, Err>> {
+        let ctx = self.context("ObjectStore::add_many");
+        let batch = batch_requests(values.into_iter().map(|v| self.sys.add(v.borrow())));
+        batch.wait().map(move |res| match res {
+            Ok(reqs) => reqs
+                .iter()
+                .map(|req| key_from_js(&request_result(req)))
+                .collect(),
+            Err(err) => Err(batch_err(&ctx, err, map_add_err)),
+        })
+    }
+
+    /// Add all the `(key, value)` pairs of `entries` to this object store
+    ///
+    /// See [`crate::ObjectStore::add_kv_many`].
+    ///
+    /// Internally, this uses [`IDBObjectStore::add`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/add).
+    pub fn add_kv_many(
+        &self,
+        entries: impl IntoIterator<Item = (impl Into<Key>, impl Borrow<JsValue>)>,
+    ) -> impl Future<Output = crate::Result<(), Err>> {
+        let ctx = self.context("ObjectStore::add_kv_many");
+        let batch = batch_requests(
+            entries
+                .into_iter()
+                .map(|(k, v)| self.sys.add_with_key(v.borrow(), &k.into().to_js())),
+        );
+        batch.wait().map(move |res| match res {
+            Ok(_) => Ok(()),
+            Err(err) => Err(batch_err(&ctx, err, map_add_err)),
+        })
+    }
+
+    /// Put all the values of `values` in this object store, and return their auto-computed keys
+    ///
+    /// See [`crate::ObjectStore::put_many`].
+    ///
+    /// Internally, this uses [`IDBObjectStore::put`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/put).
+    pub fn put_many(
+        &self,
+        values: impl IntoIterator<Item = impl Borrow<JsValue>>,
+    ) -> impl Future<Output = crate::Result<Vec<Key>, Err>> {
+        let ctx = self.context("ObjectStore::put_many");
+        let batch = batch_requests(values.into_iter().map(|v| self.sys.put(v.borrow())));
+        batch.wait().map(move |res| match res {
+            Ok(reqs) => reqs
+                .iter()
+                .map(|req| key_from_js(&request_result(req)))
+                .collect(),
+            Err(err) => Err(batch_err(&ctx, err, map_add_err)),
+        })
+    }
+
+    /// Put all the `(key, value)` pairs of `entries` in this object store
+    ///
+    /// See [`crate::ObjectStore::put_kv_many`].
+    ///
+    /// Internally, this uses [`IDBObjectStore::put`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/put).
+    pub fn put_kv_many(
+        &self,
+        entries: impl IntoIterator<Item = (impl Into<Key>, impl Borrow<JsValue>)>,
+    ) -> impl Future<Output = crate::Result<(), Err>> {
+        let ctx = self.context("ObjectStore::put_kv_many");
+        let batch = batch_requests(
+            entries
+                .into_iter()
+                .map(|(k, v)| self.sys.put_with_key(v.borrow(), &k.into().to_js())),
+        );
+        batch.wait().map(move |res| match res {
+            Ok(_) => Ok(()),
+            Err(err) => Err(batch_err(&ctx, err, map_add_err)),
+        })
+    }
+
+    /// Atomically replace the value with key `key` by the result of `f`, and return the previous and new values
+    ///
+    /// See [`crate::ObjectStore::update_with`].
+    ///
+    /// Internally, this uses [`IDBObjectStore::get`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/get),
+    /// followed by [`IDBObjectStore::put`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/put)
+    /// or [`IDBObjectStore::delete`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/delete).
+    pub fn update_with(
+        &self,
+        key: impl Into<Key>,
+        f: impl FnOnce(Option<JsValue>) -> Option<JsValue>,
+    ) -> impl Future<Output = crate::Result<(Option<JsValue>, Option<JsValue>), Err>> {
+        let key = key.into();
+        let ctx = self.context("ObjectStore::update_with").key(&key);
+        self.update(ctx, key, |current| Some(f(current)))
+    }
+
+    /// Atomically set the value with key `key` to `new` if it currently is `expected`, and return the
+    /// previous and new values
+    ///
+    /// See [`crate::ObjectStore::compare_and_swap`].
+    pub fn compare_and_swap(
+        &self,
+        key: impl Into<Key>,
+        expected: Option<&JsValue>,
+        new: Option<&JsValue>,
+    ) -> impl Future<Output = crate::Result<(Option<JsValue>, Option<JsValue>), Err>> {
+        let key = key.into();
+        let expected = expected.cloned();
+        let new = new.cloned();
+        let ctx = self.context("ObjectStore::compare_and_swap").key(&key);
+        self.update(ctx, key, move |current| {
+            let matches = match (&current, &expected) {
+                (Some(current), Some(expected)) => js_deep_equal(current, expected),
+                (None, None) => true,
+                _ => false,
+            };
+            matches.then_some(new)
+        })
+    }
+
+    /// Replace the value with key `key` by the result of `f`, and return the previous and new values
+    ///
+    /// `f` returns `None` to keep the current value, in which case nothing is written.
+    fn update(
+        &self,
+        ctx: RequestContext,
+        key: Key,
+        f: impl FnOnce(Option<JsValue>) -> Option<Option<JsValue>>,
+    ) -> impl Future<Output = crate::Result<(Option<JsValue>, Option<JsValue>), Err>> {
+        let js_key = key.to_js();
+        let read_only = self.sys.transaction().mode() == Ok(IdbTransactionMode::Readonly);
+        let get_req = match self.sys.get(&js_key) {
+            Ok(get_req) if !read_only => get_req,
+            Ok(_) => {
+                let err = crate::Error::ReadOnly(ErrorContext::default());
+                return Either::Left(std::future::ready(Err(ctx.apply(err))));
+            }
+            Err(e) => return Either::Left(std::future::ready(Err(ctx.apply(map_get_err(e))))),
+        };
+        let sys = self.sys.clone();
+        Either::Right(async move {
+            let previous = transaction_request(get_req)
+                .await
+                .map(none_if_undefined)
+                .map_err(|e| ctx.apply(map_get_err(e)))?;
+            let Some(new) = f(previous.clone()) else {
+                return Ok((previous.clone(), previous));
+            };
+            match &new {
+                Some(value) => {
+                    let key_path = sys
+                        .key_path()
+                        .map_err(crate::Error::from_js_value)
+                        .and_then(KeyPath::from_sys)
+                        .map_err(|e| ctx.apply(e))?;
+                    let put_req = match key_path {
+                        None => sys.put_with_key(value, &js_key),
+                        Some(key_path)
+                            if evaluate_key_path(value, &key_path).as_ref() == Some(&key) =>
+                        {
+                            sys.put(value)
+                        }
+                        Some(_) => {
+                            let err = crate::Error::InvalidKey(ErrorContext::new(
+                                "The new value does not have the key of the updated record",
+                            ));
+                            return Err(ctx.apply(err));
+                        }
+                    };
+                    let res = match put_req {
+                        Ok(put_req) => transaction_request(put_req).await,
+                        Err(e) => Err(e),
+                    };
+                    res.map_err(|e| ctx.apply(map_add_err(e)))?;
+                }
+                None if previous.is_some() => {
+                    let res = match sys.delete(&js_key) {
+                        Ok(delete_req) => transaction_request(delete_req).await,
+                        Err(e) => Err(e),
+                    };
+                    res.map_err(|e| ctx.apply(map_delete_err(e)))?;
+                }
+                None => (),
+            }
+            Ok((previous, new))
+        })
+    }
+
+    /// Clear this object store
+    ///
+    /// See [`crate::ObjectStore::clear`].
+    ///
+    /// Internally, this uses [`IDBObjectStore::clear`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/clear).
+    pub fn clear(&self) -> impl Future<Output = crate::Result<(), Err>> {
+        let ctx = self.context("ObjectStore::clear");
+        match self.sys.clear() {
+            Ok(clear_req) => Either::Left(
+                transaction_request(clear_req)
+                    .map(move |res| res.map_err(|e| ctx.apply(map_clear_err(e))).map(|_| ())),
+            ),
+            Err(err) => Either::Right(std::future::ready(Err(ctx.apply(map_clear_err(err))))),
+        }
+    }
+
+    /// Count the number of objects in this store
+    ///
+    /// See [`crate::ObjectStore::count`].
+    ///
+    /// Internally, this uses [`IDBObjectStore::count`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/count).
+    pub fn count(&self) -> impl Future<Output = crate::Result<usize, Err>> {
+        let ctx = self.context("ObjectStore::count");
+        match self.sys.count() {
+            Ok(count_req) => Either::Left(transaction_request(count_req).map(move |res| {
+                res.map_err(|e| ctx.apply(map_count_err(e)))
+                    .map(map_count_res)
+            })),
+            Err(e) => Either::Right(std::future::ready(Err(ctx.apply(map_count_err(e))))),
+        }
+    }
+
+    /// Checks whether the provided key exists in this object store
+    ///
+    /// See [`crate::ObjectStore::contains`].
+    ///
+    /// Internally, this uses [`IDBObjectStore::count`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/count).
+    pub fn contains(&self, key: impl Into<Key>) -> impl Future<Output = crate::Result<bool, Err>> {
+        let key = key.into();
+        let ctx = self.context("ObjectStore::contains").key(&key);
+        match self.sys.count_with_key(&key.to_js()) {
+            Ok(count_req) => Either::Left(transaction_request(count_req).map(move |res| {
+                res.map_err(|e| ctx.apply(map_count_err(e)))
+                    .map(|n| map_count_res(n) != 0)
+            })),
+            Err(e) => Either::Right(std::future::ready(Err(ctx.apply(map_count_err(e))))),
+        }
+    }
+
+    /// Counts the number of objects with a key in `range`
+    ///
+    /// See [`crate::ObjectStore::count_in`].
+    ///
+    /// Internally, this uses [`IDBObjectStore::count`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/count).
+    pub fn count_in(
+        &self,
+        range: impl RangeBounds<Key>,
+    ) -> impl Future<Output = crate::Result<usize, Err>> {
+        let ctx = self.context("ObjectStore::count_in");
+        let range = match make_key_range(range) {
+            Ok(range) => range,
+            Err(e) => return Either::Left(std::future::ready(Err(ctx.apply(e)))),
+        };
+        match self.sys.count_with_key(&range) {
+            Ok(count_req) => Either::Right(transaction_request(count_req).map(move |res| {
+                res.map_err(|e| ctx.apply(map_count_err(e)))
+                    .map(map_count_res)
+            })),
+            Err(e) => Either::Left(std::future::ready(Err(ctx.apply(map_count_err(e))))),
+        }
+    }
+
+    /// Delete the object with key `key`
+    ///
+    /// See [`crate::ObjectStore::delete`].
+    ///
+    /// Unfortunately, the IndexedDb API does not indicate whether an object was actually deleted.
+    ///
+    /// Internally, this uses [`IDBObjectStore::delete`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/delete).
+    pub fn delete(&self, key: impl Into<Key>) -> impl Future<Output = crate::Result<(), Err>> {
+        let key = key.into();
+        let ctx = self.context("ObjectStore::delete").key(&key);
+        match self.sys.delete(&key.to_js()) {
+            Ok(delete_req) => Either::Left(
+                transaction_request(delete_req)
+                    .map(move |res| res.map_err(|e| ctx.apply(map_delete_err(e))).map(|_| ())),
+            ),
+            Err(e) => Either::Right(std::future::ready(Err(ctx.apply(map_delete_err(e))))),
+        }
+    }
+
+    /// Delete all the objects with a key in `range`
+    ///
+    /// See [`crate::ObjectStore::delete_range`].
+    ///
+    /// Unfortunately, the IndexedDb API does not indicate whether an object was actually deleted.
+    ///
+    /// Internally, this uses [`IDBObjectStore::delete`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/delete).
+    pub fn delete_range(
+        &self,
+        range: impl RangeBounds<Key>,
+    ) -> impl Future<Output = crate::Result<(), Err>> {
+        let ctx = self.context("ObjectStore::delete_range");
+        let range = match make_defined_key_range(range) {
+            Ok(range) => range,
+            Err(e) => return Either::Left(std::future::ready(Err(ctx.apply(e)))),
+        };
+        match self.sys.delete(&range) {
+            Ok(delete_req) => Either::Right(
+                transaction_request(delete_req)
+                    .map(move |res| res.map_err(|e| ctx.apply(map_delete_err(e))).map(|_| ())),
+            ),
+            Err(e) => Either::Left(std::future::ready(Err(ctx.apply(map_delete_err(e))))),
+        }
+    }
+
+    /// Delete the objects with the keys of `keys`
+    ///
+    /// See [`crate::ObjectStore::delete_many`].
+    ///
+    /// Internally, this uses [`IDBObjectStore::delete`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/delete).
+    pub fn delete_many(
+        &self,
+        keys: impl IntoIterator<Item = impl Into<Key>>,
+    ) -> impl Future<Output = crate::Result<(), Err>> {
+        let ctx = self.context("ObjectStore::delete_many");
+        let batch = batch_requests(keys.into_iter().map(|k| self.sys.delete(&k.into().to_js())));
+        batch.wait().map(move |res| match res {
+            Ok(_) => Ok(()),
+            Err(err) => Err(batch_err(&ctx, err, map_delete_err)),
+        })
+    }
+
+    /// Get the object with key `key`
+    ///
+    /// See [`crate::ObjectStore::get`].
+    ///
+    /// Internally, this uses [`IDBObjectStore::get`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/get).
+    pub fn get(
+        &self,
+        key: impl Into<Key>,
+    ) -> impl Future<Output = crate::Result<Option<JsValue>, Err>> {
+        let key = key.into();
+        let ctx = self.context("ObjectStore::get").key(&key);
+        match self.sys.get(&key.to_js()) {
+            Ok(get_req) => Either::Right(transaction_request(get_req).map(move |res| {
+                res.map_err(|e| ctx.apply(map_get_err(e)))
+                    .map(none_if_undefined)
+            })),
+            Err(err) => Either::Left(std::future::ready(Err(ctx.apply(map_get_err(err))))),
+        }
+    }
+
+    /// Get the first value with a key in `range`, ordered by key
+    ///
+    /// See [`crate::ObjectStore::get_first_in`].
+    ///
+    /// Internally, this uses [`IDBObjectStore::get`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/get).
+    pub fn get_first_in(
+        &self,
+        range: impl RangeBounds<Key>,
+    ) -> impl Future<Output = crate::Result<Option<JsValue>, Err>> {
+        let ctx = self.context("ObjectStore::get_first_in");
+        let range = match make_defined_key_range(range) {
+            Ok(range) => range,
+            Err(e) => return Either::Left(std::future::ready(Err(ctx.apply(e)))),
+        };
+        match self.sys.get(&range) {
+            Ok(get_req) => Either::Right(transaction_request(get_req).map(move |res| {
+                res.map_err(|e| ctx.apply(map_get_err(e)))
+                    .map(none_if_undefined)
+            })),
+            Err(e) => Either::Left(std::future::ready(Err(ctx.apply(map_get_err(e))))),
+        }
+    }
+
+    /// Get all the objects in the store, with a maximum number of results of `limit`
+    ///
+    /// See [`crate::ObjectStore::get_all`].
+    ///
+    /// Internally, this uses [`IDBObjectStore::getAll`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/getAll).
+    pub fn get_all(
+        &self,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<JsValue>, Err>> {
+        let ctx = self.context("ObjectStore::get_all");
+        let get_req = match limit {
+            None => self.sys.get_all(),
+            Some(limit) => self
+                .sys
+                .get_all_with_key_and_limit(&JsValue::UNDEFINED, limit),
+        };
+        match get_req {
+            Ok(get_req) => Either::Right(
+                transaction_request(get_req)
+                    .map(move |res| res.map_err(|e| ctx.apply(map_get_err(e))).map(array_to_vec)),
+            ),
+            Err(err) => Either::Left(std::future::ready(Err(ctx.apply(map_get_err(err))))),
+        }
+    }
+
+    /// Get all the objects with a key in the provided range, with a maximum number of results of `limit`
+    ///
+    /// See [`crate::ObjectStore::get_all_in`].
+    ///
+    /// Internally, this uses [`IDBObjectStore::getAll`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/getAll).
+    pub fn get_all_in(
+        &self,
+        range: impl RangeBounds<Key>,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<JsValue>, Err>> {
+        let ctx = self.context("ObjectStore::get_all_in");
+        let range = match make_key_range(range) {
+            Ok(range) => range,
+            Err(e) => return Either::Left(std::future::ready(Err(ctx.apply(e)))),
+        };
+        let get_req = match limit {
+            None => self.sys.get_all_with_key(&range),
+            Some(limit) => self.sys.get_all_with_key_and_limit(&range, limit),
+        };
+        match get_req {
+            Ok(get_req) => Either::Right(
+                transaction_request(get_req)
+                    .map(move |res| res.map_err(|e| ctx.apply(map_get_err(e))).map(array_to_vec)),
+            ),
+            Err(err) => Either::Left(std::future::ready(Err(ctx.apply(map_get_err(err))))),
+        }
+    }
+
+    /// Get the first existing key in the provided range
+    ///
+    /// See [`crate::ObjectStore::get_first_key_in`].
+    ///
+    /// Internally, this uses [`IDBObjectStore::getKey`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/getKey).
+    pub fn get_first_key_in(
+        &self,
+        range: impl RangeBounds<Key>,
+    ) -> impl Future<Output = crate::Result<Option<Key>, Err>> {
+        let ctx = self.context("ObjectStore::get_first_key_in");
+        let range = match make_defined_key_range(range) {
+            Ok(range) => range,
+            Err(e) => return Either::Left(std::future::ready(Err(ctx.apply(e)))),
+        };
+        match self.sys.get_key(&range) {
+            Ok(get_req) => Either::Right(transaction_request(get_req).map(move |res| {
+                res.map_err(|e| ctx.apply(map_get_err(e)))
+                    .and_then(|key| none_if_undefined(key).map(|k| key_from_js(&k)).transpose())
+            })),
+            Err(err) => Either::Left(std::future::ready(Err(ctx.apply(map_get_err(err))))),
+        }
+    }
+
+    /// List all the keys in the object store, with a maximum number of results of `limit`
+    ///
+    /// See [`crate::ObjectStore::get_all_keys`].
+    ///
+    /// Internally, this uses [`IDBObjectStore::getAllKeys`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/getAllKeys).
+    pub fn get_all_keys(
+        &self,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<Key>, Err>> {
+        let ctx = self.context("ObjectStore::get_all_keys");
+        let get_req = match limit {
+            None => self.sys.get_all_keys(),
+            Some(limit) => self
+                .sys
+                .get_all_keys_with_key_and_limit(&JsValue::UNDEFINED, limit),
+        };
+        match get_req {
+            Ok(get_req) => Either::Right(transaction_request(get_req).map(move |res| {
+                res.map_err(|e| ctx.apply(map_get_err(e)))
+                    .and_then(array_to_keys)
+            })),
+            Err(err) => Either::Left(std::future::ready(Err(ctx.apply(map_get_err(err))))),
+        }
+    }
+
+    /// List all the keys in the provided range, with a maximum number of results of `limit`
+    ///
+    /// See [`crate::ObjectStore::get_all_keys_in`].
+    ///
+    /// Internally, this uses [`IDBObjectStore::getAllKeys`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/getAllKeys).
+    pub fn get_all_keys_in(
+        &self,
+        range: impl RangeBounds<Key>,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<Key>, Err>> {
+        let ctx = self.context("ObjectStore::get_all_keys_in");
+        let range = match make_key_range(range) {
+            Ok(range) => range,
+            Err(e) => return Either::Left(std::future::ready(Err(ctx.apply(e)))),
+        };
+        let get_req = match limit {
+            None => self.sys.get_all_keys_with_key(&range),
+            Some(limit) => self.sys.get_all_keys_with_key_and_limit(&range, limit),
+        };
+        match get_req {
+            Ok(get_req) => Either::Right(transaction_request(get_req).map(move |res| {
+                res.map_err(|e| ctx.apply(map_get_err(e)))
+                    .and_then(array_to_keys)
+            })),
+            Err(err) => Either::Left(std::future::ready(Err(ctx.apply(map_get_err(err))))),
+        }
+    }
+
+    /// Get the [`Index`] with the provided name
+    ///
+    /// See [`crate::ObjectStore::index`].
+    ///
+    /// Internally, this uses [`IDBObjectStore::index`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/index).
+    pub fn index(&self, name: &str) -> crate::Result<Index<Err>, Err> {
+        let ctx = self.context("ObjectStore::index").index_name(name);
+        Ok(Index::from_sys(self.sys.index(name).map_err(|err| {
+            ctx.apply(match error_name!(&err) {
+                Some("InvalidStateError") => {
+                    crate::Error::ObjectStoreWasRemoved(ErrorContext::from_js(&err))
+                }
+                Some("NotFoundError") => crate::Error::DoesNotExist(ErrorContext::from_js(&err)),
+                _ => crate::Error::from_js_value(err),
+            })
+        })?))
+    }
+
+    /// Open a [`Cursor`] on this object store
+    ///
+    /// See [`crate::ObjectStore::cursor`].
+    pub fn cursor(&self) -> CursorBuilder<Err> {
+        CursorBuilder::from_store(self.sys.clone())
+    }
+}
+
+fn request_result(req: &IdbRequest) -> JsValue {
+    req.result()
+        .expect("Failed retrieving the result of successful IDBRequest")
+}
+
+fn batch_err<Err>(
+    ctx: &RequestContext,
+    (index, err): (usize, JsValue),
+    map_err: fn(JsValue) -> crate::Error<Err>,
+) -> crate::Error<Err> {
+    crate::Error::Batch {
+        index,
+        error: Box::new(ctx.apply(map_err(err))),
+    }
+}
@@ -0,0 +1,429 @@
+use super::ObjectStore;
+use crate::{
+    error::RequestContext,
+    utils::{err_from_event, str_slice_to_array, transaction_blocked_err, transaction_finished},
+    Durability, ErrorContext,
+};
+use std::{
+    cell::RefCell,
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    rc::Rc,
+    sync::atomic::{AtomicBool, Ordering},
+    task::{Context, Poll},
+};
+use wasm_bindgen::prelude::wasm_bindgen;
+use web_sys::{
+    js_sys::{Object, Reflect, TypeError},
+    wasm_bindgen::{closure::Closure, JsCast, JsValue},
+    IdbDatabase, IdbRequest, IdbTransaction, IdbTransactionMode,
+};
+
+mod runner;
+pub(crate) mod unsafe_jar;
+
+pub(crate) use runner::is_explicitly_finished;
+pub use runner::{RunnableTransaction, TransactionResult};
+
+static PANIC_ON_INACTIVE_TRANSACTION: AtomicBool = AtomicBool::new(false);
+
+/// Panic instead of returning [`Error::TransactionInactive`](crate::Error::TransactionInactive)
+///
+/// This is disabled by default. It can be useful while debugging, as the panic message and backtrace then
+/// point at the faulty `.await` or request.
+pub fn set_panic_on_inactive_transaction(panic: bool) {
+    PANIC_ON_INACTIVE_TRANSACTION.store(panic, Ordering::Relaxed);
+}
+
+pub(crate) fn panics_on_inactive_transaction() -> bool {
+    PANIC_ON_INACTIVE_TRANSACTION.load(Ordering::Relaxed)
+}
+
+/// Wrapper for [`IDBTransaction`](https://developer.mozilla.org/en-US/docs/Web/API/IDBTransaction)
+#[derive(Debug)]
+pub struct Transaction<Err> {
+    sys: IdbTransaction,
+    _phantom: PhantomData<Err>,
+}
+
+impl<Err> Clone for Transaction<Err> {
+    fn clone(&self) -> Self {
+        Transaction::from_sys(self.sys.clone())
+    }
+}
+
+impl<Err> Transaction<Err> {
+    pub(crate) fn from_sys(sys: IdbTransaction) -> Transaction<Err> {
+        Transaction {
+            sys,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub(crate) fn as_sys(&self) -> &IdbTransaction {
+        &self.sys
+    }
+
+    /// Returns an [`ObjectStore`] that can be used to operate on data in this transaction
+    ///
+    /// See [`crate::Transaction::object_store`].
+    ///
+    /// Internally, this uses [`IDBTransaction::objectStore`](https://developer.mozilla.org/en-US/docs/Web/API/IDBTransaction/objectStore).
+    pub fn object_store(&self, name: &str) -> crate::Result<ObjectStore<Err>, Err> {
+        let ctx = RequestContext::new("Transaction::object_store").store_name(name);
+        Ok(ObjectStore::from_sys(self.sys.object_store(name).map_err(
+            |err| {
+                ctx.apply(match error_name!(&err) {
+                    Some("NotFoundError") => {
+                        crate::Error::DoesNotExist(ErrorContext::from_js(&err))
+                    }
+                    _ => crate::Error::from_js_value(err),
+                })
+            },
+        )?))
+    }
+
+    /// Commit this transaction without waiting for it to auto-commit
+    ///
+    /// See [`crate::Transaction::commit`].
+    ///
+    /// Internally, this uses [`IDBTransaction::commit`](https://developer.mozilla.org/en-US/docs/Web/API/IDBTransaction/commit).
+    pub fn commit(&self) -> crate::Result<(), Err> {
+        #[allow(deprecated)] // web-sys marks this as deprecated, but it is part of the standard
+        let res = self.sys.commit();
+        match res {
+            Ok(()) => (),
+            // `commit` is not supported, just let the transaction auto-commit
+            Err(err) if err.has_type::<TypeError>() => (),
+            Err(err) => {
+                return Err(match error_name!(&err) {
+                    Some("InvalidStateError") => {
+                        crate::Error::TransactionFinished(ErrorContext::from_js(&err))
+                    }
+                    _ => crate::Error::from_js_value(err),
+                })
+            }
+        }
+        runner::mark_explicitly_finished();
+        Ok(())
+    }
+
+    /// Abort this transaction, rolling back all its changes
+    ///
+    /// See [`crate::Transaction::abort`].
+    ///
+    /// Internally, this uses [`IDBTransaction::abort`](https://developer.mozilla.org/en-US/docs/Web/API/IDBTransaction/abort).
+    pub fn abort(&self) -> crate::Result<(), Err> {
+        self.sys.abort().map_err(|err| match error_name!(&err) {
+            Some("InvalidStateError") => {
+                crate::Error::TransactionFinished(ErrorContext::from_js(&err))
+            }
+            _ => crate::Error::from_js_value(err),
+        })?;
+        runner::mark_explicitly_finished();
+        Ok(())
+    }
+}
+
+impl Durability {
+    fn to_str(self) -> &'static str {
+        match self {
+            Durability::Default => "default",
+            Durability::Strict => "strict",
+            Durability::Relaxed => "relaxed",
+        }
+    }
+}
+
+#[wasm_bindgen]
+extern "C" {
+    // TODO: remove once web-sys has `IDBDatabase::transaction` with options outside of unstable APIs
+    #[wasm_bindgen(extends = IdbDatabase)]
+    type IdbDatabaseWithOptions;
+
+    #[wasm_bindgen(method, catch, js_name = transaction)]
+    fn transaction_with_options(
+        this: &IdbDatabaseWithOptions,
+        stores: &JsValue,
+        mode: IdbTransactionMode,
+        options: &Object,
+    ) -> Result<IdbTransaction, JsValue>;
+}
+
+/// Helper to build a transaction
+pub struct TransactionBuilder {
+    db: IdbDatabase,
+    stores: JsValue,
+    mode: IdbTransactionMode,
+    durability: Durability,
+}
+
+impl TransactionBuilder {
+    pub(crate) fn from_names(db: IdbDatabase, names: &[&str]) -> TransactionBuilder {
+        TransactionBuilder {
+            db,
+            stores: str_slice_to_array(names).into(),
+            mode: IdbTransactionMode::Readonly,
+            durability: Durability::Default,
+        }
+    }
+
+    /// Allow writes in this transaction
+    ///
+    /// See [`crate::TransactionBuilder::rw`].
+    pub fn rw(mut self) -> Self {
+        self.mode = IdbTransactionMode::Readwrite;
+        self
+    }
+
+    /// Set the durability hint of this transaction
+    ///
+    /// See [`crate::TransactionBuilder::durability`].
+    ///
+    /// Internally, this sets [this property](https://developer.mozilla.org/en-US/docs/Web/API/IDBDatabase/transaction#durability).
+    pub fn durability(mut self, durability: Durability) -> Self {
+        self.durability = durability;
+        self
+    }
+
+    /// Actually execute the transaction
+    ///
+    /// The `transaction` argument defines what will be run in the transaction. Note that due to
+    /// limitations of the IndexedDb API, the future returned by `transaction` cannot call `.await`
+    /// on any future except the ones provided by the [`Transaction`] itself. This function will
+    /// do its best to detect these cases to abort the transaction and return
+    /// [`Error::TransactionInactive`](crate::Error::TransactionInactive), but you should avoid doing
+    /// so anyway. [`set_panic_on_inactive_transaction`] can be used to panic instead, which makes
+    /// finding the culprit easier.
+    ///
+    /// If `transaction` returns an `Ok` value, then the transaction will be committed. If it
+    /// returns an `Err` value, then it will be aborted. [`Transaction::commit`] and [`Transaction::abort`]
+    /// can also be used to finish the transaction earlier.
+    ///
+    /// This only returns once the transaction actually completed, so that the changes are durable. If
+    /// committing fails, this returns [`Error::QuotaExceeded`](crate::Error::QuotaExceeded) if the storage
+    /// quota was exceeded, and [`Error::TransactionAborted`](crate::Error::TransactionAborted) for any other
+    /// reason. If `transaction` aborted it with [`Transaction::abort`], then its result is returned as-is.
+    /// Use [`TransactionBuilder::run_no_wait`] to return as soon as `transaction` returns instead.
+    ///
+    /// Note that you should avoid sending requests that you do not await. If you do, it is hard
+    /// to say whether the transaction will commit or abort, due to both the IndexedDB and the
+    /// `wasm-bindgen` semantics.
+    ///
+    /// Note that transactions cannot be nested.
+    ///
+    /// Internally, this uses [`IDBDatabase::transaction`](https://developer.mozilla.org/en-US/docs/Web/API/IDBDatabase/transaction).
+    // For more details of what will happen if one does not await:
+    // - If the `Closure` from `transaction_request` is not dropped yet, then the error will be
+    //   explicitly ignored, and thus transaction will commit.
+    // - If the `Closure` from `transaction_request` has already been dropped, then the callback
+    //   will panic. Most likely this will lead to the transaction aborting, but this is an
+    //   untested and unsupported code path.
+    pub async fn run<Ret, Err>(
+        self,
+        transaction: impl AsyncFnOnce(Transaction<Err>) -> crate::Result<Ret, Err>,
+    ) -> crate::Result<Ret, Err> {
+        self.run_impl(transaction, true).await
+    }
+
+    /// Execute the transaction, without waiting for it to complete
+    ///
+    /// See [`crate::TransactionBuilder::run_no_wait`].
+    pub async fn run_no_wait<Ret, Err>(
+        self,
+        transaction: impl AsyncFnOnce(Transaction<Err>) -> crate::Result<Ret, Err>,
+    ) -> crate::Result<Ret, Err> {
+        self.run_impl(transaction, false).await
+    }
+
+    async fn run_impl<Ret, Err>(
+        self,
+        transaction: impl AsyncFnOnce(Transaction<Err>) -> crate::Result<Ret, Err>,
+        wait: bool,
+    ) -> crate::Result<Ret, Err> {
+        let t = match self.durability {
+            Durability::Default => self
+                .db
+                .transaction_with_str_sequence_and_mode(&self.stores, self.mode),
+            durability => {
+                let options = Object::new();
+                Reflect::set(&options, &"durability".into(), &durability.to_str().into())
+                    .expect("Failed setting the durability of transaction options");
+                self.db
+                    .unchecked_ref::<IdbDatabaseWithOptions>()
+                    .transaction_with_options(&self.stores, self.mode, &options)
+            }
+        }
+        .map_err(|err| match error_name!(&err) {
+            Some("InvalidStateError") => {
+                crate::Error::DatabaseIsClosed(ErrorContext::from_js(&err))
+            }
+            Some("NotFoundError") => crate::Error::DoesNotExist(ErrorContext::from_js(&err)),
+            Some("InvalidAccessError") => {
+                crate::Error::InvalidArgument(ErrorContext::from_js(&err))
+            }
+            _ => crate::Error::from_js_value(err),
+        })?;
+        let finished = transaction_finished(&t);
+        let result = RefCell::new(None);
+        let result = &result;
+        let (finished_tx, finished_rx) = futures_channel::oneshot::channel();
+        unsafe_jar::extend_lifetime_to_scope_and_run(
+            Box::new(move |()| {
+                RunnableTransaction::new(
+                    t.clone(),
+                    transaction(Transaction::from_sys(t)),
+                    result,
+                    finished_tx,
+                )
+            }),
+            async move |s| {
+                s.run(());
+                let _ = finished_rx.await;
+                let result = result
+                    .borrow_mut()
+                    .take()
+                    .expect("Transaction finished without setting result");
+                match result {
+                    TransactionResult::PolledForbiddenThing => Err(transaction_blocked_err()),
+                    TransactionResult::Done(Ok(r)) if wait => match finished.await {
+                        // Without error, the transaction was aborted with `Transaction::abort`
+                        Ok(()) | Err(None) => Ok(r),
+                        Err(Some(err)) if err.name() == "QuotaExceededError" => {
+                            Err(crate::Error::QuotaExceeded(ErrorContext::from_js(&err)))
+                        }
+                        Err(Some(err)) => Err(crate::Error::TransactionAborted {
+                            reason: Some(format!("{}: {}", err.name(), err.message())),
+                        }),
+                    },
+                    TransactionResult::Done(r) => r,
+                }
+            },
+        )
+        .await
+    }
+}
+
+struct FakeFuture<'a, T> {
+    watching: &'a RefCell<Option<T>>,
+}
+
+impl<'a, T> FakeFuture<'a, T> {
+    fn new(watching: &'a RefCell<Option<T>>) -> FakeFuture<'a, T> {
+        FakeFuture { watching }
+    }
+}
+
+impl<'a, T> Future for FakeFuture<'a, T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<T> {
+        // Don't do this at home! This only works thanks to our unsafe jar polling regardless of the waker
+        match self.watching.borrow_mut().take() {
+            None => Poll::Pending,
+            Some(r) => Poll::Ready(r),
+        }
+    }
+}
+
+pub(crate) async fn transaction_request(req: IdbRequest) -> Result<JsValue, JsValue> {
+    let result = Rc::new(RefCell::new(None));
+
+    // Keep the callbacks alive until execution completed
+    let _callbacks = runner::add_request(req, &result);
+
+    match FakeFuture::new(&result).await {
+        Ok(evt) => {
+            let result = evt.target()
+                .expect("Trying to parse indexed_db::Error from an event that has no target")
+                .dyn_into::<web_sys::IdbRequest>()
+                .expect("Trying to parse indexed_db::Error from an event that is not from an IDBRequest")
+                .result()
+                .expect("Failed retrieving the result of successful IDBRequest");
+            Ok(result)
+        }
+        Err(evt) => Err(err_from_event(evt).into()),
+    }
+}
+
+/// Requests issued all at once, see [`batch_requests`]
+pub(crate) struct Batch {
+    issued: Rc<RefCell<Vec<IdbRequest>>>,
+    first_error: Rc<RefCell<Option<(usize, JsValue)>>>,
+    sync_error: Option<(usize, JsValue)>,
+}
+
+/// Issue all of `requests` at once, to then wait only for the last one to complete
+///
+/// IndexedDB runs the requests of a transaction in order, so all the requests are done once the last
+/// one is. Requests are no longer issued after one fails synchronously.
+pub(crate) fn batch_requests(
+    requests: impl IntoIterator<Item = Result<IdbRequest, JsValue>>,
+) -> Batch {
+    let issued = Rc::new(RefCell::new(Vec::new()));
+    let first_error = Rc::new(RefCell::new(None));
+
+    // A single callback for all the requests, as their successes need not be observed. It is handed
+    // over to the JS GC, so that requests failing after the batch was dropped do not call a freed closure
+    let on_error = Closure::<dyn FnMut(web_sys::Event)>::new({
+        let issued = issued.clone();
+        let first_error = first_error.clone();
+        move |evt: web_sys::Event| {
+            let req = evt.target().map(JsValue::from);
+            let err = err_from_event(evt);
+            let index = issued
+                .borrow()
+                .iter()
+                .position(|r: &IdbRequest| Some(JsValue::from(r)) == req)
+                .expect("Batch request error did not come from a request of the batch");
+            first_error
+                .borrow_mut()
+                .get_or_insert((index, JsValue::from(err)));
+        }
+    })
+    .into_js_value();
+
+    let mut sync_error = None;
+    for (i, req) in requests.into_iter().enumerate() {
+        match req {
+            Ok(req) => {
+                req.set_onerror(Some(on_error.unchecked_ref()));
+                issued.borrow_mut().push(req);
+            }
+            Err(err) => {
+                sync_error = Some((i, err));
+                break;
+            }
+        }
+    }
+
+    Batch {
+        issued,
+        first_error,
+        sync_error,
+    }
+}
+
+impl Batch {
+    /// Wait for all the requests, and return them or the first failure along with its position
+    pub(crate) async fn wait(self) -> Result<Vec<IdbRequest>, (usize, JsValue)> {
+        // The last request is awaited normally, which replaces its callbacks
+        let last = self.issued.borrow().last().cloned();
+        let last_res = match last {
+            Some(last) => transaction_request(last).await,
+            None => Ok(JsValue::UNDEFINED),
+        };
+        let issued = self.issued.take();
+        if let Some(err) = self.first_error.take() {
+            return Err(err);
+        }
+        if let Err(err) = last_res {
+            return Err((issued.len() - 1, err));
+        }
+        if let Some(err) = self.sync_error {
+            return Err(err);
+        }
+        Ok(issued)
+    }
+}
@@ -16,6 +16,8 @@ use web_sys::{
     IdbRequest, IdbTransaction,
 };
 
+use super::panics_on_inactive_transaction;
+
 pub enum TransactionResult<R> {
     PolledForbiddenThing,
     Done(R),
@@ -24,6 +26,7 @@ pub enum TransactionResult<R> {
 pub struct RunnableTransaction<'f> {
     transaction: IdbTransaction,
     inflight_requests: Cell<usize>,
+    explicitly_finished: Cell<bool>,
     future: RefCell<Pin<Box<dyn 'f + Future<Output = ()>>>>,
     polled_forbidden_thing: Box<dyn 'f + Fn()>,
     finished: RefCell<Option<oneshot::Sender<()>>>,
@@ -43,6 +46,7 @@ impl<'f> RunnableTransaction<'f> {
         RunnableTransaction {
             transaction: transaction.clone(),
             inflight_requests: Cell::new(0),
+            explicitly_finished: Cell::new(false),
             future: RefCell::new(Box::pin(async move {
                 let transaction_result = transaction_contents.await;
                 if transaction_result.is_err() {
@@ -72,7 +76,10 @@ fn panic_waker() -> Waker {
         )
     }
     fn wake(_: *const ()) {
-        panic!("IndexedDB transaction tried to await on something other than a request")
+        // The transaction was already aborted when this was awaited, so there is nothing left to do
+        if panics_on_inactive_transaction() {
+            panic!("IndexedDB transaction tried to await on something other than a request")
+        }
     }
     fn drop(_: *const ()) {}
     unsafe {
@@ -86,7 +93,7 @@ fn panic_waker() -> Waker {
 scoped_thread_local!(static CURRENT: Rc<RunnableTransaction<'static>>);
 
 pub fn poll_it(state: &Rc<RunnableTransaction<'static>>) {
-    CURRENT.set(&state, || {
+    CURRENT.set(state, || {
         // Poll once, in order to run the transaction until its next await on a request
         let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
             state
@@ -117,7 +124,7 @@ pub fn poll_it(state: &Rc<RunnableTransaction<'static>>) {
                     // an `await` on something other than transaction requests. Abort in order to
                     // avoid the default auto-commit behavior.
                     let _ = state.transaction.abort();
-                    let _ = (state.polled_forbidden_thing)();
+                    (state.polled_forbidden_thing)();
                 }
             }
             Poll::Ready(()) => {
@@ -130,13 +137,24 @@ pub fn poll_it(state: &Rc<RunnableTransaction<'static>>) {
                 if finished.send(()).is_err() {
                     // Transaction aborted by not awaiting on it
                     let _ = state.transaction.abort();
-                    return;
                 }
             }
         }
     });
 }
 
+/// Record that the transaction currently being polled was explicitly committed or aborted
+pub fn mark_explicitly_finished() {
+    if CURRENT.is_set() {
+        CURRENT.with(|state| state.explicitly_finished.set(true));
+    }
+}
+
+/// Whether the transaction currently being polled was explicitly committed or aborted
+pub fn is_explicitly_finished() -> bool {
+    CURRENT.is_set() && CURRENT.with(|state| state.explicitly_finished.get())
+}
+
 pub fn add_request(
     req: IdbRequest,
     result: &Rc<RefCell<Option<Result<web_sys::Event, web_sys::Event>>>>,
@@ -171,8 +189,8 @@ pub fn add_request(
             }
         });
 
-        req.set_onsuccess(Some(&on_success.as_ref().dyn_ref::<Function>().unwrap()));
-        req.set_onerror(Some(&on_error.as_ref().dyn_ref::<Function>().unwrap()));
+        req.set_onsuccess(Some(on_success.as_ref().dyn_ref::<Function>().unwrap()));
+        req.set_onerror(Some(on_error.as_ref().dyn_ref::<Function>().unwrap()));
 
         // Keep the callbacks alive until they're no longer needed
         (on_success, on_error)
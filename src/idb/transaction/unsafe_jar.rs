@@ -64,7 +64,7 @@ pub async fn extend_lifetime_to_scope_and_run<'scope, MakerArgs, ScopeRet>(
         std::process::abort();
     }
     if let Some(state) = state.get() {
-        if Weak::strong_count(&state) != 0 {
+        if Weak::strong_count(state) != 0 {
             // Make sure that regardless of what the user could be doing, if we're overextending the lifetime we'll panic and abort
             //
             // Note: we know this won't spuriously hit because:
@@ -0,0 +1,306 @@
+use std::{cmp::Ordering, hash::Hash};
+use web_sys::{
+    js_sys::{Array, ArrayBuffer, Date, JsString, Uint8Array},
+    wasm_bindgen::{JsCast, JsValue},
+};
+
+#[cfg(doc)]
+use crate::Factory;
+
+/// A valid IndexedDB key
+///
+/// See [the specification](https://www.w3.org/TR/IndexedDB/#key-construct) for more details.
+///
+/// Keys are ordered like IndexedDB orders them, ie. like [`Factory::cmp`] would, without needing to
+/// call into Javascript:
+/// - Keys of different types are ordered numbers first, then dates, strings, binaries and arrays
+/// - Numbers and dates are ordered numerically, with `-0.0` being equal to `0.0`
+/// - Strings are ordered by their UTF-16 code units, which is not the same as the order of [`str`]
+/// - Binaries are ordered byte by byte, and arrays element by element, shorter first on equal prefixes
+///
+/// `NaN` is not a valid key for IndexedDB, and trying to use a key containing it will fail with
+/// [`Error::InvalidKey`](crate::Error::InvalidKey). For the purpose of [`Ord`], it is considered equal
+/// to itself and greater than all other numbers.
+///
+/// ```rust
+/// # use indexed_db::Key;
+/// let mut keys = vec![
+///     Key::from((1, "b")),
+///     Key::from("z"),
+///     Key::from(vec![0u8, 1]),
+///     Key::from(42),
+///     Key::from("\u{1F600}"),
+///     Key::from("\u{FF61}"),
+/// ];
+/// keys.sort();
+/// assert_eq!(
+///     keys,
+///     [
+///         Key::from(42),
+///         Key::from("z"),
+///         Key::from("\u{1F600}"),
+///         Key::from("\u{FF61}"),
+///         Key::from(vec![0u8, 1]),
+///         Key::from((1, "b")),
+///     ]
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub enum Key {
+    /// A number
+    Number(f64),
+
+    /// A date, as a number of milliseconds since the Unix epoch
+    Date(f64),
+
+    /// A string
+    String(String),
+
+    /// A binary buffer
+    Binary(Vec<u8>),
+
+    /// An array of keys, as used by compound key paths
+    Array(Vec<Key>),
+}
+
+impl Key {
+    /// Convert a Javascript value into a key, or `None` if it is not a valid key
+    ///
+    /// Binary keys are recognized from `ArrayBuffer`s as well as from views like `Uint8Array` or `DataView`.
+    pub fn from_js(v: &JsValue) -> Option<Key> {
+        if let Some(n) = v.as_f64() {
+            (!n.is_nan()).then_some(Key::Number(n))
+        } else if let Some(s) = v.as_string() {
+            Some(Key::String(s))
+        } else if let Some(d) = v.dyn_ref::<Date>() {
+            let t = d.get_time();
+            (!t.is_nan()).then_some(Key::Date(t))
+        } else if let Some(b) = v.dyn_ref::<ArrayBuffer>() {
+            Some(Key::Binary(Uint8Array::new(b).to_vec()))
+        } else if ArrayBuffer::is_view(v) {
+            let view = v.unchecked_ref::<Uint8Array>();
+            Some(Key::Binary(
+                Uint8Array::new_with_byte_offset_and_length(
+                    &view.buffer(),
+                    view.byte_offset(),
+                    view.byte_length(),
+                )
+                .to_vec(),
+            ))
+        } else if Array::is_array(v) {
+            v.unchecked_ref::<Array>()
+                .iter()
+                .map(|v| Key::from_js(&v))
+                .collect::<Option<Vec<_>>>()
+                .map(Key::Array)
+        } else {
+            None
+        }
+    }
+
+    /// Convert this key into a Javascript value
+    ///
+    /// Binary keys are converted into `ArrayBuffer`s, like IndexedDB returns them.
+    pub fn to_js(&self) -> JsValue {
+        match self {
+            Key::Number(n) => JsValue::from_f64(*n),
+            Key::Date(t) => Date::new(&JsValue::from_f64(*t)).into(),
+            Key::String(s) => JsString::from(s as &str).into(),
+            Key::Binary(b) => Uint8Array::from(b as &[u8]).buffer().into(),
+            Key::Array(a) => a.iter().map(Key::to_js).collect::<Array>().into(),
+        }
+    }
+
+    /// Whether this key is valid for IndexedDB, ie. does not contain `NaN`
+    pub(crate) fn is_valid(&self) -> bool {
+        match self {
+            Key::Number(n) | Key::Date(n) => !n.is_nan(),
+            Key::String(_) | Key::Binary(_) => true,
+            Key::Array(a) => a.iter().all(Key::is_valid),
+        }
+    }
+
+    /// The smallest key that is strictly greater than this key
+    pub(crate) fn successor(&self) -> Key {
+        match self {
+            Key::Number(n) if *n == f64::INFINITY => Key::Date(-MAX_DATE),
+            Key::Number(n) => Key::Number(next_up(normalize(*n))),
+            // Dates are always integral numbers of milliseconds
+            Key::Date(t) if *t >= MAX_DATE => Key::String(String::new()),
+            Key::Date(t) => Key::Date(t.floor() + 1.0),
+            Key::String(s) => Key::String(format!("{s}\0")),
+            Key::Binary(b) => Key::Binary(b.iter().copied().chain([0]).collect()),
+            Key::Array(a) => Key::Array(
+                a.iter()
+                    .cloned()
+                    .chain([Key::Number(f64::NEG_INFINITY)])
+                    .collect(),
+            ),
+        }
+    }
+
+    fn type_rank(&self) -> u8 {
+        match self {
+            Key::Number(_) => 0,
+            Key::Date(_) => 1,
+            Key::String(_) => 2,
+            Key::Binary(_) => 3,
+            Key::Array(_) => 4,
+        }
+    }
+}
+
+/// The greatest valid date, in milliseconds since the Unix epoch
+const MAX_DATE: f64 = 8.64e15;
+
+fn next_up(n: f64) -> f64 {
+    // `f64::next_up` requires a more recent compiler
+    if n.is_nan() || n == f64::INFINITY {
+        n
+    } else if n == 0.0 {
+        f64::from_bits(1)
+    } else if n > 0.0 {
+        f64::from_bits(n.to_bits() + 1)
+    } else {
+        f64::from_bits(n.to_bits() - 1)
+    }
+}
+
+fn normalize(n: f64) -> f64 {
+    // Make `-0.0` and `0.0` equal, and all the `NaN`s equal
+    if n == 0.0 {
+        0.0
+    } else if n.is_nan() {
+        f64::NAN
+    } else {
+        n
+    }
+}
+
+impl Ord for Key {
+    fn cmp(&self, other: &Key) -> Ordering {
+        match (self, other) {
+            (Key::Number(a), Key::Number(b)) | (Key::Date(a), Key::Date(b)) => {
+                normalize(*a).total_cmp(&normalize(*b))
+            }
+            (Key::String(a), Key::String(b)) => a.encode_utf16().cmp(b.encode_utf16()),
+            (Key::Binary(a), Key::Binary(b)) => a.cmp(b),
+            (Key::Array(a), Key::Array(b)) => a.cmp(b),
+            _ => self.type_rank().cmp(&other.type_rank()),
+        }
+    }
+}
+
+impl PartialOrd for Key {
+    fn partial_cmp(&self, other: &Key) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Key {
+    fn eq(&self, other: &Key) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Key {}
+
+impl Hash for Key {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.type_rank().hash(state);
+        match self {
+            Key::Number(n) | Key::Date(n) => normalize(*n).to_bits().hash(state),
+            Key::String(s) => s.hash(state),
+            Key::Binary(b) => b.hash(state),
+            Key::Array(a) => a.hash(state),
+        }
+    }
+}
+
+impl TryFrom<JsValue> for Key {
+    type Error = JsValue;
+
+    fn try_from(v: JsValue) -> Result<Key, JsValue> {
+        Key::from_js(&v).ok_or(v)
+    }
+}
+
+impl From<Key> for JsValue {
+    fn from(key: Key) -> JsValue {
+        key.to_js()
+    }
+}
+
+impl From<&Key> for JsValue {
+    fn from(key: &Key) -> JsValue {
+        key.to_js()
+    }
+}
+
+impl From<&Key> for Key {
+    fn from(key: &Key) -> Key {
+        key.clone()
+    }
+}
+
+macro_rules! impl_from_number {
+    ($($t:ty),*) => {
+        $(
+            impl From<$t> for Key {
+                fn from(n: $t) -> Key {
+                    Key::Number(f64::from(n))
+                }
+            }
+        )*
+    };
+}
+
+impl_from_number!(i8, u8, i16, u16, i32, u32, f32, f64);
+
+impl From<&str> for Key {
+    fn from(s: &str) -> Key {
+        Key::String(s.to_string())
+    }
+}
+
+impl From<String> for Key {
+    fn from(s: String) -> Key {
+        Key::String(s)
+    }
+}
+
+impl From<&[u8]> for Key {
+    fn from(b: &[u8]) -> Key {
+        Key::Binary(b.to_vec())
+    }
+}
+
+impl From<Vec<u8>> for Key {
+    fn from(b: Vec<u8>) -> Key {
+        Key::Binary(b)
+    }
+}
+
+impl From<Vec<Key>> for Key {
+    fn from(a: Vec<Key>) -> Key {
+        Key::Array(a)
+    }
+}
+
+macro_rules! impl_from_tuple {
+    ($($t:ident),*) => {
+        impl<$($t: Into<Key>),*> From<($($t,)*)> for Key {
+            #[allow(non_snake_case)]
+            fn from(($($t,)*): ($($t,)*)) -> Key {
+                Key::Array(vec![$($t.into()),*])
+            }
+        }
+    };
+}
+
+impl_from_tuple!(A);
+impl_from_tuple!(A, B);
+impl_from_tuple!(A, B, C);
+impl_from_tuple!(A, B, C, D);
+impl_from_tuple!(A, B, C, D, E);
+impl_from_tuple!(A, B, C, D, E, F);
@@ -0,0 +1,80 @@
+use crate::{
+    utils::{array_to_vec, str_slice_to_array},
+    ErrorContext,
+};
+use web_sys::{js_sys::JsString, wasm_bindgen::JsValue};
+
+/// The key path of an object store or index
+///
+/// See [the MDN documentation](https://developer.mozilla.org/en-US/docs/Web/API/IndexedDB_API/Basic_Terminology#key_path)
+/// for more details.
+///
+/// With the `serde` feature, key paths are serialized like in Javascript, as either a string or an array of strings.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(untagged)
+)]
+pub enum KeyPath {
+    /// A single path, like `"id"` or `"user.id"`
+    Single(String),
+
+    /// A compound path made of multiple attributes, like `["user", "date"]`
+    Compound(Vec<String>),
+}
+
+impl KeyPath {
+    pub(crate) fn from_sys<Err>(sys: JsValue) -> crate::Result<Option<KeyPath>, Err> {
+        if sys.is_null() || sys.is_undefined() {
+            Ok(None)
+        } else if let Some(path) = sys.as_string() {
+            Ok(Some(KeyPath::Single(path)))
+        } else {
+            array_to_vec(sys)
+                .into_iter()
+                .map(|p| {
+                    p.as_string().ok_or_else(|| {
+                        crate::Error::Unknown(ErrorContext::new(
+                            "The browser returned a key path component that is not a string",
+                        ))
+                    })
+                })
+                .collect::<crate::Result<Vec<_>, Err>>()
+                .map(|paths| Some(KeyPath::Compound(paths)))
+        }
+    }
+
+    pub(crate) fn to_sys(&self) -> JsValue {
+        match self {
+            KeyPath::Single(path) => JsString::from(path as &str).into(),
+            KeyPath::Compound(paths) => {
+                str_slice_to_array(&paths.iter().map(|p| p as &str).collect::<Vec<_>>()).into()
+            }
+        }
+    }
+}
+
+impl From<&str> for KeyPath {
+    fn from(path: &str) -> KeyPath {
+        KeyPath::Single(path.to_string())
+    }
+}
+
+impl From<String> for KeyPath {
+    fn from(path: String) -> KeyPath {
+        KeyPath::Single(path)
+    }
+}
+
+impl From<&[&str]> for KeyPath {
+    fn from(paths: &[&str]) -> KeyPath {
+        KeyPath::Compound(paths.iter().map(|p| p.to_string()).collect())
+    }
+}
+
+impl From<Vec<String>> for KeyPath {
+    fn from(paths: Vec<String>) -> KeyPath {
+        KeyPath::Compound(paths)
+    }
+}
@@ -0,0 +1,181 @@
+use crate::{ErrorContext, Key};
+use std::{
+    convert::Infallible,
+    ops::{Bound, RangeBounds},
+};
+
+/// A range of keys
+///
+/// This can be passed to all the methods that accept a range, like [`ObjectStore::get_all_in`](crate::ObjectStore::get_all_in),
+/// as well as any Rust range of [`Key`]s. The unbounded range, [`KeyRange::all`] or `..`, selects all the keys.
+#[derive(Clone, Debug)]
+pub struct KeyRange {
+    lower: Bound<Key>,
+    upper: Bound<Key>,
+}
+
+impl KeyRange {
+    /// The range of all keys
+    pub fn all() -> KeyRange {
+        KeyRange {
+            lower: Bound::Unbounded,
+            upper: Bound::Unbounded,
+        }
+    }
+
+    /// The range containing only `key`
+    pub fn only(key: impl Into<Key>) -> KeyRange {
+        let key = key.into();
+        KeyRange {
+            lower: Bound::Included(key.clone()),
+            upper: Bound::Included(key),
+        }
+    }
+
+    /// The range between `lower` and `upper`
+    pub fn new(lower: Bound<Key>, upper: Bound<Key>) -> KeyRange {
+        KeyRange { lower, upper }
+    }
+
+    /// The range of the keys greater than or equal to `key`
+    pub fn at_least(key: impl Into<Key>) -> KeyRange {
+        KeyRange::new(Bound::Included(key.into()), Bound::Unbounded)
+    }
+
+    /// The range of the keys strictly greater than `key`
+    pub fn greater_than(key: impl Into<Key>) -> KeyRange {
+        KeyRange::new(Bound::Excluded(key.into()), Bound::Unbounded)
+    }
+
+    /// The range of the keys less than or equal to `key`
+    pub fn at_most(key: impl Into<Key>) -> KeyRange {
+        KeyRange::new(Bound::Unbounded, Bound::Included(key.into()))
+    }
+
+    /// The range of the keys strictly less than `key`
+    pub fn less_than(key: impl Into<Key>) -> KeyRange {
+        KeyRange::new(Bound::Unbounded, Bound::Excluded(key.into()))
+    }
+
+    /// The range of all the keys that start with `prefix`
+    ///
+    /// - For strings, this selects the strings starting with `prefix`, eg. `"user:42:"`.
+    /// - For binaries, this selects the binaries starting with the bytes of `prefix`.
+    /// - For arrays, this selects the arrays whose first elements are the elements of `prefix`, eg. the
+    ///   prefix `[x]` selects all the compound keys whose first component is `x`. The empty array selects
+    ///   all arrays.
+    /// - Numbers and dates have no notion of prefix, so this is then the same as [`KeyRange::only`].
+    pub fn prefix(prefix: impl Into<Key>) -> KeyRange {
+        let prefix = prefix.into();
+        let upper = match &prefix {
+            Key::Number(_) | Key::Date(_) => Bound::Included(prefix.clone()),
+            Key::String(s) => {
+                let mut chars = s.chars().collect::<Vec<_>>();
+                loop {
+                    match chars.pop() {
+                        // All the strings starting with `prefix`, up to the first binary
+                        None => break Bound::Excluded(Key::Binary(Vec::new())),
+                        Some(c) => {
+                            if let Some(next) = next_char(c) {
+                                chars.push(next);
+                                break Bound::Excluded(Key::String(chars.into_iter().collect()));
+                            }
+                        }
+                    }
+                }
+            }
+            Key::Binary(b) => {
+                let mut bytes = b.clone();
+                while bytes.last() == Some(&u8::MAX) {
+                    bytes.pop();
+                }
+                match bytes.last_mut() {
+                    Some(last) => {
+                        *last += 1;
+                        Bound::Excluded(Key::Binary(bytes))
+                    }
+                    // All the binaries starting with `prefix`, up to the first array
+                    None => Bound::Excluded(Key::Array(Vec::new())),
+                }
+            }
+            // Arrays starting with `[init.., last]` are between it and `[init.., successor(last)]`
+            Key::Array(a) => match a.split_last() {
+                Some((last, init)) => Bound::Excluded(Key::Array(
+                    init.iter().cloned().chain([last.successor()]).collect(),
+                )),
+                None => Bound::Unbounded,
+            },
+        };
+        KeyRange {
+            lower: Bound::Included(prefix),
+            upper,
+        }
+    }
+
+    /// Check whether `key` is in this range
+    ///
+    /// Returns an error if `key` or one of the bounds is not a valid key, or if the lower bound is
+    /// greater than the upper bound, like [`IDBKeyRange::includes`](https://developer.mozilla.org/en-US/docs/Web/API/IDBKeyRange/includes)
+    /// would.
+    pub fn includes(&self, key: &Key) -> crate::Result<bool, Infallible> {
+        for k in [&self.lower, &self.upper]
+            .into_iter()
+            .filter_map(|b| match b {
+                Bound::Included(k) | Bound::Excluded(k) => Some(k),
+                Bound::Unbounded => None,
+            })
+            .chain([key])
+        {
+            if !k.is_valid() {
+                return Err(crate::Error::InvalidKey(ErrorContext::new(
+                    "The key is not a valid key",
+                )));
+            }
+        }
+        let is_empty = match (&self.lower, &self.upper) {
+            (Bound::Included(l), Bound::Included(u)) => l > u,
+            (Bound::Included(l) | Bound::Excluded(l), Bound::Included(u) | Bound::Excluded(u)) => {
+                l >= u
+            }
+            _ => false,
+        };
+        if is_empty {
+            return Err(crate::Error::InvalidKey(ErrorContext::new(
+                "The lower bound of the range is greater than its upper bound",
+            )));
+        }
+        Ok(self.contains(key))
+    }
+}
+
+/// The smallest character that is greater than `c` in the order of UTF-16 code units, if any
+///
+/// Surrogate pairs, ie. characters above `U+FFFF`, sort between `U+D7FF` and `U+E000`.
+fn next_char(c: char) -> Option<char> {
+    match c as u32 {
+        0xD7FF => Some('\u{10000}'),
+        0x10FFFF => Some('\u{E000}'),
+        0xFFFF => None,
+        n => char::from_u32(n + 1),
+    }
+}
+
+impl RangeBounds<Key> for KeyRange {
+    fn start_bound(&self) -> Bound<&Key> {
+        self.lower.as_ref()
+    }
+
+    fn end_bound(&self) -> Bound<&Key> {
+        self.upper.as_ref()
+    }
+}
+
+impl RangeBounds<Key> for &KeyRange {
+    fn start_bound(&self) -> Bound<&Key> {
+        self.lower.as_ref()
+    }
+
+    fn end_bound(&self) -> Bound<&Key> {
+        self.upper.as_ref()
+    }
+}
@@ -8,22 +8,50 @@ macro_rules! error_name {
     };
 }
 
+pub mod backend;
 mod cursor;
 mod database;
 mod error;
+mod export;
 mod factory;
+mod idb;
 mod index;
+mod key;
+mod key_path;
+mod key_range;
+#[cfg(feature = "memory")]
+pub mod memory;
+#[cfg(feature = "memory")]
+mod native;
 mod object_store;
+mod schema;
+#[cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
+pub mod sqlite;
 mod transaction;
+#[cfg(feature = "serde")]
+mod typed;
 mod utils;
 
-pub use cursor::{Cursor, CursorBuilder, CursorDirection};
+pub use cursor::{Cursor, CursorBuilder, CursorDirection, CursorEntry, KeyCursor, KeyCursorEntry};
 pub use database::{Database, OwnedDatabase};
-pub use error::{Error, Result};
-pub use factory::{Factory, ObjectStoreBuilder, VersionChangeEvent};
+pub use error::{Error, ErrorContext, Result};
+pub use export::ExportFormat;
+pub use factory::{
+    DatabaseInfo, DeleteDatabaseBuilder, Factory, ObjectStoreBuilder, OpenBuilder,
+    VersionChangeEvent,
+};
+pub use idb::{set_panic_on_inactive_transaction, EventStream, VersionChange};
 pub use index::Index;
+pub use key::Key;
+pub use key_path::KeyPath;
+pub use key_range::KeyRange;
 pub use object_store::{IndexBuilder, ObjectStore};
-pub use transaction::{Transaction, TransactionBuilder};
+pub use schema::{
+    IndexSchema, Migration, Schema, SchemaDiff, SchemaSnapshot, StoreDiff, StoreSchema,
+};
+pub use transaction::{Durability, Transaction, TransactionBuilder};
+#[cfg(feature = "serde")]
+pub use typed::{TypedCursor, TypedCursorBuilder, TypedObjectStore};
 
 const POLLED_FORBIDDEN_THING_PANIC: &str = "Transaction blocked without any request under way.
 The developer probably called .await on something that is not an indexed-db-provided future inside a transaction.
@@ -0,0 +1,366 @@
+//! The data structures and algorithms of the in-memory storage, independent of transactions
+
+use super::Value;
+use crate::{
+    native::engine::{
+        bump_generator, index_keys, is_empty, primary_key, Failure, Range, Result, Target,
+    },
+    CursorDirection, IndexSchema, Key, KeyPath, StoreSchema,
+};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
+    ops::Bound,
+    rc::Rc,
+};
+
+/// The smallest possible key, used to build bounds on `(key, primary key)` pairs
+const MIN_KEY: Key = Key::Number(f64::NEG_INFINITY);
+
+/// The committed contents of a database
+#[derive(Clone, Debug, Default)]
+pub(crate) struct DatabaseState {
+    pub(crate) version: u32,
+    /// Shared with the transactions, that only copy the stores they modify
+    pub(crate) stores: BTreeMap<String, Rc<StoreData>>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct StoreData {
+    key_path: Option<KeyPath>,
+    auto_increment: bool,
+    current_number: f64,
+    records: BTreeMap<Key, Value>,
+    indexes: BTreeMap<String, IndexData>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct IndexData {
+    key_path: KeyPath,
+    unique: bool,
+    multi_entry: bool,
+    /// `(index key, primary key)` pairs, which is also the iteration order of index cursors
+    entries: BTreeSet<(Key, Key)>,
+}
+
+/// A bound on `(key, primary key)` pairs
+type EntryBound = Bound<(Key, Key)>;
+
+fn max_lower<T: Ord>(a: Bound<T>, b: Bound<T>) -> Bound<T> {
+    match (a, b) {
+        (Bound::Unbounded, b) => b,
+        (a, Bound::Unbounded) => a,
+        (Bound::Included(a), Bound::Included(b)) => Bound::Included(a.max(b)),
+        (Bound::Excluded(a), Bound::Excluded(b)) => Bound::Excluded(a.max(b)),
+        (Bound::Included(i), Bound::Excluded(e)) | (Bound::Excluded(e), Bound::Included(i)) => {
+            if i > e {
+                Bound::Included(i)
+            } else {
+                Bound::Excluded(e)
+            }
+        }
+    }
+}
+
+fn min_upper<T: Ord>(a: Bound<T>, b: Bound<T>) -> Bound<T> {
+    match (a, b) {
+        (Bound::Unbounded, b) => b,
+        (a, Bound::Unbounded) => a,
+        (Bound::Included(a), Bound::Included(b)) => Bound::Included(a.min(b)),
+        (Bound::Excluded(a), Bound::Excluded(b)) => Bound::Excluded(a.min(b)),
+        (Bound::Included(i), Bound::Excluded(e)) | (Bound::Excluded(e), Bound::Included(i)) => {
+            if i < e {
+                Bound::Included(i)
+            } else {
+                Bound::Excluded(e)
+            }
+        }
+    }
+}
+
+/// The bounds of `range` over `(key, primary key)` pairs
+fn entry_bounds(range: &Range) -> (EntryBound, EntryBound) {
+    let (lower, upper) = range.bounds();
+    let lower = match lower {
+        Bound::Unbounded => Bound::Unbounded,
+        Bound::Included(k) => Bound::Included((k.clone(), MIN_KEY)),
+        Bound::Excluded(k) => Bound::Included((k.successor(), MIN_KEY)),
+    };
+    let upper = match upper {
+        Bound::Unbounded => Bound::Unbounded,
+        Bound::Included(k) => Bound::Excluded((k.successor(), MIN_KEY)),
+        Bound::Excluded(k) => Bound::Excluded((k.clone(), MIN_KEY)),
+    };
+    (lower, upper)
+}
+
+pub(crate) fn limit_to_usize(limit: Option<u32>) -> usize {
+    limit.map_or(usize::MAX, |l| usize::try_from(l).unwrap_or(usize::MAX))
+}
+
+impl StoreData {
+    pub(crate) fn new(key_path: Option<KeyPath>, auto_increment: bool) -> StoreData {
+        StoreData {
+            key_path,
+            auto_increment,
+            current_number: 1.0,
+            records: BTreeMap::new(),
+            indexes: BTreeMap::new(),
+        }
+    }
+
+    pub(crate) fn schema(&self, name: &str) -> StoreSchema {
+        StoreSchema {
+            name: name.to_string(),
+            key_path: self.key_path.clone(),
+            auto_increment: self.auto_increment,
+            indexes: self
+                .indexes
+                .iter()
+                .map(|(name, index)| IndexSchema {
+                    name: name.clone(),
+                    key_path: index.key_path.clone(),
+                    unique: index.unique,
+                    multi_entry: index.multi_entry,
+                })
+                .collect(),
+        }
+    }
+
+    pub(crate) fn records(&self, range: &Range) -> impl DoubleEndedIterator<Item = (&Key, &Value)> {
+        let (lower, upper) = range.bounds();
+        // `BTreeMap::range` panics on some empty ranges
+        (!is_empty(lower, upper))
+            .then(|| self.records.range::<Key, _>((lower, upper)))
+            .into_iter()
+            .flatten()
+    }
+
+    pub(crate) fn get(&self, key: &Key) -> Option<&Value> {
+        self.records.get(key)
+    }
+
+    pub(crate) fn index(&self, name: &str) -> Result<&IndexData> {
+        self.indexes
+            .get(name)
+            .ok_or(Failure::DoesNotExist("The index does not exist"))
+    }
+
+    pub(crate) fn create_index(
+        &mut self,
+        name: &str,
+        key_path: KeyPath,
+        unique: bool,
+        multi_entry: bool,
+    ) -> Result<()> {
+        let mut index = IndexData {
+            key_path,
+            unique,
+            multi_entry,
+            entries: BTreeSet::new(),
+        };
+        for (primary_key, value) in &self.records {
+            for key in index.keys_of(value) {
+                if index.conflicts(&key, primary_key) {
+                    return Err(Failure::AlreadyExists(
+                        "Existing records violate the uniqueness of the index",
+                    ));
+                }
+                index.entries.insert((key, primary_key.clone()));
+            }
+        }
+        self.indexes.insert(name.to_string(), index);
+        Ok(())
+    }
+
+    pub(crate) fn delete_index(&mut self, name: &str) -> Result<()> {
+        self.indexes
+            .remove(name)
+            .map(|_| ())
+            .ok_or(Failure::DoesNotExist("The index does not exist"))
+    }
+
+    /// Store `value`, with key `key` for out-of-line keys, and return its key
+    ///
+    /// If `overwrite` is `false`, this fails if a record already exists with the same key.
+    pub(crate) fn put(&mut self, value: &Value, key: Option<&Key>, overwrite: bool) -> Result<Key> {
+        let mut value = Cow::Borrowed(value);
+        let generator = self.auto_increment.then_some(self.current_number);
+        let key = primary_key(self.key_path.as_ref(), generator, &mut value, key)?;
+        if !overwrite && self.records.contains_key(&key) {
+            return Err(Failure::AlreadyExists(
+                "A record with this key already exists",
+            ));
+        }
+
+        let index_keys = self
+            .indexes
+            .values()
+            .map(|index| {
+                let keys = index.keys_of(&value);
+                if keys.iter().any(|k| index.conflicts(k, &key)) {
+                    return Err(Failure::AlreadyExists(
+                        "The record violates the uniqueness of an index",
+                    ));
+                }
+                Ok(keys)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if self.auto_increment {
+            self.current_number = bump_generator(self.current_number, &key);
+        }
+        self.delete_record(&key);
+        for (index, keys) in self.indexes.values_mut().zip(index_keys) {
+            index
+                .entries
+                .extend(keys.into_iter().map(|k| (k, key.clone())));
+        }
+        self.records.insert(key.clone(), value.into_owned());
+        Ok(key)
+    }
+
+    fn delete_record(&mut self, key: &Key) {
+        if let Some(old) = self.records.remove(key) {
+            for index in self.indexes.values_mut() {
+                for index_key in index.keys_of(&old) {
+                    index.entries.remove(&(index_key, key.clone()));
+                }
+            }
+        }
+    }
+
+    pub(crate) fn delete(&mut self, range: &Range) {
+        let keys = self
+            .records(range)
+            .map(|(k, _)| k.clone())
+            .collect::<Vec<_>>();
+        for key in keys {
+            self.delete_record(&key);
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.records.clear();
+        for index in self.indexes.values_mut() {
+            index.entries.clear();
+        }
+    }
+
+    /// Move a cursor over this object store, and return the primary key it lands on
+    pub(crate) fn step(
+        &self,
+        range: &Range,
+        direction: CursorDirection,
+        target: Target,
+    ) -> Option<Key> {
+        let forward = crate::native::engine::is_forward(direction);
+        let (lower, upper) = range.bounds();
+        let (lower, upper) = match (forward, target) {
+            (_, Target::Start) => (lower, upper),
+            (true, Target::After(key, _)) => (max_lower(lower, Bound::Excluded(key)), upper),
+            (true, Target::Key(key) | Target::Entry(key, _)) => {
+                (max_lower(lower, Bound::Included(key)), upper)
+            }
+            (false, Target::After(key, _)) => (lower, min_upper(upper, Bound::Excluded(key))),
+            (false, Target::Key(key) | Target::Entry(key, _)) => {
+                (lower, min_upper(upper, Bound::Included(key)))
+            }
+        };
+        if is_empty(lower, upper) {
+            return None;
+        }
+        let mut records = self.records.range::<Key, _>((lower, upper));
+        let record = if forward {
+            records.next()
+        } else {
+            records.next_back()
+        };
+        record.map(|(k, _)| k.clone())
+    }
+}
+
+impl IndexData {
+    /// The keys of this index that reference the record `value`
+    fn keys_of(&self, value: &Value) -> Vec<Key> {
+        index_keys(&self.key_path, self.multi_entry, value)
+    }
+
+    /// Whether indexing `key` for the record with key `primary_key` would break uniqueness
+    fn conflicts(&self, key: &Key, primary_key: &Key) -> bool {
+        self.unique
+            && self
+                .entries
+                .range((
+                    Bound::Included((key.clone(), MIN_KEY)),
+                    Bound::Excluded((key.successor(), MIN_KEY)),
+                ))
+                .any(|(_, pk)| pk != primary_key)
+    }
+
+    /// The `(key, primary key)` pairs with a key in `range`
+    pub(crate) fn entries(&self, range: &Range) -> impl DoubleEndedIterator<Item = &(Key, Key)> {
+        let (lower, upper) = entry_bounds(range);
+        (!is_empty(lower.as_ref(), upper.as_ref()))
+            .then(|| self.entries.range((lower, upper)))
+            .into_iter()
+            .flatten()
+    }
+
+    /// Move a cursor over this index, and return the `(key, primary key)` pair it lands on
+    pub(crate) fn step(
+        &self,
+        range: &Range,
+        direction: CursorDirection,
+        target: Target,
+    ) -> Option<(Key, Key)> {
+        let entry = |k: &Key, pk: &Key| (k.clone(), pk.clone());
+        let (lower, upper) = entry_bounds(range);
+        let (lower, upper) = match (direction, target) {
+            (_, Target::Start) => (lower, upper),
+            (CursorDirection::Next, Target::After(k, pk)) => {
+                (max_lower(lower, Bound::Excluded(entry(k, pk))), upper)
+            }
+            (CursorDirection::NextUnique, Target::After(k, _)) => (
+                max_lower(lower, Bound::Included((k.successor(), MIN_KEY))),
+                upper,
+            ),
+            (CursorDirection::Next | CursorDirection::NextUnique, Target::Key(k)) => (
+                max_lower(lower, Bound::Included((k.clone(), MIN_KEY))),
+                upper,
+            ),
+            (CursorDirection::Next | CursorDirection::NextUnique, Target::Entry(k, pk)) => {
+                (max_lower(lower, Bound::Included(entry(k, pk))), upper)
+            }
+            (CursorDirection::Prev, Target::After(k, pk)) => {
+                (lower, min_upper(upper, Bound::Excluded(entry(k, pk))))
+            }
+            (CursorDirection::PrevUnique, Target::After(k, _)) => (
+                lower,
+                min_upper(upper, Bound::Excluded((k.clone(), MIN_KEY))),
+            ),
+            (CursorDirection::Prev | CursorDirection::PrevUnique, Target::Key(k)) => (
+                lower,
+                min_upper(upper, Bound::Excluded((k.successor(), MIN_KEY))),
+            ),
+            (CursorDirection::Prev | CursorDirection::PrevUnique, Target::Entry(k, pk)) => {
+                (lower, min_upper(upper, Bound::Included(entry(k, pk))))
+            }
+        };
+        if is_empty(lower.as_ref(), upper.as_ref()) {
+            return None;
+        }
+        let mut entries = self.entries.range((lower, upper));
+        match direction {
+            CursorDirection::Next | CursorDirection::NextUnique => entries.next().cloned(),
+            CursorDirection::Prev => entries.next_back().cloned(),
+            // Unique cursors land on the first record of each key, even when going backwards
+            CursorDirection::PrevUnique => {
+                let (key, _) = entries.next_back()?;
+                self.entries
+                    .range((Bound::Included((key.clone(), MIN_KEY)), Bound::Unbounded))
+                    .next()
+                    .cloned()
+            }
+        }
+    }
+}
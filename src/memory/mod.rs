@@ -0,0 +1,90 @@
+//! In-memory backend, to run code built on this crate natively, eg. in `cargo test`
+//!
+//! This is used through the types of the crate root, with [`Memory`] as their backend and [`Value`]s
+//! instead of `JsValue`s, and implements the IndexedDB semantics in Rust: key ordering, key generators,
+//! unique and multi-entry indexes, cursors, rollback of the transactions that return `Err` and
+//! `versionchange` upgrades. Nothing is persisted, and each `Factory::in_memory()` starts empty. The
+//! aliases of this module name the types of the crate root with this backend.
+//!
+//! Unlike in browsers, transactions on the same database never run concurrently, even read-only ones or
+//! ones with disjoint scopes: each one waits for the previous one to complete before starting.
+//!
+//! ```rust
+//! # use indexed_db::{memory::{Factory, Value}, Key};
+//! # use std::convert::Infallible;
+//! # futures::executor::block_on(async {
+//! let factory = Factory::in_memory();
+//! let db = factory
+//!     .open::<Infallible>("db", 1, async |evt| {
+//!         let store = evt.build_object_store("users").key_path("id").create()?;
+//!         store.build_index("name", "name").create()?;
+//!         Ok(())
+//!     })
+//!     .await?;
+//! let user = db
+//!     .transaction(&["users"])
+//!     .rw()
+//!     .run::<_, Infallible>(async |t| {
+//!         let users = t.object_store("users")?;
+//!         users.add(&Value::object([("id", Value::from(1)), ("name", Value::from("Jane"))])).await?;
+//!         users.index("name")?.get(&Key::from("Jane")).await
+//!     })
+//!     .await?;
+//! assert_eq!(user.unwrap().get("id"), Some(&Value::from(1)));
+//! # Ok::<_, indexed_db::Error<Infallible>>(())
+//! # }).unwrap();
+//! ```
+
+use crate::native;
+
+pub(crate) mod engine;
+#[cfg(feature = "serde")]
+pub(crate) mod serde;
+mod storage;
+mod value;
+
+pub use value::Value;
+
+/// The in-memory backend, to use as the `B` parameter of the types of the crate root
+#[derive(Clone, Copy, Debug)]
+pub struct Memory;
+
+/// Factory of the in-memory backend
+///
+/// Clones of a factory share the same databases.
+///
+/// Upgrading a database closes the other connections to it, instead of firing `versionchange` events on
+/// them and waiting for them to close like browsers do. Their transactions then fail with
+/// [`Error::DatabaseIsClosed`](crate::Error::DatabaseIsClosed), as after [`Factory::delete_database`].
+pub type Factory = crate::Factory<Memory>;
+
+/// Database connection of the in-memory backend
+pub type Database = crate::Database<Memory>;
+
+/// Owned database connection of the in-memory backend
+pub type OwnedDatabase = crate::OwnedDatabase<Memory>;
+
+/// Version change event of the in-memory backend
+pub type VersionChangeEvent<Err> = crate::VersionChangeEvent<Err, Memory>;
+
+/// Transaction of the in-memory backend
+pub type Transaction<Err> = crate::Transaction<Err, Memory>;
+
+/// Object store of the in-memory backend
+pub type ObjectStore<Err> = crate::ObjectStore<Err, Memory>;
+
+/// Index of the in-memory backend
+pub type Index<Err> = crate::Index<Err, Memory>;
+
+/// Cursor of the in-memory backend
+pub type Cursor<Err> = crate::Cursor<Err, Memory>;
+
+/// Key-only cursor of the in-memory backend
+pub type KeyCursor<Err> = crate::KeyCursor<Err, Memory>;
+
+impl crate::Factory<Memory> {
+    /// Create a new factory, without any database
+    pub fn in_memory() -> Factory {
+        Factory::from_backend(native::Factory::new(Default::default()))
+    }
+}
@@ -0,0 +1,319 @@
+//! The [`Storage`] of the in-memory backend
+
+use super::{
+    engine::{limit_to_usize, DatabaseState, StoreData},
+    Memory, Value,
+};
+use crate::{
+    native::{
+        engine::{Failure, Range, Result, Target},
+        Mode, Storage, StorageTx,
+    },
+    CursorDirection, DatabaseInfo, Key, KeyPath, StoreSchema,
+};
+use futures_util::lock::Mutex;
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeMap,
+    rc::Rc,
+};
+
+/// The databases of a factory
+#[derive(Debug, Default)]
+pub struct Databases {
+    databases: RefCell<BTreeMap<String, Rc<DbShared>>>,
+    /// The lock of each database name, that serializes all the transactions on the database
+    ///
+    /// It is kept even once the database is deleted, so that a new database with the same name waits for
+    /// the transactions of the previous one.
+    locks: RefCell<BTreeMap<String, Rc<Mutex<()>>>>,
+}
+
+/// A database, shared by the factory and all its connections
+#[derive(Debug)]
+pub struct DbShared {
+    name: String,
+    state: RefCell<DatabaseState>,
+    deleted: Cell<bool>,
+}
+
+/// A transaction of the in-memory backend, working on a copy of the database
+#[derive(Debug)]
+pub struct MemoryTx {
+    databases: Rc<Databases>,
+    db: Rc<DbShared>,
+    /// Whether the database is created by this transaction, and only exists once it commits
+    created: bool,
+    version: u32,
+    /// The object stores, copied on write
+    stores: BTreeMap<String, Rc<StoreData>>,
+}
+
+impl MemoryTx {
+    fn new(databases: &Rc<Databases>, db: Rc<DbShared>, created: bool) -> MemoryTx {
+        let state = db.state.borrow().clone();
+        MemoryTx {
+            databases: databases.clone(),
+            db: db.clone(),
+            created,
+            version: state.version,
+            stores: state.stores,
+        }
+    }
+
+    fn data(&self, store: &str) -> Result<&StoreData> {
+        self.stores
+            .get(store)
+            .map(|s| &**s)
+            .ok_or(Failure::ObjectStoreWasRemoved)
+    }
+
+    fn data_mut(&mut self, store: &str) -> Result<&mut StoreData> {
+        self.stores
+            .get_mut(store)
+            .map(Rc::make_mut)
+            .ok_or(Failure::ObjectStoreWasRemoved)
+    }
+}
+
+impl Storage for Memory {
+    type Shared = Rc<Databases>;
+    type Db = Rc<DbShared>;
+    type Tx = MemoryTx;
+
+    fn lock(shared: &Rc<Databases>, name: &str) -> Rc<Mutex<()>> {
+        shared
+            .locks
+            .borrow_mut()
+            .entry(name.to_string())
+            .or_default()
+            .clone()
+    }
+
+    fn databases(shared: &Rc<Databases>) -> Result<Vec<DatabaseInfo>> {
+        Ok(shared
+            .databases
+            .borrow()
+            .values()
+            .map(|db| DatabaseInfo {
+                name: db.name.clone(),
+                version: db.state.borrow().version,
+            })
+            .collect())
+    }
+
+    fn find(shared: &Rc<Databases>, name: &str) -> Result<Option<(Rc<DbShared>, u32)>> {
+        Ok(shared
+            .databases
+            .borrow()
+            .get(name)
+            .map(|db| (db.clone(), db.state.borrow().version)))
+    }
+
+    fn delete_database(shared: &Rc<Databases>, name: &str) -> Result<()> {
+        if let Some(db) = shared.databases.borrow_mut().remove(name) {
+            db.deleted.set(true);
+        }
+        Ok(())
+    }
+
+    fn begin_upgrade(shared: &Rc<Databases>, name: &str) -> Result<(Rc<DbShared>, MemoryTx)> {
+        let existing = shared.databases.borrow().get(name).cloned();
+        let (db, created) = match existing {
+            Some(db) => (db, false),
+            None => {
+                let db = DbShared {
+                    name: name.to_string(),
+                    state: RefCell::new(DatabaseState::default()),
+                    deleted: Cell::new(false),
+                };
+                (Rc::new(db), true)
+            }
+        };
+        Ok((db.clone(), MemoryTx::new(shared, db, created)))
+    }
+
+    fn begin(shared: &Rc<Databases>, db: &Rc<DbShared>, _mode: Mode) -> Result<Option<MemoryTx>> {
+        if db.deleted.get() {
+            return Ok(None);
+        }
+        Ok(Some(MemoryTx::new(shared, db.clone(), false)))
+    }
+
+    fn store_names(_shared: &Rc<Databases>, db: &Rc<DbShared>) -> Vec<String> {
+        if db.deleted.get() {
+            return Vec::new();
+        }
+        db.state.borrow().stores.keys().cloned().collect()
+    }
+}
+
+impl StorageTx for MemoryTx {
+    fn version(&self) -> u32 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u32) -> Result<()> {
+        self.version = version;
+        Ok(())
+    }
+
+    fn store_names(&self) -> Vec<String> {
+        self.stores.keys().cloned().collect()
+    }
+
+    fn store(&self, store: &str) -> Result<StoreSchema> {
+        Ok(self.data(store)?.schema(store))
+    }
+
+    fn create_store(
+        &mut self,
+        store: &str,
+        key_path: Option<KeyPath>,
+        auto_increment: bool,
+    ) -> Result<()> {
+        let data = StoreData::new(key_path, auto_increment);
+        self.stores.insert(store.to_string(), Rc::new(data));
+        Ok(())
+    }
+
+    fn delete_store(&mut self, store: &str) -> Result<()> {
+        self.stores.remove(store);
+        Ok(())
+    }
+
+    fn create_index(
+        &mut self,
+        store: &str,
+        index: &str,
+        key_path: KeyPath,
+        unique: bool,
+        multi_entry: bool,
+    ) -> Result<()> {
+        self.data_mut(store)?
+            .create_index(index, key_path, unique, multi_entry)
+    }
+
+    fn delete_index(&mut self, store: &str, index: &str) -> Result<()> {
+        self.data_mut(store)?.delete_index(index)
+    }
+
+    fn count(&self, store: &str, range: &Range) -> Result<usize> {
+        Ok(self.data(store)?.records(range).count())
+    }
+
+    fn keys(&self, store: &str, range: &Range, limit: Option<u32>) -> Result<Vec<Key>> {
+        Ok(self
+            .data(store)?
+            .records(range)
+            .take(limit_to_usize(limit))
+            .map(|(k, _)| k.clone())
+            .collect())
+    }
+
+    fn values(&self, store: &str, range: &Range, limit: Option<u32>) -> Result<Vec<Value>> {
+        Ok(self
+            .data(store)?
+            .records(range)
+            .take(limit_to_usize(limit))
+            .map(|(_, v)| v.clone())
+            .collect())
+    }
+
+    fn step(
+        &self,
+        store: &str,
+        range: &Range,
+        direction: CursorDirection,
+        target: Target,
+    ) -> Result<Option<Key>> {
+        Ok(self.data(store)?.step(range, direction, target))
+    }
+
+    fn put(
+        &mut self,
+        store: &str,
+        value: &Value,
+        key: Option<&Key>,
+        overwrite: bool,
+    ) -> Result<Key> {
+        self.data_mut(store)?.put(value, key, overwrite)
+    }
+
+    fn delete(&mut self, store: &str, range: &Range) -> Result<()> {
+        self.data_mut(store)?.delete(range);
+        Ok(())
+    }
+
+    fn clear(&mut self, store: &str) -> Result<()> {
+        self.data_mut(store)?.clear();
+        Ok(())
+    }
+
+    fn index_count(&self, store: &str, index: &str, range: &Range) -> Result<usize> {
+        Ok(self.data(store)?.index(index)?.entries(range).count())
+    }
+
+    fn index_entries(
+        &self,
+        store: &str,
+        index: &str,
+        range: &Range,
+        limit: Option<u32>,
+    ) -> Result<Vec<(Key, Key)>> {
+        Ok(self
+            .data(store)?
+            .index(index)?
+            .entries(range)
+            .take(limit_to_usize(limit))
+            .cloned()
+            .collect())
+    }
+
+    fn index_values(
+        &self,
+        store: &str,
+        index: &str,
+        range: &Range,
+        limit: Option<u32>,
+    ) -> Result<Vec<Value>> {
+        let data = self.data(store)?;
+        Ok(data
+            .index(index)?
+            .entries(range)
+            .take(limit_to_usize(limit))
+            .filter_map(|(_, pk)| data.get(pk).cloned())
+            .collect())
+    }
+
+    fn index_step(
+        &self,
+        store: &str,
+        index: &str,
+        range: &Range,
+        direction: CursorDirection,
+        target: Target,
+    ) -> Result<Option<(Key, Key)>> {
+        Ok(self
+            .data(store)?
+            .index(index)?
+            .step(range, direction, target))
+    }
+
+    fn commit(&mut self) -> Result<()> {
+        // The stores are kept, so that the handles can still read the schema
+        *self.db.state.borrow_mut() = DatabaseState {
+            version: self.version,
+            stores: self.stores.clone(),
+        };
+        if self.created {
+            self.databases
+                .databases
+                .borrow_mut()
+                .insert(self.db.name.clone(), self.db.clone());
+        }
+        Ok(())
+    }
+
+    fn rollback(&mut self) {}
+}
@@ -0,0 +1,222 @@
+use crate::{Key, KeyPath};
+use std::{borrow::Cow, collections::BTreeMap};
+
+/// A value stored by the in-memory backend
+///
+/// This models the values that IndexedDB can store, ie. the ones that survive the structured clone
+/// algorithm, restricted to the ones that are useful as records. Keys can be extracted from values
+/// with key paths, like `"user.id"` for the `id` field of the object in the `user` field.
+///
+/// ```rust
+/// # use indexed_db::{Key, memory::Value};
+/// let value = Value::object([("id", Value::from(42)), ("name", Value::from("Jane"))]);
+/// assert_eq!(value.get("id"), Some(&Value::from(42)));
+/// assert_eq!(value.get("id").and_then(Value::to_key), Some(Key::from(42)));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    /// `undefined`
+    Undefined,
+
+    /// `null`
+    Null,
+
+    /// A boolean
+    Bool(bool),
+
+    /// A number
+    Number(f64),
+
+    /// A string
+    String(String),
+
+    /// A date, as a number of milliseconds since the Unix epoch
+    Date(f64),
+
+    /// A binary buffer, like an `ArrayBuffer` or a `Uint8Array`
+    Binary(Vec<u8>),
+
+    /// An array
+    Array(Vec<Value>),
+
+    /// An object, with its fields
+    Object(BTreeMap<String, Value>),
+}
+
+impl Value {
+    /// Build an object from its fields
+    pub fn object<K: Into<String>, V: Into<Value>>(
+        fields: impl IntoIterator<Item = (K, V)>,
+    ) -> Value {
+        Value::Object(
+            fields
+                .into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        )
+    }
+
+    /// The field `name` of this value, if it is an object that has this field
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        match self {
+            Value::Object(fields) => fields.get(name),
+            _ => None,
+        }
+    }
+
+    /// Convert this value into a key, or `None` if it is not a valid key
+    ///
+    /// This works like [`Key::from_js`], so eg. booleans, objects and `NaN` are not valid keys.
+    pub fn to_key(&self) -> Option<Key> {
+        match self {
+            Value::Number(n) => (!n.is_nan()).then_some(Key::Number(*n)),
+            Value::Date(t) => (!t.is_nan()).then_some(Key::Date(*t)),
+            Value::String(s) => Some(Key::String(s.clone())),
+            Value::Binary(b) => Some(Key::Binary(b.clone())),
+            Value::Array(a) => a
+                .iter()
+                .map(Value::to_key)
+                .collect::<Option<Vec<_>>>()
+                .map(Key::Array),
+            _ => None,
+        }
+    }
+
+    /// The value at `path`, which is a dot-separated list of fields, or the empty string for the value itself
+    ///
+    /// Like in Javascript, strings and arrays also have a `length` field.
+    pub(crate) fn lookup(&self, path: &str) -> Option<Cow<'_, Value>> {
+        let mut current = Cow::Borrowed(self);
+        if path.is_empty() {
+            return Some(current);
+        }
+        for name in path.split('.') {
+            current = match (current, name) {
+                (Cow::Borrowed(Value::Object(fields)), _) => Cow::Borrowed(fields.get(name)?),
+                (Cow::Borrowed(Value::String(s)), "length") => {
+                    Cow::Owned(Value::Number(s.encode_utf16().count() as f64))
+                }
+                (Cow::Borrowed(Value::Array(a)), "length") => {
+                    Cow::Owned(Value::Number(a.len() as f64))
+                }
+                _ => return None,
+            };
+        }
+        Some(current)
+    }
+
+    /// Evaluate `key_path` on this value
+    ///
+    /// This returns `None` if one of the paths is missing, and `Some(None)` if the key path resolved to
+    /// something that is not a valid key.
+    pub(crate) fn evaluate(&self, key_path: &KeyPath) -> Option<Option<Key>> {
+        match key_path {
+            KeyPath::Single(path) => Some(self.lookup(path)?.to_key()),
+            KeyPath::Compound(paths) => {
+                let mut keys = Vec::with_capacity(paths.len());
+                for path in paths {
+                    match self.lookup(path)?.to_key() {
+                        Some(key) => keys.push(key),
+                        None => return Some(None),
+                    }
+                }
+                Some(Some(Key::Array(keys)))
+            }
+        }
+    }
+
+    /// Set the value at `path` to `key`, creating the missing intermediate objects
+    ///
+    /// Returns `false` if this is not possible, because one of the intermediate values is not an object.
+    pub(crate) fn inject(&mut self, path: &str, key: &Key) -> bool {
+        let mut current = self;
+        let mut names = path.split('.').peekable();
+        while let Some(name) = names.next() {
+            let Value::Object(fields) = current else {
+                return false;
+            };
+            if names.peek().is_none() {
+                fields.insert(name.to_string(), Value::from(key.clone()));
+                return true;
+            }
+            current = fields
+                .entry(name.to_string())
+                .or_insert_with(|| Value::Object(BTreeMap::new()));
+        }
+        false
+    }
+}
+
+impl From<Key> for Value {
+    fn from(key: Key) -> Value {
+        match key {
+            Key::Number(n) => Value::Number(n),
+            Key::Date(t) => Value::Date(t),
+            Key::String(s) => Value::String(s),
+            Key::Binary(b) => Value::Binary(b),
+            Key::Array(a) => Value::Array(a.into_iter().map(Value::from).collect()),
+        }
+    }
+}
+
+impl From<&Key> for Value {
+    fn from(key: &Key) -> Value {
+        Value::from(key.clone())
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Value {
+        Value::Bool(b)
+    }
+}
+
+macro_rules! impl_from_number {
+    ($($t:ty),*) => {
+        $(
+            impl From<$t> for Value {
+                fn from(n: $t) -> Value {
+                    Value::Number(f64::from(n))
+                }
+            }
+        )*
+    };
+}
+
+impl_from_number!(i8, u8, i16, u16, i32, u32, f32, f64);
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Value {
+        Value::String(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Value {
+        Value::String(s)
+    }
+}
+
+impl From<Vec<u8>> for Value {
+    fn from(b: Vec<u8>) -> Value {
+        Value::Binary(b)
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(a: Vec<Value>) -> Value {
+        Value::Array(a)
+    }
+}
+
+impl From<BTreeMap<String, Value>> for Value {
+    fn from(fields: BTreeMap<String, Value>) -> Value {
+        Value::Object(fields)
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(v: Option<T>) -> Value {
+        v.map_or(Value::Null, Into::into)
+    }
+}
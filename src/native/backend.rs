@@ -0,0 +1,509 @@
+use super::{
+    Cursor, CursorBuilder, Database, Factory, Index, KeyCursor, ObjectStore, Storage, Transaction,
+    TransactionBuilder, Value, VersionChangeEvent,
+};
+use crate::{
+    backend::{
+        Backend, BackendCursor, BackendCursorBuilder, BackendDatabase, BackendFactory,
+        BackendIndex, BackendKeyCursor, BackendObjectStore, BackendTransaction,
+        BackendTransactionBuilder, BackendVersionChangeEvent,
+    },
+    CursorDirection, DatabaseInfo, Durability, Key, KeyPath,
+};
+use std::{borrow::Borrow, convert::Infallible, future::Future, ops::RangeBounds};
+
+impl<S: Storage> Backend for S {
+    type Value = Value;
+    type Factory = Factory<S>;
+    type Database = Database<S>;
+    type VersionChangeEvent<Err> = VersionChangeEvent<Err, S>;
+    type TransactionBuilder = TransactionBuilder<S>;
+    type Transaction<Err> = Transaction<Err, S>;
+    type ObjectStore<Err> = ObjectStore<Err, S>;
+    type Index<Err> = Index<Err, S>;
+    type CursorBuilder<Err> = CursorBuilder<Err, S>;
+    type Cursor<Err> = Cursor<Err, S>;
+    type KeyCursor<Err> = KeyCursor<Err, S>;
+}
+
+impl<S: Storage> BackendFactory<S> for Factory<S> {
+    fn open<Err: 'static>(
+        &self,
+        name: &str,
+        version: u32,
+        on_upgrade_needed: impl AsyncFnOnce(VersionChangeEvent<Err, S>) -> crate::Result<(), Err>,
+    ) -> impl Future<Output = crate::Result<Database<S>, Err>> {
+        self.open(name, version, on_upgrade_needed)
+    }
+
+    fn open_latest_version(
+        &self,
+        name: &str,
+    ) -> impl Future<Output = crate::Result<Database<S>, Infallible>> {
+        self.open_latest_version(name)
+    }
+
+    fn delete_database(&self, name: &str) -> impl Future<Output = crate::Result<(), Infallible>> {
+        self.delete_database(name)
+    }
+
+    fn databases(&self) -> impl Future<Output = crate::Result<Vec<DatabaseInfo>, Infallible>> {
+        self.databases()
+    }
+}
+
+impl<S: Storage> BackendDatabase<S> for Database<S> {
+    fn name(&self) -> String {
+        self.name()
+    }
+
+    fn version(&self) -> u32 {
+        self.version()
+    }
+
+    fn object_store_names(&self) -> Vec<String> {
+        self.object_store_names()
+    }
+
+    fn transaction(&self, stores: &[&str]) -> TransactionBuilder<S> {
+        self.transaction(stores)
+    }
+
+    fn close(&self) {
+        self.close()
+    }
+}
+
+impl<Err, S: Storage> BackendVersionChangeEvent<S, Err> for VersionChangeEvent<Err, S> {
+    fn old_version(&self) -> u32 {
+        self.old_version()
+    }
+
+    fn new_version(&self) -> u32 {
+        self.new_version()
+    }
+
+    fn database(&self) -> &Database<S> {
+        self.database()
+    }
+
+    fn create_object_store(
+        &self,
+        name: &str,
+        key_path: Option<&KeyPath>,
+        auto_increment: bool,
+    ) -> crate::Result<ObjectStore<Err, S>, Err> {
+        self.create_object_store(name, key_path, auto_increment)
+    }
+
+    fn delete_object_store(&self, name: &str) -> crate::Result<(), Err> {
+        self.delete_object_store(name)
+    }
+
+    fn transaction(&self) -> &Transaction<Err, S> {
+        self.transaction()
+    }
+}
+
+impl<S: Storage> BackendTransactionBuilder<S> for TransactionBuilder<S> {
+    fn rw(self) -> Self {
+        self.rw()
+    }
+
+    fn durability(self, durability: Durability) -> Self {
+        self.durability(durability)
+    }
+
+    fn run<Ret, Err>(
+        self,
+        transaction: impl AsyncFnOnce(Transaction<Err, S>) -> crate::Result<Ret, Err>,
+    ) -> impl Future<Output = crate::Result<Ret, Err>> {
+        self.run(transaction)
+    }
+
+    fn run_no_wait<Ret, Err>(
+        self,
+        transaction: impl AsyncFnOnce(Transaction<Err, S>) -> crate::Result<Ret, Err>,
+    ) -> impl Future<Output = crate::Result<Ret, Err>> {
+        self.run_no_wait(transaction)
+    }
+}
+
+impl<Err, S: Storage> BackendTransaction<S, Err> for Transaction<Err, S> {
+    fn object_store(&self, name: &str) -> crate::Result<ObjectStore<Err, S>, Err> {
+        self.object_store(name)
+    }
+
+    fn commit(&self) -> crate::Result<(), Err> {
+        self.commit()
+    }
+
+    fn abort(&self) -> crate::Result<(), Err> {
+        self.abort()
+    }
+}
+
+impl<Err, S: Storage> BackendObjectStore<S, Err> for ObjectStore<Err, S> {
+    fn name(&self) -> String {
+        self.name()
+    }
+
+    fn key_path(&self) -> crate::Result<Option<KeyPath>, Err> {
+        Ok(self.key_path())
+    }
+
+    fn auto_increment(&self) -> bool {
+        self.auto_increment()
+    }
+
+    fn index_names(&self) -> Vec<String> {
+        self.index_names()
+    }
+
+    fn create_index(
+        &self,
+        name: &str,
+        key_path: &KeyPath,
+        unique: bool,
+        multi_entry: bool,
+    ) -> crate::Result<(), Err> {
+        self.create_index(name, key_path, unique, multi_entry)
+    }
+
+    fn delete_index(&self, name: &str) -> crate::Result<(), Err> {
+        self.delete_index(name)
+    }
+
+    fn add(&self, value: &Value) -> impl Future<Output = crate::Result<Key, Err>> + use<Err, S> {
+        self.add(value)
+    }
+
+    fn add_kv(
+        &self,
+        key: &Key,
+        value: &Value,
+    ) -> impl Future<Output = crate::Result<(), Err>> + use<Err, S> {
+        self.add_kv(key, value)
+    }
+
+    fn put(&self, value: &Value) -> impl Future<Output = crate::Result<Key, Err>> + use<Err, S> {
+        self.put(value)
+    }
+
+    fn put_kv(
+        &self,
+        key: &Key,
+        value: &Value,
+    ) -> impl Future<Output = crate::Result<(), Err>> + use<Err, S> {
+        self.put_kv(key, value)
+    }
+
+    fn add_many<I: IntoIterator<Item = V>, V: Borrow<Value>>(
+        &self,
+        values: I,
+    ) -> impl Future<Output = crate::Result<Vec<Key>, Err>> + use<Err, S, I, V> {
+        self.add_many(values)
+    }
+
+    fn add_kv_many<I: IntoIterator<Item = (Key, V)>, V: Borrow<Value>>(
+        &self,
+        entries: I,
+    ) -> impl Future<Output = crate::Result<(), Err>> + use<Err, S, I, V> {
+        self.add_kv_many(entries)
+    }
+
+    fn put_many<I: IntoIterator<Item = V>, V: Borrow<Value>>(
+        &self,
+        values: I,
+    ) -> impl Future<Output = crate::Result<Vec<Key>, Err>> + use<Err, S, I, V> {
+        self.put_many(values)
+    }
+
+    fn put_kv_many<I: IntoIterator<Item = (Key, V)>, V: Borrow<Value>>(
+        &self,
+        entries: I,
+    ) -> impl Future<Output = crate::Result<(), Err>> + use<Err, S, I, V> {
+        self.put_kv_many(entries)
+    }
+
+    fn update_with<F: FnOnce(Option<Value>) -> Option<Value>>(
+        &self,
+        key: &Key,
+        f: F,
+    ) -> impl Future<Output = crate::Result<(Option<Value>, Option<Value>), Err>> + use<Err, S, F>
+    {
+        self.update_with(key, f)
+    }
+
+    fn compare_and_swap(
+        &self,
+        key: &Key,
+        expected: Option<&Value>,
+        new: Option<&Value>,
+    ) -> impl Future<Output = crate::Result<(Option<Value>, Option<Value>), Err>> + use<Err, S>
+    {
+        self.compare_and_swap(key, expected, new)
+    }
+
+    fn clear(&self) -> impl Future<Output = crate::Result<(), Err>> + use<Err, S> {
+        self.clear()
+    }
+
+    fn count(&self) -> impl Future<Output = crate::Result<usize, Err>> + use<Err, S> {
+        self.count()
+    }
+
+    fn contains(&self, key: &Key) -> impl Future<Output = crate::Result<bool, Err>> + use<Err, S> {
+        self.contains(key)
+    }
+
+    fn count_in<R: RangeBounds<Key>>(
+        &self,
+        range: R,
+    ) -> impl Future<Output = crate::Result<usize, Err>> + use<Err, S, R> {
+        self.count_in(range)
+    }
+
+    fn delete(&self, key: &Key) -> impl Future<Output = crate::Result<(), Err>> + use<Err, S> {
+        self.delete(key)
+    }
+
+    fn delete_range<R: RangeBounds<Key>>(
+        &self,
+        range: R,
+    ) -> impl Future<Output = crate::Result<(), Err>> + use<Err, S, R> {
+        self.delete_range(range)
+    }
+
+    fn delete_many<I: IntoIterator<Item = Key>>(
+        &self,
+        keys: I,
+    ) -> impl Future<Output = crate::Result<(), Err>> + use<Err, S, I> {
+        self.delete_many(keys)
+    }
+
+    fn get(
+        &self,
+        key: &Key,
+    ) -> impl Future<Output = crate::Result<Option<Value>, Err>> + use<Err, S> {
+        self.get(key)
+    }
+
+    fn get_first_in<R: RangeBounds<Key>>(
+        &self,
+        range: R,
+    ) -> impl Future<Output = crate::Result<Option<Value>, Err>> + use<Err, S, R> {
+        self.get_first_in(range)
+    }
+
+    fn get_all(
+        &self,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<Value>, Err>> + use<Err, S> {
+        self.get_all(limit)
+    }
+
+    fn get_all_in<R: RangeBounds<Key>>(
+        &self,
+        range: R,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<Value>, Err>> + use<Err, S, R> {
+        self.get_all_in(range, limit)
+    }
+
+    fn get_first_key_in<R: RangeBounds<Key>>(
+        &self,
+        range: R,
+    ) -> impl Future<Output = crate::Result<Option<Key>, Err>> + use<Err, S, R> {
+        self.get_first_key_in(range)
+    }
+
+    fn get_all_keys(
+        &self,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<Key>, Err>> + use<Err, S> {
+        self.get_all_keys(limit)
+    }
+
+    fn get_all_keys_in<R: RangeBounds<Key>>(
+        &self,
+        range: R,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<Key>, Err>> + use<Err, S, R> {
+        self.get_all_keys_in(range, limit)
+    }
+
+    fn index(&self, name: &str) -> crate::Result<Index<Err, S>, Err> {
+        self.index(name)
+    }
+
+    fn cursor(&self) -> CursorBuilder<Err, S> {
+        self.cursor()
+    }
+}
+
+impl<Err, S: Storage> BackendIndex<S, Err> for Index<Err, S> {
+    fn name(&self) -> String {
+        self.name()
+    }
+
+    fn key_path(&self) -> crate::Result<KeyPath, Err> {
+        Ok(self.key_path())
+    }
+
+    fn is_unique(&self) -> bool {
+        self.is_unique()
+    }
+
+    fn is_multi_entry(&self) -> bool {
+        self.is_multi_entry()
+    }
+
+    fn contains(&self, key: &Key) -> impl Future<Output = crate::Result<bool, Err>> + use<Err, S> {
+        self.contains(key)
+    }
+
+    fn count_in<R: RangeBounds<Key>>(
+        &self,
+        range: R,
+    ) -> impl Future<Output = crate::Result<usize, Err>> + use<Err, S, R> {
+        self.count_in(range)
+    }
+
+    fn get(
+        &self,
+        key: &Key,
+    ) -> impl Future<Output = crate::Result<Option<Value>, Err>> + use<Err, S> {
+        self.get(key)
+    }
+
+    fn get_first_in<R: RangeBounds<Key>>(
+        &self,
+        range: R,
+    ) -> impl Future<Output = crate::Result<Option<Value>, Err>> + use<Err, S, R> {
+        self.get_first_in(range)
+    }
+
+    fn get_all(
+        &self,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<Value>, Err>> + use<Err, S> {
+        self.get_all(limit)
+    }
+
+    fn get_all_in<R: RangeBounds<Key>>(
+        &self,
+        range: R,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<Value>, Err>> + use<Err, S, R> {
+        self.get_all_in(range, limit)
+    }
+
+    fn get_first_key_in<R: RangeBounds<Key>>(
+        &self,
+        range: R,
+    ) -> impl Future<Output = crate::Result<Option<Key>, Err>> + use<Err, S, R> {
+        self.get_first_key_in(range)
+    }
+
+    fn get_all_keys(
+        &self,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<Key>, Err>> + use<Err, S> {
+        self.get_all_keys(limit)
+    }
+
+    fn get_all_keys_in<R: RangeBounds<Key>>(
+        &self,
+        range: R,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<Key>, Err>> + use<Err, S, R> {
+        self.get_all_keys_in(range, limit)
+    }
+
+    fn cursor(&self) -> CursorBuilder<Err, S> {
+        self.cursor()
+    }
+}
+
+impl<Err, S: Storage> BackendCursorBuilder<S, Err> for CursorBuilder<Err, S> {
+    fn open(self) -> impl Future<Output = crate::Result<Cursor<Err, S>, Err>> {
+        self.open()
+    }
+
+    fn open_key(self) -> impl Future<Output = crate::Result<KeyCursor<Err, S>, Err>> {
+        self.open_key()
+    }
+
+    fn range(self, range: impl RangeBounds<Key>) -> crate::Result<Self, Err> {
+        self.range(range)
+    }
+
+    fn direction(self, direction: CursorDirection) -> Self {
+        self.direction(direction)
+    }
+}
+
+impl<Err, S: Storage> BackendCursor<S, Err> for Cursor<Err, S> {
+    fn key(&self) -> Option<Key> {
+        self.key()
+    }
+
+    fn primary_key(&self) -> Option<Key> {
+        self.primary_key()
+    }
+
+    fn value(&self) -> Option<Value> {
+        self.value()
+    }
+
+    fn advance(&mut self, count: u32) -> impl Future<Output = crate::Result<(), Err>> {
+        self.advance(count)
+    }
+
+    fn advance_until(&mut self, key: &Key) -> impl Future<Output = crate::Result<(), Err>> {
+        self.advance_until(key)
+    }
+
+    fn advance_until_primary_key(
+        &mut self,
+        index_key: &Key,
+        primary_key: &Key,
+    ) -> impl Future<Output = crate::Result<(), Err>> {
+        self.advance_until_primary_key(index_key, primary_key)
+    }
+
+    fn delete(&self) -> impl Future<Output = crate::Result<(), Err>> {
+        self.delete()
+    }
+
+    fn update(&self, value: &Value) -> impl Future<Output = crate::Result<(), Err>> {
+        self.update(value)
+    }
+}
+
+impl<Err, S: Storage> BackendKeyCursor<S, Err> for KeyCursor<Err, S> {
+    fn key(&self) -> Option<Key> {
+        self.key()
+    }
+
+    fn primary_key(&self) -> Option<Key> {
+        self.primary_key()
+    }
+
+    fn advance(&mut self, count: u32) -> impl Future<Output = crate::Result<(), Err>> {
+        self.advance(count)
+    }
+
+    fn advance_until(&mut self, key: &Key) -> impl Future<Output = crate::Result<(), Err>> {
+        self.advance_until(key)
+    }
+
+    fn advance_until_primary_key(
+        &mut self,
+        index_key: &Key,
+        primary_key: &Key,
+    ) -> impl Future<Output = crate::Result<(), Err>> {
+        self.advance_until_primary_key(index_key, primary_key)
+    }
+}
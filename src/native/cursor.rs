@@ -0,0 +1,383 @@
+use super::{
+    engine::{self, Failure, Range, Target},
+    index::check_index_exists,
+    read,
+    transaction::SharedTx,
+    write, OpContext, Storage, StorageTx, Value,
+};
+use crate::{CursorDirection, Error, ErrorContext, Key};
+use std::{future::Future, marker::PhantomData, ops::RangeBounds};
+
+/// Helper to build cursors of a native backend, see [`crate::CursorBuilder`]
+pub struct CursorBuilder<Err, S: Storage> {
+    source: Source<S>,
+    range: Range,
+    direction: CursorDirection,
+    _phantom: PhantomData<Err>,
+}
+
+impl<Err, S: Storage> CursorBuilder<Err, S> {
+    pub(crate) fn new(tx: SharedTx<S>, store: &str, index: Option<&str>) -> CursorBuilder<Err, S> {
+        CursorBuilder {
+            source: Source {
+                tx,
+                store: store.to_string(),
+                index: index.map(str::to_string),
+            },
+            range: Range::all(),
+            direction: CursorDirection::Next,
+            _phantom: PhantomData,
+        }
+    }
+
+    fn open_raw(
+        self,
+        operation: &'static str,
+        with_values: bool,
+    ) -> crate::Result<RawCursor<Err, S>, Err> {
+        let ctx = self.source.context(operation);
+        let mut cursor = RawCursor {
+            source: self.source,
+            range: self.range,
+            direction: self.direction,
+            with_values,
+            position: None,
+            _phantom: PhantomData,
+        };
+        cursor.step(&ctx, Target::Start)?;
+        Ok(cursor)
+    }
+
+    /// Open the cursor
+    ///
+    /// See [`crate::CursorBuilder::open`].
+    pub fn open(self) -> impl Future<Output = crate::Result<Cursor<Err, S>, Err>> {
+        std::future::ready(
+            self.open_raw("CursorBuilder::open", true)
+                .map(|raw| Cursor { raw }),
+        )
+    }
+
+    /// Open the cursor as a key-only cursor
+    ///
+    /// See [`crate::CursorBuilder::open_key`].
+    pub fn open_key(self) -> impl Future<Output = crate::Result<KeyCursor<Err, S>, Err>> {
+        std::future::ready(
+            self.open_raw("CursorBuilder::open_key", false)
+                .map(|raw| KeyCursor { raw }),
+        )
+    }
+
+    /// Limit the range of the cursor
+    ///
+    /// See [`crate::CursorBuilder::range`].
+    pub fn range(mut self, range: impl RangeBounds<Key>) -> crate::Result<Self, Err> {
+        self.range = Range::new(range).map_err(Failure::into_error)?;
+        Ok(self)
+    }
+
+    /// Define the direction of the cursor
+    ///
+    /// See [`crate::CursorBuilder::direction`].
+    pub fn direction(mut self, direction: CursorDirection) -> Self {
+        self.direction = direction;
+        self
+    }
+}
+
+/// What a cursor iterates over
+struct Source<S: Storage> {
+    tx: SharedTx<S>,
+    store: String,
+    index: Option<String>,
+}
+
+impl<S: Storage> Source<S> {
+    fn context(&self, operation: &'static str) -> OpContext {
+        let ctx = OpContext::new(operation).store(&self.store);
+        match &self.index {
+            Some(index) => ctx.index(index),
+            None => ctx,
+        }
+    }
+}
+
+/// Where a cursor currently is
+struct Position {
+    key: Key,
+    primary_key: Key,
+    /// The value of the record when the cursor reached it, for cursors that read values
+    value: Option<Value>,
+}
+
+/// Common implementation of [`Cursor`] and [`KeyCursor`]
+struct RawCursor<Err, S: Storage> {
+    source: Source<S>,
+    range: Range,
+    direction: CursorDirection,
+    with_values: bool,
+    /// `None` once the cursor is completed
+    position: Option<Position>,
+    _phantom: PhantomData<Err>,
+}
+
+impl<Err, S: Storage> RawCursor<Err, S> {
+    /// Move the cursor to `target`, or complete it if there is nothing there
+    fn step(&mut self, ctx: &OpContext, target: Target) -> crate::Result<(), Err> {
+        let source = &self.source;
+        self.position = read(&source.tx, ctx, |tx| {
+            let (key, primary_key) = match &source.index {
+                None => match tx.step(&source.store, &self.range, self.direction, target)? {
+                    Some(key) => (key.clone(), key),
+                    None => return Ok(None),
+                },
+                Some(index) => {
+                    check_index_exists(tx, &source.store, index)?;
+                    match tx.index_step(
+                        &source.store,
+                        index,
+                        &self.range,
+                        self.direction,
+                        target,
+                    )? {
+                        Some(entry) => entry,
+                        None => return Ok(None),
+                    }
+                }
+            };
+            let value = match self.with_values {
+                true => Some(record(tx, &source.store, &primary_key)?),
+                false => None,
+            };
+            Ok(Some(Position {
+                key,
+                primary_key,
+                value,
+            }))
+        })?;
+        Ok(())
+    }
+
+    fn position(&self) -> crate::Result<&Position, Err> {
+        self.position.as_ref().ok_or(Error::CursorCompleted)
+    }
+
+    fn is_forward(&self) -> bool {
+        engine::is_forward(self.direction)
+    }
+
+    fn key(&self) -> Option<Key> {
+        self.position.as_ref().map(|p| p.key.clone())
+    }
+
+    fn primary_key(&self) -> Option<Key> {
+        self.position.as_ref().map(|p| p.primary_key.clone())
+    }
+
+    fn advance(&mut self, count: u32) -> crate::Result<(), Err> {
+        let ctx = self.source.context("Cursor::advance");
+        if count == 0 {
+            return Err(ctx.apply(Error::InvalidArgument(ErrorContext::new(
+                "Cursors cannot advance by zero elements",
+            ))));
+        }
+        self.position()?;
+        for _ in 0..count {
+            let Some(Position {
+                key, primary_key, ..
+            }) = &self.position
+            else {
+                break;
+            };
+            let (key, primary_key) = (key.clone(), primary_key.clone());
+            self.step(&ctx, Target::After(&key, &primary_key))?;
+        }
+        Ok(())
+    }
+
+    fn advance_until(&mut self, key: &Key) -> crate::Result<(), Err> {
+        let ctx = self.source.context("Cursor::advance_until").key(key);
+        let current = &self.position()?.key;
+        if self.is_forward() != (key > current) {
+            return Err(ctx.apply(Error::InvalidKey(ErrorContext::new(
+                "The key is not after the current key in the direction of the cursor",
+            ))));
+        }
+        self.step(&ctx, Target::Key(key))
+    }
+
+    fn advance_until_primary_key(
+        &mut self,
+        index_key: &Key,
+        primary_key: &Key,
+    ) -> crate::Result<(), Err> {
+        let ctx = self
+            .source
+            .context("Cursor::advance_until_primary_key")
+            .key(primary_key);
+        let unique = matches!(
+            self.direction,
+            CursorDirection::NextUnique | CursorDirection::PrevUnique
+        );
+        if self.source.index.is_none() || unique {
+            return Err(ctx.apply(Error::InvalidCall(ErrorContext::new(
+                "Only cursors over indexes, without unique directions, can advance until a primary key",
+            ))));
+        }
+        let current = self.position()?;
+        let target = (index_key, primary_key);
+        if self.is_forward() != (target > (&current.key, &current.primary_key)) {
+            return Err(ctx.apply(Error::InvalidKey(ErrorContext::new(
+                "The entry is not after the current one in the direction of the cursor",
+            ))));
+        }
+        self.step(&ctx, Target::Entry(index_key, primary_key))
+    }
+}
+
+/// The value of the record with key `primary_key` in the object store `store`
+fn record(tx: &impl StorageTx, store: &str, primary_key: &Key) -> engine::Result<Value> {
+    tx.values(store, &Range::only(primary_key)?, Some(1))?
+        .pop()
+        .ok_or(Failure::Unknown(String::from(
+            "The cursor landed on a missing record",
+        )))
+}
+
+/// Cursor of a native backend, see [`crate::Cursor`]
+pub struct Cursor<Err, S: Storage> {
+    raw: RawCursor<Err, S>,
+}
+
+impl<Err, S: Storage> Cursor<Err, S> {
+    /// Retrieve the value this [`Cursor`] is currently pointing at, or `None` if the cursor is completed
+    ///
+    /// See [`crate::Cursor::value`].
+    pub fn value(&self) -> Option<Value> {
+        self.raw.position.as_ref().and_then(|p| p.value.clone())
+    }
+
+    /// Retrieve the key this [`Cursor`] is currently pointing at, or `None` if the cursor is completed
+    ///
+    /// See [`crate::Cursor::key`].
+    pub fn key(&self) -> Option<Key> {
+        self.raw.key()
+    }
+
+    /// Retrieve the primary key this [`Cursor`] is currently pointing at, or `None` if the cursor is completed
+    ///
+    /// See [`crate::Cursor::primary_key`].
+    pub fn primary_key(&self) -> Option<Key> {
+        self.raw.primary_key()
+    }
+
+    /// Advance this [`Cursor`] by `count` elements
+    ///
+    /// See [`crate::Cursor::advance`].
+    pub async fn advance(&mut self, count: u32) -> crate::Result<(), Err> {
+        self.raw.advance(count)
+    }
+
+    /// Advance this [`Cursor`] until the provided key
+    ///
+    /// See [`crate::Cursor::advance_until`].
+    pub async fn advance_until(&mut self, key: &Key) -> crate::Result<(), Err> {
+        self.raw.advance_until(key)
+    }
+
+    /// Advance this [`Cursor`] until the provided primary key
+    ///
+    /// See [`crate::Cursor::advance_until_primary_key`].
+    pub async fn advance_until_primary_key(
+        &mut self,
+        index_key: &Key,
+        primary_key: &Key,
+    ) -> crate::Result<(), Err> {
+        self.raw.advance_until_primary_key(index_key, primary_key)
+    }
+
+    /// Deletes the value currently pointed by this [`Cursor`]
+    ///
+    /// See [`crate::Cursor::delete`].
+    pub async fn delete(&self) -> crate::Result<(), Err> {
+        let position = self.raw.position()?;
+        let source = &self.raw.source;
+        let ctx = source.context("Cursor::delete").key(&position.primary_key);
+        write(&source.tx, &ctx, |tx| {
+            tx.delete(&source.store, &Range::only(&position.primary_key)?)
+        })
+    }
+
+    /// Update the value currently pointed by this [`Cursor`] to `value`
+    ///
+    /// See [`crate::Cursor::update`].
+    ///
+    /// For object stores with in-line keys, `value` must keep the same primary key.
+    pub async fn update(&self, value: &Value) -> crate::Result<(), Err> {
+        let position = self.raw.position()?;
+        let source = &self.raw.source;
+        let ctx = source.context("Cursor::update").key(&position.primary_key);
+        write(&source.tx, &ctx, |tx| {
+            match &tx.store(&source.store)?.key_path {
+                Some(key_path) => {
+                    if value.evaluate(key_path).flatten().as_ref() != Some(&position.primary_key) {
+                        return Err(Failure::InvalidKey(
+                            "The updated value does not have the primary key of the record",
+                        ));
+                    }
+                    tx.put(&source.store, value, None, true)?;
+                }
+                None => {
+                    tx.put(&source.store, value, Some(&position.primary_key), true)?;
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Key-only cursor of a native backend, see [`crate::KeyCursor`]
+pub struct KeyCursor<Err, S: Storage> {
+    raw: RawCursor<Err, S>,
+}
+
+impl<Err, S: Storage> KeyCursor<Err, S> {
+    /// Retrieve the key this [`KeyCursor`] is currently pointing at, or `None` if the cursor is completed
+    ///
+    /// See [`crate::KeyCursor::key`].
+    pub fn key(&self) -> Option<Key> {
+        self.raw.key()
+    }
+
+    /// Retrieve the primary key this [`KeyCursor`] is currently pointing at, or `None` if the cursor is completed
+    ///
+    /// See [`crate::KeyCursor::primary_key`].
+    pub fn primary_key(&self) -> Option<Key> {
+        self.raw.primary_key()
+    }
+
+    /// Advance this [`KeyCursor`] by `count` elements
+    ///
+    /// See [`crate::KeyCursor::advance`].
+    pub async fn advance(&mut self, count: u32) -> crate::Result<(), Err> {
+        self.raw.advance(count)
+    }
+
+    /// Advance this [`KeyCursor`] until the provided key
+    ///
+    /// See [`crate::KeyCursor::advance_until`].
+    pub async fn advance_until(&mut self, key: &Key) -> crate::Result<(), Err> {
+        self.raw.advance_until(key)
+    }
+
+    /// Advance this [`KeyCursor`] until the provided primary key
+    ///
+    /// See [`crate::Cursor::advance_until_primary_key`].
+    pub async fn advance_until_primary_key(
+        &mut self,
+        index_key: &Key,
+        primary_key: &Key,
+    ) -> crate::Result<(), Err> {
+        self.raw.advance_until_primary_key(index_key, primary_key)
+    }
+}
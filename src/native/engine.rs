@@ -0,0 +1,242 @@
+//! The IndexedDB semantics shared by the native storages: key validation, key generators and key paths
+
+use super::Value;
+use crate::{CursorDirection, Error, ErrorContext, Key, KeyPath};
+use std::{
+    borrow::Cow,
+    collections::BTreeSet,
+    ops::{Bound, RangeBounds},
+};
+
+/// The greatest key that a key generator can produce
+const MAX_GENERATED_KEY: f64 = 9007199254740992.0;
+
+/// An error of the engine, turned into an [`Error`] by the caller that knows what was being done
+#[derive(Clone, Debug)]
+pub enum Failure {
+    InvalidKey(&'static str),
+    InvalidArgument(&'static str),
+    AlreadyExists(&'static str),
+    DoesNotExist(&'static str),
+    ObjectStoreWasRemoved,
+    /// The storage ran out of space
+    QuotaExceeded(String),
+    /// The storage failed, for a reason that is not covered by IndexedDB
+    Unknown(String),
+}
+
+impl Failure {
+    pub(crate) fn into_error<Err>(self) -> Error<Err> {
+        match self {
+            Failure::InvalidKey(msg) => Error::InvalidKey(ErrorContext::new(msg)),
+            Failure::InvalidArgument(msg) => Error::InvalidArgument(ErrorContext::new(msg)),
+            Failure::AlreadyExists(msg) => Error::AlreadyExists(ErrorContext::new(msg)),
+            Failure::DoesNotExist(msg) => Error::DoesNotExist(ErrorContext::new(msg)),
+            Failure::ObjectStoreWasRemoved => {
+                Error::ObjectStoreWasRemoved(ErrorContext::new("The object store was deleted"))
+            }
+            Failure::QuotaExceeded(msg) => Error::QuotaExceeded(ErrorContext::new(&msg)),
+            Failure::Unknown(msg) => Error::Unknown(ErrorContext::new(&msg)),
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Failure>;
+
+/// A validated range of keys
+#[derive(Clone, Debug)]
+pub struct Range {
+    lower: Bound<Key>,
+    upper: Bound<Key>,
+}
+
+/// Where a cursor should move, relative to its direction
+#[derive(Clone, Copy, Debug)]
+pub enum Target<'a> {
+    /// The first entry
+    Start,
+
+    /// The entry after `(key, primary key)`
+    After(&'a Key, &'a Key),
+
+    /// The first entry whose key is `key` or after it
+    Key(&'a Key),
+
+    /// The first entry that is `(key, primary key)` or after it
+    Entry(&'a Key, &'a Key),
+}
+
+pub(crate) fn check_key(key: &Key) -> Result<()> {
+    if key.is_valid() {
+        Ok(())
+    } else {
+        Err(Failure::InvalidKey("The key is not a valid key"))
+    }
+}
+
+/// Check the parameters of a new object store
+pub(crate) fn check_store_params(key_path: Option<&KeyPath>, auto_increment: bool) -> Result<()> {
+    if auto_increment {
+        match key_path {
+            Some(KeyPath::Compound(_)) => {
+                return Err(Failure::InvalidArgument(
+                    "Auto-increment cannot be used with a compound key path",
+                ))
+            }
+            Some(KeyPath::Single(path)) if path.is_empty() => {
+                return Err(Failure::InvalidArgument(
+                    "Auto-increment cannot be used with an empty key path",
+                ))
+            }
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+/// Check the parameters of a new index
+pub(crate) fn check_index_params(key_path: &KeyPath, multi_entry: bool) -> Result<()> {
+    if multi_entry && matches!(key_path, KeyPath::Compound(_)) {
+        return Err(Failure::InvalidArgument(
+            "A multi-entry index cannot have a compound key path",
+        ));
+    }
+    Ok(())
+}
+
+/// The key that a key generator at `current_number` would produce next
+fn generated_key(current_number: f64) -> Result<Key> {
+    if current_number > MAX_GENERATED_KEY {
+        return Err(Failure::AlreadyExists("The key generator is exhausted"));
+    }
+    Ok(Key::Number(current_number))
+}
+
+/// The current number of a key generator once `key` was used, so that it never produces `key` or a
+/// key below it
+pub(crate) fn bump_generator(current_number: f64, key: &Key) -> f64 {
+    match key {
+        Key::Number(n) if *n >= current_number => (n.floor() + 1.0).min(MAX_GENERATED_KEY + 1.0),
+        _ => current_number,
+    }
+}
+
+/// The primary key of `value` in an object store with key path `key_path`, given the explicit `key`
+///
+/// `generator` is the current number of the key generator of the object store, if it has one. Generated
+/// keys are injected in `value` for object stores with in-line keys.
+pub(crate) fn primary_key(
+    key_path: Option<&KeyPath>,
+    generator: Option<f64>,
+    value: &mut Cow<'_, Value>,
+    key: Option<&Key>,
+) -> Result<Key> {
+    match (key_path, key) {
+        (Some(_), Some(_)) => Err(Failure::InvalidKey(
+            "The object store uses in-line keys, but a key was provided",
+        )),
+        (None, Some(key)) => {
+            check_key(key)?;
+            Ok(key.clone())
+        }
+        (None, None) => match generator {
+            Some(current_number) => generated_key(current_number),
+            None => Err(Failure::InvalidKey(
+                "The object store uses out-of-line keys and has no key generator, but no key was provided",
+            )),
+        },
+        (Some(key_path), None) => match value.evaluate(key_path) {
+            Some(Some(key)) => {
+                check_key(&key)?;
+                Ok(key)
+            }
+            Some(None) => Err(Failure::InvalidKey(
+                "The key path of the object store did not yield a valid key",
+            )),
+            None => {
+                let (Some(current_number), KeyPath::Single(path)) = (generator, key_path) else {
+                    return Err(Failure::InvalidKey(
+                        "The key path of the object store did not yield a key",
+                    ));
+                };
+                let key = generated_key(current_number)?;
+                if !value.to_mut().inject(path, &key) {
+                    return Err(Failure::InvalidKey(
+                        "The generated key cannot be set in the value",
+                    ));
+                }
+                Ok(key)
+            }
+        },
+    }
+}
+
+/// The keys that an index with key path `key_path` has for the record `value`
+pub(crate) fn index_keys(key_path: &KeyPath, multi_entry: bool, value: &Value) -> Vec<Key> {
+    match key_path {
+        KeyPath::Single(path) if multi_entry => match value.lookup(path).as_deref() {
+            Some(Value::Array(values)) => values
+                .iter()
+                .filter_map(Value::to_key)
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect(),
+            Some(value) => value.to_key().into_iter().collect(),
+            None => Vec::new(),
+        },
+        key_path => value.evaluate(key_path).flatten().into_iter().collect(),
+    }
+}
+
+pub(crate) fn is_forward(direction: CursorDirection) -> bool {
+    matches!(
+        direction,
+        CursorDirection::Next | CursorDirection::NextUnique
+    )
+}
+
+/// Whether no value can be both after `lower` and before `upper`
+pub(crate) fn is_empty<T: Ord>(lower: Bound<&T>, upper: Bound<&T>) -> bool {
+    match (lower, upper) {
+        (Bound::Included(l), Bound::Included(u)) => l > u,
+        (Bound::Included(l) | Bound::Excluded(l), Bound::Included(u) | Bound::Excluded(u)) => {
+            l >= u
+        }
+        _ => false,
+    }
+}
+
+impl Range {
+    pub(crate) fn all() -> Range {
+        Range {
+            lower: Bound::Unbounded,
+            upper: Bound::Unbounded,
+        }
+    }
+
+    pub(crate) fn only(key: &Key) -> Result<Range> {
+        Range::new(key..=key)
+    }
+
+    pub(crate) fn new(range: impl RangeBounds<Key>) -> Result<Range> {
+        let range = Range {
+            lower: range.start_bound().cloned(),
+            upper: range.end_bound().cloned(),
+        };
+        for bound in [&range.lower, &range.upper] {
+            if let Bound::Included(key) | Bound::Excluded(key) = bound {
+                check_key(key)?;
+            }
+        }
+        if is_empty(range.lower.as_ref(), range.upper.as_ref()) {
+            return Err(Failure::InvalidKey(
+                "The lower bound of the range is greater than its upper bound",
+            ));
+        }
+        Ok(range)
+    }
+
+    pub(crate) fn bounds(&self) -> (Bound<&Key>, Bound<&Key>) {
+        (self.lower.as_ref(), self.upper.as_ref())
+    }
+}
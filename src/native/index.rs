@@ -0,0 +1,222 @@
+use super::{
+    engine::{self, Failure, Range},
+    read,
+    transaction::SharedTx,
+    CursorBuilder, OpContext, Storage, StorageTx, Value,
+};
+use crate::{IndexSchema, Key, KeyPath};
+use std::{future::Future, marker::PhantomData, ops::RangeBounds};
+
+/// Index of a native backend, see [`crate::Index`]
+///
+/// If the index was built with a compound key, then you should use eg.
+/// `Key::Array(vec![key_1, key_2])` as the key.
+#[derive(Debug)]
+pub struct Index<Err, S: Storage> {
+    tx: SharedTx<S>,
+    store: String,
+    name: String,
+    _phantom: PhantomData<Err>,
+}
+
+/// Fail if the object store `store` has no index `index`
+pub(super) fn check_index_exists(
+    tx: &impl StorageTx,
+    store: &str,
+    index: &str,
+) -> engine::Result<()> {
+    if tx.store(store)?.indexes.iter().any(|i| i.name == index) {
+        Ok(())
+    } else {
+        Err(Failure::DoesNotExist("The index does not exist"))
+    }
+}
+
+impl<Err, S: Storage> Index<Err, S> {
+    pub(crate) fn new(tx: SharedTx<S>, store: &str, name: &str) -> Index<Err, S> {
+        Index {
+            tx,
+            store: store.to_string(),
+            name: name.to_string(),
+            _phantom: PhantomData,
+        }
+    }
+
+    fn context(&self, operation: &'static str) -> OpContext {
+        OpContext::new(operation)
+            .store(&self.store)
+            .index(&self.name)
+    }
+
+    /// Run `f` on this index, restricted to `range`
+    fn read_range<T>(
+        &self,
+        ctx: &OpContext,
+        range: impl RangeBounds<Key>,
+        f: impl FnOnce(&S::Tx, &Range) -> engine::Result<T>,
+    ) -> crate::Result<T, Err> {
+        read(&self.tx, ctx, |tx| {
+            check_index_exists(tx, &self.store, &self.name)?;
+            f(tx, &Range::new(range)?)
+        })
+    }
+
+    /// The schema of this index, even if the transaction is no longer active
+    fn schema(&self) -> Option<IndexSchema> {
+        let schema = self.tx.borrow().tx.store(&self.store).ok()?;
+        schema.indexes.into_iter().find(|i| i.name == self.name)
+    }
+
+    /// The name of this index
+    ///
+    /// See [`crate::Index::name`].
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// The key path of this index
+    ///
+    /// See [`crate::Index::key_path`].
+    pub fn key_path(&self) -> KeyPath {
+        self.schema().expect("Index was deleted").key_path
+    }
+
+    /// Whether this index enforces unicity of its keys
+    ///
+    /// See [`crate::Index::is_unique`].
+    pub fn is_unique(&self) -> bool {
+        self.schema().is_some_and(|i| i.unique)
+    }
+
+    /// Whether this index adds one entry per element when its key path resolves to an array
+    ///
+    /// See [`crate::Index::is_multi_entry`].
+    pub fn is_multi_entry(&self) -> bool {
+        self.schema().is_some_and(|i| i.multi_entry)
+    }
+
+    /// Checks whether the provided key (for this index) already exists
+    ///
+    /// See [`crate::Index::contains`].
+    pub fn contains(&self, key: &Key) -> impl Future<Output = crate::Result<bool, Err>> {
+        let ctx = self.context("Index::contains").key(key);
+        std::future::ready(self.read_range(&ctx, key..=key, |tx, range| {
+            Ok(tx.index_count(&self.store, &self.name, range)? > 0)
+        }))
+    }
+
+    /// Count all the keys (for this index) in the provided range
+    ///
+    /// See [`crate::Index::count_in`].
+    pub fn count_in(
+        &self,
+        range: impl RangeBounds<Key>,
+    ) -> impl Future<Output = crate::Result<usize, Err>> {
+        let ctx = self.context("Index::count_in");
+        std::future::ready(self.read_range(&ctx, range, |tx, range| {
+            tx.index_count(&self.store, &self.name, range)
+        }))
+    }
+
+    /// Get the object with key `key` for this index
+    ///
+    /// See [`crate::Index::get`].
+    pub fn get(&self, key: &Key) -> impl Future<Output = crate::Result<Option<Value>, Err>> {
+        let ctx = self.context("Index::get").key(key);
+        std::future::ready(self.read_range(&ctx, key..=key, |tx, range| {
+            Ok(tx
+                .index_values(&self.store, &self.name, range, Some(1))?
+                .pop())
+        }))
+    }
+
+    /// Get the first value with a key (for this index) in the specified range
+    ///
+    /// See [`crate::Index::get_first_in`].
+    pub fn get_first_in(
+        &self,
+        range: impl RangeBounds<Key>,
+    ) -> impl Future<Output = crate::Result<Option<Value>, Err>> {
+        let ctx = self.context("Index::get_first_in");
+        std::future::ready(self.read_range(&ctx, range, |tx, range| {
+            Ok(tx
+                .index_values(&self.store, &self.name, range, Some(1))?
+                .pop())
+        }))
+    }
+
+    /// Get all the objects in the store, ordered by this index, with a maximum number of results of `limit`
+    ///
+    /// See [`crate::Index::get_all`].
+    pub fn get_all(
+        &self,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<Value>, Err>> {
+        self.get_all_in(.., limit)
+    }
+
+    /// Get all the objects with a key (for this index) in the specified range, with a maximum number of
+    /// results of `limit`
+    ///
+    /// See [`crate::Index::get_all_in`].
+    pub fn get_all_in(
+        &self,
+        range: impl RangeBounds<Key>,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<Value>, Err>> {
+        let ctx = self.context("Index::get_all_in");
+        std::future::ready(self.read_range(&ctx, range, |tx, range| {
+            tx.index_values(&self.store, &self.name, range, limit)
+        }))
+    }
+
+    /// Get the first existing primary key for a key (for this index) in the specified range
+    ///
+    /// See [`crate::Index::get_first_key_in`].
+    pub fn get_first_key_in(
+        &self,
+        range: impl RangeBounds<Key>,
+    ) -> impl Future<Output = crate::Result<Option<Key>, Err>> {
+        let ctx = self.context("Index::get_first_key_in");
+        std::future::ready(self.read_range(&ctx, range, |tx, range| {
+            let entry = tx
+                .index_entries(&self.store, &self.name, range, Some(1))?
+                .pop();
+            Ok(entry.map(|(_, pk)| pk))
+        }))
+    }
+
+    /// List all the primary keys in the object store, ordered by this index, with a maximum number of
+    /// results of `limit`
+    ///
+    /// See [`crate::Index::get_all_keys`].
+    pub fn get_all_keys(
+        &self,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<Key>, Err>> {
+        self.get_all_keys_in(.., limit)
+    }
+
+    /// List all the primary keys with a key (for this index) in the specified range, with a maximum
+    /// number of results of `limit`
+    ///
+    /// See [`crate::Index::get_all_keys_in`].
+    pub fn get_all_keys_in(
+        &self,
+        range: impl RangeBounds<Key>,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<Key>, Err>> {
+        let ctx = self.context("Index::get_all_keys_in");
+        std::future::ready(self.read_range(&ctx, range, |tx, range| {
+            let entries = tx.index_entries(&self.store, &self.name, range, limit)?;
+            Ok(entries.into_iter().map(|(_, pk)| pk).collect())
+        }))
+    }
+
+    /// Open a [`Cursor`](super::Cursor) on this index
+    ///
+    /// See [`crate::Index::cursor`].
+    pub fn cursor(&self) -> CursorBuilder<Err, S> {
+        CursorBuilder::new(self.tx.clone(), &self.store, Some(&self.name))
+    }
+}
@@ -0,0 +1,566 @@
+//! The front-end shared by the native backends, on top of the storage engine of each backend
+//!
+//! This implements the parts of the IndexedDB semantics that do not depend on how the data is stored:
+//! the lifecycle of the transactions, `versionchange` upgrades, the checks done on the arguments, batches
+//! and cursors. The backends only have to implement [`Storage`] and [`StorageTx`].
+
+use crate::{
+    memory::Value, CursorDirection, DatabaseInfo, Error, ErrorContext, Key, KeyPath, StoreSchema,
+};
+use engine::{Range, Target};
+use futures_util::lock::Mutex;
+use std::{cell::Cell, convert::Infallible, fmt::Debug, rc::Rc};
+use transaction::{SharedTx, TxState};
+
+mod backend;
+mod cursor;
+pub(crate) mod engine;
+mod index;
+mod object_store;
+mod transaction;
+
+pub use cursor::{Cursor, CursorBuilder, KeyCursor};
+pub use index::Index;
+pub use object_store::ObjectStore;
+pub use transaction::{Mode, Transaction, TransactionBuilder};
+
+/// The storage engine of a native backend
+///
+/// Each type implementing this trait is a [`Backend`](crate::backend::Backend), with the types of this
+/// module as the implementation of the types of the crate root.
+pub trait Storage: Sized + Debug + 'static {
+    /// The databases of a factory, shared by all its clones and connections
+    type Shared: Clone + Debug;
+
+    /// A handle to a database, that stays valid until the database is deleted
+    type Db: Clone + Debug;
+
+    /// A running transaction
+    type Tx: StorageTx + Debug;
+
+    /// The lock that transactions on the database `name` must hold while they run
+    ///
+    /// Transactions hold it for their whole duration whatever their mode, so the transactions that share a
+    /// lock run one after the other, read-only ones included. This is simpler than tracking overlapping
+    /// scopes like IndexedDB does, and only costs concurrency that a single-threaded test rarely needs.
+    fn lock(shared: &Self::Shared, name: &str) -> Rc<Mutex<()>>;
+
+    /// List the existing databases, sorted by name
+    fn databases(shared: &Self::Shared) -> engine::Result<Vec<DatabaseInfo>>;
+
+    /// The database `name` and its version, if it exists
+    fn find(shared: &Self::Shared, name: &str) -> engine::Result<Option<(Self::Db, u32)>>;
+
+    /// Delete the database `name`, if it exists
+    fn delete_database(shared: &Self::Shared, name: &str) -> engine::Result<()>;
+
+    /// Start a `versionchange` transaction on the database `name`, creating it with version `0` if it
+    /// does not exist yet
+    ///
+    /// Rolling back the transaction also rolls back the creation of the database.
+    fn begin_upgrade(shared: &Self::Shared, name: &str) -> engine::Result<(Self::Db, Self::Tx)>;
+
+    /// Start a transaction on `db`, or return `None` if it was deleted
+    fn begin(shared: &Self::Shared, db: &Self::Db, mode: Mode) -> engine::Result<Option<Self::Tx>>;
+
+    /// The names of the object stores of `db`, sorted by name, or nothing if it was deleted
+    fn store_names(shared: &Self::Shared, db: &Self::Db) -> Vec<String>;
+}
+
+/// A transaction of a [`Storage`]
+///
+/// The front-end validates the arguments before calling these methods, so the ranges are valid and
+/// the object stores and indexes to create do not exist yet. Operations on an object store that
+/// does not exist fail with [`Failure::ObjectStoreWasRemoved`](engine::Failure::ObjectStoreWasRemoved).
+/// Each write either succeeds or leaves the data unchanged.
+pub trait StorageTx {
+    /// The version of the database
+    fn version(&self) -> u32;
+
+    /// Change the version of the database, in a `versionchange` transaction
+    fn set_version(&mut self, version: u32) -> engine::Result<()>;
+
+    /// The names of all the object stores, sorted by name
+    fn store_names(&self) -> Vec<String>;
+
+    /// The schema of the object store `store`, with its indexes sorted by name
+    fn store(&self, store: &str) -> engine::Result<StoreSchema>;
+
+    fn create_store(
+        &mut self,
+        store: &str,
+        key_path: Option<KeyPath>,
+        auto_increment: bool,
+    ) -> engine::Result<()>;
+
+    fn delete_store(&mut self, store: &str) -> engine::Result<()>;
+
+    /// Create an index over the existing records, failing if they break its uniqueness
+    fn create_index(
+        &mut self,
+        store: &str,
+        index: &str,
+        key_path: KeyPath,
+        unique: bool,
+        multi_entry: bool,
+    ) -> engine::Result<()>;
+
+    fn delete_index(&mut self, store: &str, index: &str) -> engine::Result<()>;
+
+    /// Count the records with a key in `range`
+    fn count(&self, store: &str, range: &Range) -> engine::Result<usize>;
+
+    /// The keys in `range`, in order, with a maximum number of results of `limit`
+    fn keys(&self, store: &str, range: &Range, limit: Option<u32>) -> engine::Result<Vec<Key>>;
+
+    /// The values with a key in `range`, ordered by key, with a maximum number of results of `limit`
+    fn values(&self, store: &str, range: &Range, limit: Option<u32>) -> engine::Result<Vec<Value>>;
+
+    /// Move a cursor over the object store, and return the primary key it lands on
+    fn step(
+        &self,
+        store: &str,
+        range: &Range,
+        direction: CursorDirection,
+        target: Target,
+    ) -> engine::Result<Option<Key>>;
+
+    /// Store `value`, with key `key` for out-of-line keys, and return its key
+    ///
+    /// If `overwrite` is `false`, this fails if a record already exists with the same key.
+    fn put(
+        &mut self,
+        store: &str,
+        value: &Value,
+        key: Option<&Key>,
+        overwrite: bool,
+    ) -> engine::Result<Key>;
+
+    /// Delete the records with a key in `range`
+    fn delete(&mut self, store: &str, range: &Range) -> engine::Result<()>;
+
+    fn clear(&mut self, store: &str) -> engine::Result<()>;
+
+    /// Count the entries of the index with a key in `range`
+    fn index_count(&self, store: &str, index: &str, range: &Range) -> engine::Result<usize>;
+
+    /// The `(key, primary key)` pairs of the index with a key in `range`, in order, with a maximum
+    /// number of results of `limit`
+    fn index_entries(
+        &self,
+        store: &str,
+        index: &str,
+        range: &Range,
+        limit: Option<u32>,
+    ) -> engine::Result<Vec<(Key, Key)>>;
+
+    /// The values of the records referenced by the entries of the index with a key in `range`, in the
+    /// order of the index, with a maximum number of results of `limit`
+    fn index_values(
+        &self,
+        store: &str,
+        index: &str,
+        range: &Range,
+        limit: Option<u32>,
+    ) -> engine::Result<Vec<Value>>;
+
+    /// Move a cursor over the index, and return the `(key, primary key)` pair it lands on
+    fn index_step(
+        &self,
+        store: &str,
+        index: &str,
+        range: &Range,
+        direction: CursorDirection,
+        target: Target,
+    ) -> engine::Result<Option<(Key, Key)>>;
+
+    /// Commit the changes of this transaction
+    fn commit(&mut self) -> engine::Result<()>;
+
+    /// Roll back the changes of this transaction
+    fn rollback(&mut self);
+}
+
+/// What an operation was about, used to fill the [`ErrorContext`] of its errors
+#[derive(Clone, Debug)]
+pub(crate) struct OpContext {
+    operation: &'static str,
+    store: Option<String>,
+    index: Option<String>,
+    key: Option<Key>,
+}
+
+impl OpContext {
+    pub(crate) fn new(operation: &'static str) -> OpContext {
+        OpContext {
+            operation,
+            store: None,
+            index: None,
+            key: None,
+        }
+    }
+
+    pub(crate) fn store(mut self, name: &str) -> OpContext {
+        self.store = Some(name.to_string());
+        self
+    }
+
+    pub(crate) fn index(mut self, name: &str) -> OpContext {
+        self.index = Some(name.to_string());
+        self
+    }
+
+    pub(crate) fn key(mut self, key: &Key) -> OpContext {
+        self.key = Some(key.clone());
+        self
+    }
+
+    pub(crate) fn apply<Err>(&self, err: Error<Err>) -> Error<Err> {
+        err.located(
+            self.operation,
+            self.store.as_deref(),
+            self.index.as_deref(),
+            self.key.as_ref(),
+        )
+    }
+
+    pub(crate) fn fail<Err>(&self, failure: engine::Failure) -> Error<Err> {
+        self.apply(failure.into_error())
+    }
+}
+
+impl<S: Storage> crate::Factory<S> {
+    /// Compare two keys for ordering
+    ///
+    /// Returns an error if one of the two keys is not a valid key, ie. contains `NaN`.
+    pub fn cmp(&self, lhs: &Key, rhs: &Key) -> crate::Result<std::cmp::Ordering, Infallible> {
+        let ctx = OpContext::new("Factory::cmp");
+        engine::check_key(lhs)
+            .and(engine::check_key(rhs))
+            .map_err(|e| ctx.fail(e))?;
+        Ok(lhs.cmp(rhs))
+    }
+}
+
+/// Factory of a native backend, see [`crate::Factory`]
+///
+/// Clones of a factory share the same databases.
+#[derive(Debug)]
+pub struct Factory<S: Storage> {
+    shared: S::Shared,
+}
+
+impl<S: Storage> Clone for Factory<S> {
+    fn clone(&self) -> Self {
+        Factory {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<S: Storage> Factory<S> {
+    pub(crate) fn new(shared: S::Shared) -> Factory<S> {
+        Factory { shared }
+    }
+
+    /// List the existing databases, sorted by name
+    ///
+    /// See [`crate::Factory::databases`].
+    pub async fn databases(&self) -> crate::Result<Vec<DatabaseInfo>, Infallible> {
+        let ctx = OpContext::new("Factory::databases");
+        S::databases(&self.shared).map_err(|e| ctx.fail(e))
+    }
+
+    /// Delete a database
+    ///
+    /// See [`crate::Factory::delete_database`].
+    ///
+    /// This waits for the running transactions to complete. The open connections to the database then
+    /// fail with [`Error::DatabaseIsClosed`].
+    pub async fn delete_database(&self, name: &str) -> crate::Result<(), Infallible> {
+        let ctx = OpContext::new("Factory::delete_database");
+        let lock = S::lock(&self.shared, name);
+        let _lock = lock.lock().await;
+        S::delete_database(&self.shared, name).map_err(|e| ctx.fail(e))
+    }
+
+    /// Open a database
+    ///
+    /// See [`crate::Factory::open`].
+    ///
+    /// Returns an error if something failed while opening or upgrading the database.
+    ///
+    /// Note that `version` must be at least `1`. `on_upgrade_needed` will be called when `version` is higher
+    /// than the previous database version, or upon database creation. If it returns an error, all its changes
+    /// are rolled back, and a database created for the occasion is deleted.
+    ///
+    /// Unlike with IndexedDB, an upgrade neither fires `versionchange` events on the other connections to the
+    /// database nor waits for them to close. It closes them instead: once it is done, their transactions fail
+    /// with [`Error::DatabaseIsClosed`].
+    pub async fn open<Err>(
+        &self,
+        name: &str,
+        version: u32,
+        on_upgrade_needed: impl AsyncFnOnce(VersionChangeEvent<Err, S>) -> crate::Result<(), Err>,
+    ) -> crate::Result<Database<S>, Err> {
+        if version == 0 {
+            return Err(Error::VersionMustNotBeZero);
+        }
+        let ctx = OpContext::new("Factory::open");
+        let lock = S::lock(&self.shared, name);
+        let _lock = lock.lock().await;
+        let existing = S::find(&self.shared, name).map_err(|e| ctx.fail(e))?;
+        let old_version = existing.as_ref().map_or(0, |(_, version)| *version);
+        if version < old_version {
+            return Err(ctx.apply(Error::VersionTooOld(ErrorContext::new(
+                "The requested version is older than the existing version",
+            ))));
+        }
+        if let Some((db, _)) = existing.filter(|_| version == old_version) {
+            return Ok(Database::new(self.shared.clone(), db, name, version, None));
+        }
+
+        let (db, mut tx) = S::begin_upgrade(&self.shared, name).map_err(|e| ctx.fail(e))?;
+        tx.set_version(version).map_err(|e| ctx.fail(e))?;
+        let tx = TxState::new(Mode::VersionChange, None, tx);
+        let evt = VersionChangeEvent {
+            old_version,
+            new_version: version,
+            db: Database::new(
+                self.shared.clone(),
+                db.clone(),
+                name,
+                version,
+                Some(tx.clone()),
+            ),
+            transaction: Transaction::new(tx.clone()),
+        };
+        let res = on_upgrade_needed(evt).await;
+        if !tx
+            .borrow_mut()
+            .finish(res.is_ok())
+            .map_err(|e| ctx.fail(e))?
+        {
+            res?;
+            return Err(ctx.apply(Error::Aborted(ErrorContext::new(
+                "The versionchange transaction was aborted",
+            ))));
+        }
+        Ok(Database::new(self.shared.clone(), db, name, version, None))
+    }
+
+    /// Open a database at the latest version
+    ///
+    /// See [`crate::Factory::open_latest_version`].
+    ///
+    /// If the database does not exist yet, it is created with version `1` and no object store.
+    pub async fn open_latest_version(&self, name: &str) -> crate::Result<Database<S>, Infallible> {
+        let ctx = OpContext::new("Factory::open_latest_version");
+        let version = S::find(&self.shared, name)
+            .map_err(|e| ctx.fail(e))?
+            .map_or(1, |(_, version)| version);
+        self.open(name, version.max(1), async |_| Ok(())).await
+    }
+}
+
+/// Database connection of a native backend, see [`crate::Database`]
+#[derive(Debug)]
+pub struct Database<S: Storage> {
+    shared: S::Shared,
+    db: S::Db,
+    name: String,
+    version: u32,
+    /// The `versionchange` transaction, when this connection is the one being upgraded
+    upgrade: Option<SharedTx<S>>,
+    /// Shared by the clones of this connection, that are the same connection
+    closed: Rc<Cell<bool>>,
+}
+
+impl<S: Storage> Clone for Database<S> {
+    fn clone(&self) -> Self {
+        Database {
+            shared: self.shared.clone(),
+            db: self.db.clone(),
+            name: self.name.clone(),
+            version: self.version,
+            upgrade: self.upgrade.clone(),
+            closed: self.closed.clone(),
+        }
+    }
+}
+
+impl<S: Storage> Database<S> {
+    fn new(
+        shared: S::Shared,
+        db: S::Db,
+        name: &str,
+        version: u32,
+        upgrade: Option<SharedTx<S>>,
+    ) -> Database<S> {
+        Database {
+            shared,
+            db,
+            name: name.to_string(),
+            version,
+            upgrade,
+            closed: Rc::new(Cell::new(false)),
+        }
+    }
+
+    /// The name of this database
+    ///
+    /// See [`crate::Database::name`].
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// The version of this database, clamped at `u32::MAX`
+    ///
+    /// See [`crate::Database::version`].
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// The names of all [`ObjectStore`]s in this [`Database`], sorted by name
+    ///
+    /// See [`crate::Database::object_store_names`].
+    ///
+    /// This is empty if the database was deleted.
+    pub fn object_store_names(&self) -> Vec<String> {
+        match &self.upgrade {
+            Some(tx) => tx.borrow().tx.store_names(),
+            None => S::store_names(&self.shared, &self.db),
+        }
+    }
+
+    /// Run a transaction
+    ///
+    /// See [`crate::Database::transaction`].
+    pub fn transaction(&self, stores: &[&str]) -> TransactionBuilder<S> {
+        TransactionBuilder::new(
+            self.shared.clone(),
+            self.db.clone(),
+            &self.name,
+            self.version,
+            self.closed.get(),
+            stores,
+        )
+    }
+
+    /// Closes this database connection
+    ///
+    /// See [`crate::Database::close`].
+    ///
+    /// The transactions started afterwards fail with [`Error::DatabaseIsClosed`].
+    pub fn close(&self) {
+        self.closed.set(true);
+    }
+}
+
+/// Version change event of a native backend, see [`crate::VersionChangeEvent`]
+#[derive(Debug)]
+pub struct VersionChangeEvent<Err, S: Storage> {
+    old_version: u32,
+    new_version: u32,
+    db: Database<S>,
+    transaction: Transaction<Err, S>,
+}
+
+impl<Err, S: Storage> VersionChangeEvent<Err, S> {
+    /// The version before the database upgrade
+    ///
+    /// See [`crate::VersionChangeEvent::old_version`].
+    pub fn old_version(&self) -> u32 {
+        self.old_version
+    }
+
+    /// The version after the database upgrade
+    ///
+    /// See [`crate::VersionChangeEvent::new_version`].
+    pub fn new_version(&self) -> u32 {
+        self.new_version
+    }
+
+    /// The database under creation
+    ///
+    /// See [`crate::VersionChangeEvent::database`].
+    pub fn database(&self) -> &Database<S> {
+        &self.db
+    }
+
+    /// Create an [`ObjectStore`], see [`crate::ObjectStoreBuilder`]
+    pub fn create_object_store(
+        &self,
+        name: &str,
+        key_path: Option<&KeyPath>,
+        auto_increment: bool,
+    ) -> crate::Result<ObjectStore<Err, S>, Err> {
+        let ctx = OpContext::new("ObjectStoreBuilder::create").store(name);
+        let tx = self.transaction.state();
+        let mut state = tx.borrow_mut();
+        state
+            .lifecycle
+            .check_version_change()
+            .map_err(|e| ctx.apply(e))?;
+        if state.tx.store_names().iter().any(|s| s == name) {
+            return Err(ctx.apply(Error::AlreadyExists(ErrorContext::new(
+                "An object store with this name already exists",
+            ))));
+        }
+        engine::check_store_params(key_path, auto_increment)
+            .and_then(|()| {
+                state
+                    .tx
+                    .create_store(name, key_path.cloned(), auto_increment)
+            })
+            .map_err(|e| ctx.fail(e))?;
+        drop(state);
+        Ok(ObjectStore::new(tx.clone(), name))
+    }
+
+    /// Deletes an [`ObjectStore`]
+    ///
+    /// See [`crate::VersionChangeEvent::delete_object_store`].
+    pub fn delete_object_store(&self, name: &str) -> crate::Result<(), Err> {
+        let ctx = OpContext::new("VersionChangeEvent::delete_object_store").store(name);
+        let mut tx = self.transaction.state().borrow_mut();
+        tx.lifecycle
+            .check_version_change()
+            .map_err(|e| ctx.apply(e))?;
+        if !tx.tx.store_names().iter().any(|s| s == name) {
+            return Err(ctx.apply(Error::DoesNotExist(ErrorContext::new(
+                "The object store does not exist",
+            ))));
+        }
+        tx.tx.delete_store(name).map_err(|e| ctx.fail(e))
+    }
+
+    /// The `versionchange` transaction that triggered this event
+    ///
+    /// See [`crate::VersionChangeEvent::transaction`].
+    pub fn transaction(&self) -> &Transaction<Err, S> {
+        &self.transaction
+    }
+}
+
+/// Run `f` on the storage of `tx`
+fn read<T, Err, S: Storage>(
+    tx: &SharedTx<S>,
+    ctx: &OpContext,
+    f: impl FnOnce(&S::Tx) -> engine::Result<T>,
+) -> crate::Result<T, Err> {
+    let tx = tx.borrow();
+    tx.lifecycle.check_active().map_err(|e| ctx.apply(e))?;
+    f(&tx.tx).map_err(|e| ctx.fail(e))
+}
+
+/// Run `f` on the storage of `tx`, failing if the transaction is read-only
+fn write<T, Err, S: Storage>(
+    tx: &SharedTx<S>,
+    ctx: &OpContext,
+    f: impl FnOnce(&mut S::Tx) -> engine::Result<T>,
+) -> crate::Result<T, Err> {
+    let mut tx = tx.borrow_mut();
+    tx.lifecycle.check_writable().map_err(|e| ctx.apply(e))?;
+    f(&mut tx.tx).map_err(|e| ctx.fail(e))
+}
@@ -0,0 +1,499 @@
+use super::{
+    engine::{self, Failure, Range},
+    index::check_index_exists,
+    read,
+    transaction::SharedTx,
+    write, CursorBuilder, Index, OpContext, Storage, StorageTx, Value,
+};
+use crate::{Error, Key, KeyPath, StoreSchema};
+use std::{borrow::Borrow, cell::RefCell, future::Future, marker::PhantomData, ops::RangeBounds};
+
+/// Object store of a native backend, see [`crate::ObjectStore`]
+#[derive(Debug)]
+pub struct ObjectStore<Err, S: Storage> {
+    tx: SharedTx<S>,
+    name: String,
+    _phantom: PhantomData<Err>,
+}
+
+impl<Err, S: Storage> ObjectStore<Err, S> {
+    pub(crate) fn new(tx: SharedTx<S>, name: &str) -> ObjectStore<Err, S> {
+        ObjectStore {
+            tx,
+            name: name.to_string(),
+            _phantom: PhantomData,
+        }
+    }
+
+    fn context(&self, operation: &'static str) -> OpContext {
+        OpContext::new(operation).store(&self.name)
+    }
+
+    fn read<T>(
+        &self,
+        ctx: &OpContext,
+        f: impl FnOnce(&S::Tx) -> engine::Result<T>,
+    ) -> crate::Result<T, Err> {
+        read(&self.tx, ctx, f)
+    }
+
+    fn write<T>(
+        &self,
+        ctx: &OpContext,
+        f: impl FnOnce(&mut S::Tx) -> engine::Result<T>,
+    ) -> crate::Result<T, Err> {
+        write(&self.tx, ctx, f)
+    }
+
+    /// Run `f` on the range `range`
+    fn read_range<T>(
+        &self,
+        ctx: &OpContext,
+        range: impl RangeBounds<Key>,
+        f: impl FnOnce(&S::Tx, &Range) -> engine::Result<T>,
+    ) -> crate::Result<T, Err> {
+        self.read(ctx, |tx| f(tx, &Range::new(range)?))
+    }
+
+    /// The schema of this object store, even if the transaction is no longer active
+    fn schema(&self) -> Option<StoreSchema> {
+        RefCell::borrow(&self.tx).tx.store(&self.name).ok()
+    }
+
+    /// The name of this object store
+    ///
+    /// See [`crate::ObjectStore::name`].
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// The key path of this object store, or `None` if it uses out-of-line keys
+    ///
+    /// See [`crate::ObjectStore::key_path`].
+    pub fn key_path(&self) -> Option<KeyPath> {
+        self.schema()?.key_path
+    }
+
+    /// Whether the keys of this object store are auto-incremented
+    ///
+    /// See [`crate::ObjectStore::auto_increment`].
+    pub fn auto_increment(&self) -> bool {
+        self.schema().is_some_and(|s| s.auto_increment)
+    }
+
+    /// The names of all [`Index`]es on this object store, sorted by name
+    ///
+    /// See [`crate::ObjectStore::index_names`].
+    pub fn index_names(&self) -> Vec<String> {
+        self.schema()
+            .map(|s| s.indexes.into_iter().map(|i| i.name).collect())
+            .unwrap_or_default()
+    }
+
+    /// Create an index over this object store, see [`crate::IndexBuilder`]
+    pub fn create_index(
+        &self,
+        name: &str,
+        key_path: &KeyPath,
+        unique: bool,
+        multi_entry: bool,
+    ) -> crate::Result<(), Err> {
+        let ctx = OpContext::new("IndexBuilder::create")
+            .store(&self.name)
+            .index(name);
+        RefCell::borrow(&self.tx)
+            .lifecycle
+            .check_version_change()
+            .map_err(|e| ctx.apply(e))?;
+        self.write(&ctx, |tx| {
+            engine::check_index_params(key_path, multi_entry)?;
+            if tx.store(&self.name)?.indexes.iter().any(|i| i.name == name) {
+                return Err(Failure::AlreadyExists(
+                    "An index with this name already exists",
+                ));
+            }
+            tx.create_index(&self.name, name, key_path.clone(), unique, multi_entry)
+        })
+    }
+
+    /// Delete an index from this object store
+    ///
+    /// See [`crate::ObjectStore::delete_index`].
+    pub fn delete_index(&self, name: &str) -> crate::Result<(), Err> {
+        let ctx = self.context("ObjectStore::delete_index").index(name);
+        RefCell::borrow(&self.tx)
+            .lifecycle
+            .check_version_change()
+            .map_err(|e| ctx.apply(e))?;
+        self.write(&ctx, |tx| {
+            check_index_exists(tx, &self.name, name)?;
+            tx.delete_index(&self.name, name)
+        })
+    }
+
+    /// Add the value `value` to this object store, and return its key
+    ///
+    /// See [`crate::ObjectStore::add`].
+    pub fn add(&self, value: &Value) -> impl Future<Output = crate::Result<Key, Err>> {
+        let ctx = self.context("ObjectStore::add");
+        std::future::ready(self.write(&ctx, |tx| tx.put(&self.name, value, None, false)))
+    }
+
+    /// Add the value `value` to this object store, with key `key`
+    ///
+    /// See [`crate::ObjectStore::add_kv`].
+    pub fn add_kv(&self, key: &Key, value: &Value) -> impl Future<Output = crate::Result<(), Err>> {
+        let ctx = self.context("ObjectStore::add_kv").key(key);
+        std::future::ready(self.write(&ctx, |tx| {
+            tx.put(&self.name, value, Some(key), false).map(|_| ())
+        }))
+    }
+
+    /// Add the value `value` to this object store, and return its key
+    ///
+    /// See [`crate::ObjectStore::put`].
+    pub fn put(&self, value: &Value) -> impl Future<Output = crate::Result<Key, Err>> {
+        let ctx = self.context("ObjectStore::put");
+        std::future::ready(self.write(&ctx, |tx| tx.put(&self.name, value, None, true)))
+    }
+
+    /// Add the value `value` to this object store, with key `key`
+    ///
+    /// See [`crate::ObjectStore::put_kv`].
+    pub fn put_kv(&self, key: &Key, value: &Value) -> impl Future<Output = crate::Result<(), Err>> {
+        let ctx = self.context("ObjectStore::put_kv").key(key);
+        std::future::ready(self.write(&ctx, |tx| {
+            tx.put(&self.name, value, Some(key), true).map(|_| ())
+        }))
+    }
+
+    /// Run `f` on each item of `items`, and return the results or the first error with its position
+    ///
+    /// Like with IndexedDB, the items after a failed one are still written.
+    fn batch<I, T>(
+        &self,
+        items: impl IntoIterator<Item = I>,
+        mut f: impl FnMut(&Self, I) -> crate::Result<T, Err>,
+    ) -> crate::Result<Vec<T>, Err> {
+        let mut results = Vec::new();
+        let mut first_error = None;
+        for (index, item) in items.into_iter().enumerate() {
+            match f(self, item) {
+                Ok(res) => results.push(res),
+                Err(error) => {
+                    first_error.get_or_insert(Error::Batch {
+                        index,
+                        error: Box::new(error),
+                    });
+                }
+            }
+        }
+        match first_error {
+            None => Ok(results),
+            Some(err) => Err(err),
+        }
+    }
+
+    /// Add all the values of `values` to this object store, and return their keys
+    ///
+    /// See [`crate::ObjectStore::add_many`].
+    ///
+    /// If some fail, this returns [`Error::Batch`](crate::Error::Batch) with the position of the first
+    /// failed value. The other values are still added, unless the transaction is aborted.
+    pub fn add_many(
+        &self,
+        values: impl IntoIterator<Item = impl Borrow<Value>>,
+    ) -> impl Future<Output = crate::Result<Vec<Key>, Err>> {
+        let ctx = self.context("ObjectStore::add_many");
+        std::future::ready(self.batch(values, |this, value| {
+            this.write(&ctx, |tx| tx.put(&this.name, value.borrow(), None, false))
+        }))
+    }
+
+    /// Add all the `(key, value)` pairs of `entries` to this object store
+    ///
+    /// See [`crate::ObjectStore::add_kv_many`].
+    pub fn add_kv_many(
+        &self,
+        entries: impl IntoIterator<Item = (impl Borrow<Key>, impl Borrow<Value>)>,
+    ) -> impl Future<Output = crate::Result<(), Err>> {
+        let ctx = self.context("ObjectStore::add_kv_many");
+        std::future::ready(
+            self.batch(entries, |this, (key, value)| {
+                let ctx = ctx.clone().key(key.borrow());
+                this.write(&ctx, |tx| {
+                    tx.put(&this.name, value.borrow(), Some(key.borrow()), false)
+                })
+            })
+            .map(|_| ()),
+        )
+    }
+
+    /// Put all the values of `values` in this object store, and return their keys
+    ///
+    /// See [`crate::ObjectStore::put_many`].
+    pub fn put_many(
+        &self,
+        values: impl IntoIterator<Item = impl Borrow<Value>>,
+    ) -> impl Future<Output = crate::Result<Vec<Key>, Err>> {
+        let ctx = self.context("ObjectStore::put_many");
+        std::future::ready(self.batch(values, |this, value| {
+            this.write(&ctx, |tx| tx.put(&this.name, value.borrow(), None, true))
+        }))
+    }
+
+    /// Put all the `(key, value)` pairs of `entries` in this object store
+    ///
+    /// See [`crate::ObjectStore::put_kv_many`].
+    pub fn put_kv_many(
+        &self,
+        entries: impl IntoIterator<Item = (impl Borrow<Key>, impl Borrow<Value>)>,
+    ) -> impl Future<Output = crate::Result<(), Err>> {
+        let ctx = self.context("ObjectStore::put_kv_many");
+        std::future::ready(
+            self.batch(entries, |this, (key, value)| {
+                let ctx = ctx.clone().key(key.borrow());
+                this.write(&ctx, |tx| {
+                    tx.put(&this.name, value.borrow(), Some(key.borrow()), true)
+                })
+            })
+            .map(|_| ()),
+        )
+    }
+
+    /// Replace the value with key `key` by the result of `f`, and return the previous and new values
+    ///
+    /// `f` returns `None` to keep the current value, in which case nothing is written.
+    fn update(
+        &self,
+        ctx: &OpContext,
+        key: &Key,
+        f: impl FnOnce(Option<Value>) -> Option<Option<Value>>,
+    ) -> crate::Result<(Option<Value>, Option<Value>), Err> {
+        self.write(ctx, |tx| {
+            let only = Range::only(key)?;
+            let previous = tx.values(&self.name, &only, Some(1))?.pop();
+            let Some(new) = f(previous.clone()) else {
+                return Ok((previous.clone(), previous));
+            };
+            match (&new, &tx.store(&self.name)?.key_path) {
+                (Some(value), Some(key_path)) => {
+                    if value.evaluate(key_path).flatten().as_ref() != Some(key) {
+                        return Err(Failure::InvalidKey(
+                            "The new value does not have the key of the updated record",
+                        ));
+                    }
+                    tx.put(&self.name, value, None, true)?;
+                }
+                (Some(value), None) => {
+                    tx.put(&self.name, value, Some(key), true)?;
+                }
+                (None, _) if previous.is_some() => tx.delete(&self.name, &only)?,
+                (None, _) => (),
+            }
+            Ok((previous, new))
+        })
+    }
+
+    /// Atomically replace the value with key `key` by the result of `f`, and return the previous and new values
+    ///
+    /// See [`crate::ObjectStore::update_with`].
+    pub fn update_with(
+        &self,
+        key: &Key,
+        f: impl FnOnce(Option<Value>) -> Option<Value>,
+    ) -> impl Future<Output = crate::Result<(Option<Value>, Option<Value>), Err>> {
+        let ctx = self.context("ObjectStore::update_with").key(key);
+        std::future::ready(self.update(&ctx, key, |current| Some(f(current))))
+    }
+
+    /// Atomically set the value with key `key` to `new` if it currently is `expected`, and return the
+    /// previous and new values
+    ///
+    /// See [`crate::ObjectStore::compare_and_swap`].
+    pub fn compare_and_swap(
+        &self,
+        key: &Key,
+        expected: Option<&Value>,
+        new: Option<&Value>,
+    ) -> impl Future<Output = crate::Result<(Option<Value>, Option<Value>), Err>> {
+        let ctx = self.context("ObjectStore::compare_and_swap").key(key);
+        std::future::ready(self.update(&ctx, key, |current| {
+            (current.as_ref() == expected).then(|| new.cloned())
+        }))
+    }
+
+    /// Clear this object store
+    ///
+    /// See [`crate::ObjectStore::clear`].
+    pub fn clear(&self) -> impl Future<Output = crate::Result<(), Err>> {
+        let ctx = self.context("ObjectStore::clear");
+        std::future::ready(self.write(&ctx, |tx| tx.clear(&self.name)))
+    }
+
+    /// Count the number of objects in this store
+    ///
+    /// See [`crate::ObjectStore::count`].
+    pub fn count(&self) -> impl Future<Output = crate::Result<usize, Err>> {
+        self.count_in(..)
+    }
+
+    /// Checks whether the provided key exists in this object store
+    ///
+    /// See [`crate::ObjectStore::contains`].
+    pub fn contains(&self, key: &Key) -> impl Future<Output = crate::Result<bool, Err>> {
+        let ctx = self.context("ObjectStore::contains").key(key);
+        std::future::ready(self.read_range(&ctx, key..=key, |tx, range| {
+            Ok(tx.count(&self.name, range)? > 0)
+        }))
+    }
+
+    /// Counts the number of objects with a key in `range`
+    ///
+    /// See [`crate::ObjectStore::count_in`].
+    pub fn count_in(
+        &self,
+        range: impl RangeBounds<Key>,
+    ) -> impl Future<Output = crate::Result<usize, Err>> {
+        let ctx = self.context("ObjectStore::count_in");
+        std::future::ready(self.read_range(&ctx, range, |tx, range| tx.count(&self.name, range)))
+    }
+
+    /// Delete the object with key `key`
+    ///
+    /// See [`crate::ObjectStore::delete`].
+    pub fn delete(&self, key: &Key) -> impl Future<Output = crate::Result<(), Err>> {
+        let ctx = self.context("ObjectStore::delete").key(key);
+        std::future::ready(self.write(&ctx, |tx| tx.delete(&self.name, &Range::only(key)?)))
+    }
+
+    /// Delete all the objects with a key in `range`
+    ///
+    /// See [`crate::ObjectStore::delete_range`].
+    pub fn delete_range(
+        &self,
+        range: impl RangeBounds<Key>,
+    ) -> impl Future<Output = crate::Result<(), Err>> {
+        let ctx = self.context("ObjectStore::delete_range");
+        std::future::ready(self.write(&ctx, |tx| tx.delete(&self.name, &Range::new(range)?)))
+    }
+
+    /// Delete the objects with the keys of `keys`
+    ///
+    /// See [`crate::ObjectStore::delete_many`].
+    pub fn delete_many(
+        &self,
+        keys: impl IntoIterator<Item = impl Borrow<Key>>,
+    ) -> impl Future<Output = crate::Result<(), Err>> {
+        let ctx = self.context("ObjectStore::delete_many");
+        std::future::ready(
+            self.batch(keys, |this, key| {
+                let ctx = ctx.clone().key(key.borrow());
+                this.write(&ctx, |tx| {
+                    tx.delete(&this.name, &Range::only(key.borrow())?)
+                })
+            })
+            .map(|_| ()),
+        )
+    }
+
+    /// Get the object with key `key`
+    ///
+    /// See [`crate::ObjectStore::get`].
+    pub fn get(&self, key: &Key) -> impl Future<Output = crate::Result<Option<Value>, Err>> {
+        let ctx = self.context("ObjectStore::get").key(key);
+        std::future::ready(self.read_range(&ctx, key..=key, |tx, range| {
+            Ok(tx.values(&self.name, range, Some(1))?.pop())
+        }))
+    }
+
+    /// Get the first value with a key in `range`, ordered by key
+    ///
+    /// See [`crate::ObjectStore::get_first_in`].
+    pub fn get_first_in(
+        &self,
+        range: impl RangeBounds<Key>,
+    ) -> impl Future<Output = crate::Result<Option<Value>, Err>> {
+        let ctx = self.context("ObjectStore::get_first_in");
+        std::future::ready(self.read_range(&ctx, range, |tx, range| {
+            Ok(tx.values(&self.name, range, Some(1))?.pop())
+        }))
+    }
+
+    /// Get all the objects in the store, with a maximum number of results of `limit`
+    ///
+    /// See [`crate::ObjectStore::get_all`].
+    pub fn get_all(
+        &self,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<Value>, Err>> {
+        self.get_all_in(.., limit)
+    }
+
+    /// Get all the objects with a key in the provided range, with a maximum number of results of `limit`
+    ///
+    /// See [`crate::ObjectStore::get_all_in`].
+    pub fn get_all_in(
+        &self,
+        range: impl RangeBounds<Key>,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<Value>, Err>> {
+        let ctx = self.context("ObjectStore::get_all_in");
+        std::future::ready(
+            self.read_range(&ctx, range, |tx, range| tx.values(&self.name, range, limit)),
+        )
+    }
+
+    /// Get the first existing key in the provided range
+    ///
+    /// See [`crate::ObjectStore::get_first_key_in`].
+    pub fn get_first_key_in(
+        &self,
+        range: impl RangeBounds<Key>,
+    ) -> impl Future<Output = crate::Result<Option<Key>, Err>> {
+        let ctx = self.context("ObjectStore::get_first_key_in");
+        std::future::ready(self.read_range(&ctx, range, |tx, range| {
+            Ok(tx.keys(&self.name, range, Some(1))?.pop())
+        }))
+    }
+
+    /// List all the keys in the object store, with a maximum number of results of `limit`
+    ///
+    /// See [`crate::ObjectStore::get_all_keys`].
+    pub fn get_all_keys(
+        &self,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<Key>, Err>> {
+        self.get_all_keys_in(.., limit)
+    }
+
+    /// List all the keys in the provided range, with a maximum number of results of `limit`
+    ///
+    /// See [`crate::ObjectStore::get_all_keys_in`].
+    pub fn get_all_keys_in(
+        &self,
+        range: impl RangeBounds<Key>,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<Key>, Err>> {
+        let ctx = self.context("ObjectStore::get_all_keys_in");
+        std::future::ready(
+            self.read_range(&ctx, range, |tx, range| tx.keys(&self.name, range, limit)),
+        )
+    }
+
+    /// Get the [`Index`] with the provided name
+    ///
+    /// See [`crate::ObjectStore::index`].
+    pub fn index(&self, name: &str) -> crate::Result<Index<Err, S>, Err> {
+        let ctx = self.context("ObjectStore::index").index(name);
+        self.read(&ctx, |tx| check_index_exists(tx, &self.name, name))?;
+        Ok(Index::new(self.tx.clone(), &self.name, name))
+    }
+
+    /// Open a [`Cursor`](super::Cursor) on this object store
+    ///
+    /// See [`crate::ObjectStore::cursor`].
+    pub fn cursor(&self) -> CursorBuilder<Err, S> {
+        CursorBuilder::new(self.tx.clone(), &self.name, None)
+    }
+}
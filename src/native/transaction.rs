@@ -0,0 +1,310 @@
+use super::{engine, ObjectStore, OpContext, Storage, StorageTx};
+use crate::{Durability, Error, ErrorContext};
+use std::{cell::RefCell, marker::PhantomData, rc::Rc};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    ReadOnly,
+    ReadWrite,
+    VersionChange,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Status {
+    Active,
+    Committed,
+    Aborted,
+    /// The closure returned, so the transaction is no longer usable
+    Done,
+}
+
+/// What a transaction is allowed to do, and whether it is still running
+#[derive(Debug)]
+pub(crate) struct Lifecycle {
+    mode: Mode,
+    status: Status,
+}
+
+impl Lifecycle {
+    pub(crate) fn new(mode: Mode) -> Lifecycle {
+        Lifecycle {
+            mode,
+            status: Status::Active,
+        }
+    }
+
+    pub(crate) fn check_active<Err>(&self) -> crate::Result<(), Err> {
+        match self.status {
+            Status::Active => Ok(()),
+            Status::Committed | Status::Aborted => Err(Error::TransactionFinished(
+                ErrorContext::new("The transaction was already explicitly committed or aborted"),
+            )),
+            Status::Done => Err(Error::TransactionInactive(ErrorContext::new(
+                "The transaction is over",
+            ))),
+        }
+    }
+
+    pub(crate) fn check_writable<Err>(&self) -> crate::Result<(), Err> {
+        self.check_active()?;
+        match self.mode {
+            Mode::ReadOnly => Err(Error::ReadOnly(ErrorContext::new(
+                "The transaction is read-only",
+            ))),
+            Mode::ReadWrite | Mode::VersionChange => Ok(()),
+        }
+    }
+
+    pub(crate) fn check_version_change<Err>(&self) -> crate::Result<(), Err> {
+        self.check_active()?;
+        match self.mode {
+            Mode::VersionChange => Ok(()),
+            Mode::ReadOnly | Mode::ReadWrite => Err(Error::InvalidCall(ErrorContext::new(
+                "The schema can only be changed in a versionchange transaction",
+            ))),
+        }
+    }
+
+    /// Mark the transaction as explicitly committed
+    pub(crate) fn commit<Err>(&mut self) -> crate::Result<(), Err> {
+        self.check_active()?;
+        self.status = Status::Committed;
+        Ok(())
+    }
+
+    /// Mark the transaction as explicitly aborted
+    pub(crate) fn abort<Err>(&mut self) -> crate::Result<(), Err> {
+        self.check_active()?;
+        self.status = Status::Aborted;
+        Ok(())
+    }
+
+    /// Mark the transaction as done, and return whether its changes should be committed
+    pub(crate) fn finish(&mut self, succeeded: bool) -> bool {
+        let commit = match self.status {
+            Status::Active => succeeded,
+            Status::Committed => true,
+            Status::Aborted | Status::Done => false,
+        };
+        self.status = Status::Done;
+        commit
+    }
+}
+
+/// The state of a running transaction, shared by all the handles it gave out
+#[derive(Debug)]
+pub(crate) struct TxState<S: Storage> {
+    pub(crate) lifecycle: Lifecycle,
+    /// The object stores in the scope of the transaction, or `None` for all of them
+    scope: Option<Vec<String>>,
+    pub(crate) tx: S::Tx,
+}
+
+pub(crate) type SharedTx<S> = Rc<RefCell<TxState<S>>>;
+
+impl<S: Storage> TxState<S> {
+    pub(crate) fn new(mode: Mode, scope: Option<Vec<String>>, tx: S::Tx) -> SharedTx<S> {
+        Rc::new(RefCell::new(TxState {
+            lifecycle: Lifecycle::new(mode),
+            scope,
+            tx,
+        }))
+    }
+
+    /// Mark the transaction as done, and commit or roll back its changes
+    ///
+    /// Returns whether the changes were committed.
+    pub(crate) fn finish(&mut self, succeeded: bool) -> engine::Result<bool> {
+        if self.lifecycle.finish(succeeded) {
+            self.tx.commit()?;
+            Ok(true)
+        } else {
+            self.tx.rollback();
+            Ok(false)
+        }
+    }
+}
+
+/// Transaction of a native backend, see [`crate::Transaction`]
+#[derive(Debug)]
+pub struct Transaction<Err, S: Storage> {
+    state: SharedTx<S>,
+    _phantom: PhantomData<Err>,
+}
+
+impl<Err, S: Storage> Clone for Transaction<Err, S> {
+    fn clone(&self) -> Self {
+        Transaction::new(self.state.clone())
+    }
+}
+
+impl<Err, S: Storage> Transaction<Err, S> {
+    pub(crate) fn new(state: SharedTx<S>) -> Transaction<Err, S> {
+        Transaction {
+            state,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub(crate) fn state(&self) -> &SharedTx<S> {
+        &self.state
+    }
+
+    /// Returns an [`ObjectStore`] that can be used to operate on data in this transaction
+    ///
+    /// See [`crate::Transaction::object_store`].
+    pub fn object_store(&self, name: &str) -> crate::Result<ObjectStore<Err, S>, Err> {
+        let ctx = OpContext::new("Transaction::object_store").store(name);
+        let state = self.state.borrow();
+        state.lifecycle.check_active().map_err(|e| ctx.apply(e))?;
+        let in_scope = match &state.scope {
+            Some(scope) => scope.iter().any(|s| s == name),
+            None => state.tx.store_names().iter().any(|s| s == name),
+        };
+        if !in_scope {
+            return Err(ctx.apply(Error::DoesNotExist(ErrorContext::new(
+                "The object store is not in the scope of the transaction",
+            ))));
+        }
+        Ok(ObjectStore::new(self.state.clone(), name))
+    }
+
+    /// Commit this transaction without waiting for it to auto-commit
+    ///
+    /// See [`crate::Transaction::commit`].
+    ///
+    /// Any request made after this call fails with [`Error::TransactionFinished`](crate::Error::TransactionFinished).
+    pub fn commit(&self) -> crate::Result<(), Err> {
+        self.state.borrow_mut().lifecycle.commit()
+    }
+
+    /// Abort this transaction, rolling back all its changes
+    ///
+    /// See [`crate::Transaction::abort`].
+    ///
+    /// Any request made after this call fails with [`Error::TransactionFinished`](crate::Error::TransactionFinished).
+    pub fn abort(&self) -> crate::Result<(), Err> {
+        self.state.borrow_mut().lifecycle.abort()
+    }
+}
+
+/// Helper to build a transaction of a native backend, see [`crate::TransactionBuilder`]
+pub struct TransactionBuilder<S: Storage> {
+    shared: S::Shared,
+    db: S::Db,
+    name: String,
+    /// The version of the connection, that gets closed when another connection upgrades the database
+    version: u32,
+    closed: bool,
+    stores: Vec<String>,
+    mode: Mode,
+}
+
+impl<S: Storage> TransactionBuilder<S> {
+    pub(super) fn new(
+        shared: S::Shared,
+        db: S::Db,
+        name: &str,
+        version: u32,
+        closed: bool,
+        stores: &[&str],
+    ) -> TransactionBuilder<S> {
+        TransactionBuilder {
+            shared,
+            db,
+            name: name.to_string(),
+            version,
+            closed,
+            stores: stores.iter().map(|s| s.to_string()).collect(),
+            mode: Mode::ReadOnly,
+        }
+    }
+
+    /// Allow writes in this transaction
+    ///
+    /// See [`crate::TransactionBuilder::rw`].
+    pub fn rw(mut self) -> Self {
+        self.mode = Mode::ReadWrite;
+        self
+    }
+
+    /// Set the durability hint of this transaction
+    ///
+    /// See [`crate::TransactionBuilder::durability`].
+    ///
+    /// This has no effect on the native backends: the in-memory one does not persist anything, and the
+    /// transactions of the SQLite one are always durable once committed.
+    pub fn durability(self, _durability: Durability) -> Self {
+        self
+    }
+
+    /// Actually execute the transaction
+    ///
+    /// See [`crate::TransactionBuilder::run`].
+    ///
+    /// Transactions run one after the other, see the documentation of each backend for which ones wait
+    /// for each other. If `transaction` returns an `Ok` value, then the transaction will be committed. If
+    /// it returns an `Err` value, then it will be aborted, rolling back all its changes.
+    /// [`Transaction::commit`] and [`Transaction::abort`] can also be used to finish the transaction
+    /// earlier.
+    ///
+    /// Unlike with IndexedDB, `transaction` can await anything, as the transaction stays active until
+    /// it returns.
+    pub async fn run<Ret, Err>(
+        self,
+        transaction: impl AsyncFnOnce(Transaction<Err, S>) -> crate::Result<Ret, Err>,
+    ) -> crate::Result<Ret, Err> {
+        let ctx = OpContext::new("TransactionBuilder::run");
+        if self.closed {
+            return Err(ctx.apply(Error::DatabaseIsClosed(ErrorContext::new(
+                "The database connection was closed",
+            ))));
+        }
+        if self.stores.is_empty() {
+            return Err(ctx.apply(Error::InvalidArgument(ErrorContext::new(
+                "A transaction needs at least one object store",
+            ))));
+        }
+        // Serializes this transaction with all the others on the database, even read-only ones
+        let lock = S::lock(&self.shared, &self.name);
+        let _lock = lock.lock().await;
+        let Some(mut tx) = S::begin(&self.shared, &self.db, self.mode).map_err(|e| ctx.fail(e))?
+        else {
+            return Err(ctx.apply(Error::DatabaseIsClosed(ErrorContext::new(
+                "The database was deleted",
+            ))));
+        };
+        if tx.version() != self.version {
+            tx.rollback();
+            return Err(ctx.apply(Error::DatabaseIsClosed(ErrorContext::new(
+                "The database was upgraded by another connection",
+            ))));
+        }
+        let names = tx.store_names();
+        if let Some(name) = self.stores.iter().find(|s| !names.contains(s)) {
+            return Err(ctx.store(name).apply(Error::DoesNotExist(ErrorContext::new(
+                "The object store does not exist",
+            ))));
+        }
+
+        let state = TxState::new(self.mode, Some(self.stores), tx);
+        let res = transaction(Transaction::new(state.clone())).await;
+        state
+            .borrow_mut()
+            .finish(res.is_ok())
+            .map_err(|e| ctx.fail(e))?;
+        res
+    }
+
+    /// Execute the transaction
+    ///
+    /// See [`crate::TransactionBuilder::run_no_wait`].
+    ///
+    /// On the native backends, this is the same as [`TransactionBuilder::run`].
+    pub async fn run_no_wait<Ret, Err>(
+        self,
+        transaction: impl AsyncFnOnce(Transaction<Err, S>) -> crate::Result<Ret, Err>,
+    ) -> crate::Result<Ret, Err> {
+        self.run(transaction).await
+    }
+}
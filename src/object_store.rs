@@ -1,50 +1,75 @@
 use crate::{
-    transaction::transaction_request,
-    utils::{
-        array_to_vec, make_key_range, map_add_err, map_clear_err, map_count_err, map_count_res,
-        map_delete_err, map_get_err, none_if_undefined, str_slice_to_array,
-    },
-    CursorBuilder, Index,
+    backend::{Backend, BackendObjectStore, WebSys},
+    CursorBuilder, Index, Key, KeyPath,
 };
-use futures_util::future::{Either, FutureExt};
-use std::{future::Future, marker::PhantomData, ops::RangeBounds};
-use web_sys::{js_sys::JsString, wasm_bindgen::JsValue, IdbIndexParameters, IdbObjectStore};
+use std::{borrow::Borrow, future::Future, ops::RangeBounds};
 
 #[cfg(doc)]
 use crate::Cursor;
 
-/// Wrapper for [`IDBObjectStore`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore),
-/// for use in transactions
-#[derive(Debug)]
-pub struct ObjectStore<Err> {
-    sys: IdbObjectStore,
-    _phantom: PhantomData<Err>,
+/// An object store, for use in transactions
+pub struct ObjectStore<Err, B: Backend = WebSys> {
+    inner: B::ObjectStore<Err>,
 }
 
-impl<Err> ObjectStore<Err> {
-    pub(crate) fn from_sys(sys: IdbObjectStore) -> ObjectStore<Err> {
-        ObjectStore {
-            sys,
-            _phantom: PhantomData,
+impl<Err, B: Backend> std::fmt::Debug for ObjectStore<Err, B>
+where
+    B::ObjectStore<Err>: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ObjectStore")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl<Err, B: Backend> ObjectStore<Err, B> {
+    pub(crate) fn from_backend(inner: B::ObjectStore<Err>) -> ObjectStore<Err, B> {
+        ObjectStore { inner }
+    }
+
+    pub(crate) fn build_index_sys<'a>(
+        &'a self,
+        name: &'a str,
+        key_path: &KeyPath,
+    ) -> IndexBuilder<'a, Err, B> {
+        IndexBuilder {
+            store: self,
+            name,
+            key_path: key_path.clone(),
+            unique: false,
+            multi_entry: false,
         }
     }
 
+    /// The name of this object store
+    pub fn name(&self) -> String {
+        self.inner.name()
+    }
+
+    /// The key path of this object store, or `None` if it uses out-of-line keys
+    pub fn key_path(&self) -> crate::Result<Option<KeyPath>, Err> {
+        self.inner.key_path()
+    }
+
+    /// Whether the keys of this object store are auto-incremented
+    pub fn auto_increment(&self) -> bool {
+        self.inner.auto_increment()
+    }
+
+    /// The names of all [`Index`]es on this object store
+    pub fn index_names(&self) -> Vec<String> {
+        self.inner.index_names()
+    }
+
     /// Build an index over this object store
     ///
     /// Note that this method can only be called from within an `on_upgrade_needed` callback. It returns
     /// a builder, and calling the `create` method on this builder will perform the actual creation.
     ///
     /// If you want to make an index that searches multiple columns, please use [`ObjectStore::build_compound_index`].
-    ///
-    /// Internally, this uses [`IDBObjectStore::createIndex`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/createIndex).
-    pub fn build_index<'a>(&self, name: &'a str, key_path: &str) -> IndexBuilder<'a, Err> {
-        IndexBuilder {
-            store: self.sys.clone(),
-            name,
-            key_path: JsString::from(key_path).into(),
-            options: IdbIndexParameters::new(),
-            _phantom: PhantomData,
-        }
+    pub fn build_index<'a>(&'a self, name: &'a str, key_path: &str) -> IndexBuilder<'a, Err, B> {
+        self.build_index_sys(name, &KeyPath::from(key_path))
     }
 
     /// Build a compound index over this object store
@@ -53,408 +78,292 @@ impl<Err> ObjectStore<Err> {
     /// a builder, and calling the `create` method on this builder will perform the actual creation.
     ///
     /// Interesting points about indices:
-    /// - It is not possible to index `bool` in IndexedDB.
+    /// - It is not possible to index `bool`.
     /// - If your index uses a column that does not exist, then the object will not be recorded in the index.
     ///   This is useful for unique compound indices, usually when you would have conditionally indexed a `bool` column otherwise.
     /// - You cannot build a compound multi-entry index, it needs to be a regular index.
-    ///
-    /// Internally, this uses [`IDBObjectStore::createIndex`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/createIndex).
     pub fn build_compound_index<'a>(
-        &self,
+        &'a self,
         name: &'a str,
         key_paths: &[&str],
-    ) -> IndexBuilder<'a, Err> {
-        IndexBuilder {
-            store: self.sys.clone(),
-            name,
-            key_path: str_slice_to_array(key_paths).into(),
-            options: IdbIndexParameters::new(),
-            _phantom: PhantomData,
-        }
+    ) -> IndexBuilder<'a, Err, B> {
+        self.build_index_sys(name, &KeyPath::from(key_paths))
     }
 
     /// Delete an index from this object store
     ///
     /// Note that this method can only be called from within an `on_upgrade_needed` callback.
-    ///
-    /// Internally, this uses [`IDBObjectStore::deleteIndex`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/deleteIndex).
     pub fn delete_index(&self, name: &str) -> crate::Result<(), Err> {
-        self.sys
-            .delete_index(name)
-            .map_err(|err| match error_name!(&err) {
-                Some("InvalidStateError") => crate::Error::ObjectStoreWasRemoved,
-                Some("NotFoundError") => crate::Error::DoesNotExist,
-                _ => crate::Error::from_js_value(err),
-            })
+        self.inner.delete_index(name)
     }
 
     /// Add the value `value` to this object store, and return its auto-computed key
     ///
     /// This will error if the key already existed.
-    ///
-    /// Internally, this uses [`IDBObjectStore::add`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/add).
-    pub fn add(&self, value: &JsValue) -> impl Future<Output = crate::Result<JsValue, Err>> {
-        match self.sys.add(value) {
-            Ok(add_req) => {
-                Either::Left(transaction_request(add_req).map(|res| res.map_err(map_add_err)))
-            }
-            Err(e) => Either::Right(std::future::ready(Err(map_add_err(e)))),
-        }
+    pub fn add(&self, value: &B::Value) -> impl Future<Output = crate::Result<Key, Err>> {
+        self.inner.add(value)
     }
 
     /// Add the value `value` to this object store, with key `key`
     ///
     /// This will error if the key already existed.
-    ///
-    /// Internally, this uses [`IDBObjectStore::add`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/add).
     pub fn add_kv(
         &self,
-        key: &JsValue,
-        value: &JsValue,
+        key: impl Into<Key>,
+        value: &B::Value,
     ) -> impl Future<Output = crate::Result<(), Err>> {
-        match self.sys.add_with_key(value, key) {
-            Ok(add_req) => Either::Left(
-                transaction_request(add_req).map(|res| res.map_err(map_add_err).map(|_| ())),
-            ),
-            Err(e) => Either::Right(std::future::ready(Err(map_add_err(e)))),
-        }
+        self.inner.add_kv(&key.into(), value)
     }
 
     /// Add the value `value` to this object store, and return its auto-computed key
     ///
     /// This will overwrite the previous value if the key already existed.
-    ///
-    /// Internally, this uses [`IDBObjectStore::add`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/add).
-    pub fn put(&self, value: &JsValue) -> impl Future<Output = crate::Result<JsValue, Err>> {
-        match self.sys.put(value) {
-            Ok(add_req) => {
-                Either::Left(transaction_request(add_req).map(|res| res.map_err(map_add_err)))
-            }
-            Err(e) => Either::Right(std::future::ready(Err(map_add_err(e)))),
-        }
+    pub fn put(&self, value: &B::Value) -> impl Future<Output = crate::Result<Key, Err>> {
+        self.inner.put(value)
     }
 
     /// Add the value `value` to this object store, with key `key`
     ///
     /// This will overwrite the previous value if the key already existed.
-    ///
-    /// Internally, this uses [`IDBObjectStore::add`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/add).
     pub fn put_kv(
         &self,
-        key: &JsValue,
-        value: &JsValue,
+        key: impl Into<Key>,
+        value: &B::Value,
     ) -> impl Future<Output = crate::Result<(), Err>> {
-        match self.sys.put_with_key(value, key) {
-            Ok(add_req) => Either::Left(
-                transaction_request(add_req).map(|res| res.map_err(map_add_err).map(|_| ())),
-            ),
-            Err(e) => Either::Right(std::future::ready(Err(map_add_err(e)))),
-        }
+        self.inner.put_kv(&key.into(), value)
     }
 
-    /// Clear this object store
+    /// Add all the values of `values` to this object store, and return their auto-computed keys
+    ///
+    /// This issues all the requests at once and only waits for the last one, which is much faster than
+    /// awaiting [`ObjectStore::add`] for each value. If some fail, this returns [`Error::Batch`](crate::Error::Batch)
+    /// with the position of the first failed value. The other values are still added, unless the
+    /// transaction is aborted, eg. by returning this error.
+    pub fn add_many(
+        &self,
+        values: impl IntoIterator<Item = impl Borrow<B::Value>>,
+    ) -> impl Future<Output = crate::Result<Vec<Key>, Err>> {
+        self.inner.add_many(values)
+    }
+
+    /// Add all the `(key, value)` pairs of `entries` to this object store
+    ///
+    /// This will error if one of the keys already existed. See [`ObjectStore::add_many`] for how
+    /// batches work.
+    pub fn add_kv_many(
+        &self,
+        entries: impl IntoIterator<Item = (impl Into<Key>, impl Borrow<B::Value>)>,
+    ) -> impl Future<Output = crate::Result<(), Err>> {
+        self.inner
+            .add_kv_many(entries.into_iter().map(|(k, v)| (k.into(), v)))
+    }
+
+    /// Put all the values of `values` in this object store, and return their auto-computed keys
+    ///
+    /// This will overwrite the previous values if the keys already existed. See [`ObjectStore::add_many`]
+    /// for how batches work.
+    pub fn put_many(
+        &self,
+        values: impl IntoIterator<Item = impl Borrow<B::Value>>,
+    ) -> impl Future<Output = crate::Result<Vec<Key>, Err>> {
+        self.inner.put_many(values)
+    }
+
+    /// Put all the `(key, value)` pairs of `entries` in this object store
+    ///
+    /// This will overwrite the previous values if the keys already existed. See [`ObjectStore::add_many`]
+    /// for how batches work.
+    pub fn put_kv_many(
+        &self,
+        entries: impl IntoIterator<Item = (impl Into<Key>, impl Borrow<B::Value>)>,
+    ) -> impl Future<Output = crate::Result<(), Err>> {
+        self.inner
+            .put_kv_many(entries.into_iter().map(|(k, v)| (k.into(), v)))
+    }
+
+    /// Atomically replace the value with key `key` by the result of `f`, and return the previous and new values
+    ///
+    /// `f` receives the current value, or `None` if there is none. Returning `Some` inserts or
+    /// overwrites the value, while returning `None` deletes it. For object stores with in-line keys,
+    /// the returned value must keep key `key`, otherwise this fails with
+    /// [`Error::InvalidKey`](crate::Error::InvalidKey) and nothing is written.
+    ///
+    /// This runs within the current transaction, so no other transaction can modify the value between
+    /// the read and the write. It returns [`Error::ReadOnly`](crate::Error::ReadOnly) in a readonly
+    /// transaction.
+    #[allow(clippy::type_complexity)]
+    pub fn update_with(
+        &self,
+        key: impl Into<Key>,
+        f: impl FnOnce(Option<B::Value>) -> Option<B::Value>,
+    ) -> impl Future<Output = crate::Result<(Option<B::Value>, Option<B::Value>), Err>> {
+        self.inner.update_with(&key.into(), f)
+    }
+
+    /// Atomically set the value with key `key` to `new` if it currently is `expected`, and return the
+    /// previous and new values
     ///
-    /// Internally, this uses [`IDBObjectStore::clear`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/clear).
+    /// `None` stands for the absence of a value, both for `expected` and `new`. Values are compared
+    /// structurally, so eg. an object read from this store is equal to an object with the same fields.
+    /// If the current value is not `expected`, nothing is written and the returned new value is the
+    /// previous one.
+    ///
+    /// See [`ObjectStore::update_with`] for the details.
+    #[allow(clippy::type_complexity)]
+    pub fn compare_and_swap(
+        &self,
+        key: impl Into<Key>,
+        expected: Option<&B::Value>,
+        new: Option<&B::Value>,
+    ) -> impl Future<Output = crate::Result<(Option<B::Value>, Option<B::Value>), Err>> {
+        self.inner.compare_and_swap(&key.into(), expected, new)
+    }
+
+    /// Clear this object store
     pub fn clear(&self) -> impl Future<Output = crate::Result<(), Err>> {
-        match self.sys.clear() {
-            Ok(clear_req) => Either::Left(
-                transaction_request(clear_req).map(|res| res.map_err(map_clear_err).map(|_| ())),
-            ),
-            Err(err) => Either::Right(std::future::ready(Err(map_clear_err(err)))),
-        }
+        self.inner.clear()
     }
 
     /// Count the number of objects in this store
-    ///
-    /// Internally, this uses [`IDBObjectStore::count`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/count).
     pub fn count(&self) -> impl Future<Output = crate::Result<usize, Err>> {
-        match self.sys.count() {
-            Ok(count_req) => Either::Left(
-                transaction_request(count_req)
-                    .map(|res| res.map_err(map_count_err).map(map_count_res)),
-            ),
-            Err(e) => Either::Right(std::future::ready(Err(map_count_err(e)))),
-        }
+        self.inner.count()
     }
 
     /// Checks whether the provided key exists in this object store
-    ///
-    /// Internally, this uses [`IDBObjectStore::count`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/count).
-    pub fn contains(&self, key: &JsValue) -> impl Future<Output = crate::Result<bool, Err>> {
-        match self.sys.count_with_key(key) {
-            Ok(count_req) => Either::Left(
-                transaction_request(count_req)
-                    .map(|res| res.map_err(map_count_err).map(|n| map_count_res(n) != 0)),
-            ),
-            Err(e) => Either::Right(std::future::ready(Err(map_count_err(e)))),
-        }
+    pub fn contains(&self, key: impl Into<Key>) -> impl Future<Output = crate::Result<bool, Err>> {
+        self.inner.contains(&key.into())
     }
 
     /// Counts the number of objects with a key in `range`
-    ///
-    /// Note that the unbounded range is not a valid range for IndexedDB.
-    ///
-    /// Internally, this uses [`IDBObjectStore::count`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/count).
     pub fn count_in(
         &self,
-        range: impl RangeBounds<JsValue>,
+        range: impl RangeBounds<Key>,
     ) -> impl Future<Output = crate::Result<usize, Err>> {
-        let range = match make_key_range(range) {
-            Ok(range) => range,
-            Err(e) => return Either::Left(std::future::ready(Err(e))),
-        };
-        match self.sys.count_with_key(&range) {
-            Ok(count_req) => Either::Right(
-                transaction_request(count_req)
-                    .map(|res| res.map_err(map_count_err).map(map_count_res)),
-            ),
-            Err(e) => Either::Left(std::future::ready(Err(map_count_err(e)))),
-        }
+        self.inner.count_in(range)
     }
 
     /// Delete the object with key `key`
     ///
-    /// Unfortunately, the IndexedDb API does not indicate whether an object was actually deleted.
-    ///
-    /// Internally, this uses [`IDBObjectStore::delete`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/delete).
-    pub fn delete(&self, key: &JsValue) -> impl Future<Output = crate::Result<(), Err>> {
-        match self.sys.delete(key) {
-            Ok(delete_req) => Either::Left(
-                transaction_request(delete_req).map(|res| res.map_err(map_delete_err).map(|_| ())),
-            ),
-            Err(e) => Either::Right(std::future::ready(Err(map_delete_err(e)))),
-        }
+    /// Note that this does not indicate whether an object was actually deleted.
+    pub fn delete(&self, key: impl Into<Key>) -> impl Future<Output = crate::Result<(), Err>> {
+        self.inner.delete(&key.into())
     }
 
     /// Delete all the objects with a key in `range`
     ///
-    /// Note that the unbounded range is not a valid range for IndexedDB.
-    /// Unfortunately, the IndexedDb API does not indicate whether an object was actually deleted.
-    ///
-    /// Internally, this uses [`IDBObjectStore::delete`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/delete).
+    /// Note that this does not indicate whether an object was actually deleted.
     pub fn delete_range(
         &self,
-        range: impl RangeBounds<JsValue>,
+        range: impl RangeBounds<Key>,
     ) -> impl Future<Output = crate::Result<(), Err>> {
-        let range = match make_key_range(range) {
-            Ok(range) => range,
-            Err(e) => return Either::Left(std::future::ready(Err(e))),
-        };
-        match self.sys.delete(&range) {
-            Ok(delete_req) => Either::Right(
-                transaction_request(delete_req).map(|res| res.map_err(map_delete_err).map(|_| ())),
-            ),
-            Err(e) => Either::Left(std::future::ready(Err(map_delete_err(e)))),
-        }
+        self.inner.delete_range(range)
     }
 
-    /// Get the object with key `key`
+    /// Delete the objects with the keys of `keys`
     ///
-    /// Internally, this uses [`IDBObjectStore::get`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/get).
-    pub fn get(&self, key: &JsValue) -> impl Future<Output = crate::Result<Option<JsValue>, Err>> {
-        match self.sys.get(key) {
-            Ok(get_req) => Either::Right(
-                transaction_request(get_req)
-                    .map(|res| res.map_err(map_get_err).map(none_if_undefined)),
-            ),
-            Err(err) => Either::Left(std::future::ready(Err(map_get_err(err)))),
-        }
+    /// See [`ObjectStore::add_many`] for how batches work.
+    pub fn delete_many(
+        &self,
+        keys: impl IntoIterator<Item = impl Into<Key>>,
+    ) -> impl Future<Output = crate::Result<(), Err>> {
+        self.inner.delete_many(keys.into_iter().map(Into::into))
+    }
+
+    /// Get the object with key `key`
+    pub fn get(
+        &self,
+        key: impl Into<Key>,
+    ) -> impl Future<Output = crate::Result<Option<B::Value>, Err>> {
+        self.inner.get(&key.into())
     }
 
     /// Get the first value with a key in `range`, ordered by key
-    ///
-    /// Note that the unbounded range is not a valid range for IndexedDB.
-    ///
-    /// Internally, this uses [`IDBObjectStore::get`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/get).
     pub fn get_first_in(
         &self,
-        range: impl RangeBounds<JsValue>,
-    ) -> impl Future<Output = crate::Result<Option<JsValue>, Err>> {
-        let range = match make_key_range(range) {
-            Ok(range) => range,
-            Err(e) => return Either::Left(std::future::ready(Err(e))),
-        };
-        match self.sys.get(&range) {
-            Ok(get_req) => Either::Right(
-                transaction_request(get_req)
-                    .map(|res| res.map_err(map_get_err).map(none_if_undefined)),
-            ),
-            Err(e) => Either::Left(std::future::ready(Err(map_get_err(e)))),
-        }
+        range: impl RangeBounds<Key>,
+    ) -> impl Future<Output = crate::Result<Option<B::Value>, Err>> {
+        self.inner.get_first_in(range)
     }
 
     /// Get all the objects in the store, with a maximum number of results of `limit`
-    ///
-    /// Internally, this uses [`IDBObjectStore::getAll`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/getAll).
     pub fn get_all(
         &self,
         limit: Option<u32>,
-    ) -> impl Future<Output = crate::Result<Vec<JsValue>, Err>> {
-        let get_req = match limit {
-            None => self.sys.get_all(),
-            Some(limit) => self
-                .sys
-                .get_all_with_key_and_limit(&JsValue::UNDEFINED, limit),
-        };
-        match get_req {
-            Ok(get_req) => Either::Right(
-                transaction_request(get_req).map(|res| res.map_err(map_get_err).map(array_to_vec)),
-            ),
-            Err(err) => Either::Left(std::future::ready(Err(map_get_err(err)))),
-        }
+    ) -> impl Future<Output = crate::Result<Vec<B::Value>, Err>> {
+        self.inner.get_all(limit)
     }
 
     /// Get all the objects with a key in the provided range, with a maximum number of results of `limit`
-    ///
-    /// Internally, this uses [`IDBObjectStore::getAll`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/getAll).
     pub fn get_all_in(
         &self,
-        range: impl RangeBounds<JsValue>,
+        range: impl RangeBounds<Key>,
         limit: Option<u32>,
-    ) -> impl Future<Output = crate::Result<Vec<JsValue>, Err>> {
-        let range = match make_key_range(range) {
-            Ok(range) => range,
-            Err(e) => return Either::Left(std::future::ready(Err(e))),
-        };
-        let get_req = match limit {
-            None => self.sys.get_all_with_key(&range),
-            Some(limit) => self.sys.get_all_with_key_and_limit(&range, limit),
-        };
-        match get_req {
-            Ok(get_req) => Either::Right(
-                transaction_request(get_req).map(|res| res.map_err(map_get_err).map(array_to_vec)),
-            ),
-            Err(err) => Either::Left(std::future::ready(Err(map_get_err(err)))),
-        }
+    ) -> impl Future<Output = crate::Result<Vec<B::Value>, Err>> {
+        self.inner.get_all_in(range, limit)
     }
 
     /// Get the first existing key in the provided range
-    ///
-    /// Internally, this uses [`IDBObjectStore::getKey`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/getKey).
     pub fn get_first_key_in(
         &self,
-        range: impl RangeBounds<JsValue>,
-    ) -> impl Future<Output = crate::Result<Option<JsValue>, Err>> {
-        let range = match make_key_range(range) {
-            Ok(range) => range,
-            Err(e) => return Either::Left(std::future::ready(Err(e))),
-        };
-        match self.sys.get_key(&range) {
-            Ok(get_req) => Either::Right(
-                transaction_request(get_req)
-                    .map(|res| res.map_err(map_get_err).map(none_if_undefined)),
-            ),
-            Err(err) => Either::Left(std::future::ready(Err(map_get_err(err)))),
-        }
+        range: impl RangeBounds<Key>,
+    ) -> impl Future<Output = crate::Result<Option<Key>, Err>> {
+        self.inner.get_first_key_in(range)
     }
 
     /// List all the keys in the object store, with a maximum number of results of `limit`
-    ///
-    /// Internally, this uses [`IDBObjectStore::getAllKeys`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/getAllKeys).
     pub fn get_all_keys(
         &self,
         limit: Option<u32>,
-    ) -> impl Future<Output = crate::Result<Vec<JsValue>, Err>> {
-        let get_req = match limit {
-            None => self.sys.get_all_keys(),
-            Some(limit) => self
-                .sys
-                .get_all_keys_with_key_and_limit(&JsValue::UNDEFINED, limit),
-        };
-        match get_req {
-            Ok(get_req) => Either::Right(
-                transaction_request(get_req).map(|res| res.map_err(map_get_err).map(array_to_vec)),
-            ),
-            Err(err) => Either::Left(std::future::ready(Err(map_get_err(err)))),
-        }
+    ) -> impl Future<Output = crate::Result<Vec<Key>, Err>> {
+        self.inner.get_all_keys(limit)
     }
 
     /// List all the keys in the provided range, with a maximum number of results of `limit`
-    ///
-    /// Internally, this uses [`IDBObjectStore::getAllKeys`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/getAllKeys).
     pub fn get_all_keys_in(
         &self,
-        range: impl RangeBounds<JsValue>,
+        range: impl RangeBounds<Key>,
         limit: Option<u32>,
-    ) -> impl Future<Output = crate::Result<Vec<JsValue>, Err>> {
-        let range = match make_key_range(range) {
-            Ok(range) => range,
-            Err(e) => return Either::Left(std::future::ready(Err(e))),
-        };
-        let get_req = match limit {
-            None => self.sys.get_all_keys_with_key(&range),
-            Some(limit) => self.sys.get_all_keys_with_key_and_limit(&range, limit),
-        };
-        match get_req {
-            Ok(get_req) => Either::Right(
-                transaction_request(get_req).map(|res| res.map_err(map_get_err).map(array_to_vec)),
-            ),
-            Err(err) => Either::Left(std::future::ready(Err(map_get_err(err)))),
-        }
+    ) -> impl Future<Output = crate::Result<Vec<Key>, Err>> {
+        self.inner.get_all_keys_in(range, limit)
     }
 
     /// Get the [`Index`] with the provided name
-    ///
-    /// Internally, this uses [`IDBObjectStore::index`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/index).
-    pub fn index(&self, name: &str) -> crate::Result<Index<Err>, Err> {
-        Ok(Index::from_sys(self.sys.index(name).map_err(
-            |err| match error_name!(&err) {
-                Some("InvalidStateError") => crate::Error::ObjectStoreWasRemoved,
-                Some("NotFoundError") => crate::Error::DoesNotExist,
-                _ => crate::Error::from_js_value(err),
-            },
-        )?))
+    pub fn index(&self, name: &str) -> crate::Result<Index<Err, B>, Err> {
+        self.inner.index(name).map(Index::from_backend)
     }
 
     /// Open a [`Cursor`] on this object store
-    pub fn cursor(&self) -> CursorBuilder<Err> {
-        CursorBuilder::from_store(self.sys.clone())
+    pub fn cursor(&self) -> CursorBuilder<Err, B> {
+        CursorBuilder::from_backend(self.inner.cursor())
     }
 }
 
 /// Helper to build indexes over an [`ObjectStore`]
-pub struct IndexBuilder<'a, Err> {
-    store: IdbObjectStore,
+pub struct IndexBuilder<'a, Err, B: Backend = WebSys> {
+    store: &'a ObjectStore<Err, B>,
     name: &'a str,
-    key_path: JsValue,
-    options: IdbIndexParameters,
-    _phantom: PhantomData<Err>,
+    key_path: KeyPath,
+    unique: bool,
+    multi_entry: bool,
 }
 
-impl<'a, Err> IndexBuilder<'a, Err> {
+impl<'a, Err, B: Backend> IndexBuilder<'a, Err, B> {
     /// Create the index
-    ///
-    /// Internally, this uses [`IDBObjectStore::createIndex`](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/createIndex).
     pub fn create(self) -> crate::Result<(), Err> {
         self.store
-            .create_index_with_str_sequence_and_optional_parameters(
-                self.name,
-                &self.key_path,
-                &self.options,
-            )
-            .map_err(|err| match error_name!(&err) {
-                Some("ConstraintError") => crate::Error::AlreadyExists,
-                Some("InvalidAccessError") => crate::Error::InvalidArgument,
-                Some("InvalidStateError") => crate::Error::ObjectStoreWasRemoved,
-                Some("SyntaxError") => crate::Error::InvalidKey,
-                _ => crate::Error::from_js_value(err),
-            })
-            .map(|_| ())
+            .inner
+            .create_index(self.name, &self.key_path, self.unique, self.multi_entry)
     }
 
     /// Mark this index as unique
-    ///
-    /// Internally, this sets [this property](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/createIndex#unique).
-    pub fn unique(self) -> Self {
-        self.options.set_unique(true);
+    pub fn unique(mut self) -> Self {
+        self.unique = true;
         self
     }
 
     /// Mark this index as multi-entry
-    ///
-    /// Internally, this sets [this property](https://developer.mozilla.org/en-US/docs/Web/API/IDBObjectStore/createIndex#multientry).
-    pub fn multi_entry(self) -> Self {
-        self.options.set_multi_entry(true);
+    pub fn multi_entry(mut self) -> Self {
+        self.multi_entry = true;
         self
     }
 }
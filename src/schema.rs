@@ -0,0 +1,497 @@
+use crate::{
+    backend::Backend, Database, Index, KeyPath, ObjectStore, Transaction, VersionChangeEvent,
+};
+use std::collections::BTreeMap;
+
+/// Declarative description of the object stores and indexes of a database
+///
+/// A schema is made of an ordered list of versions, each version being a list of [`Migration`] steps. Version
+/// `n` of the database is reached by applying the steps of the `n` first versions, in order. The object stores
+/// and indexes expected at the latest version are deduced from these steps.
+///
+/// Use [`Factory::open_with_schema`](crate::Factory::open_with_schema) to open a database with a schema, running only the missing steps.
+///
+/// ```rust
+/// # use indexed_db::{IndexSchema, Migration, Schema, StoreSchema};
+/// let schema = Schema::new()
+///     .version([Migration::create_store(
+///         StoreSchema::new("users")
+///             .key_path("id")
+///             .index(IndexSchema::new("by_email", "email").unique()),
+///     )])
+///     .version([
+///         Migration::create_store(StoreSchema::new("logs").auto_increment()),
+///         Migration::create_index("users", IndexSchema::new("by_tag", "tags").multi_entry()),
+///     ]);
+/// assert_eq!(schema.latest_version(), 2);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Schema {
+    versions: Vec<Vec<Migration>>,
+}
+
+impl Schema {
+    /// Create an empty schema, without any version
+    pub fn new() -> Schema {
+        Schema::default()
+    }
+
+    /// Add a version, reached by running `migrations` in order on top of the previous version
+    pub fn version(mut self, migrations: impl IntoIterator<Item = Migration>) -> Self {
+        self.versions.push(migrations.into_iter().collect());
+        self
+    }
+
+    /// The latest version of this schema, ie. the number of versions that were added to it
+    pub fn latest_version(&self) -> u32 {
+        u32::try_from(self.versions.len()).expect("Too many versions in schema")
+    }
+
+    /// The object stores expected at the latest version, sorted by name
+    ///
+    /// The indexes of each object store are sorted by name too.
+    pub fn stores(&self) -> Vec<StoreSchema> {
+        let mut stores = BTreeMap::new();
+        for migration in self.versions.iter().flatten() {
+            match migration {
+                Migration::CreateStore(store) => {
+                    stores.insert(store.name.clone(), store.clone());
+                }
+                Migration::DeleteStore(name) => {
+                    stores.remove(name);
+                }
+                Migration::CreateIndex { store, index } => {
+                    if let Some(store) = stores.get_mut(store) {
+                        store.indexes.retain(|i| i.name != index.name);
+                        store.indexes.push(index.clone());
+                    }
+                }
+                Migration::DeleteIndex { store, index } => {
+                    if let Some(store) = stores.get_mut(store) {
+                        store.indexes.retain(|i| &i.name != index);
+                    }
+                }
+            }
+        }
+        stores
+            .into_values()
+            .map(|mut store| {
+                store.indexes.sort_by(|a, b| a.name.cmp(&b.name));
+                store
+            })
+            .collect()
+    }
+
+    /// The snapshot of the database expected at the latest version
+    pub fn snapshot(&self) -> SchemaSnapshot {
+        SchemaSnapshot {
+            version: self.latest_version(),
+            stores: self.stores(),
+        }
+    }
+
+    pub(crate) fn upgrade<Err, B: Backend>(
+        &self,
+        evt: &VersionChangeEvent<Err, B>,
+    ) -> crate::Result<(), Err> {
+        let old_version = usize::try_from(evt.old_version()).unwrap();
+        let new_version = usize::try_from(evt.new_version()).unwrap();
+        for migration in self.versions[old_version..new_version].iter().flatten() {
+            match migration {
+                Migration::CreateStore(store) => {
+                    let mut builder = evt.build_object_store(&store.name);
+                    if let Some(key_path) = &store.key_path {
+                        builder = builder.key_path_sys(key_path);
+                    }
+                    if store.auto_increment {
+                        builder = builder.auto_increment();
+                    }
+                    let created = builder.create()?;
+                    for index in &store.indexes {
+                        create_index(&created, index)?;
+                    }
+                }
+                Migration::DeleteStore(name) => evt.delete_object_store(name)?,
+                Migration::CreateIndex { store, index } => {
+                    create_index(&evt.transaction().object_store(store)?, index)?;
+                }
+                Migration::DeleteIndex { store, index } => {
+                    evt.transaction().object_store(store)?.delete_index(index)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check<Err, B: Backend>(
+        &self,
+        db: &Database<B>,
+        transaction: &Transaction<Err, B>,
+    ) -> crate::Result<(), Err> {
+        if SchemaSnapshot::from_transaction(db, transaction)?.stores != self.stores() {
+            return Err(crate::Error::SchemaMismatch);
+        }
+        Ok(())
+    }
+}
+
+fn create_index<Err, B: Backend>(
+    store: &ObjectStore<Err, B>,
+    index: &IndexSchema,
+) -> crate::Result<(), Err> {
+    let mut builder = store.build_index_sys(&index.name, &index.key_path);
+    if index.unique {
+        builder = builder.unique();
+    }
+    if index.multi_entry {
+        builder = builder.multi_entry();
+    }
+    builder.create()
+}
+
+/// Description of all the object stores and indexes of a database at a given version
+///
+/// Snapshots can be taken from an open database with [`Database::schema_snapshot`], or computed from a
+/// [`Schema`] with [`Schema::snapshot`], and compared with [`SchemaSnapshot::diff`]. With the `serde`
+/// feature, they can be serialized, eg. to check in a reference snapshot.
+///
+/// ```rust
+/// # use indexed_db::{IndexSchema, Migration, Schema, StoreSchema};
+/// let v1 = Schema::new().version([Migration::create_store(StoreSchema::new("users"))]);
+/// let v2 = v1.clone().version([
+///     Migration::create_store(StoreSchema::new("logs")),
+///     Migration::create_index("users", IndexSchema::new("by_email", "email")),
+/// ]);
+/// let diff = v1.snapshot().diff(&v2.snapshot());
+/// assert_eq!(diff.added_stores[0].name, "logs");
+/// assert_eq!(diff.changed_stores[0].added_indexes[0].name, "by_email");
+/// assert!(v2.snapshot().diff(&v2.snapshot()).is_empty());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct SchemaSnapshot {
+    /// The version of the database
+    pub version: u32,
+
+    /// The object stores of the database, sorted by name, each with its indexes sorted by name
+    pub stores: Vec<StoreSchema>,
+}
+
+impl SchemaSnapshot {
+    pub(crate) fn from_transaction<Err, B: Backend>(
+        db: &Database<B>,
+        transaction: &Transaction<Err, B>,
+    ) -> crate::Result<SchemaSnapshot, Err> {
+        let stores = db
+            .object_store_names()
+            .iter()
+            .map(|name| StoreSchema::from_store(&transaction.object_store(name)?))
+            .collect::<crate::Result<Vec<_>, Err>>()?;
+        Ok(SchemaSnapshot {
+            version: db.version(),
+            stores,
+        })
+    }
+
+    /// Compute the changes needed to go from `self` to `new`
+    ///
+    /// Note that the version is not part of the diff: two snapshots with the same stores and indexes but
+    /// different versions result in an empty diff.
+    pub fn diff(&self, new: &SchemaSnapshot) -> SchemaDiff {
+        let mut diff = SchemaDiff {
+            old_version: self.version,
+            new_version: new.version,
+            added_stores: Vec::new(),
+            removed_stores: Vec::new(),
+            changed_stores: Vec::new(),
+        };
+        for old in &self.stores {
+            if !new.stores.iter().any(|s| s.name == old.name) {
+                diff.removed_stores.push(old.clone());
+            }
+        }
+        for new in &new.stores {
+            match self.stores.iter().find(|s| s.name == new.name) {
+                None => diff.added_stores.push(new.clone()),
+                Some(old) if old != new => diff.changed_stores.push(StoreDiff::new(old, new)),
+                Some(_) => (),
+            }
+        }
+        diff
+    }
+}
+
+/// Differences between two [`SchemaSnapshot`]s, as returned by [`SchemaSnapshot::diff`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct SchemaDiff {
+    /// The version of the old snapshot
+    pub old_version: u32,
+
+    /// The version of the new snapshot
+    pub new_version: u32,
+
+    /// The object stores that only exist in the new snapshot
+    pub added_stores: Vec<StoreSchema>,
+
+    /// The object stores that only exist in the old snapshot
+    pub removed_stores: Vec<StoreSchema>,
+
+    /// The object stores that exist in both snapshots, but differ
+    pub changed_stores: Vec<StoreDiff>,
+}
+
+impl SchemaDiff {
+    /// Whether the two snapshots have the same object stores and indexes
+    pub fn is_empty(&self) -> bool {
+        self.added_stores.is_empty()
+            && self.removed_stores.is_empty()
+            && self.changed_stores.is_empty()
+    }
+}
+
+/// Differences between two versions of the same object store, as part of a [`SchemaDiff`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct StoreDiff {
+    /// The object store in the old snapshot
+    pub old: StoreSchema,
+
+    /// The object store in the new snapshot
+    pub new: StoreSchema,
+
+    /// The indexes that only exist in the new snapshot
+    pub added_indexes: Vec<IndexSchema>,
+
+    /// The indexes that only exist in the old snapshot
+    pub removed_indexes: Vec<IndexSchema>,
+
+    /// The indexes that exist in both snapshots but differ, as `(old, new)` pairs
+    pub changed_indexes: Vec<(IndexSchema, IndexSchema)>,
+}
+
+impl StoreDiff {
+    fn new(old: &StoreSchema, new: &StoreSchema) -> StoreDiff {
+        let mut diff = StoreDiff {
+            old: old.clone(),
+            new: new.clone(),
+            added_indexes: Vec::new(),
+            removed_indexes: Vec::new(),
+            changed_indexes: Vec::new(),
+        };
+        for old_index in &old.indexes {
+            if !new.indexes.iter().any(|i| i.name == old_index.name) {
+                diff.removed_indexes.push(old_index.clone());
+            }
+        }
+        for new_index in &new.indexes {
+            match old.indexes.iter().find(|i| i.name == new_index.name) {
+                None => diff.added_indexes.push(new_index.clone()),
+                Some(old_index) if old_index != new_index => diff
+                    .changed_indexes
+                    .push((old_index.clone(), new_index.clone())),
+                Some(_) => (),
+            }
+        }
+        diff
+    }
+
+    /// Whether the key path or auto-increment setting of the object store changed
+    ///
+    /// Such a change cannot be done by a migration, and requires recreating the object store.
+    pub fn key_changed(&self) -> bool {
+        self.old.key_path != self.new.key_path || self.old.auto_increment != self.new.auto_increment
+    }
+}
+
+/// A step of a [`Schema`] version
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Migration {
+    /// Create an object store, along with its indexes
+    CreateStore(StoreSchema),
+
+    /// Delete the object store with this name
+    DeleteStore(String),
+
+    /// Create an index on an existing object store
+    CreateIndex {
+        /// The name of the object store
+        store: String,
+
+        /// The index to create
+        index: IndexSchema,
+    },
+
+    /// Delete an index from an existing object store
+    DeleteIndex {
+        /// The name of the object store
+        store: String,
+
+        /// The name of the index to delete
+        index: String,
+    },
+}
+
+impl Migration {
+    /// Create an object store, along with its indexes
+    pub fn create_store(store: StoreSchema) -> Migration {
+        Migration::CreateStore(store)
+    }
+
+    /// Delete the object store named `name`
+    pub fn delete_store(name: &str) -> Migration {
+        Migration::DeleteStore(name.to_string())
+    }
+
+    /// Create `index` on the existing object store named `store`
+    pub fn create_index(store: &str, index: IndexSchema) -> Migration {
+        Migration::CreateIndex {
+            store: store.to_string(),
+            index,
+        }
+    }
+
+    /// Delete the index named `index` from the existing object store named `store`
+    pub fn delete_index(store: &str, index: &str) -> Migration {
+        Migration::DeleteIndex {
+            store: store.to_string(),
+            index: index.to_string(),
+        }
+    }
+}
+
+/// Description of an object store
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct StoreSchema {
+    /// The name of the object store
+    pub name: String,
+
+    /// The key path of the object store, or `None` for out-of-line keys
+    pub key_path: Option<KeyPath>,
+
+    /// Whether the keys of the object store are auto-incremented
+    pub auto_increment: bool,
+
+    /// The indexes of the object store
+    pub indexes: Vec<IndexSchema>,
+}
+
+impl StoreSchema {
+    /// Describe an object store named `name`, with out-of-line keys and no index
+    pub fn new(name: &str) -> StoreSchema {
+        StoreSchema {
+            name: name.to_string(),
+            key_path: None,
+            auto_increment: false,
+            indexes: Vec::new(),
+        }
+    }
+
+    /// Set the key path
+    pub fn key_path(mut self, path: &str) -> Self {
+        self.key_path = Some(KeyPath::from(path));
+        self
+    }
+
+    /// Set a compound key path made of multiple attributes
+    pub fn compound_key_path(mut self, paths: &[&str]) -> Self {
+        self.key_path = Some(KeyPath::from(paths));
+        self
+    }
+
+    /// Enable auto-increment for the key
+    pub fn auto_increment(mut self) -> Self {
+        self.auto_increment = true;
+        self
+    }
+
+    /// Add an index
+    pub fn index(mut self, index: IndexSchema) -> Self {
+        self.indexes.push(index);
+        self
+    }
+
+    pub(crate) fn from_store<Err, B: Backend>(
+        store: &ObjectStore<Err, B>,
+    ) -> crate::Result<StoreSchema, Err> {
+        let mut indexes = store
+            .index_names()
+            .iter()
+            .map(|name| IndexSchema::from_index(&store.index(name)?))
+            .collect::<crate::Result<Vec<_>, Err>>()?;
+        indexes.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(StoreSchema {
+            name: store.name(),
+            key_path: store.key_path()?,
+            auto_increment: store.auto_increment(),
+            indexes,
+        })
+    }
+}
+
+/// Description of an index
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct IndexSchema {
+    /// The name of the index
+    pub name: String,
+
+    /// The key path of the index
+    pub key_path: KeyPath,
+
+    /// Whether the index enforces unicity of its keys
+    pub unique: bool,
+
+    /// Whether the index adds one entry per element when the key path resolves to an array
+    pub multi_entry: bool,
+}
+
+impl IndexSchema {
+    /// Describe an index named `name` over `key_path`
+    pub fn new(name: &str, key_path: &str) -> IndexSchema {
+        IndexSchema {
+            name: name.to_string(),
+            key_path: KeyPath::from(key_path),
+            unique: false,
+            multi_entry: false,
+        }
+    }
+
+    /// Describe a compound index named `name` over multiple attributes
+    pub fn compound(name: &str, key_paths: &[&str]) -> IndexSchema {
+        IndexSchema {
+            name: name.to_string(),
+            key_path: KeyPath::from(key_paths),
+            unique: false,
+            multi_entry: false,
+        }
+    }
+
+    /// Mark this index as unique
+    pub fn unique(mut self) -> Self {
+        self.unique = true;
+        self
+    }
+
+    /// Mark this index as multi-entry
+    pub fn multi_entry(mut self) -> Self {
+        self.multi_entry = true;
+        self
+    }
+
+    fn from_index<Err, B: Backend>(index: &Index<Err, B>) -> crate::Result<IndexSchema, Err> {
+        Ok(IndexSchema {
+            name: index.name(),
+            key_path: index.key_path()?,
+            unique: index.is_unique(),
+            multi_entry: index.is_multi_entry(),
+        })
+    }
+}
@@ -0,0 +1,311 @@
+//! The encoding of keys and values into the blobs stored in SQLite
+//!
+//! SQLite compares blobs byte by byte, so keys are encoded such that this orders them like [`Key`]s
+//! are ordered. Values only need to round-trip, and use a compact tagged encoding.
+
+use crate::{memory::Value, Key, KeyPath};
+use std::collections::BTreeMap;
+
+const KEY_NUMBER: u8 = 0x10;
+const KEY_DATE: u8 = 0x20;
+const KEY_STRING: u8 = 0x30;
+const KEY_BINARY: u8 = 0x40;
+const KEY_ARRAY: u8 = 0x50;
+
+/// Ends arrays, and is below all the key tags so that shorter arrays come first
+const END_OF_ARRAY: u8 = 0x00;
+
+/// Ends strings and binaries, which escape their `0x00` bytes as `0x00 0xFF`
+const END_OF_BYTES: [u8; 2] = [0x00, 0x01];
+const ESCAPED_ZERO: u8 = 0xFF;
+
+const VALUE_UNDEFINED: u8 = 0;
+const VALUE_NULL: u8 = 1;
+const VALUE_FALSE: u8 = 2;
+const VALUE_TRUE: u8 = 3;
+const VALUE_NUMBER: u8 = 4;
+const VALUE_DATE: u8 = 5;
+const VALUE_STRING: u8 = 6;
+const VALUE_BINARY: u8 = 7;
+const VALUE_ARRAY: u8 = 8;
+const VALUE_OBJECT: u8 = 9;
+
+/// Encode `key`, which must be a valid key
+pub(crate) fn encode_key(key: &Key) -> Vec<u8> {
+    let mut out = Vec::new();
+    push_key(&mut out, key);
+    out
+}
+
+/// Decode a key encoded by [`encode_key`], or return `None` if `bytes` is not such a key
+pub(crate) fn decode_key(mut bytes: &[u8]) -> Option<Key> {
+    let key = read_key(&mut bytes)?;
+    bytes.is_empty().then_some(key)
+}
+
+fn push_key(out: &mut Vec<u8>, key: &Key) {
+    match key {
+        Key::Number(n) => {
+            out.push(KEY_NUMBER);
+            push_ordered_number(out, *n);
+        }
+        Key::Date(t) => {
+            out.push(KEY_DATE);
+            push_ordered_number(out, *t);
+        }
+        Key::String(s) => {
+            out.push(KEY_STRING);
+            let units = s.encode_utf16().flat_map(u16::to_be_bytes);
+            push_escaped_bytes(out, units);
+        }
+        Key::Binary(b) => {
+            out.push(KEY_BINARY);
+            push_escaped_bytes(out, b.iter().copied());
+        }
+        Key::Array(a) => {
+            out.push(KEY_ARRAY);
+            for key in a {
+                push_key(out, key);
+            }
+            out.push(END_OF_ARRAY);
+        }
+    }
+}
+
+fn read_key(bytes: &mut &[u8]) -> Option<Key> {
+    match read_byte(bytes)? {
+        KEY_NUMBER => Some(Key::Number(read_ordered_number(bytes)?)),
+        KEY_DATE => Some(Key::Date(read_ordered_number(bytes)?)),
+        KEY_STRING => {
+            let bytes = read_escaped_bytes(bytes)?;
+            if bytes.len() % 2 != 0 {
+                return None;
+            }
+            let units = bytes
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect::<Vec<_>>();
+            String::from_utf16(&units).ok().map(Key::String)
+        }
+        KEY_BINARY => Some(Key::Binary(read_escaped_bytes(bytes)?)),
+        KEY_ARRAY => {
+            let mut keys = Vec::new();
+            while *bytes.first()? != END_OF_ARRAY {
+                keys.push(read_key(bytes)?);
+            }
+            *bytes = &bytes[1..];
+            Some(Key::Array(keys))
+        }
+        _ => None,
+    }
+}
+
+/// Push `n` such that the byte order of the encodings is the numeric order, with `-0.0` equal to `0.0`
+fn push_ordered_number(out: &mut Vec<u8>, n: f64) {
+    let n = if n == 0.0 { 0.0 } else { n };
+    let bits = n.to_bits();
+    let bits = if n.is_sign_negative() {
+        !bits
+    } else {
+        bits | (1 << 63)
+    };
+    out.extend(bits.to_be_bytes());
+}
+
+fn read_ordered_number(bytes: &mut &[u8]) -> Option<f64> {
+    let bits = u64::from_be_bytes(read_array(bytes)?);
+    let bits = if bits & (1 << 63) != 0 {
+        bits & !(1 << 63)
+    } else {
+        !bits
+    };
+    Some(f64::from_bits(bits))
+}
+
+fn push_escaped_bytes(out: &mut Vec<u8>, bytes: impl Iterator<Item = u8>) {
+    for b in bytes {
+        out.push(b);
+        if b == 0 {
+            out.push(ESCAPED_ZERO);
+        }
+    }
+    out.extend(END_OF_BYTES);
+}
+
+fn read_escaped_bytes(bytes: &mut &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    loop {
+        match read_byte(bytes)? {
+            0 => match read_byte(bytes)? {
+                ESCAPED_ZERO => out.push(0),
+                b if [0, b] == END_OF_BYTES => return Some(out),
+                _ => return None,
+            },
+            b => out.push(b),
+        }
+    }
+}
+
+/// Encode `value`
+pub(crate) fn encode_value(value: &Value) -> Vec<u8> {
+    let mut out = Vec::new();
+    push_value(&mut out, value);
+    out
+}
+
+/// Decode a value encoded by [`encode_value`], or return `None` if `bytes` is not such a value
+pub(crate) fn decode_value(mut bytes: &[u8]) -> Option<Value> {
+    let value = read_value(&mut bytes)?;
+    bytes.is_empty().then_some(value)
+}
+
+fn push_value(out: &mut Vec<u8>, value: &Value) {
+    match value {
+        Value::Undefined => out.push(VALUE_UNDEFINED),
+        Value::Null => out.push(VALUE_NULL),
+        Value::Bool(false) => out.push(VALUE_FALSE),
+        Value::Bool(true) => out.push(VALUE_TRUE),
+        Value::Number(n) => {
+            out.push(VALUE_NUMBER);
+            out.extend(n.to_le_bytes());
+        }
+        Value::Date(t) => {
+            out.push(VALUE_DATE);
+            out.extend(t.to_le_bytes());
+        }
+        Value::String(s) => {
+            out.push(VALUE_STRING);
+            push_bytes(out, s.as_bytes());
+        }
+        Value::Binary(b) => {
+            out.push(VALUE_BINARY);
+            push_bytes(out, b);
+        }
+        Value::Array(a) => {
+            out.push(VALUE_ARRAY);
+            push_len(out, a.len());
+            for value in a {
+                push_value(out, value);
+            }
+        }
+        Value::Object(fields) => {
+            out.push(VALUE_OBJECT);
+            push_len(out, fields.len());
+            for (name, value) in fields {
+                push_bytes(out, name.as_bytes());
+                push_value(out, value);
+            }
+        }
+    }
+}
+
+fn read_value(bytes: &mut &[u8]) -> Option<Value> {
+    Some(match read_byte(bytes)? {
+        VALUE_UNDEFINED => Value::Undefined,
+        VALUE_NULL => Value::Null,
+        VALUE_FALSE => Value::Bool(false),
+        VALUE_TRUE => Value::Bool(true),
+        VALUE_NUMBER => Value::Number(f64::from_le_bytes(read_array(bytes)?)),
+        VALUE_DATE => Value::Date(f64::from_le_bytes(read_array(bytes)?)),
+        VALUE_STRING => Value::String(read_string(bytes)?),
+        VALUE_BINARY => Value::Binary(read_bytes(bytes)?.to_vec()),
+        VALUE_ARRAY => {
+            let len = read_len(bytes)?;
+            // Do not trust `len` for allocating, each element takes at least one byte
+            let mut values = Vec::with_capacity(len.min(bytes.len()));
+            for _ in 0..len {
+                values.push(read_value(bytes)?);
+            }
+            Value::Array(values)
+        }
+        VALUE_OBJECT => {
+            let len = read_len(bytes)?;
+            let mut fields = BTreeMap::new();
+            for _ in 0..len {
+                let name = read_string(bytes)?;
+                fields.insert(name, read_value(bytes)?);
+            }
+            Value::Object(fields)
+        }
+        _ => return None,
+    })
+}
+
+/// Encode `key_path`
+pub(crate) fn encode_key_path(key_path: &KeyPath) -> Vec<u8> {
+    encode_value(&match key_path {
+        KeyPath::Single(path) => Value::from(path.as_str()),
+        KeyPath::Compound(paths) => {
+            Value::Array(paths.iter().map(|p| Value::from(p.as_str())).collect())
+        }
+    })
+}
+
+/// Decode a key path encoded by [`encode_key_path`], or return `None` if `bytes` is not such a key path
+pub(crate) fn decode_key_path(bytes: &[u8]) -> Option<KeyPath> {
+    match decode_value(bytes)? {
+        Value::String(path) => Some(KeyPath::Single(path)),
+        Value::Array(paths) => paths
+            .into_iter()
+            .map(|p| match p {
+                Value::String(p) => Some(p),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()
+            .map(KeyPath::Compound),
+        _ => None,
+    }
+}
+
+/// Push `len` as a LEB128 variable-length integer
+fn push_len(out: &mut Vec<u8>, len: usize) {
+    let mut len = len as u64;
+    while len >= 0x80 {
+        out.push((len as u8) | 0x80);
+        len >>= 7;
+    }
+    out.push(len as u8);
+}
+
+fn read_len(bytes: &mut &[u8]) -> Option<usize> {
+    let mut len = 0u64;
+    for shift in (0..64).step_by(7) {
+        let b = read_byte(bytes)?;
+        len |= u64::from(b & 0x7F) << shift;
+        if b & 0x80 == 0 {
+            return usize::try_from(len).ok();
+        }
+    }
+    None
+}
+
+fn push_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    push_len(out, bytes.len());
+    out.extend_from_slice(bytes);
+}
+
+fn read_bytes<'a>(bytes: &mut &'a [u8]) -> Option<&'a [u8]> {
+    let len = read_len(bytes)?;
+    if len > bytes.len() {
+        return None;
+    }
+    let (res, rest) = bytes.split_at(len);
+    *bytes = rest;
+    Some(res)
+}
+
+fn read_string(bytes: &mut &[u8]) -> Option<String> {
+    String::from_utf8(read_bytes(bytes)?.to_vec()).ok()
+}
+
+fn read_byte(bytes: &mut &[u8]) -> Option<u8> {
+    let (&b, rest) = bytes.split_first()?;
+    *bytes = rest;
+    Some(b)
+}
+
+fn read_array<const N: usize>(bytes: &mut &[u8]) -> Option<[u8; N]> {
+    let res = bytes.get(..N)?.try_into().ok()?;
+    *bytes = &bytes[N..];
+    Some(res)
+}
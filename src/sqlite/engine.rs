@@ -0,0 +1,753 @@
+//! The SQL schema and queries of the SQLite backend, independent of transactions
+//!
+//! Each database is a row of the `databases` table, each object store a row of `object_stores` with
+//! its records in a `records_{id}` table, and each index a row of `indexes` with its entries in an
+//! `index_{id}` table. Keys are stored encoded by [`codec::encode_key`], so that SQLite orders them
+//! like IndexedDB does.
+
+use super::codec;
+use crate::{
+    memory::Value,
+    native::engine::{self, Failure, Range, Result, Target},
+    CursorDirection, DatabaseInfo, IndexSchema, Key, KeyPath, StoreSchema,
+};
+use rusqlite::{params, params_from_iter, Connection, ErrorCode, OptionalExtension};
+use std::{borrow::Cow, collections::BTreeMap, ops::Bound};
+
+impl From<rusqlite::Error> for Failure {
+    fn from(err: rusqlite::Error) -> Failure {
+        match err.sqlite_error_code() {
+            Some(ErrorCode::DiskFull) => Failure::QuotaExceeded(err.to_string()),
+            _ => Failure::Unknown(err.to_string()),
+        }
+    }
+}
+
+/// The failure for data that this backend cannot decode
+fn corrupted() -> Failure {
+    Failure::Unknown(String::from(
+        "The database file contains data that was not written by this backend",
+    ))
+}
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS databases (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        name TEXT NOT NULL UNIQUE,
+        version INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS object_stores (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        database INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        key_path BLOB,
+        auto_increment INTEGER NOT NULL,
+        current_number REAL NOT NULL,
+        UNIQUE (database, name)
+    );
+    CREATE TABLE IF NOT EXISTS indexes (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        store INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        key_path BLOB NOT NULL,
+        is_unique INTEGER NOT NULL,
+        multi_entry INTEGER NOT NULL,
+        UNIQUE (store, name)
+    );
+";
+
+/// The schema of a database, as seen by a transaction
+#[derive(Clone, Debug)]
+pub(crate) struct DatabaseMeta {
+    pub(crate) version: u32,
+    pub(crate) stores: BTreeMap<String, StoreMeta>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct StoreMeta {
+    id: i64,
+    pub(crate) key_path: Option<KeyPath>,
+    pub(crate) auto_increment: bool,
+    pub(crate) indexes: BTreeMap<String, IndexMeta>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct IndexMeta {
+    id: i64,
+    pub(crate) key_path: KeyPath,
+    pub(crate) unique: bool,
+    pub(crate) multi_entry: bool,
+}
+
+/// The conditions of a `WHERE` clause, with the encoded keys they compare to
+#[derive(Default)]
+struct Filter {
+    conditions: Vec<String>,
+    keys: Vec<Vec<u8>>,
+}
+
+impl Filter {
+    /// Restrict `column` to the keys of `range`
+    fn range(column: &str, range: &Range) -> Filter {
+        let mut filter = Filter::default();
+        let (lower, upper) = range.bounds();
+        for (bound, included, excluded) in [(lower, ">=", ">"), (upper, "<=", "<")] {
+            match bound {
+                Bound::Included(key) => filter.push(format!("{column} {included} ?"), &[key]),
+                Bound::Excluded(key) => filter.push(format!("{column} {excluded} ?"), &[key]),
+                Bound::Unbounded => (),
+            }
+        }
+        filter
+    }
+
+    /// Add `condition`, whose `?` placeholders are filled with `keys`
+    fn push(&mut self, condition: String, keys: &[&Key]) {
+        self.conditions.push(condition);
+        self.keys.extend(keys.iter().map(|k| codec::encode_key(k)));
+    }
+
+    fn where_clause(&self) -> String {
+        if self.conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", self.conditions.join(" AND "))
+        }
+    }
+}
+
+fn order(forward: bool) -> &'static str {
+    if forward {
+        "ASC"
+    } else {
+        "DESC"
+    }
+}
+
+fn limit_clause(limit: Option<u32>) -> String {
+    format!("LIMIT {}", limit.map_or(-1, i64::from))
+}
+
+fn decode_key(bytes: &[u8]) -> Result<Key> {
+    codec::decode_key(bytes).ok_or_else(corrupted)
+}
+
+fn decode_value(bytes: &[u8]) -> Result<Value> {
+    codec::decode_value(bytes).ok_or_else(corrupted)
+}
+
+fn decode_key_path(bytes: &[u8]) -> Result<KeyPath> {
+    codec::decode_key_path(bytes).ok_or_else(corrupted)
+}
+
+/// Run `sql` with `keys` as parameters, and return the blobs of the first column of each row
+fn query_blobs(conn: &Connection, sql: &str, keys: &[Vec<u8>]) -> Result<Vec<Vec<u8>>> {
+    let mut stmt = conn.prepare_cached(sql)?;
+    let rows = stmt.query_map(params_from_iter(keys), |row| row.get(0))?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+/// Run `sql` with `keys` as parameters, and return the blobs of the first two columns of each row
+fn query_blob_pairs(
+    conn: &Connection,
+    sql: &str,
+    keys: &[Vec<u8>],
+) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+    let mut stmt = conn.prepare_cached(sql)?;
+    let rows = stmt.query_map(params_from_iter(keys), |row| Ok((row.get(0)?, row.get(1)?)))?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+fn query_count(conn: &Connection, sql: &str, keys: &[Vec<u8>]) -> Result<usize> {
+    let count: i64 = conn
+        .prepare_cached(sql)?
+        .query_row(params_from_iter(keys), |row| row.get(0))?;
+    Ok(usize::try_from(count).unwrap_or(usize::MAX))
+}
+
+/// Create the tables of the SQLite file, if it was just created
+pub(crate) fn init(conn: &Connection) -> Result<()> {
+    conn.execute_batch(SCHEMA)?;
+    Ok(())
+}
+
+/// Run `f` atomically, rolling back its changes if it fails
+pub(crate) fn atomically<T>(conn: &Connection, f: impl FnOnce() -> Result<T>) -> Result<T> {
+    conn.execute_batch("SAVEPOINT request")?;
+    match f() {
+        Ok(res) => {
+            conn.execute_batch("RELEASE request")?;
+            Ok(res)
+        }
+        Err(err) => {
+            conn.execute_batch("ROLLBACK TO request; RELEASE request")?;
+            Err(err)
+        }
+    }
+}
+
+/// List the existing databases, sorted by name
+pub(crate) fn databases(conn: &Connection) -> Result<Vec<DatabaseInfo>> {
+    let mut stmt = conn.prepare_cached("SELECT name, version FROM databases ORDER BY name")?;
+    let rows = stmt.query_map([], |row| {
+        Ok(DatabaseInfo {
+            name: row.get(0)?,
+            version: row.get(1)?,
+        })
+    })?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+/// The id and version of the database named `name`, if it exists
+pub(crate) fn find_database(conn: &Connection, name: &str) -> Result<Option<(i64, u32)>> {
+    Ok(conn
+        .prepare_cached("SELECT id, version FROM databases WHERE name = ?1")?
+        .query_row([name], |row| Ok((row.get(0)?, row.get(1)?)))
+        .optional()?)
+}
+
+/// Create the database `name`, with version `0`, and return its id
+pub(crate) fn create_database(conn: &Connection, name: &str) -> Result<i64> {
+    conn.execute(
+        "INSERT INTO databases (name, version) VALUES (?1, 0)",
+        [name],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub(crate) fn set_version(conn: &Connection, db: i64, version: u32) -> Result<()> {
+    conn.execute(
+        "UPDATE databases SET version = ?2 WHERE id = ?1",
+        params![db, version],
+    )?;
+    Ok(())
+}
+
+/// Load the schema of the database with id `db`, or return `None` if it was deleted
+pub(crate) fn load_database(conn: &Connection, db: i64) -> Result<Option<DatabaseMeta>> {
+    let Some(version) = conn
+        .prepare_cached("SELECT version FROM databases WHERE id = ?1")?
+        .query_row([db], |row| row.get(0))
+        .optional()?
+    else {
+        return Ok(None);
+    };
+    let mut stores = BTreeMap::new();
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, name, key_path, auto_increment FROM object_stores WHERE database = ?1",
+    )?;
+    let rows = stmt.query_map([db], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, Option<Vec<u8>>>(2)?,
+            row.get::<_, bool>(3)?,
+        ))
+    })?;
+    for row in rows {
+        let (id, name, key_path, auto_increment) = row?;
+        let store = StoreMeta {
+            id,
+            key_path: key_path.as_deref().map(decode_key_path).transpose()?,
+            auto_increment,
+            indexes: load_indexes(conn, id)?,
+        };
+        stores.insert(name, store);
+    }
+    Ok(Some(DatabaseMeta { version, stores }))
+}
+
+fn load_indexes(conn: &Connection, store: i64) -> Result<BTreeMap<String, IndexMeta>> {
+    let mut indexes = BTreeMap::new();
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, name, key_path, is_unique, multi_entry FROM indexes WHERE store = ?1",
+    )?;
+    let rows = stmt.query_map([store], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, Vec<u8>>(2)?,
+            row.get::<_, bool>(3)?,
+            row.get::<_, bool>(4)?,
+        ))
+    })?;
+    for row in rows {
+        let (id, name, key_path, unique, multi_entry) = row?;
+        let index = IndexMeta {
+            id,
+            key_path: decode_key_path(&key_path)?,
+            unique,
+            multi_entry,
+        };
+        indexes.insert(name, index);
+    }
+    Ok(indexes)
+}
+
+/// Delete the database with id `db` and all its object stores
+pub(crate) fn delete_database(conn: &Connection, db: i64) -> Result<()> {
+    if let Some(meta) = load_database(conn, db)? {
+        for store in meta.stores.values() {
+            delete_store(conn, store)?;
+        }
+    }
+    conn.execute("DELETE FROM databases WHERE id = ?1", [db])?;
+    Ok(())
+}
+
+/// Create the object store `name` in the database with id `db`
+pub(crate) fn create_store(
+    conn: &Connection,
+    db: i64,
+    name: &str,
+    key_path: Option<KeyPath>,
+    auto_increment: bool,
+) -> Result<StoreMeta> {
+    conn.execute(
+        "INSERT INTO object_stores (database, name, key_path, auto_increment, current_number)
+            VALUES (?1, ?2, ?3, ?4, 1)",
+        params![
+            db,
+            name,
+            key_path.as_ref().map(codec::encode_key_path),
+            auto_increment
+        ],
+    )?;
+    let store = StoreMeta {
+        id: conn.last_insert_rowid(),
+        key_path,
+        auto_increment,
+        indexes: BTreeMap::new(),
+    };
+    conn.execute_batch(&format!(
+        "CREATE TABLE {} (key BLOB NOT NULL PRIMARY KEY, value BLOB NOT NULL) WITHOUT ROWID",
+        store.table()
+    ))?;
+    Ok(store)
+}
+
+/// Delete the object store `store`, with its records and indexes
+pub(crate) fn delete_store(conn: &Connection, store: &StoreMeta) -> Result<()> {
+    for index in store.indexes.values() {
+        conn.execute_batch(&format!("DROP TABLE {}", index.table()))?;
+    }
+    conn.execute_batch(&format!("DROP TABLE {}", store.table()))?;
+    conn.execute("DELETE FROM indexes WHERE store = ?1", [store.id])?;
+    conn.execute("DELETE FROM object_stores WHERE id = ?1", [store.id])?;
+    Ok(())
+}
+
+impl StoreMeta {
+    fn table(&self) -> String {
+        format!("records_{}", self.id)
+    }
+
+    /// The schema of this object store, named `name`
+    pub(crate) fn schema(&self, name: &str) -> StoreSchema {
+        StoreSchema {
+            name: name.to_string(),
+            key_path: self.key_path.clone(),
+            auto_increment: self.auto_increment,
+            indexes: self
+                .indexes
+                .iter()
+                .map(|(name, index)| IndexSchema {
+                    name: name.clone(),
+                    key_path: index.key_path.clone(),
+                    unique: index.unique,
+                    multi_entry: index.multi_entry,
+                })
+                .collect(),
+        }
+    }
+
+    pub(crate) fn index(&self, name: &str) -> Result<&IndexMeta> {
+        self.indexes
+            .get(name)
+            .ok_or(Failure::DoesNotExist("The index does not exist"))
+    }
+
+    pub(crate) fn create_index(
+        &mut self,
+        conn: &Connection,
+        name: &str,
+        key_path: KeyPath,
+        unique: bool,
+        multi_entry: bool,
+    ) -> Result<()> {
+        conn.execute(
+            "INSERT INTO indexes (store, name, key_path, is_unique, multi_entry)
+                VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                self.id,
+                name,
+                codec::encode_key_path(&key_path),
+                unique,
+                multi_entry
+            ],
+        )?;
+        let index = IndexMeta {
+            id: conn.last_insert_rowid(),
+            key_path,
+            unique,
+            multi_entry,
+        };
+        conn.execute_batch(&format!(
+            "CREATE TABLE {table} (
+                key BLOB NOT NULL,
+                primary_key BLOB NOT NULL,
+                PRIMARY KEY (key, primary_key)
+            ) WITHOUT ROWID;
+            CREATE INDEX {table}_primary_keys ON {table} (primary_key);",
+            table = index.table(),
+        ))?;
+        let mut stmt = conn.prepare(&format!("SELECT key, value FROM {}", self.table()))?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let primary_key: Vec<u8> = row.get(0)?;
+            let value = decode_value(&row.get::<_, Vec<u8>>(1)?)?;
+            for key in index.keys_of(&value) {
+                if index.conflicts(conn, &key, &primary_key)? {
+                    return Err(Failure::AlreadyExists(
+                        "Existing records violate the uniqueness of the index",
+                    ));
+                }
+                index.insert(conn, &key, &primary_key)?;
+            }
+        }
+        self.indexes.insert(name.to_string(), index);
+        Ok(())
+    }
+
+    pub(crate) fn delete_index(&mut self, conn: &Connection, name: &str) -> Result<()> {
+        let index = self.index(name)?;
+        conn.execute_batch(&format!("DROP TABLE {}", index.table()))?;
+        conn.execute("DELETE FROM indexes WHERE id = ?1", [index.id])?;
+        self.indexes.remove(name);
+        Ok(())
+    }
+
+    fn contains(&self, conn: &Connection, key: &Key) -> Result<bool> {
+        let sql = format!(
+            "SELECT EXISTS (SELECT 1 FROM {} WHERE key = ?1)",
+            self.table()
+        );
+        Ok(conn
+            .prepare_cached(&sql)?
+            .query_row([codec::encode_key(key)], |row| row.get(0))?)
+    }
+
+    /// The keys in `range`, in order, with a maximum number of results of `limit`
+    pub(crate) fn keys(
+        &self,
+        conn: &Connection,
+        range: &Range,
+        limit: Option<u32>,
+    ) -> Result<Vec<Key>> {
+        let filter = Filter::range("key", range);
+        let sql = format!(
+            "SELECT key FROM {} {} ORDER BY key {}",
+            self.table(),
+            filter.where_clause(),
+            limit_clause(limit),
+        );
+        query_blobs(conn, &sql, &filter.keys)?
+            .iter()
+            .map(|k| decode_key(k))
+            .collect()
+    }
+
+    /// The values with a key in `range`, ordered by key, with a maximum number of results of `limit`
+    pub(crate) fn values(
+        &self,
+        conn: &Connection,
+        range: &Range,
+        limit: Option<u32>,
+    ) -> Result<Vec<Value>> {
+        let filter = Filter::range("key", range);
+        let sql = format!(
+            "SELECT value FROM {} {} ORDER BY key {}",
+            self.table(),
+            filter.where_clause(),
+            limit_clause(limit),
+        );
+        query_blobs(conn, &sql, &filter.keys)?
+            .iter()
+            .map(|v| decode_value(v))
+            .collect()
+    }
+
+    pub(crate) fn count(&self, conn: &Connection, range: &Range) -> Result<usize> {
+        let filter = Filter::range("key", range);
+        let sql = format!(
+            "SELECT COUNT(*) FROM {} {}",
+            self.table(),
+            filter.where_clause()
+        );
+        query_count(conn, &sql, &filter.keys)
+    }
+
+    fn current_number(&self, conn: &Connection) -> Result<f64> {
+        Ok(conn
+            .prepare_cached("SELECT current_number FROM object_stores WHERE id = ?1")?
+            .query_row([self.id], |row| row.get(0))?)
+    }
+
+    /// Store `value`, with key `key` for out-of-line keys, and return its key
+    ///
+    /// If `overwrite` is `false`, this fails if a record already exists with the same key.
+    pub(crate) fn put(
+        &self,
+        conn: &Connection,
+        value: &Value,
+        key: Option<&Key>,
+        overwrite: bool,
+    ) -> Result<Key> {
+        let mut value = Cow::Borrowed(value);
+        let generator = match self.auto_increment {
+            true => Some(self.current_number(conn)?),
+            false => None,
+        };
+        let key = engine::primary_key(self.key_path.as_ref(), generator, &mut value, key)?;
+        if let Some(current_number) = generator {
+            let next = engine::bump_generator(current_number, &key);
+            if next != current_number {
+                conn.execute(
+                    "UPDATE object_stores SET current_number = ?2 WHERE id = ?1",
+                    params![self.id, next],
+                )?;
+            }
+        }
+        let primary_key = codec::encode_key(&key);
+        if !overwrite && self.contains(conn, &key)? {
+            return Err(Failure::AlreadyExists(
+                "A record with this key already exists",
+            ));
+        }
+
+        let mut index_keys = Vec::with_capacity(self.indexes.len());
+        for index in self.indexes.values() {
+            let keys = index.keys_of(&value);
+            for k in &keys {
+                if index.conflicts(conn, k, &primary_key)? {
+                    return Err(Failure::AlreadyExists(
+                        "The record violates the uniqueness of an index",
+                    ));
+                }
+            }
+            index_keys.push(keys);
+        }
+        for (index, keys) in self.indexes.values().zip(index_keys) {
+            index.remove_record(conn, &primary_key)?;
+            for k in &keys {
+                index.insert(conn, k, &primary_key)?;
+            }
+        }
+        let sql = format!(
+            "INSERT OR REPLACE INTO {} (key, value) VALUES (?1, ?2)",
+            self.table()
+        );
+        conn.prepare_cached(&sql)?
+            .execute(params![primary_key, codec::encode_value(&value)])?;
+        Ok(key)
+    }
+
+    /// Delete the records with a key in `range`
+    pub(crate) fn delete(&self, conn: &Connection, range: &Range) -> Result<()> {
+        let filter = Filter::range("key", range);
+        for index in self.indexes.values() {
+            let sql = format!(
+                "DELETE FROM {} WHERE primary_key IN (SELECT key FROM {} {})",
+                index.table(),
+                self.table(),
+                filter.where_clause(),
+            );
+            conn.prepare_cached(&sql)?
+                .execute(params_from_iter(&filter.keys))?;
+        }
+        let sql = format!("DELETE FROM {} {}", self.table(), filter.where_clause());
+        conn.prepare_cached(&sql)?
+            .execute(params_from_iter(&filter.keys))?;
+        Ok(())
+    }
+
+    pub(crate) fn clear(&self, conn: &Connection) -> Result<()> {
+        self.delete(conn, &Range::all())
+    }
+
+    /// Move a cursor over this object store, and return the primary key it lands on
+    pub(crate) fn step(
+        &self,
+        conn: &Connection,
+        range: &Range,
+        direction: CursorDirection,
+        target: Target,
+    ) -> Result<Option<Key>> {
+        let forward = engine::is_forward(direction);
+        let (after, at) = if forward { (">", ">=") } else { ("<", "<=") };
+        let mut filter = Filter::range("key", range);
+        match target {
+            Target::Start => (),
+            Target::After(key, _) => filter.push(format!("key {after} ?"), &[key]),
+            Target::Key(key) | Target::Entry(key, _) => filter.push(format!("key {at} ?"), &[key]),
+        }
+        let sql = format!(
+            "SELECT key FROM {} {} ORDER BY key {} LIMIT 1",
+            self.table(),
+            filter.where_clause(),
+            order(forward),
+        );
+        query_blobs(conn, &sql, &filter.keys)?
+            .first()
+            .map(|k| decode_key(k))
+            .transpose()
+    }
+}
+
+impl IndexMeta {
+    fn table(&self) -> String {
+        format!("index_{}", self.id)
+    }
+
+    /// The encoded keys of this index that reference the record `value`
+    fn keys_of(&self, value: &Value) -> Vec<Vec<u8>> {
+        engine::index_keys(&self.key_path, self.multi_entry, value)
+            .iter()
+            .map(codec::encode_key)
+            .collect()
+    }
+
+    /// Whether indexing `key` for the record with key `primary_key` would break uniqueness
+    fn conflicts(&self, conn: &Connection, key: &[u8], primary_key: &[u8]) -> Result<bool> {
+        if !self.unique {
+            return Ok(false);
+        }
+        let sql = format!(
+            "SELECT EXISTS (SELECT 1 FROM {} WHERE key = ?1 AND primary_key != ?2)",
+            self.table()
+        );
+        Ok(conn
+            .prepare_cached(&sql)?
+            .query_row(params![key, primary_key], |row| row.get(0))?)
+    }
+
+    fn insert(&self, conn: &Connection, key: &[u8], primary_key: &[u8]) -> Result<()> {
+        let sql = format!(
+            "INSERT INTO {} (key, primary_key) VALUES (?1, ?2)",
+            self.table()
+        );
+        conn.prepare_cached(&sql)?
+            .execute(params![key, primary_key])?;
+        Ok(())
+    }
+
+    fn remove_record(&self, conn: &Connection, primary_key: &[u8]) -> Result<()> {
+        let sql = format!("DELETE FROM {} WHERE primary_key = ?1", self.table());
+        conn.prepare_cached(&sql)?.execute([primary_key])?;
+        Ok(())
+    }
+
+    /// The `(key, primary key)` pairs with a key in `range`, in order, with a maximum number of results
+    /// of `limit`
+    pub(crate) fn entries(
+        &self,
+        conn: &Connection,
+        range: &Range,
+        limit: Option<u32>,
+    ) -> Result<Vec<(Key, Key)>> {
+        let filter = Filter::range("key", range);
+        let sql = format!(
+            "SELECT key, primary_key FROM {} {} ORDER BY key, primary_key {}",
+            self.table(),
+            filter.where_clause(),
+            limit_clause(limit),
+        );
+        query_blob_pairs(conn, &sql, &filter.keys)?
+            .iter()
+            .map(|(k, pk)| Ok((decode_key(k)?, decode_key(pk)?)))
+            .collect()
+    }
+
+    /// The values of the records of `store` referenced by the entries with a key in `range`, in the
+    /// order of this index, with a maximum number of results of `limit`
+    pub(crate) fn values(
+        &self,
+        conn: &Connection,
+        store: &StoreMeta,
+        range: &Range,
+        limit: Option<u32>,
+    ) -> Result<Vec<Value>> {
+        let filter = Filter::range("i.key", range);
+        let sql = format!(
+            "SELECT r.value FROM {} AS i JOIN {} AS r ON r.key = i.primary_key
+                {} ORDER BY i.key, i.primary_key {}",
+            self.table(),
+            store.table(),
+            filter.where_clause(),
+            limit_clause(limit),
+        );
+        query_blobs(conn, &sql, &filter.keys)?
+            .iter()
+            .map(|v| decode_value(v))
+            .collect()
+    }
+
+    pub(crate) fn count(&self, conn: &Connection, range: &Range) -> Result<usize> {
+        let filter = Filter::range("key", range);
+        let sql = format!(
+            "SELECT COUNT(*) FROM {} {}",
+            self.table(),
+            filter.where_clause()
+        );
+        query_count(conn, &sql, &filter.keys)
+    }
+
+    /// Move a cursor over this index, and return the `(key, primary key)` pair it lands on
+    pub(crate) fn step(
+        &self,
+        conn: &Connection,
+        range: &Range,
+        direction: CursorDirection,
+        target: Target,
+    ) -> Result<Option<(Key, Key)>> {
+        let forward = engine::is_forward(direction);
+        let (after, at) = if forward { (">", ">=") } else { ("<", "<=") };
+        let mut filter = Filter::range("key", range);
+        match (direction, target) {
+            (_, Target::Start) => (),
+            (CursorDirection::Next | CursorDirection::Prev, Target::After(k, pk)) => {
+                filter.push(format!("(key, primary_key) {after} (?, ?)"), &[k, pk])
+            }
+            (CursorDirection::NextUnique | CursorDirection::PrevUnique, Target::After(k, _)) => {
+                filter.push(format!("key {after} ?"), &[k])
+            }
+            (_, Target::Key(k)) => filter.push(format!("key {at} ?"), &[k]),
+            (_, Target::Entry(k, pk)) => {
+                filter.push(format!("(key, primary_key) {at} (?, ?)"), &[k, pk])
+            }
+        }
+        let sql = format!(
+            "SELECT key, primary_key FROM {} {} ORDER BY key {order}, primary_key {order} LIMIT 1",
+            self.table(),
+            filter.where_clause(),
+            order = order(forward),
+        );
+        let Some((key, mut primary_key)) = query_blob_pairs(conn, &sql, &filter.keys)?
+            .into_iter()
+            .next()
+        else {
+            return Ok(None);
+        };
+        // Unique cursors land on the first record of each key, even when going backwards
+        if direction == CursorDirection::PrevUnique {
+            let sql = format!(
+                "SELECT primary_key FROM {} WHERE key = ?1 ORDER BY primary_key LIMIT 1",
+                self.table()
+            );
+            primary_key = conn
+                .prepare_cached(&sql)?
+                .query_row([&key], |row| row.get(0))?;
+        }
+        Ok(Some((decode_key(&key)?, decode_key(&primary_key)?)))
+    }
+}
@@ -0,0 +1,110 @@
+//! SQLite backend, to persist databases natively, eg. in a desktop build
+//!
+//! This is used through the types of the crate root like [`crate::memory`] is, with [`Sqlite`] as their
+//! backend, the same [`Value`]s, and the same IndexedDB semantics: key ordering, key generators, unique and
+//! multi-entry indexes, cursors, rollback of the transactions that return `Err` and `versionchange` upgrades.
+//! All the databases of a [`Factory`] are stored in a single SQLite file, and each transaction is an
+//! SQLite transaction. As they share one SQLite connection, the transactions of a factory never run
+//! concurrently, even when they are read-only or on different databases.
+//!
+//! This module is not available on `wasm32` targets, where the `sqlite` feature only enables [`crate::memory`].
+//!
+//! ```rust
+//! # use indexed_db::{sqlite::{Factory, Value}, Key};
+//! # use std::convert::Infallible;
+//! # futures::executor::block_on(async {
+//! let factory = Factory::in_memory()?;
+//! let db = factory
+//!     .open::<Infallible>("db", 1, async |evt| {
+//!         let store = evt.build_object_store("users").key_path("id").create()?;
+//!         store.build_index("name", "name").create()?;
+//!         Ok(())
+//!     })
+//!     .await?;
+//! let user = db
+//!     .transaction(&["users"])
+//!     .rw()
+//!     .run::<_, Infallible>(async |t| {
+//!         let users = t.object_store("users")?;
+//!         users.add(&Value::object([("id", Value::from(1)), ("name", Value::from("Jane"))])).await?;
+//!         users.index("name")?.get(&Key::from("Jane")).await
+//!     })
+//!     .await?;
+//! assert_eq!(user.unwrap().get("id"), Some(&Value::from(1)));
+//! # Ok::<_, indexed_db::Error<Infallible>>(())
+//! # }).unwrap();
+//! ```
+
+use crate::native::{self, OpContext};
+use rusqlite::Connection;
+use std::{convert::Infallible, path::Path};
+
+mod codec;
+mod engine;
+mod storage;
+
+pub use crate::memory::Value;
+
+/// The SQLite backend, to use as the `B` parameter of the types of the crate root
+#[derive(Clone, Copy, Debug)]
+pub struct Sqlite;
+
+/// Factory of the SQLite backend
+///
+/// Clones of a factory share the same SQLite connection.
+///
+/// Upgrading a database closes the other connections to it, instead of firing `versionchange` events on
+/// them and waiting for them to close like browsers do. Their transactions then fail with
+/// [`Error::DatabaseIsClosed`](crate::Error::DatabaseIsClosed), as after [`Factory::delete_database`].
+pub type Factory = crate::Factory<Sqlite>;
+
+/// Database connection of the SQLite backend
+pub type Database = crate::Database<Sqlite>;
+
+/// Owned database connection of the SQLite backend
+pub type OwnedDatabase = crate::OwnedDatabase<Sqlite>;
+
+/// Version change event of the SQLite backend
+pub type VersionChangeEvent<Err> = crate::VersionChangeEvent<Err, Sqlite>;
+
+/// Transaction of the SQLite backend
+pub type Transaction<Err> = crate::Transaction<Err, Sqlite>;
+
+/// Object store of the SQLite backend
+pub type ObjectStore<Err> = crate::ObjectStore<Err, Sqlite>;
+
+/// Index of the SQLite backend
+pub type Index<Err> = crate::Index<Err, Sqlite>;
+
+/// Cursor of the SQLite backend
+pub type Cursor<Err> = crate::Cursor<Err, Sqlite>;
+
+/// Key-only cursor of the SQLite backend
+pub type KeyCursor<Err> = crate::KeyCursor<Err, Sqlite>;
+
+impl crate::Factory<Sqlite> {
+    fn from_connection(
+        conn: rusqlite::Result<Connection>,
+        ctx: OpContext,
+    ) -> crate::Result<Factory, Infallible> {
+        storage::open(conn)
+            .map(|shared| Factory::from_backend(native::Factory::new(shared)))
+            .map_err(|e| ctx.fail(e))
+    }
+
+    /// Open the SQLite file at `path`, creating it if it does not exist yet
+    ///
+    /// Factories opened on the same file see the same databases, with SQLite making their transactions
+    /// wait for each other.
+    pub fn from_file(path: impl AsRef<Path>) -> crate::Result<Factory, Infallible> {
+        Factory::from_connection(Connection::open(path), OpContext::new("Factory::from_file"))
+    }
+
+    /// Create a new factory backed by an SQLite database in memory, without any database
+    pub fn in_memory() -> crate::Result<Factory, Infallible> {
+        Factory::from_connection(
+            Connection::open_in_memory(),
+            OpContext::new("Factory::in_memory"),
+        )
+    }
+}
@@ -0,0 +1,315 @@
+//! The [`Storage`] of the SQLite backend
+
+use super::{
+    engine::{self, StoreMeta},
+    Sqlite, Value,
+};
+use crate::{
+    native::{
+        engine::{Failure, Range, Result, Target},
+        Mode, Storage, StorageTx,
+    },
+    CursorDirection, DatabaseInfo, Key, KeyPath, StoreSchema,
+};
+use futures_util::lock::Mutex;
+use rusqlite::{Connection, TransactionBehavior};
+use std::{collections::BTreeMap, rc::Rc};
+
+/// The SQLite connection of a factory, shared by all its database connections
+#[derive(Debug)]
+pub struct SqliteShared {
+    conn: Connection,
+    /// Held by the running transaction, as they all share the same SQLite connection
+    ///
+    /// This serializes all the transactions of the factory, on any database and read-only ones included.
+    lock: Rc<Mutex<()>>,
+}
+
+/// Set up the SQLite file of a new factory
+pub(super) fn open(conn: rusqlite::Result<Connection>) -> Result<Rc<SqliteShared>> {
+    let conn = conn?;
+    engine::init(&conn)?;
+    Ok(Rc::new(SqliteShared {
+        conn,
+        lock: Rc::new(Mutex::new(())),
+    }))
+}
+
+/// A transaction of the SQLite backend, which is an SQLite transaction on the shared connection
+///
+/// It is rolled back when dropped without being committed.
+#[derive(Debug)]
+pub struct SqliteTx {
+    shared: Rc<SqliteShared>,
+    /// The id of the database in the SQLite file
+    db: i64,
+    version: u32,
+    stores: BTreeMap<String, StoreMeta>,
+    finished: bool,
+}
+
+impl SqliteTx {
+    /// Start an SQLite transaction, before loading the database
+    fn begin(shared: &Rc<SqliteShared>, behavior: TransactionBehavior) -> Result<SqliteTx> {
+        shared.conn.execute_batch(match behavior {
+            TransactionBehavior::Deferred => "BEGIN DEFERRED",
+            _ => "BEGIN IMMEDIATE",
+        })?;
+        Ok(SqliteTx {
+            shared: shared.clone(),
+            db: 0,
+            version: 0,
+            stores: BTreeMap::new(),
+            finished: false,
+        })
+    }
+
+    /// Load the database with id `db`, and return whether it still exists
+    fn load(&mut self, db: i64) -> Result<bool> {
+        let Some(meta) = engine::load_database(self.conn(), db)? else {
+            return Ok(false);
+        };
+        self.db = db;
+        self.version = meta.version;
+        self.stores = meta.stores;
+        Ok(true)
+    }
+
+    fn conn(&self) -> &Connection {
+        &self.shared.conn
+    }
+
+    fn meta(&self, store: &str) -> Result<&StoreMeta> {
+        self.stores.get(store).ok_or(Failure::ObjectStoreWasRemoved)
+    }
+
+    /// Run `f` atomically on the connection and the object store `store`
+    fn write<T>(
+        &mut self,
+        store: &str,
+        f: impl FnOnce(&Connection, &mut StoreMeta) -> Result<T>,
+    ) -> Result<T> {
+        let meta = self
+            .stores
+            .get_mut(store)
+            .ok_or(Failure::ObjectStoreWasRemoved)?;
+        let conn = &self.shared.conn;
+        engine::atomically(conn, || f(conn, meta))
+    }
+}
+
+impl Drop for SqliteTx {
+    fn drop(&mut self) {
+        self.rollback();
+    }
+}
+
+impl Storage for Sqlite {
+    type Shared = Rc<SqliteShared>;
+    type Db = i64;
+    type Tx = SqliteTx;
+
+    fn lock(shared: &Rc<SqliteShared>, _name: &str) -> Rc<Mutex<()>> {
+        shared.lock.clone()
+    }
+
+    fn databases(shared: &Rc<SqliteShared>) -> Result<Vec<DatabaseInfo>> {
+        engine::databases(&shared.conn)
+    }
+
+    fn find(shared: &Rc<SqliteShared>, name: &str) -> Result<Option<(i64, u32)>> {
+        engine::find_database(&shared.conn, name)
+    }
+
+    fn delete_database(shared: &Rc<SqliteShared>, name: &str) -> Result<()> {
+        let mut tx = SqliteTx::begin(shared, TransactionBehavior::Immediate)?;
+        if let Some((db, _)) = engine::find_database(tx.conn(), name)? {
+            engine::delete_database(tx.conn(), db)?;
+        }
+        tx.commit()
+    }
+
+    fn begin_upgrade(shared: &Rc<SqliteShared>, name: &str) -> Result<(i64, SqliteTx)> {
+        let mut tx = SqliteTx::begin(shared, TransactionBehavior::Immediate)?;
+        let db = match engine::find_database(tx.conn(), name)? {
+            Some((db, _)) => db,
+            None => engine::create_database(tx.conn(), name)?,
+        };
+        if !tx.load(db)? {
+            return Err(Failure::Unknown(String::from(
+                "The database disappeared within its transaction",
+            )));
+        }
+        Ok((db, tx))
+    }
+
+    fn begin(shared: &Rc<SqliteShared>, db: &i64, mode: Mode) -> Result<Option<SqliteTx>> {
+        let behavior = match mode {
+            Mode::ReadOnly => TransactionBehavior::Deferred,
+            Mode::ReadWrite | Mode::VersionChange => TransactionBehavior::Immediate,
+        };
+        let mut tx = SqliteTx::begin(shared, behavior)?;
+        Ok(tx.load(*db)?.then_some(tx))
+    }
+
+    fn store_names(shared: &Rc<SqliteShared>, db: &i64) -> Vec<String> {
+        engine::load_database(&shared.conn, *db)
+            .ok()
+            .flatten()
+            .map(|meta| meta.stores.into_keys().collect())
+            .unwrap_or_default()
+    }
+}
+
+impl StorageTx for SqliteTx {
+    fn version(&self) -> u32 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u32) -> Result<()> {
+        engine::set_version(self.conn(), self.db, version)?;
+        self.version = version;
+        Ok(())
+    }
+
+    fn store_names(&self) -> Vec<String> {
+        self.stores.keys().cloned().collect()
+    }
+
+    fn store(&self, store: &str) -> Result<StoreSchema> {
+        Ok(self.meta(store)?.schema(store))
+    }
+
+    fn create_store(
+        &mut self,
+        store: &str,
+        key_path: Option<KeyPath>,
+        auto_increment: bool,
+    ) -> Result<()> {
+        let conn = &self.shared.conn;
+        let meta = engine::atomically(conn, || {
+            engine::create_store(conn, self.db, store, key_path, auto_increment)
+        })?;
+        self.stores.insert(store.to_string(), meta);
+        Ok(())
+    }
+
+    fn delete_store(&mut self, store: &str) -> Result<()> {
+        let meta = self.meta(store)?;
+        engine::atomically(self.conn(), || engine::delete_store(self.conn(), meta))?;
+        self.stores.remove(store);
+        Ok(())
+    }
+
+    fn create_index(
+        &mut self,
+        store: &str,
+        index: &str,
+        key_path: KeyPath,
+        unique: bool,
+        multi_entry: bool,
+    ) -> Result<()> {
+        self.write(store, |conn, meta| {
+            meta.create_index(conn, index, key_path, unique, multi_entry)
+        })
+    }
+
+    fn delete_index(&mut self, store: &str, index: &str) -> Result<()> {
+        self.write(store, |conn, meta| meta.delete_index(conn, index))
+    }
+
+    fn count(&self, store: &str, range: &Range) -> Result<usize> {
+        self.meta(store)?.count(self.conn(), range)
+    }
+
+    fn keys(&self, store: &str, range: &Range, limit: Option<u32>) -> Result<Vec<Key>> {
+        self.meta(store)?.keys(self.conn(), range, limit)
+    }
+
+    fn values(&self, store: &str, range: &Range, limit: Option<u32>) -> Result<Vec<Value>> {
+        self.meta(store)?.values(self.conn(), range, limit)
+    }
+
+    fn step(
+        &self,
+        store: &str,
+        range: &Range,
+        direction: CursorDirection,
+        target: Target,
+    ) -> Result<Option<Key>> {
+        self.meta(store)?
+            .step(self.conn(), range, direction, target)
+    }
+
+    fn put(
+        &mut self,
+        store: &str,
+        value: &Value,
+        key: Option<&Key>,
+        overwrite: bool,
+    ) -> Result<Key> {
+        self.write(store, |conn, meta| meta.put(conn, value, key, overwrite))
+    }
+
+    fn delete(&mut self, store: &str, range: &Range) -> Result<()> {
+        self.write(store, |conn, meta| meta.delete(conn, range))
+    }
+
+    fn clear(&mut self, store: &str) -> Result<()> {
+        self.write(store, |conn, meta| meta.clear(conn))
+    }
+
+    fn index_count(&self, store: &str, index: &str, range: &Range) -> Result<usize> {
+        self.meta(store)?.index(index)?.count(self.conn(), range)
+    }
+
+    fn index_entries(
+        &self,
+        store: &str,
+        index: &str,
+        range: &Range,
+        limit: Option<u32>,
+    ) -> Result<Vec<(Key, Key)>> {
+        self.meta(store)?
+            .index(index)?
+            .entries(self.conn(), range, limit)
+    }
+
+    fn index_values(
+        &self,
+        store: &str,
+        index: &str,
+        range: &Range,
+        limit: Option<u32>,
+    ) -> Result<Vec<Value>> {
+        let meta = self.meta(store)?;
+        meta.index(index)?.values(self.conn(), meta, range, limit)
+    }
+
+    fn index_step(
+        &self,
+        store: &str,
+        index: &str,
+        range: &Range,
+        direction: CursorDirection,
+        target: Target,
+    ) -> Result<Option<(Key, Key)>> {
+        self.meta(store)?
+            .index(index)?
+            .step(self.conn(), range, direction, target)
+    }
+
+    fn commit(&mut self) -> Result<()> {
+        self.conn().execute_batch("COMMIT")?;
+        self.finished = true;
+        Ok(())
+    }
+
+    fn rollback(&mut self) {
+        if !self.finished {
+            self.finished = true;
+            // Failing to roll back means that SQLite already did
+            let _ = self.conn().execute_batch("ROLLBACK");
+        }
+    }
+}
@@ -1,191 +1,209 @@
+#[cfg(feature = "serde")]
+use crate::TypedObjectStore;
 use crate::{
-    utils::{err_from_event, str_slice_to_array},
+    backend::{Backend, BackendTransaction, BackendTransactionBuilder, WebSys},
     ObjectStore,
 };
 use std::{
-    cell::RefCell,
     future::Future,
-    marker::PhantomData,
-    pin::Pin,
-    rc::Rc,
-    task::{Context, Poll},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll, Wake, Waker},
 };
-use web_sys::{
-    wasm_bindgen::{JsCast, JsValue},
-    IdbDatabase, IdbRequest, IdbTransaction, IdbTransactionMode,
-};
-
-mod runner;
-pub(crate) mod unsafe_jar;
 
-pub use runner::{RunnableTransaction, TransactionResult};
+#[cfg(doc)]
+use crate::set_panic_on_inactive_transaction;
 
-/// Wrapper for [`IDBTransaction`](https://developer.mozilla.org/en-US/docs/Web/API/IDBTransaction)
-#[derive(Debug)]
-pub struct Transaction<Err> {
-    sys: IdbTransaction,
-    _phantom: PhantomData<Err>,
+/// A transaction over some object stores of a database
+pub struct Transaction<Err, B: Backend = WebSys> {
+    inner: B::Transaction<Err>,
 }
 
-impl<Err> Transaction<Err> {
-    pub(crate) fn from_sys(sys: IdbTransaction) -> Transaction<Err> {
+impl<Err, B: Backend> Clone for Transaction<Err, B> {
+    fn clone(&self) -> Self {
         Transaction {
-            sys,
-            _phantom: PhantomData,
+            inner: self.inner.clone(),
         }
     }
+}
 
-    pub(crate) fn as_sys(&self) -> &IdbTransaction {
-        &self.sys
+impl<Err, B: Backend> std::fmt::Debug for Transaction<Err, B>
+where
+    B::Transaction<Err>: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Transaction")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl<Err, B: Backend> Transaction<Err, B> {
+    pub(crate) fn from_backend(inner: B::Transaction<Err>) -> Transaction<Err, B> {
+        Transaction { inner }
     }
 
     /// Returns an [`ObjectStore`] that can be used to operate on data in this transaction
+    pub fn object_store(&self, name: &str) -> crate::Result<ObjectStore<Err, B>, Err> {
+        self.inner.object_store(name).map(ObjectStore::from_backend)
+    }
+
+    /// Commit this transaction without waiting for it to auto-commit
+    ///
+    /// All the requests already made are still run, but any request made after this call fails with
+    /// [`Error::TransactionFinished`](crate::Error::TransactionFinished). Browsers that do not support
+    /// explicit commits keep accepting requests, and auto-commit the transaction as usual.
+    pub fn commit(&self) -> crate::Result<(), Err> {
+        self.inner.commit()
+    }
+
+    /// Abort this transaction, rolling back all its changes
     ///
-    /// Internally, this uses [`IDBTransaction::objectStore`](https://developer.mozilla.org/en-US/docs/Web/API/IDBTransaction/objectStore).
-    pub fn object_store(&self, name: &str) -> crate::Result<ObjectStore<Err>, Err> {
-        Ok(ObjectStore::from_sys(self.sys.object_store(name).map_err(
-            |err| match error_name!(&err) {
-                Some("NotFoundError") => crate::Error::DoesNotExist,
-                _ => crate::Error::from_js_value(err),
-            },
-        )?))
+    /// Any request made after this call fails with [`Error::TransactionFinished`](crate::Error::TransactionFinished),
+    /// as do the requests that were still in flight.
+    pub fn abort(&self) -> crate::Result<(), Err> {
+        self.inner.abort()
     }
+
+    /// Run `fut`, a future that completes without needing the event loop, and return its output
+    ///
+    /// Transactions cannot await anything other than their requests, as they would auto-commit meanwhile.
+    /// This makes it possible to still reuse async code that does not actually wait on anything, like
+    /// an async hashing function or a channel that is already filled. If `fut` does need the event loop,
+    /// this returns [`Error::LocalFutureNotReady`](crate::Error::LocalFutureNotReady) and `fut` is dropped.
+    ///
+    /// `fut` is polled again when it wakes itself synchronously, eg. with a yield, but at most
+    /// 16 times, so that a future that keeps yielding also returns this error.
+    pub fn run_local<F: Future>(&self, fut: F) -> crate::Result<F::Output, Err> {
+        let mut fut = std::pin::pin!(fut);
+        let woken = Arc::new(WokenFlag(AtomicBool::new(true)));
+        let waker = Waker::from(woken.clone());
+        for _ in 0..MAX_LOCAL_POLLS {
+            if !woken.0.swap(false, Ordering::Relaxed) {
+                break;
+            }
+            if let Poll::Ready(res) = fut.as_mut().poll(&mut Context::from_waker(&waker)) {
+                return Ok(res);
+            }
+        }
+        Err(crate::Error::LocalFutureNotReady)
+    }
+
+    /// Returns a [`TypedObjectStore`] with keys of type `K` and values of type `V`
+    ///
+    /// This is only available with the `serde` feature. See [`Transaction::object_store`].
+    #[cfg(feature = "serde")]
+    pub fn typed_store<K, V>(
+        &self,
+        name: &str,
+    ) -> crate::Result<TypedObjectStore<K, V, Err, B>, Err>
+    where
+        K: serde::Serialize + serde::de::DeserializeOwned,
+        V: serde::Serialize + serde::de::DeserializeOwned,
+    {
+        Ok(TypedObjectStore::from_store(self.object_store(name)?))
+    }
+}
+
+/// Maximum number of times [`Transaction::run_local`] polls a future that keeps waking itself
+const MAX_LOCAL_POLLS: usize = 16;
+
+struct WokenFlag(AtomicBool);
+
+impl Wake for WokenFlag {
+    fn wake(self: Arc<Self>) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Durability hint for a transaction, see [`TransactionBuilder::durability`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Durability {
+    /// Let the backend decide, which is usually the same as `Strict`
+    #[default]
+    Default,
+
+    /// Only report the transaction as complete once its changes are flushed to persistent storage
+    Strict,
+
+    /// Report the transaction as complete as soon as its changes are written to the operating system,
+    /// which is faster but may lose the latest transactions upon a crash
+    Relaxed,
 }
 
 /// Helper to build a transaction
-pub struct TransactionBuilder {
-    db: IdbDatabase,
-    stores: JsValue,
-    mode: IdbTransactionMode,
-    // TODO: add support for transaction durability when web-sys gets it
+pub struct TransactionBuilder<B: Backend = WebSys> {
+    inner: B::TransactionBuilder,
 }
 
-impl TransactionBuilder {
-    pub(crate) fn from_names(db: IdbDatabase, names: &[&str]) -> TransactionBuilder {
-        TransactionBuilder {
-            db,
-            stores: str_slice_to_array(names).into(),
-            mode: IdbTransactionMode::Readonly,
-        }
+impl<B: Backend> TransactionBuilder<B> {
+    pub(crate) fn from_backend(inner: B::TransactionBuilder) -> TransactionBuilder<B> {
+        TransactionBuilder { inner }
     }
 
     /// Allow writes in this transaction
     ///
     /// Without this, the transaction will only be allowed reads, and will error upon trying to
     /// write objects.
-    pub fn rw(mut self) -> Self {
-        self.mode = IdbTransactionMode::Readwrite;
-        self
+    pub fn rw(self) -> Self {
+        TransactionBuilder {
+            inner: self.inner.rw(),
+        }
+    }
+
+    /// Set the durability hint of this transaction
+    ///
+    /// Browsers that do not support durability hints ignore it, and use their default durability.
+    pub fn durability(self, durability: Durability) -> Self {
+        TransactionBuilder {
+            inner: self.inner.durability(durability),
+        }
     }
 
     /// Actually execute the transaction
     ///
-    /// The `transaction` argument defines what will be run in the transaction. Note that due to
-    /// limitations of the IndexedDb API, the future returned by `transaction` cannot call `.await`
-    /// on any future except the ones provided by the [`Transaction`] itself. This function will
-    /// do its best to detect these cases to abort the transaction and panic, but you should avoid
-    /// doing so anyway. Note also that these errors are not recoverable: even if wasm32 were not
-    /// having `panic=abort`, once there is such a panic no `indexed-db` functions will work any
-    /// longer.
+    /// The `transaction` argument defines what will be run in the transaction. Note that the future
+    /// returned by `transaction` cannot call `.await` on any future except the ones provided by the
+    /// [`Transaction`] itself. This function will do its best to detect these cases to abort the
+    /// transaction and return [`Error::TransactionInactive`](crate::Error::TransactionInactive), but you
+    /// should avoid doing so anyway. [`set_panic_on_inactive_transaction`] can be used to panic instead,
+    /// which makes finding the culprit easier.
     ///
     /// If `transaction` returns an `Ok` value, then the transaction will be committed. If it
-    /// returns an `Err` value, then it will be aborted.
+    /// returns an `Err` value, then it will be aborted. [`Transaction::commit`] and [`Transaction::abort`]
+    /// can also be used to finish the transaction earlier.
+    ///
+    /// This only returns once the transaction actually completed, so that the changes are durable. If
+    /// committing fails, this returns [`Error::QuotaExceeded`](crate::Error::QuotaExceeded) if the storage
+    /// quota was exceeded, and [`Error::TransactionAborted`](crate::Error::TransactionAborted) for any other
+    /// reason. If `transaction` aborted it with [`Transaction::abort`], then its result is returned as-is.
+    /// Use [`TransactionBuilder::run_no_wait`] to return as soon as `transaction` returns instead.
     ///
     /// Note that you should avoid sending requests that you do not await. If you do, it is hard
-    /// to say whether the transaction will commit or abort, due to both the IndexedDB and the
-    /// `wasm-bindgen` semantics.
+    /// to say whether the transaction will commit or abort.
     ///
     /// Note that transactions cannot be nested.
-    ///
-    /// Internally, this uses [`IDBDatabase::transaction`](https://developer.mozilla.org/en-US/docs/Web/API/IDBDatabase/transaction).
-    // For more details of what will happen if one does not await:
-    // - If the `Closure` from `transaction_request` is not dropped yet, then the error will be
-    //   explicitly ignored, and thus transaction will commit.
-    // - If the `Closure` from `transaction_request` has already been dropped, then the callback
-    //   will panic. Most likely this will lead to the transaction aborting, but this is an
-    //   untested and unsupported code path.
     pub async fn run<Ret, Err>(
         self,
-        transaction: impl AsyncFnOnce(Transaction<Err>) -> crate::Result<Ret, Err>,
+        transaction: impl AsyncFnOnce(Transaction<Err, B>) -> crate::Result<Ret, Err>,
     ) -> crate::Result<Ret, Err> {
-        let t = self
-            .db
-            .transaction_with_str_sequence_and_mode(&self.stores, self.mode)
-            .map_err(|err| match error_name!(&err) {
-                Some("InvalidStateError") => crate::Error::DatabaseIsClosed,
-                Some("NotFoundError") => crate::Error::DoesNotExist,
-                Some("InvalidAccessError") => crate::Error::InvalidArgument,
-                _ => crate::Error::from_js_value(err),
-            })?;
-        let result = RefCell::new(None);
-        let result = &result;
-        let (finished_tx, finished_rx) = futures_channel::oneshot::channel();
-        unsafe_jar::extend_lifetime_to_scope_and_run(
-            Box::new(move |()| {
-                RunnableTransaction::new(
-                    t.clone(),
-                    transaction(Transaction::from_sys(t)),
-                    result,
-                    finished_tx,
-                )
-            }),
-            async move |s| {
-                s.run(());
-                let _ = finished_rx.await;
-                let result = result
-                    .borrow_mut()
-                    .take()
-                    .expect("Transaction finished without setting result");
-                match result {
-                    TransactionResult::PolledForbiddenThing => {
-                        panic!("{}", crate::POLLED_FORBIDDEN_THING_PANIC)
-                    }
-                    TransactionResult::Done(r) => r,
-                }
-            },
-        )
-        .await
+        self.inner
+            .run(async move |t| transaction(Transaction::from_backend(t)).await)
+            .await
     }
-}
 
-struct FakeFuture<'a, T> {
-    watching: &'a RefCell<Option<T>>,
-}
-
-impl<'a, T> FakeFuture<'a, T> {
-    fn new(watching: &'a RefCell<Option<T>>) -> FakeFuture<'a, T> {
-        FakeFuture { watching }
-    }
-}
-
-impl<'a, T> Future for FakeFuture<'a, T> {
-    type Output = T;
-
-    fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<T> {
-        // Don't do this at home! This only works thanks to our unsafe jar polling regardless of the waker
-        match self.watching.borrow_mut().take() {
-            None => Poll::Pending,
-            Some(r) => Poll::Ready(r),
-        }
-    }
-}
-
-pub(crate) async fn transaction_request(req: IdbRequest) -> Result<JsValue, JsValue> {
-    let result = Rc::new(RefCell::new(None));
-
-    // Keep the callbacks alive until execution completed
-    let _callbacks = runner::add_request(req, &result);
-
-    match FakeFuture::new(&result).await {
-        Ok(evt) => {
-            let result = evt.target()
-                .expect("Trying to parse indexed_db::Error from an event that has no target")
-                .dyn_into::<web_sys::IdbRequest>()
-                .expect("Trying to parse indexed_db::Error from an event that is not from an IDBRequest")
-                .result()
-                .expect("Failed retrieving the result of successful IDBRequest");
-            Ok(result)
-        }
-        Err(evt) => Err(err_from_event(evt).into()),
+    /// Execute the transaction, without waiting for it to complete
+    ///
+    /// This is the same as [`TransactionBuilder::run`], except that this returns as soon as `transaction`
+    /// returns. The transaction could then still fail to commit, without this being reported.
+    pub async fn run_no_wait<Ret, Err>(
+        self,
+        transaction: impl AsyncFnOnce(Transaction<Err, B>) -> crate::Result<Ret, Err>,
+    ) -> crate::Result<Ret, Err> {
+        self.inner
+            .run_no_wait(async move |t| transaction(Transaction::from_backend(t)).await)
+            .await
     }
 }
@@ -0,0 +1,312 @@
+use crate::{
+    backend::{Backend, BackendValue, WebSys},
+    Cursor, CursorBuilder, CursorDirection, ErrorContext, Key, ObjectStore,
+};
+use futures_util::future::{Either, FutureExt};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    future::Future,
+    marker::PhantomData,
+    ops::{Bound, RangeBounds},
+};
+
+#[cfg(doc)]
+use crate::Transaction;
+
+fn to_value<B: Backend, T: Serialize, Err>(v: &T) -> crate::Result<B::Value, Err> {
+    B::Value::serialize(v).map_err(crate::Error::Serialization)
+}
+
+fn from_value<B: Backend, T: DeserializeOwned, Err>(v: B::Value) -> crate::Result<T, Err> {
+    v.deserialize().map_err(crate::Error::Serialization)
+}
+
+fn to_key<B: Backend, K: Serialize, Err>(k: &K) -> crate::Result<Key, Err> {
+    to_value::<B, _, _>(k)?.to_key().ok_or_else(|| {
+        crate::Error::InvalidKey(ErrorContext::new(
+            "The key was not serialized into a valid key",
+        ))
+    })
+}
+
+fn from_key<B: Backend, K: DeserializeOwned, Err>(k: Key) -> crate::Result<K, Err> {
+    from_value::<B, _, _>(B::Value::from_key(&k))
+}
+
+fn bound_to_key<B: Backend, K: Serialize, Err>(bound: Bound<&K>) -> crate::Result<Bound<Key>, Err> {
+    Ok(match bound {
+        Bound::Included(k) => Bound::Included(to_key::<B, _, _>(k)?),
+        Bound::Excluded(k) => Bound::Excluded(to_key::<B, _, _>(k)?),
+        Bound::Unbounded => Bound::Unbounded,
+    })
+}
+
+fn range_to_keys<B: Backend, K: Serialize, Err>(
+    range: impl RangeBounds<K>,
+) -> crate::Result<(Bound<Key>, Bound<Key>), Err> {
+    Ok((
+        bound_to_key::<B, _, _>(range.start_bound())?,
+        bound_to_key::<B, _, _>(range.end_bound())?,
+    ))
+}
+
+/// Wrapper around an [`ObjectStore`] whose keys are of type `K` and values of type `V`
+///
+/// Keys and values are converted from and to the values of the backend in a JSON-compatible way. With
+/// the default backend, this uses [`serde_wasm_bindgen`] in its JSON-compatible mode. In particular,
+/// structs become plain Javascript objects, so key paths can refer to their fields, and 64-bit integers
+/// become numbers, as IndexedDB keys cannot be `BigInt`s.
+///
+/// This is only available with the `serde` feature, and can be obtained with [`Transaction::typed_store`].
+pub struct TypedObjectStore<K, V, Err, B: Backend = WebSys> {
+    store: ObjectStore<Err, B>,
+    _phantom: PhantomData<(K, V)>,
+}
+
+impl<K, V, Err, B: Backend> TypedObjectStore<K, V, Err, B>
+where
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+{
+    pub(crate) fn from_store(store: ObjectStore<Err, B>) -> TypedObjectStore<K, V, Err, B> {
+        TypedObjectStore {
+            store,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// The underlying untyped [`ObjectStore`]
+    pub fn untyped(&self) -> &ObjectStore<Err, B> {
+        &self.store
+    }
+
+    /// Add the value `value` to this object store, and return its auto-computed key
+    ///
+    /// This will error if the key already existed.
+    ///
+    /// See [`ObjectStore::add`].
+    pub fn add(&self, value: &V) -> impl Future<Output = crate::Result<K, Err>> {
+        let value = match to_value::<B, _, _>(value) {
+            Ok(value) => value,
+            Err(e) => return Either::Left(std::future::ready(Err(e))),
+        };
+        Either::Right(self.store.add(&value).map(|res| from_key::<B, _, _>(res?)))
+    }
+
+    /// Add the value `value` to this object store, with key `key`
+    ///
+    /// This will error if the key already existed.
+    ///
+    /// See [`ObjectStore::add_kv`].
+    pub fn add_kv(&self, key: &K, value: &V) -> impl Future<Output = crate::Result<(), Err>> {
+        let (key, value) =
+            match to_key::<B, _, _>(key).and_then(|k| Ok((k, to_value::<B, _, _>(value)?))) {
+                Ok(kv) => kv,
+                Err(e) => return Either::Left(std::future::ready(Err(e))),
+            };
+        Either::Right(self.store.add_kv(key, &value))
+    }
+
+    /// Add the value `value` to this object store, and return its auto-computed key
+    ///
+    /// This will overwrite the previous value if the key already existed.
+    ///
+    /// See [`ObjectStore::put`].
+    pub fn put(&self, value: &V) -> impl Future<Output = crate::Result<K, Err>> {
+        let value = match to_value::<B, _, _>(value) {
+            Ok(value) => value,
+            Err(e) => return Either::Left(std::future::ready(Err(e))),
+        };
+        Either::Right(self.store.put(&value).map(|res| from_key::<B, _, _>(res?)))
+    }
+
+    /// Add the value `value` to this object store, with key `key`
+    ///
+    /// This will overwrite the previous value if the key already existed.
+    ///
+    /// See [`ObjectStore::put_kv`].
+    pub fn put_kv(&self, key: &K, value: &V) -> impl Future<Output = crate::Result<(), Err>> {
+        let (key, value) =
+            match to_key::<B, _, _>(key).and_then(|k| Ok((k, to_value::<B, _, _>(value)?))) {
+                Ok(kv) => kv,
+                Err(e) => return Either::Left(std::future::ready(Err(e))),
+            };
+        Either::Right(self.store.put_kv(key, &value))
+    }
+
+    /// Count the number of objects in this store
+    ///
+    /// See [`ObjectStore::count`].
+    pub fn count(&self) -> impl Future<Output = crate::Result<usize, Err>> {
+        self.store.count()
+    }
+
+    /// Checks whether the provided key exists in this object store
+    ///
+    /// See [`ObjectStore::contains`].
+    pub fn contains(&self, key: &K) -> impl Future<Output = crate::Result<bool, Err>> {
+        match to_key::<B, _, _>(key) {
+            Ok(key) => Either::Right(self.store.contains(key)),
+            Err(e) => Either::Left(std::future::ready(Err(e))),
+        }
+    }
+
+    /// Delete the object with key `key`
+    ///
+    /// See [`ObjectStore::delete`].
+    pub fn delete(&self, key: &K) -> impl Future<Output = crate::Result<(), Err>> {
+        match to_key::<B, _, _>(key) {
+            Ok(key) => Either::Right(self.store.delete(key)),
+            Err(e) => Either::Left(std::future::ready(Err(e))),
+        }
+    }
+
+    /// Get the object with key `key`
+    ///
+    /// See [`ObjectStore::get`].
+    pub fn get(&self, key: &K) -> impl Future<Output = crate::Result<Option<V>, Err>> {
+        let key = match to_key::<B, _, _>(key) {
+            Ok(key) => key,
+            Err(e) => return Either::Left(std::future::ready(Err(e))),
+        };
+        Either::Right(
+            self.store
+                .get(key)
+                .map(|res| res?.map(from_value::<B, _, _>).transpose()),
+        )
+    }
+
+    /// Get all the objects in the store, with a maximum number of results of `limit`
+    ///
+    /// See [`ObjectStore::get_all`].
+    pub fn get_all(&self, limit: Option<u32>) -> impl Future<Output = crate::Result<Vec<V>, Err>> {
+        self.store
+            .get_all(limit)
+            .map(|res| res?.into_iter().map(from_value::<B, _, _>).collect())
+    }
+
+    /// Get all the objects with a key in the provided range, with a maximum number of results of `limit`
+    ///
+    /// See [`ObjectStore::get_all_in`].
+    pub fn get_all_in(
+        &self,
+        range: impl RangeBounds<K>,
+        limit: Option<u32>,
+    ) -> impl Future<Output = crate::Result<Vec<V>, Err>> {
+        let range = match range_to_keys::<B, _, _>(range) {
+            Ok(range) => range,
+            Err(e) => return Either::Left(std::future::ready(Err(e))),
+        };
+        Either::Right(
+            self.store
+                .get_all_in(range, limit)
+                .map(|res| res?.into_iter().map(from_value::<B, _, _>).collect()),
+        )
+    }
+
+    /// Open a [`TypedCursor`] on this object store
+    pub fn cursor(&self) -> TypedCursorBuilder<K, V, Err, B> {
+        TypedCursorBuilder {
+            builder: self.store.cursor(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+/// Helper to build cursors over [`TypedObjectStore`]s
+pub struct TypedCursorBuilder<K, V, Err, B: Backend = WebSys> {
+    builder: CursorBuilder<Err, B>,
+    _phantom: PhantomData<(K, V)>,
+}
+
+impl<K, V, Err, B: Backend> TypedCursorBuilder<K, V, Err, B>
+where
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+{
+    /// Open the cursor
+    ///
+    /// See [`CursorBuilder::open`].
+    pub fn open(self) -> impl Future<Output = crate::Result<TypedCursor<K, V, Err, B>, Err>> {
+        self.builder.open().map(|res| {
+            Ok(TypedCursor {
+                cursor: res?,
+                _phantom: PhantomData,
+            })
+        })
+    }
+
+    /// Limit the range of the cursor
+    ///
+    /// See [`CursorBuilder::range`].
+    pub fn range(self, range: impl RangeBounds<K>) -> crate::Result<Self, Err> {
+        Ok(TypedCursorBuilder {
+            builder: self.builder.range(range_to_keys::<B, _, _>(range)?)?,
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Define the direction of the cursor
+    ///
+    /// See [`CursorBuilder::direction`].
+    pub fn direction(self, direction: CursorDirection) -> Self {
+        TypedCursorBuilder {
+            builder: self.builder.direction(direction),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+/// Wrapper around a [`Cursor`] over a [`TypedObjectStore`]
+pub struct TypedCursor<K, V, Err, B: Backend = WebSys> {
+    cursor: Cursor<Err, B>,
+    _phantom: PhantomData<(K, V)>,
+}
+
+impl<K, V, Err, B: Backend> TypedCursor<K, V, Err, B>
+where
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+{
+    /// Retrieve the value this cursor is currently pointing at, or `None` if the cursor is completed
+    ///
+    /// See [`Cursor::value`].
+    pub fn value(&self) -> crate::Result<Option<V>, Err> {
+        self.cursor.value().map(from_value::<B, _, _>).transpose()
+    }
+
+    /// Retrieve the key this cursor is currently pointing at, or `None` if the cursor is completed
+    ///
+    /// See [`Cursor::key`].
+    pub fn key(&self) -> crate::Result<Option<K>, Err> {
+        self.cursor.key().map(from_key::<B, _, _>).transpose()
+    }
+
+    /// Advance this cursor by `count` elements
+    ///
+    /// See [`Cursor::advance`].
+    pub async fn advance(&mut self, count: u32) -> crate::Result<(), Err> {
+        self.cursor.advance(count).await
+    }
+
+    /// Advance this cursor until the provided key
+    ///
+    /// See [`Cursor::advance_until`].
+    pub async fn advance_until(&mut self, key: &K) -> crate::Result<(), Err> {
+        self.cursor.advance_until(to_key::<B, _, _>(key)?).await
+    }
+
+    /// Deletes the value currently pointed by this cursor
+    ///
+    /// See [`Cursor::delete`].
+    pub async fn delete(&self) -> crate::Result<(), Err> {
+        self.cursor.delete().await
+    }
+
+    /// Update the value currently pointed by this cursor to `value`
+    ///
+    /// See [`Cursor::update`].
+    pub async fn update(&self, value: &V) -> crate::Result<(), Err> {
+        self.cursor.update(&to_value::<B, _, _>(value)?).await
+    }
+}
@@ -1,12 +1,37 @@
-use futures_channel::oneshot;
-use futures_util::future::{self, Either};
-use std::ops::{Bound, RangeBounds};
+use crate::{idb::transaction, ErrorContext, Key, KeyPath};
+use futures_channel::{mpsc, oneshot};
+use futures_util::{
+    future::{self, Either},
+    FutureExt, StreamExt,
+};
+use std::{
+    cell::Cell,
+    future::Future,
+    ops::{Bound, RangeBounds},
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use wasm_bindgen::prelude::wasm_bindgen;
 use web_sys::{
-    js_sys::{Array, Function, JsString, Number, TypeError},
+    js_sys::{
+        Array, ArrayBuffer, Date, Function, JsString, Map, Number, Object, Promise, Reflect, Set,
+        TypeError, Uint8Array,
+    },
     wasm_bindgen::{closure::Closure, JsCast, JsValue},
-    DomException, IdbKeyRange, IdbRequest,
+    DomException, DomStringList, Event, IdbDatabase, IdbKeyRange, IdbOpenDbRequest, IdbRequest,
+    IdbTransaction, IdbVersionChangeEvent,
 };
 
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_name = setTimeout)]
+    fn set_timeout(handler: &Function, timeout: i32) -> i32;
+
+    #[wasm_bindgen(js_name = clearTimeout)]
+    fn clear_timeout(handle: i32);
+}
+
 pub(crate) async fn non_transaction_request(
     req: IdbRequest,
 ) -> Result<web_sys::Event, web_sys::Event> {
@@ -25,6 +50,211 @@ pub(crate) async fn non_transaction_request(
     }
 }
 
+/// Callback receiving the old and new versions of a `blocked` event
+pub(crate) type OnBlocked<'a> = Box<dyn 'a + FnMut(u32, Option<u32>)>;
+
+/// Settings for reacting to `blocked` events on an [`IdbOpenDbRequest`]
+pub(crate) struct BlockedOptions<'a> {
+    pub(crate) on_blocked: Option<OnBlocked<'a>>,
+    pub(crate) timeout: Option<Duration>,
+}
+
+impl BlockedOptions<'_> {
+    pub(crate) fn new() -> Self {
+        BlockedOptions {
+            on_blocked: None,
+            timeout: None,
+        }
+    }
+}
+
+/// Wait for an open or delete request to complete, while handling its `blocked` events
+///
+/// Returns `None` if the request stayed blocked for longer than the configured timeout. In this case,
+/// the request is detached: should it later complete, the resulting connection is closed immediately
+/// and any upgrade is aborted.
+pub(crate) async fn open_db_request(
+    req: &IdbOpenDbRequest,
+    mut blocked: BlockedOptions<'_>,
+) -> Option<Result<web_sys::Event, web_sys::Event>> {
+    let (blocked_tx, mut blocked_rx) = mpsc::unbounded();
+    let on_blocked = Closure::<dyn FnMut(_)>::new(move |evt: IdbVersionChangeEvent| {
+        let _ = blocked_tx.unbounded_send((
+            evt.old_version() as u32,
+            evt.new_version().map(|v| v as u32),
+        ));
+    });
+    req.set_onblocked(Some(on_blocked.as_ref().dyn_ref::<Function>().unwrap()));
+
+    let mut completion = non_transaction_request(req.clone().into()).boxed_local();
+    let mut timer = None;
+    let res = future::poll_fn(|cx| {
+        if let Poll::Ready(res) = completion.poll_unpin(cx) {
+            return Poll::Ready(Some(res));
+        }
+        while let Poll::Ready(Some((old_version, new_version))) = blocked_rx.poll_next_unpin(cx) {
+            if let Some(on_blocked) = &mut blocked.on_blocked {
+                on_blocked(old_version, new_version);
+            }
+            if timer.is_none() {
+                timer = blocked.timeout.take().map(Timeout::new);
+            }
+        }
+        if let Some(t) = &mut timer {
+            if t.poll_unpin(cx).is_ready() {
+                timer = None;
+                // If the upgrade transaction already started, we are no longer blocked
+                if req.transaction().is_none() {
+                    return Poll::Ready(None);
+                }
+            }
+        }
+        Poll::Pending
+    })
+    .await;
+
+    req.set_onblocked(None);
+    if res.is_none() {
+        // The request cannot be cancelled, so make sure it has no effect whenever it completes
+        req.set_onupgradeneeded(Some(
+            Closure::once_into_js(|evt: IdbVersionChangeEvent| {
+                if let Some(transaction) = evt
+                    .target()
+                    .and_then(|t| t.dyn_into::<IdbRequest>().ok())
+                    .and_then(|req| req.transaction())
+                {
+                    let _ = transaction.abort();
+                }
+            })
+            .unchecked_ref(),
+        ));
+        req.set_onsuccess(Some(
+            Closure::once_into_js(|evt: web_sys::Event| {
+                if let Some(db) = evt
+                    .target()
+                    .and_then(|t| t.dyn_into::<IdbRequest>().ok())
+                    .and_then(|req| req.result().ok())
+                    .and_then(|db| db.dyn_into::<IdbDatabase>().ok())
+                {
+                    db.close();
+                }
+            })
+            .unchecked_ref(),
+        ));
+        req.set_onerror(None);
+    }
+    res
+}
+
+/// Future that resolves after the provided duration elapsed
+pub(crate) struct Timeout {
+    handle: i32,
+    fired: oneshot::Receiver<()>,
+    _callback: Closure<dyn FnMut()>,
+}
+
+impl Timeout {
+    pub(crate) fn new(duration: Duration) -> Timeout {
+        let (fired_tx, fired) = oneshot::channel();
+        let callback = Closure::once(move || {
+            let _ = fired_tx.send(());
+        });
+        let millis = i32::try_from(duration.as_millis()).unwrap_or(i32::MAX);
+        let handle = set_timeout(callback.as_ref().unchecked_ref(), millis);
+        Timeout {
+            handle,
+            fired,
+            _callback: callback,
+        }
+    }
+}
+
+impl Future for Timeout {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        self.fired.poll_unpin(cx).map(|_| ())
+    }
+}
+
+impl Drop for Timeout {
+    fn drop(&mut self) {
+        clear_timeout(self.handle);
+    }
+}
+
+pub(crate) async fn await_promise(promise: Promise) -> Result<JsValue, JsValue> {
+    let (resolve_tx, resolve_rx) = oneshot::channel();
+    let (reject_tx, reject_rx) = oneshot::channel();
+
+    let on_resolve = Closure::once(move |v| {
+        let _ = resolve_tx.send(v);
+    });
+    let on_reject = Closure::once(move |v| {
+        let _ = reject_tx.send(v);
+    });
+
+    let _ = promise.then2(&on_resolve, &on_reject);
+
+    match future::select(resolve_rx, reject_rx).await {
+        Either::Left((res, _)) => Ok(res.unwrap()),
+        Either::Right((res, _)) => Err(res.unwrap()),
+    }
+}
+
+/// Wait for `transaction` to either complete or abort
+///
+/// The event listeners are registered immediately, so this must be called before the transaction can finish.
+/// Returns the error that caused the transaction to abort, if any, or `None` if it was aborted explicitly.
+pub(crate) fn transaction_finished(
+    transaction: &IdbTransaction,
+) -> impl Future<Output = Result<(), Option<DomException>>> {
+    let (complete_tx, complete_rx) = oneshot::channel();
+    let (abort_tx, abort_rx) = oneshot::channel();
+
+    let on_complete = Closure::once(move |_: Event| {
+        let _ = complete_tx.send(());
+    });
+    let on_abort = Closure::once(move |_: Event| {
+        let _ = abort_tx.send(());
+    });
+
+    transaction.set_oncomplete(Some(on_complete.as_ref().dyn_ref::<Function>().unwrap()));
+    transaction.set_onabort(Some(on_abort.as_ref().dyn_ref::<Function>().unwrap()));
+
+    let callbacks = FinishedCallbacks {
+        transaction: transaction.clone(),
+        _on_complete: on_complete,
+        _on_abort: on_abort,
+    };
+    async move {
+        let res = match future::select(complete_rx, abort_rx).await {
+            Either::Left(_) => Ok(()),
+            Either::Right(_) => Err(callbacks.transaction.error()),
+        };
+        drop(callbacks);
+        res
+    }
+}
+
+/// Callbacks registered by [`transaction_finished`]
+///
+/// The returned future can be dropped before the transaction finishes, eg. by
+/// [`TransactionBuilder::run_no_wait`](crate::TransactionBuilder::run_no_wait). The callbacks are then
+/// unregistered before being freed, so that the browser does not call a dropped closure.
+struct FinishedCallbacks {
+    transaction: IdbTransaction,
+    _on_complete: Closure<dyn FnMut(Event)>,
+    _on_abort: Closure<dyn FnMut(Event)>,
+}
+
+impl Drop for FinishedCallbacks {
+    fn drop(&mut self) {
+        self.transaction.set_oncomplete(None);
+        self.transaction.set_onabort(None);
+    }
+}
+
 pub(crate) fn none_if_undefined(v: JsValue) -> Option<JsValue> {
     if v.is_undefined() {
         None
@@ -45,6 +275,18 @@ pub(crate) fn array_to_vec(v: JsValue) -> Vec<JsValue> {
     res
 }
 
+pub(crate) fn dom_string_list_to_vec(list: DomStringList) -> Vec<String> {
+    let len = list.length();
+    let mut res = Vec::with_capacity(usize::try_from(len).unwrap());
+    for i in 0..len {
+        res.push(
+            list.get(i)
+                .expect("DOMStringList did not contain as many elements as its length"),
+        );
+    }
+    res
+}
+
 pub(crate) fn str_slice_to_array(s: &[&str]) -> Array {
     let res = Array::new_with_length(u32::try_from(s.len()).unwrap());
     for (i, v) in s.iter().enumerate() {
@@ -53,6 +295,98 @@ pub(crate) fn str_slice_to_array(s: &[&str]) -> Array {
     res
 }
 
+/// Compare two values structurally, as they would be after a round-trip through IndexedDB
+///
+/// Primitives are compared with `Object.is`, except that `0` and `-0` are equal. Arrays, plain objects,
+/// `Date`s, `ArrayBuffer`s and their views, `Map`s and `Set`s are compared by contents. Other objects, like
+/// `Blob`s, are only equal to themselves.
+pub(crate) fn js_deep_equal(a: &JsValue, b: &JsValue) -> bool {
+    if a == b || Object::is(a, b) {
+        return true;
+    }
+    let (Some(a), Some(b)) = (a.dyn_ref::<Object>(), b.dyn_ref::<Object>()) else {
+        return false;
+    };
+    if !Object::is(&Object::get_prototype_of(a), &Object::get_prototype_of(b)) {
+        return false;
+    }
+    if let (Some(a), Some(b)) = (a.dyn_ref::<Date>(), b.dyn_ref::<Date>()) {
+        return Object::is(&a.get_time().into(), &b.get_time().into());
+    }
+    if Array::is_array(a) {
+        let (a, b) = (a.unchecked_ref::<Array>(), b.unchecked_ref::<Array>());
+        return a.length() == b.length()
+            && (0..a.length()).all(|i| js_deep_equal(&a.get(i), &b.get(i)));
+    }
+    if a.is_instance_of::<ArrayBuffer>() || ArrayBuffer::is_view(a) {
+        return view_bytes(a) == view_bytes(b);
+    }
+    if let (Some(a), Some(b)) = (a.dyn_ref::<Map>(), b.dyn_ref::<Map>()) {
+        let equal = Cell::new(a.size() == b.size());
+        a.for_each(&mut |v, k| {
+            equal.set(equal.get() && b.has(&k) && js_deep_equal(&v, &b.get(&k)));
+        });
+        return equal.get();
+    }
+    if let (Some(a), Some(b)) = (a.dyn_ref::<Set>(), b.dyn_ref::<Set>()) {
+        let equal = Cell::new(a.size() == b.size());
+        a.for_each(&mut |v, _, _| equal.set(equal.get() && b.has(&v)));
+        return equal.get();
+    }
+    if !Object::is(
+        &Object::get_prototype_of(a),
+        &Object::get_prototype_of(&Object::new()),
+    ) {
+        return false;
+    }
+    let (keys_a, keys_b) = (Object::keys(a), Object::keys(b));
+    keys_a.length() == keys_b.length()
+        && keys_a.iter().all(|k| {
+            Reflect::has(b, &k).unwrap_or(false)
+                && js_deep_equal(
+                    &Reflect::get(a, &k).unwrap_or_default(),
+                    &Reflect::get(b, &k).unwrap_or_default(),
+                )
+        })
+}
+
+/// The key at `key_path` in `value`, or `None` if there is no valid key there
+pub(crate) fn evaluate_key_path(value: &JsValue, key_path: &KeyPath) -> Option<Key> {
+    let lookup = |path: &str| {
+        let mut current = value.clone();
+        for name in path.split('.').filter(|name| !name.is_empty()) {
+            if !current.is_object() {
+                return None;
+            }
+            current = Reflect::get(&current, &JsValue::from_str(name)).ok()?;
+        }
+        Key::from_js(&current)
+    };
+    match key_path {
+        KeyPath::Single(path) => lookup(path),
+        KeyPath::Compound(paths) => paths
+            .iter()
+            .map(|path| lookup(path))
+            .collect::<Option<Vec<_>>>()
+            .map(Key::Array),
+    }
+}
+
+/// The bytes of an `ArrayBuffer` or of a view on one, like a typed array or a `DataView`
+fn view_bytes(v: &Object) -> Vec<u8> {
+    if v.is_instance_of::<ArrayBuffer>() {
+        return Uint8Array::new(v).to_vec();
+    }
+    let get = |field: &str| Reflect::get(v, &JsValue::from_str(field)).unwrap_or_default();
+    let as_u32 = |v: JsValue| v.as_f64().map_or(0, |v| v as u32);
+    Uint8Array::new_with_byte_offset_and_length(
+        &get("buffer"),
+        as_u32(get("byteOffset")),
+        as_u32(get("byteLength")),
+    )
+    .to_vec()
+}
+
 pub(crate) fn err_from_event(evt: web_sys::Event) -> DomException {
     evt.prevent_default(); // Avoid the transaction aborting upon an error
     let idb_request = evt
@@ -66,26 +400,53 @@ pub(crate) fn err_from_event(evt: web_sys::Event) -> DomException {
         .expect("IDBRequest::error did not return a DOMException")
 }
 
+/// Error for a request made on an inactive transaction
+///
+/// This is [`crate::Error::TransactionFinished`] if the transaction was explicitly committed or aborted,
+/// and [`crate::Error::TransactionInactive`] otherwise, or a panic if requested.
+pub(crate) fn transaction_inactive_err<Err>(context: ErrorContext, msg: &str) -> crate::Error<Err> {
+    if transaction::is_explicitly_finished() {
+        crate::Error::TransactionFinished(context)
+    } else if transaction::panics_on_inactive_transaction() {
+        panic!("{msg}")
+    } else {
+        crate::Error::TransactionInactive(context)
+    }
+}
+
+/// Error for a transaction whose future awaited on something other than a request
+pub(crate) fn transaction_blocked_err<Err>() -> crate::Error<Err> {
+    if transaction::panics_on_inactive_transaction() {
+        panic!("{}", crate::POLLED_FORBIDDEN_THING_PANIC)
+    } else {
+        crate::Error::TransactionInactive(ErrorContext::default())
+    }
+}
+
 pub(crate) fn map_add_err<Err>(err: JsValue) -> crate::Error<Err> {
     match error_name!(&err) {
-        Some("ReadOnlyError") => crate::Error::ReadOnly,
-        Some("TransactionInactiveError") => {
-            panic!("Tried adding to an ObjectStore while the transaction was inactive")
-        }
-        Some("DataError") => crate::Error::InvalidKey,
-        Some("InvalidStateError") => crate::Error::ObjectStoreWasRemoved,
-        Some("DataCloneError") => crate::Error::FailedClone,
-        Some("ConstraintError") => crate::Error::AlreadyExists,
+        Some("ReadOnlyError") => crate::Error::ReadOnly(ErrorContext::from_js(&err)),
+        Some("TransactionInactiveError") => transaction_inactive_err(
+            ErrorContext::from_js(&err),
+            "Tried adding to an ObjectStore while the transaction was inactive",
+        ),
+        Some("DataError") => crate::Error::InvalidKey(ErrorContext::from_js(&err)),
+        Some("InvalidStateError") => {
+            crate::Error::ObjectStoreWasRemoved(ErrorContext::from_js(&err))
+        }
+        Some("DataCloneError") => crate::Error::FailedClone(ErrorContext::from_js(&err)),
+        Some("ConstraintError") => crate::Error::AlreadyExists(ErrorContext::from_js(&err)),
         _ => crate::Error::from_js_value(err),
     }
 }
 
 pub(crate) fn map_clear_err<Err>(err: JsValue) -> crate::Error<Err> {
     match error_name!(&err) {
-        Some("ReadOnlyError") => crate::Error::ReadOnly,
-        Some("TransactionInactiveError") => {
-            panic!("Tried clearing an ObjectStore while the transaction was inactive")
-        }
+        Some("ReadOnlyError") => crate::Error::ReadOnly(ErrorContext::from_js(&err)),
+        Some("TransactionInactiveError") => transaction_inactive_err(
+            ErrorContext::from_js(&err),
+            "Tried clearing an ObjectStore while the transaction was inactive",
+        ),
         _ => crate::Error::from_js_value(err),
     }
 }
@@ -103,45 +464,57 @@ pub(crate) fn map_count_res(res: JsValue) -> usize {
 
 pub(crate) fn map_count_err<Err>(err: JsValue) -> crate::Error<Err> {
     match error_name!(&err) {
-        Some("InvalidStateError") => crate::Error::ObjectStoreWasRemoved,
-        Some("TransactionInactiveError") => {
-            panic!("Tried counting in an ObjectStore while the transaction was inactive")
+        Some("InvalidStateError") => {
+            crate::Error::ObjectStoreWasRemoved(ErrorContext::from_js(&err))
         }
-        Some("DataError") => crate::Error::InvalidKey,
+        Some("TransactionInactiveError") => transaction_inactive_err(
+            ErrorContext::from_js(&err),
+            "Tried counting in an ObjectStore while the transaction was inactive",
+        ),
+        Some("DataError") => crate::Error::InvalidKey(ErrorContext::from_js(&err)),
         _ => crate::Error::from_js_value(err),
     }
 }
 
 pub(crate) fn map_delete_err<Err>(err: JsValue) -> crate::Error<Err> {
     match error_name!(&err) {
-        Some("ReadOnlyError") => crate::Error::ReadOnly,
-        Some("InvalidStateError") => crate::Error::ObjectStoreWasRemoved,
-        Some("TransactionInactiveError") => {
-            panic!("Tried deleting from an ObjectStore while the transaction was inactive")
+        Some("ReadOnlyError") => crate::Error::ReadOnly(ErrorContext::from_js(&err)),
+        Some("InvalidStateError") => {
+            crate::Error::ObjectStoreWasRemoved(ErrorContext::from_js(&err))
         }
-        Some("DataError") => crate::Error::InvalidKey,
+        Some("TransactionInactiveError") => transaction_inactive_err(
+            ErrorContext::from_js(&err),
+            "Tried deleting from an ObjectStore while the transaction was inactive",
+        ),
+        Some("DataError") => crate::Error::InvalidKey(ErrorContext::from_js(&err)),
         _ => crate::Error::from_js_value(err),
     }
 }
 
 pub(crate) fn map_get_err<Err>(err: JsValue) -> crate::Error<Err> {
     match error_name!(&err) {
-        Some("InvalidStateError") => crate::Error::ObjectStoreWasRemoved,
-        Some("TransactionInactiveError") => {
-            panic!("Tried getting from an ObjectStore while the transaction was inactive")
+        Some("InvalidStateError") => {
+            crate::Error::ObjectStoreWasRemoved(ErrorContext::from_js(&err))
         }
-        Some("DataError") => crate::Error::InvalidKey,
+        Some("TransactionInactiveError") => transaction_inactive_err(
+            ErrorContext::from_js(&err),
+            "Tried getting from an ObjectStore while the transaction was inactive",
+        ),
+        Some("DataError") => crate::Error::InvalidKey(ErrorContext::from_js(&err)),
         _ => crate::Error::from_js_value(err),
     }
 }
 
 pub(crate) fn map_open_cursor_err<Err>(err: JsValue) -> crate::Error<Err> {
     match error_name!(&err) {
-        Some("InvalidStateError") => crate::Error::ObjectStoreWasRemoved,
-        Some("TransactionInactiveError") => {
-            panic!("Tried opening a Cursor on an ObjectStore while the transaction was inactive")
+        Some("InvalidStateError") => {
+            crate::Error::ObjectStoreWasRemoved(ErrorContext::from_js(&err))
         }
-        Some("DataError") => crate::Error::InvalidKey,
+        Some("TransactionInactiveError") => transaction_inactive_err(
+            ErrorContext::from_js(&err),
+            "Tried opening a Cursor on an ObjectStore while the transaction was inactive",
+        ),
+        Some("DataError") => crate::Error::InvalidKey(ErrorContext::from_js(&err)),
         _ => crate::Error::from_js_value(err),
     }
 }
@@ -149,10 +522,13 @@ pub(crate) fn map_open_cursor_err<Err>(err: JsValue) -> crate::Error<Err> {
 pub(crate) fn map_cursor_advance_err<Err>(err: JsValue) -> crate::Error<Err> {
     match error_name!(&err) {
         Some("InvalidStateError") => crate::Error::CursorCompleted,
-        Some("TransactionInactiveError") => {
-            panic!("Tried advancing a Cursor on an ObjectStore while the transaction was inactive")
+        Some("TransactionInactiveError") => transaction_inactive_err(
+            ErrorContext::from_js(&err),
+            "Tried advancing a Cursor on an ObjectStore while the transaction was inactive",
+        ),
+        None if err.has_type::<TypeError>() => {
+            crate::Error::InvalidArgument(ErrorContext::from_js(&err))
         }
-        None if err.has_type::<TypeError>() => crate::Error::InvalidArgument,
         _ => crate::Error::from_js_value(err),
     }
 }
@@ -160,10 +536,11 @@ pub(crate) fn map_cursor_advance_err<Err>(err: JsValue) -> crate::Error<Err> {
 pub(crate) fn map_cursor_advance_until_err<Err>(err: JsValue) -> crate::Error<Err> {
     match error_name!(&err) {
         Some("InvalidStateError") => crate::Error::CursorCompleted,
-        Some("TransactionInactiveError") => {
-            panic!("Tried advancing a Cursor on an ObjectStore while the transaction was inactive")
-        }
-        Some("DataError") => crate::Error::InvalidKey,
+        Some("TransactionInactiveError") => transaction_inactive_err(
+            ErrorContext::from_js(&err),
+            "Tried advancing a Cursor on an ObjectStore while the transaction was inactive",
+        ),
+        Some("DataError") => crate::Error::InvalidKey(ErrorContext::from_js(&err)),
         _ => crate::Error::from_js_value(err),
     }
 }
@@ -171,11 +548,12 @@ pub(crate) fn map_cursor_advance_until_err<Err>(err: JsValue) -> crate::Error<Er
 pub(crate) fn map_cursor_advance_until_primary_key_err<Err>(err: JsValue) -> crate::Error<Err> {
     match error_name!(&err) {
         Some("InvalidStateError") => crate::Error::CursorCompleted,
-        Some("TransactionInactiveError") => {
-            panic!("Tried advancing a Cursor on an ObjectStore while the transaction was inactive")
-        }
-        Some("DataError") => crate::Error::InvalidKey,
-        Some("InvalidAccessError") => crate::Error::InvalidArgument,
+        Some("TransactionInactiveError") => transaction_inactive_err(
+            ErrorContext::from_js(&err),
+            "Tried advancing a Cursor on an ObjectStore while the transaction was inactive",
+        ),
+        Some("DataError") => crate::Error::InvalidKey(ErrorContext::from_js(&err)),
+        Some("InvalidAccessError") => crate::Error::InvalidArgument(ErrorContext::from_js(&err)),
         _ => crate::Error::from_js_value(err),
     }
 }
@@ -183,10 +561,11 @@ pub(crate) fn map_cursor_advance_until_primary_key_err<Err>(err: JsValue) -> cra
 pub(crate) fn map_cursor_delete_err<Err>(err: JsValue) -> crate::Error<Err> {
     match error_name!(&err) {
         Some("InvalidStateError") => crate::Error::CursorCompleted,
-        Some("TransactionInactiveError") => {
-            panic!("Tried advancing a Cursor on an ObjectStore while the transaction was inactive")
-        }
-        Some("ReadOnlyError") => crate::Error::ReadOnly,
+        Some("TransactionInactiveError") => transaction_inactive_err(
+            ErrorContext::from_js(&err),
+            "Tried advancing a Cursor on an ObjectStore while the transaction was inactive",
+        ),
+        Some("ReadOnlyError") => crate::Error::ReadOnly(ErrorContext::from_js(&err)),
         _ => crate::Error::from_js_value(err),
     }
 }
@@ -194,39 +573,66 @@ pub(crate) fn map_cursor_delete_err<Err>(err: JsValue) -> crate::Error<Err> {
 pub(crate) fn map_cursor_update_err<Err>(err: JsValue) -> crate::Error<Err> {
     match error_name!(&err) {
         Some("InvalidStateError") => crate::Error::CursorCompleted,
-        Some("TransactionInactiveError") => {
-            panic!("Tried advancing a Cursor on an ObjectStore while the transaction was inactive")
-        }
-        Some("ReadOnlyError") => crate::Error::ReadOnly,
-        Some("DataError") => crate::Error::InvalidKey,
-        Some("DataCloneError") => crate::Error::FailedClone,
+        Some("TransactionInactiveError") => transaction_inactive_err(
+            ErrorContext::from_js(&err),
+            "Tried advancing a Cursor on an ObjectStore while the transaction was inactive",
+        ),
+        Some("ReadOnlyError") => crate::Error::ReadOnly(ErrorContext::from_js(&err)),
+        Some("DataError") => crate::Error::InvalidKey(ErrorContext::from_js(&err)),
+        Some("DataCloneError") => crate::Error::FailedClone(ErrorContext::from_js(&err)),
         _ => crate::Error::from_js_value(err),
     }
 }
 
-pub(crate) fn make_key_range<Err>(range: impl RangeBounds<JsValue>) -> crate::Result<JsValue, Err> {
-    match (range.start_bound(), range.end_bound()) {
-        (Bound::Unbounded, Bound::Unbounded) => return Err(crate::Error::InvalidRange),
-        (Bound::Unbounded, Bound::Included(b)) => IdbKeyRange::upper_bound_with_open(b, false),
-        (Bound::Unbounded, Bound::Excluded(b)) => IdbKeyRange::upper_bound_with_open(b, true),
-        (Bound::Included(b), Bound::Unbounded) => IdbKeyRange::lower_bound_with_open(b, false),
-        (Bound::Excluded(b), Bound::Unbounded) => IdbKeyRange::lower_bound_with_open(b, true),
+/// Build the query for `range`, the unbounded range being `undefined`
+pub(crate) fn make_key_range<Err>(range: impl RangeBounds<Key>) -> crate::Result<JsValue, Err> {
+    let bound = |b: Bound<&Key>| b.map(Key::to_js);
+    match (bound(range.start_bound()), bound(range.end_bound())) {
+        (Bound::Unbounded, Bound::Unbounded) => return Ok(JsValue::UNDEFINED),
+        (Bound::Unbounded, Bound::Included(b)) => IdbKeyRange::upper_bound_with_open(&b, false),
+        (Bound::Unbounded, Bound::Excluded(b)) => IdbKeyRange::upper_bound_with_open(&b, true),
+        (Bound::Included(b), Bound::Unbounded) => IdbKeyRange::lower_bound_with_open(&b, false),
+        (Bound::Excluded(b), Bound::Unbounded) => IdbKeyRange::lower_bound_with_open(&b, true),
         (Bound::Included(l), Bound::Included(u)) => {
-            IdbKeyRange::bound_with_lower_open_and_upper_open(l, u, false, false)
+            IdbKeyRange::bound_with_lower_open_and_upper_open(&l, &u, false, false)
         }
         (Bound::Included(l), Bound::Excluded(u)) => {
-            IdbKeyRange::bound_with_lower_open_and_upper_open(l, u, false, true)
+            IdbKeyRange::bound_with_lower_open_and_upper_open(&l, &u, false, true)
         }
         (Bound::Excluded(l), Bound::Included(u)) => {
-            IdbKeyRange::bound_with_lower_open_and_upper_open(l, u, true, false)
+            IdbKeyRange::bound_with_lower_open_and_upper_open(&l, &u, true, false)
         }
         (Bound::Excluded(l), Bound::Excluded(u)) => {
-            IdbKeyRange::bound_with_lower_open_and_upper_open(l, u, true, true)
+            IdbKeyRange::bound_with_lower_open_and_upper_open(&l, &u, true, true)
         }
     }
     .map(|k| k.into())
     .map_err(|err| match error_name!(&err) {
-        Some("DataError") => crate::Error::InvalidKey,
+        Some("DataError") => crate::Error::InvalidKey(ErrorContext::from_js(&err)),
         _ => crate::Error::from_js_value(err),
     })
 }
+
+/// Build the query for `range`, for the methods that do not accept `undefined`
+///
+/// The unbounded range is then a range starting at `-Infinity`, the smallest possible key.
+pub(crate) fn make_defined_key_range<Err>(
+    range: impl RangeBounds<Key>,
+) -> crate::Result<JsValue, Err> {
+    match (range.start_bound(), range.end_bound()) {
+        (Bound::Unbounded, Bound::Unbounded) => make_key_range(Key::Number(f64::NEG_INFINITY)..),
+        _ => make_key_range(range),
+    }
+}
+
+/// Convert a key returned by the browser into a [`Key`]
+pub(crate) fn key_from_js<Err>(v: &JsValue) -> crate::Result<Key, Err> {
+    Key::from_js(v).ok_or_else(|| {
+        crate::Error::Unknown(ErrorContext::new("The browser returned an invalid key"))
+    })
+}
+
+/// Convert an array of keys returned by the browser into [`Key`]s
+pub(crate) fn array_to_keys<Err>(v: JsValue) -> crate::Result<Vec<Key>, Err> {
+    array_to_vec(v).iter().map(key_from_js).collect()
+}
@@ -1,3 +1,5 @@
+#![cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+
 wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
 
 mod common_panic;
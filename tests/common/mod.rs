@@ -1,77 +1,119 @@
-use std::convert::Infallible;
-
-use indexed_db::{Error, Factory};
-use wasm_bindgen_test::wasm_bindgen_test;
-use web_sys::{
-    js_sys::{global, JsString, Number, Uint8Array},
-    wasm_bindgen::{JsCast, JsValue},
-    WorkerGlobalScope,
+use std::{convert::Infallible, fmt::Debug, ops::Bound, task::Poll};
+
+use futures::{StreamExt, TryStreamExt};
+use indexed_db::{
+    backend::{Backend, BackendValue},
+    CursorDirection, Durability, Error, Factory, IndexSchema, Key, KeyPath, KeyRange, Migration,
+    Schema, StoreSchema,
 };
 
-/// Returns the duration in milliseconds, with the result
-async fn time_it<R>(cb: impl std::future::Future<Output = R>) -> (f64, R) {
-    let now = {
-        let performance = if let Some(window) = web_sys::window() {
-            window.performance()
-        } else if let Ok(worker_scope) = global().dyn_into::<WorkerGlobalScope>() {
-            worker_scope.performance()
-        } else {
-            None
-        }
-        .expect("No `performance` available (not in a browser environment?)");
-        move || performance.now()
-    };
-    let start_ms = now();
-    let ret = cb.await;
-    let elapsed_ms = now() - start_ms;
-    (elapsed_ms, ret)
+/// A backend that this test suite runs on
+pub trait TestBackend: Backend<Value: Debug + PartialEq> {
+    /// The factory to run a test on, that can hold the databases of other tests
+    ///
+    /// Tests delete the databases they use before opening them, to start from scratch anyway.
+    fn factory() -> Factory<Self>;
+
+    /// An object with the fields `fields`, in this order
+    fn object(fields: Vec<(&str, Self::Value)>) -> Self::Value;
+
+    /// An array of `items`
+    fn array(items: Vec<Self::Value>) -> Self::Value;
+
+    /// The `null` value
+    fn null() -> Self::Value;
+
+    /// The field `name` of `value`, if it is an object with such a field
+    fn field(value: &Self::Value, name: &str) -> Option<Self::Value>;
 }
 
-#[wasm_bindgen_test]
-async fn close_and_delete_before_reopen() {
-    // tracing_wasm::set_as_global_default();
-    // std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+/// Generate one test per scenario of this suite, that runs it on the backend `$backend`
+macro_rules! backend_tests {
+    ($backend:ty) => {
+        backend_tests!(
+            @tests $backend:
+            into_manual_close,
+            smoke_test,
+            auto_rollback,
+            abort_and_read_only_errors,
+            duplicate_insert_returns_proper_error_and_does_not_abort,
+            key_ordering_and_auto_increment,
+            typed_array_keys,
+            borrowing_transaction,
+            list_databases,
+            schema_migrations,
+            store_and_index_introspection,
+            unique_and_multi_entry_indexes,
+            unbounded_and_key_ranges,
+            prefix_ranges,
+            cursor_streams,
+            key_cursors,
+            cursor_directions,
+            update_all_with_cursor,
+            run_waits_for_completion,
+            transaction_durability,
+            run_local_futures,
+            batch_writes,
+            read_modify_write,
+        );
+        #[cfg(feature = "serde")]
+        backend_tests!(@tests $backend: typed_object_store);
+    };
+    (@tests $backend:ty: $($name:ident),* $(,)?) => {
+        $(
+            #[cfg(target_arch = "wasm32")]
+            #[wasm_bindgen_test::wasm_bindgen_test]
+            async fn $name() {
+                crate::common::$name::<$backend>().await;
+            }
 
-    const DATABASE_NAME: &str = "close_and_delete_before_reopen";
-    const ITERATIONS: usize = 10;
+            #[cfg(not(target_arch = "wasm32"))]
+            #[test]
+            fn $name() {
+                futures::executor::block_on(crate::common::$name::<$backend>());
+            }
+        )*
+    };
+}
 
-    let (delete_duration_ms, _) = time_it(async {
-        for _ in 0..ITERATIONS {
-            let factory = Factory::get().unwrap();
+/// The value of `key`, eg. a string or a number
+fn val<B: TestBackend>(key: impl Into<Key>) -> B::Value {
+    B::Value::from_key(&key.into())
+}
 
-            factory.delete_database(DATABASE_NAME).await.unwrap();
+/// The number that `value` holds
+fn number<B: TestBackend>(value: &B::Value) -> Option<f64> {
+    match value.to_key() {
+        Some(Key::Number(n)) => Some(n),
+        _ => None,
+    }
+}
 
-            let _db = factory
-                .open::<()>(DATABASE_NAME, 1, async move |_| Ok(()))
-                .await
-                .unwrap();
-
-            // Here the database wrapper got dropped which should trigger database close.
-        }
-    })
-    .await;
-
-    // Deleting the database should be almost instantaneous in theory.
-    // However this operation will hang as long as the database is still opened,
-    // which can last for 10s of seconds if our code forget to close is (in
-    // which case the close will only occur when the underlying javascript
-    // object got garbage collected...).
-    assert!(
-        // 1s per iteration should be plenty under normal circumstances
-        delete_duration_ms < 1000f64 * ITERATIONS as f64,
-        "Deleting the database took too long: {}ms",
-        delete_duration_ms
-    );
+pub fn user<B: TestBackend>(id: u32, name: &str, tags: &[&str]) -> B::Value {
+    B::object(vec![
+        ("id", val::<B>(id)),
+        ("name", val::<B>(name)),
+        (
+            "tags",
+            B::array(tags.iter().map(|t| val::<B>(*t)).collect()),
+        ),
+    ])
+}
+
+/// The JSON encoding of `value`, to compare objects by contents on all backends
+fn json<B: TestBackend>(value: &B::Value) -> String {
+    let mut out = String::new();
+    value.push_json(&mut out).unwrap();
+    out
 }
 
-#[wasm_bindgen_test]
-async fn into_manual_close() {
+pub async fn into_manual_close<B: TestBackend>() {
     // tracing_wasm::set_as_global_default();
     // std::panic::set_hook(Box::new(console_error_panic_hook::hook));
 
     const DATABASE_NAME: &str = "into_manual_close";
 
-    let factory = Factory::get().unwrap();
+    let factory = B::factory();
     factory.delete_database(DATABASE_NAME).await.unwrap();
 
     let manual_close_db = {
@@ -92,7 +134,7 @@ async fn into_manual_close() {
         .transaction(&["objects"])
         .run::<_, ()>(async move |t| {
             let objects = t.object_store("objects")?;
-            assert!(objects.get(&JsString::from("nokey")).await?.is_none());
+            assert!(objects.get("nokey").await?.is_none());
             Ok(())
         })
         .await
@@ -108,31 +150,17 @@ async fn into_manual_close() {
         })
         .await;
     assert!(
-        matches!(outcome, Err(Error::DatabaseIsClosed)),
+        matches!(outcome, Err(Error::DatabaseIsClosed(_))),
         "Unexpected outcome: {:?}",
         outcome
     );
 }
 
-#[wasm_bindgen_test]
-async fn smoke_test() {
+pub async fn smoke_test<B: TestBackend>() {
     // tracing_wasm::set_as_global_default();
     // std::panic::set_hook(Box::new(console_error_panic_hook::hook));
 
-    // Factory::get
-    let factory = Factory::get().unwrap();
-
-    // Factory::cmp
-    assert_eq!(
-        factory
-            .cmp(&JsValue::from_str("foo"), &JsValue::from_str("bar"))
-            .unwrap(),
-        std::cmp::Ordering::Greater
-    );
-    assert!(matches!(
-        factory.cmp(&JsValue::TRUE, &JsValue::FALSE),
-        Err(Error::InvalidKey),
-    ));
+    let factory = B::factory();
 
     // Factory::delete_database
     factory.delete_database("foo").await.unwrap();
@@ -193,18 +221,18 @@ async fn smoke_test() {
             let stuffs = t.object_store("stuffs")?;
 
             // Run one simple addition
-            stuffs.add(&JsString::from("foo")).await?;
+            stuffs.add(&val::<B>("foo")).await?;
             assert_eq!(stuffs.count().await?, 1);
 
             // Run two additions in parallel
-            let a = stuffs.add(&JsString::from("bar"));
-            let b = objects.add_kv(&JsString::from("key"), &JsString::from("value"));
+            let a = stuffs.add(&val::<B>("bar"));
+            let b = objects.add_kv("key", &val::<B>("value"));
             let (a, b) = futures::join!(a, b);
             a?;
             b?;
             assert_eq!(stuffs.count().await?, 2);
             assert_eq!(objects.count().await?, 1);
-            assert!(objects.contains(&JsString::from("key")).await?);
+            assert!(objects.contains("key").await?);
 
             Ok(())
         })
@@ -221,19 +249,12 @@ async fn smoke_test() {
             assert_eq!(objects.count().await?, 0);
 
             // Count range
-            assert_eq!(
-                stuffs
-                    .count_in(Number::from(2).as_ref()..=Number::from(3).as_ref())
-                    .await?,
-                1
-            );
+            assert_eq!(stuffs.count_in(Key::from(2)..=Key::from(3)).await?, 1);
 
             // Delete
-            stuffs
-                .delete_range(Number::from(2).as_ref()..=Number::from(3).as_ref())
-                .await?;
+            stuffs.delete_range(Key::from(2)..=Key::from(3)).await?;
             assert_eq!(stuffs.count().await?, 1);
-            stuffs.delete(&Number::from(1)).await?;
+            stuffs.delete(1).await?;
             assert_eq!(stuffs.count().await?, 0);
 
             Ok(())
@@ -246,30 +267,17 @@ async fn smoke_test() {
             let objects = t.object_store("objects")?;
 
             // Get
-            objects
-                .add_kv(&JsString::from("key"), &JsString::from("value"))
-                .await?;
-            assert_eq!(
-                objects.get(&JsString::from("key")).await?.unwrap(),
-                **JsString::from("value")
-            );
-            assert!(objects.get(&JsString::from("nokey")).await?.is_none());
+            objects.add_kv("key", &val::<B>("value")).await?;
+            assert_eq!(objects.get("key").await?.unwrap(), val::<B>("value"));
+            assert!(objects.get("nokey").await?.is_none());
             assert_eq!(
-                objects
-                    .get_first_in(..JsString::from("zzz").as_ref())
-                    .await?
-                    .unwrap(),
-                **JsString::from("value")
-            );
-            assert_eq!(
-                objects.get_all(None).await?,
-                vec![(**JsString::from("value")).clone()],
+                objects.get_first_in(..Key::from("zzz")).await?.unwrap(),
+                val::<B>("value")
             );
+            assert_eq!(objects.get_all(None).await?, vec![val::<B>("value")]);
             assert_eq!(
-                objects
-                    .get_all_in(JsString::from("zzz").as_ref().., None)
-                    .await?,
-                Vec::<JsValue>::new(),
+                objects.get_all_in(Key::from("zzz").., None).await?,
+                Vec::<B::Value>::new(),
             );
 
             Ok(())
@@ -282,24 +290,16 @@ async fn smoke_test() {
             let stuffs = t.object_store("stuffs")?;
 
             // Index
-            stuffs.add(&JsString::from("value3")).await?;
-            stuffs.put(&JsString::from("value2")).await?;
-            stuffs.add(&JsString::from("value1")).await?;
+            stuffs.add(&val::<B>("value3")).await?;
+            stuffs.put(&val::<B>("value2")).await?;
+            stuffs.add(&val::<B>("value1")).await?;
             assert_eq!(
                 stuffs.get_all(None).await?,
-                vec![
-                    (**JsString::from("value3")).clone(),
-                    (**JsString::from("value2")).clone(),
-                    (**JsString::from("value1")).clone()
-                ]
+                vec![val::<B>("value3"), val::<B>("value2"), val::<B>("value1")]
             );
             assert_eq!(
                 stuffs.index("contents").unwrap().get_all(None).await?,
-                vec![
-                    (**JsString::from("value1")).clone(),
-                    (**JsString::from("value2")).clone(),
-                    (**JsString::from("value3")).clone()
-                ]
+                vec![val::<B>("value1"), val::<B>("value2"), val::<B>("value3")]
             );
 
             // Cursors
@@ -313,9 +313,9 @@ async fn smoke_test() {
             assert_eq!(
                 all,
                 vec![
-                    (JsValue::from(3), (**JsString::from("value3")).clone()),
-                    (JsValue::from(4), (**JsString::from("value2")).clone()),
-                    (JsValue::from(5), (**JsString::from("value1")).clone())
+                    (Key::from(3), val::<B>("value3")),
+                    (Key::from(4), val::<B>("value2")),
+                    (Key::from(5), val::<B>("value1"))
                 ]
             );
             assert_eq!(stuffs.count().await.unwrap(), 0);
@@ -326,12 +326,11 @@ async fn smoke_test() {
         .unwrap();
 }
 
-#[wasm_bindgen_test]
-async fn auto_rollback() {
+pub async fn auto_rollback<B: TestBackend>() {
     // tracing_wasm::set_as_global_default();
     // std::panic::set_hook(Box::new(console_error_panic_hook::hook));
 
-    let factory = Factory::get().unwrap();
+    let factory = B::factory();
 
     let db = factory
         .open::<()>("baz", 1, async move |evt| {
@@ -344,8 +343,8 @@ async fn auto_rollback() {
     db.transaction(&["data"])
         .rw()
         .run::<_, ()>(async move |t| {
-            t.object_store("data")?.add(&JsString::from("foo")).await?;
-            t.object_store("data")?.add(&JsString::from("bar")).await?;
+            t.object_store("data")?.add(&val::<B>("foo")).await?;
+            t.object_store("data")?.add(&val::<B>("bar")).await?;
             if true {
                 // Something went wrong!
                 Err::<(), _>(())?;
@@ -358,7 +357,7 @@ async fn auto_rollback() {
     db.transaction(&["data"])
         .rw()
         .run::<_, ()>(async move |t| {
-            t.object_store("data")?.add(&JsString::from("baz")).await?;
+            t.object_store("data")?.add(&val::<B>("baz")).await?;
             Ok::<_, indexed_db::Error<()>>(())
         })
         .await
@@ -374,9 +373,73 @@ async fn auto_rollback() {
         .unwrap();
 }
 
-#[wasm_bindgen_test]
-async fn duplicate_insert_returns_proper_error_and_does_not_abort() {
-    let factory = Factory::get().unwrap();
+pub async fn abort_and_read_only_errors<B: TestBackend>() {
+    let factory = B::factory();
+    factory
+        .delete_database("abort_and_read_only")
+        .await
+        .unwrap();
+
+    let db = factory
+        .open::<()>("abort_and_read_only", 1, async |evt| {
+            evt.build_object_store("data").auto_increment().create()?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+    db.transaction(&["data"])
+        .rw()
+        .run::<_, ()>(async |t| {
+            t.object_store("data")?.add(&val::<B>("foo")).await?;
+            t.object_store("data")?.add(&val::<B>("bar")).await?;
+            Err::<(), _>(())?;
+            Ok(())
+        })
+        .await
+        .unwrap_err();
+
+    db.transaction(&["data"])
+        .rw()
+        .run::<_, ()>(async |t| {
+            let data = t.object_store("data")?;
+            data.add(&val::<B>("baz")).await?;
+            t.abort()?;
+            assert!(matches!(
+                data.count().await,
+                Err(Error::TransactionFinished(_))
+            ));
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+    // The key generator was rolled back too
+    db.transaction(&["data"])
+        .rw()
+        .run::<_, ()>(async |t| {
+            let data = t.object_store("data")?;
+            assert_eq!(data.add(&val::<B>("qux")).await?, Key::from(1));
+            assert_eq!(data.count().await?, 1);
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+    let err = db
+        .transaction(&["data"])
+        .run::<_, ()>(async |t| {
+            t.object_store("data")?.clear().await?;
+            Ok(())
+        })
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::ReadOnly(_)));
+    assert_eq!(err.context().unwrap().store(), Some("data"));
+}
+
+pub async fn duplicate_insert_returns_proper_error_and_does_not_abort<B: TestBackend>() {
+    let factory = B::factory();
 
     let db = factory
         .open::<()>("quux", 1, async move |evt| {
@@ -390,7 +453,7 @@ async fn duplicate_insert_returns_proper_error_and_does_not_abort() {
         .rw()
         .run::<_, ()>(async move |t| {
             t.object_store("data")?
-                .add_kv(&JsString::from("key1"), &JsString::from("foo"))
+                .add_kv("key1", &val::<B>("foo"))
                 .await?;
             Ok(())
         })
@@ -402,13 +465,13 @@ async fn duplicate_insert_returns_proper_error_and_does_not_abort() {
         .run::<_, ()>(async move |t| {
             assert!(matches!(
                 t.object_store("data")?
-                    .add_kv(&JsString::from("key1"), &JsString::from("bar"))
+                    .add_kv("key1", &val::<B>("bar"))
                     .await
                     .unwrap_err(),
-                indexed_db::Error::AlreadyExists
+                indexed_db::Error::AlreadyExists(_)
             ));
             t.object_store("data")?
-                .add_kv(&JsString::from("key2"), &JsString::from("baz"))
+                .add_kv("key2", &val::<B>("baz"))
                 .await?;
             Ok(())
         })
@@ -420,11 +483,11 @@ async fn duplicate_insert_returns_proper_error_and_does_not_abort() {
         .run::<_, ()>(async move |t| {
             assert_eq!(
                 t.object_store("data")?.get_all_keys(None).await?,
-                vec![JsValue::from("key1"), JsValue::from("key2")]
+                vec![Key::from("key1"), Key::from("key2")]
             );
             assert_eq!(
                 t.object_store("data")?.get_all(None).await?,
-                vec![JsValue::from("foo"), JsValue::from("baz")]
+                vec![val::<B>("foo"), val::<B>("baz")]
             );
             Ok(())
         })
@@ -432,38 +495,97 @@ async fn duplicate_insert_returns_proper_error_and_does_not_abort() {
         .unwrap();
 }
 
-#[wasm_bindgen_test]
-async fn typed_array_keys() {
-    let factory = Factory::get().unwrap();
+pub async fn key_ordering_and_auto_increment<B: TestBackend>() {
+    let factory = B::factory();
+    factory.delete_database("auto_increment").await.unwrap();
 
     let db = factory
-        .open::<()>("db12", 1, async move |evt| {
+        .open::<()>("auto_increment", 1, async |evt| {
             evt.build_object_store("data").create()?;
+            evt.build_object_store("generated")
+                .key_path("id")
+                .auto_increment()
+                .create()?;
             Ok(())
         })
         .await
         .unwrap();
 
-    db.transaction(&["data"])
+    db.transaction(&["data", "generated"])
         .rw()
-        .run::<_, ()>(async move |t| {
+        .run::<_, ()>(async |t| {
             let data = t.object_store("data")?;
-            data.add_kv(&Uint8Array::from(&b"key1"[..]), &JsString::from("foo"))
-                .await?;
-            data.add_kv(&Uint8Array::from(&b"key2"[..]), &JsString::from("bar"))
-                .await?;
-            data.add_kv(&Uint8Array::from(&b"key3"[..]), &JsString::from("baz"))
-                .await?;
+            let keys = [
+                Key::Array(vec![Key::from(1)]),
+                Key::Binary(vec![1, 2]),
+                Key::from("b"),
+                Key::from("a"),
+                Key::Date(0.0),
+                Key::from(2),
+                Key::from(-1),
+            ];
+            for key in &keys {
+                data.add_kv(key, &B::null()).await?;
+            }
             assert_eq!(
-                2,
-                data.count_in(Uint8Array::from(&b"key2"[..]).as_ref()..)
-                    .await?
+                data.get_all_keys(None).await?,
+                vec![
+                    Key::from(-1),
+                    Key::from(2),
+                    Key::Date(0.0),
+                    Key::from("a"),
+                    Key::from("b"),
+                    Key::Binary(vec![1, 2]),
+                    Key::Array(vec![Key::from(1)]),
+                ]
             );
+            assert!(matches!(
+                data.add_kv(Key::Number(f64::NAN), &B::null()).await,
+                Err(Error::InvalidKey(_))
+            ));
+
+            let generated = t.object_store("generated")?;
+            let first = generated
+                .add(&B::object(vec![("name", val::<B>("first"))]))
+                .await?;
+            assert_eq!(first, Key::from(1));
+            let stored = generated.get(&first).await?.unwrap();
+            assert_eq!(B::field(&stored, "id"), Some(val::<B>(1)));
+            generated
+                .put(&B::object(vec![("id", val::<B>(10))]))
+                .await?;
             assert_eq!(
-                1,
-                data.count_in(..Uint8Array::from(&b"key2"[..]).as_ref())
-                    .await?
+                generated
+                    .add(&B::object(vec![("name", val::<B>("next"))]))
+                    .await?,
+                Key::from(11)
             );
+            Ok(())
+        })
+        .await
+        .unwrap();
+}
+
+pub async fn typed_array_keys<B: TestBackend>() {
+    let factory = B::factory();
+
+    let db = factory
+        .open::<()>("db12", 1, async move |evt| {
+            evt.build_object_store("data").create()?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+    db.transaction(&["data"])
+        .rw()
+        .run::<_, ()>(async move |t| {
+            let data = t.object_store("data")?;
+            data.add_kv(&b"key1"[..], &val::<B>("foo")).await?;
+            data.add_kv(&b"key2"[..], &val::<B>("bar")).await?;
+            data.add_kv(&b"key3"[..], &val::<B>("baz")).await?;
+            assert_eq!(2, data.count_in(Key::from(&b"key2"[..])..).await?);
+            assert_eq!(1, data.count_in(..Key::from(&b"key2"[..])).await?);
 
             Ok(())
         })
@@ -471,9 +593,8 @@ async fn typed_array_keys() {
         .unwrap();
 }
 
-#[wasm_bindgen_test]
-async fn borrowing_transaction() {
-    let factory = Factory::get().unwrap();
+pub async fn borrowing_transaction<B: TestBackend>() {
+    let factory = B::factory();
 
     let db = factory
         .open::<Infallible>("foo42", 1, async move |evt| {
@@ -487,11 +608,8 @@ async fn borrowing_transaction() {
         .rw()
         .run::<_, Infallible>(async |t| {
             let data = t.object_store("data")?;
-            data.add_kv(
-                &JsString::from("data"),
-                &JsString::from(&data_from_outside as &str),
-            )
-            .await?;
+            data.add_kv("data", &val::<B>(&data_from_outside as &str))
+                .await?;
             Ok(())
         })
         .await
@@ -500,12 +618,977 @@ async fn borrowing_transaction() {
         .run::<_, Infallible>(async |t| {
             let data = t.object_store("data")?;
             assert_eq!(
-                data.get(&JsString::from("data"))
-                    .await?
-                    .unwrap()
-                    .as_string()
-                    .unwrap(),
-                data_from_outside
+                data.get("data").await?.unwrap(),
+                val::<B>(data_from_outside.as_str())
+            );
+            Ok(())
+        })
+        .await
+        .unwrap();
+}
+
+pub async fn list_databases<B: TestBackend>() {
+    let factory = B::factory();
+
+    factory.delete_database("list_databases").await.unwrap();
+    let db = factory
+        .open::<Infallible>("list_databases", 3, async move |_| Ok(()))
+        .await
+        .unwrap();
+
+    let databases = factory.databases().await.unwrap();
+    let info = databases
+        .iter()
+        .find(|info| info.name == "list_databases")
+        .expect("Newly-created database was not listed");
+    assert_eq!(info.version, 3);
+
+    db.close();
+    factory.delete_database("list_databases").await.unwrap();
+    assert!(!factory
+        .databases()
+        .await
+        .unwrap()
+        .iter()
+        .any(|info| info.name == "list_databases"));
+}
+
+pub async fn schema_migrations<B: TestBackend>() {
+    let factory = B::factory();
+    factory.delete_database("schema_migrations").await.unwrap();
+
+    let v1 = Schema::new().version([Migration::create_store(
+        StoreSchema::new("users")
+            .key_path("id")
+            .index(IndexSchema::new("by_email", "email").unique()),
+    )]);
+    let db = factory
+        .open_with_schema("schema_migrations", &v1)
+        .await
+        .unwrap();
+    assert_eq!(db.version(), 1);
+    assert_eq!(db.object_store_names(), &["users"]);
+    db.close();
+
+    // Only the second version runs when upgrading
+    let v2 = v1.clone().version([
+        Migration::create_store(StoreSchema::new("logs").auto_increment()),
+        Migration::create_index("users", IndexSchema::new("by_tag", "tags").multi_entry()),
+        Migration::delete_index("users", "by_email"),
+    ]);
+    let db = factory
+        .open_with_schema("schema_migrations", &v2)
+        .await
+        .unwrap();
+    assert_eq!(db.version(), 2);
+    assert_eq!(db.object_store_names(), &["logs", "users"]);
+    db.close();
+
+    // Reopening at the same version checks the schema without upgrading
+    let db = factory
+        .open_with_schema("schema_migrations", &v2)
+        .await
+        .unwrap();
+    db.close();
+
+    // Snapshots of the database match the declarative schema
+    let db = factory
+        .open_with_schema("schema_migrations", &v2)
+        .await
+        .unwrap();
+    let snapshot = db.schema_snapshot().await.unwrap();
+    assert_eq!(snapshot, v2.snapshot());
+    let diff = v1.snapshot().diff(&snapshot);
+    assert_eq!((diff.old_version, diff.new_version), (1, 2));
+    assert_eq!(diff.added_stores.len(), 1);
+    assert_eq!(diff.changed_stores[0].added_indexes[0].name, "by_tag");
+    assert_eq!(diff.changed_stores[0].removed_indexes[0].name, "by_email");
+    assert!(!diff.changed_stores[0].key_changed());
+    db.close();
+
+    // A schema that does not match what is in the database is rejected
+    let wrong = Schema::new()
+        .version([Migration::create_store(StoreSchema::new("users"))])
+        .version([Migration::create_store(StoreSchema::new("logs"))]);
+    let res = factory.open_with_schema("schema_migrations", &wrong).await;
+    assert!(
+        matches!(res, Err(Error::SchemaMismatch)),
+        "Unexpected result: {res:?}"
+    );
+}
+
+pub async fn store_and_index_introspection<B: TestBackend>() {
+    let factory = B::factory();
+    factory.delete_database("introspection").await.unwrap();
+
+    let db = factory
+        .open::<Infallible>("introspection", 1, async move |evt| {
+            let store = evt.build_object_store("objects").key_path("id").create()?;
+            store.build_index("by_name", "name").unique().create()?;
+            store
+                .build_compound_index("by_tags", &["tag1", "tag2"])
+                .create()?;
+            evt.build_object_store("auto").auto_increment().create()?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+    db.transaction(&["objects", "auto"])
+        .run::<_, Infallible>(async move |t| {
+            let objects = t.object_store("objects")?;
+            assert_eq!(objects.name(), "objects");
+            assert_eq!(objects.key_path()?, Some(KeyPath::from("id")));
+            assert!(!objects.auto_increment());
+            assert_eq!(objects.index_names(), &["by_name", "by_tags"]);
+
+            let by_name = objects.index("by_name")?;
+            assert_eq!(by_name.name(), "by_name");
+            assert_eq!(by_name.key_path()?, KeyPath::Single(String::from("name")));
+            assert!(by_name.is_unique());
+            assert!(!by_name.is_multi_entry());
+
+            let by_tags = objects.index("by_tags")?;
+            assert_eq!(by_tags.key_path()?, KeyPath::from(&["tag1", "tag2"][..]));
+            assert!(!by_tags.is_unique());
+
+            let auto = t.object_store("auto")?;
+            assert_eq!(auto.key_path()?, None);
+            assert!(auto.auto_increment());
+            assert!(auto.index_names().is_empty());
+            Ok(())
+        })
+        .await
+        .unwrap();
+}
+
+pub async fn unique_and_multi_entry_indexes<B: TestBackend>() {
+    let factory = B::factory();
+    factory
+        .delete_database("unique_and_multi_entry")
+        .await
+        .unwrap();
+
+    let db = factory
+        .open::<()>("unique_and_multi_entry", 1, async |evt| {
+            let users = evt.build_object_store("users").key_path("id").create()?;
+            users.build_index("name", "name").unique().create()?;
+            users.build_index("tags", "tags").multi_entry().create()?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+    db.transaction(&["users"])
+        .rw()
+        .run::<_, ()>(async |t| {
+            let users = t.object_store("users")?;
+            users.add(&user::<B>(1, "Alice", &["admin", "dev"])).await?;
+            users.add(&user::<B>(2, "Bob", &["dev", "dev"])).await?;
+            let err = users.add(&user::<B>(3, "Alice", &[])).await.unwrap_err();
+            assert!(matches!(err, Error::AlreadyExists(_)));
+            assert_eq!(users.count().await?, 2);
+            // Overwriting a record can keep its own unique key
+            users.put(&user::<B>(1, "Alice", &["admin"])).await?;
+
+            let name = users.index("name")?;
+            assert!(name.is_unique());
+            assert_eq!(
+                name.get("Bob").await?.as_ref().map(json::<B>),
+                Some(json::<B>(&user::<B>(2, "Bob", &["dev", "dev"])))
+            );
+
+            let tags = users.index("tags")?;
+            assert!(tags.is_multi_entry());
+            assert_eq!(tags.count_in(..).await?, 2);
+            assert_eq!(
+                tags.get_all_keys_in(Key::from("dev")..=Key::from("dev"), None)
+                    .await?,
+                vec![Key::from(2)]
+            );
+            assert_eq!(
+                tags.get_first_key_in(Key::from("admin")..).await?,
+                Some(Key::from(1))
+            );
+            Ok(())
+        })
+        .await
+        .unwrap();
+}
+
+#[cfg(feature = "serde")]
+pub async fn typed_object_store<B: TestBackend>() {
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct User {
+        id: u64,
+        name: String,
+        tags: Vec<String>,
+    }
+
+    let factory = B::factory();
+    factory.delete_database("typed").await.unwrap();
+
+    let db = factory
+        .open::<Infallible>("typed", 1, async move |evt| {
+            evt.build_object_store("users").key_path("id").create()?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+    db.transaction(&["users"])
+        .rw()
+        .run::<_, Infallible>(async move |t| {
+            let users = t.typed_store::<u64, User>("users")?;
+            for id in 1..=3 {
+                let user = User {
+                    id,
+                    name: format!("user {id}"),
+                    tags: vec![String::from("tag")],
+                };
+                assert_eq!(users.add(&user).await?, id);
+            }
+            assert!(matches!(
+                users
+                    .add(&User {
+                        id: 1,
+                        name: String::from("duplicate"),
+                        tags: Vec::new(),
+                    })
+                    .await,
+                Err(Error::AlreadyExists(_))
+            ));
+            assert_eq!(users.get(&2).await?.unwrap().name, "user 2");
+            assert_eq!(users.get(&4).await?, None);
+            assert_eq!(users.get_all(None).await?.len(), 3);
+            let in_range = users.get_all_in(2..=3, None).await?;
+            assert_eq!(in_range.iter().map(|u| u.id).collect::<Vec<_>>(), [2, 3]);
+
+            let mut cursor = users.cursor().range(2..)?.open().await?;
+            assert_eq!(cursor.key()?, Some(2));
+            let mut user = cursor.value()?.unwrap();
+            user.name = String::from("renamed");
+            cursor.update(&user).await?;
+            cursor.advance(1).await?;
+            assert_eq!(cursor.key()?, Some(3));
+            cursor.advance(1).await?;
+            assert_eq!(cursor.value()?, None);
+            assert_eq!(users.get(&2).await?.unwrap().name, "renamed");
+
+            users.delete(&1).await?;
+            assert_eq!(users.count().await?, 2);
+
+            // Values that do not match the expected type are reported as such
+            let wrong = t.typed_store::<u64, String>("users")?;
+            assert!(matches!(wrong.get(&2).await, Err(Error::Serialization(_))));
+            Ok(())
+        })
+        .await
+        .unwrap();
+}
+
+pub async fn unbounded_and_key_ranges<B: TestBackend>() {
+    let factory = B::factory();
+    factory.delete_database("key_ranges").await.unwrap();
+
+    let db = factory
+        .open::<Infallible>("key_ranges", 1, async move |evt| {
+            evt.build_object_store("objects").create()?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+    db.transaction(&["objects"])
+        .rw()
+        .run::<_, Infallible>(async move |t| {
+            let objects = t.object_store("objects")?;
+            for i in 0..5 {
+                objects.put_kv(i, &val::<B>(i * 10)).await?;
+            }
+
+            // The unbounded range selects everything
+            assert_eq!(objects.count_in(..).await?, 5);
+            assert_eq!(objects.get_all_in(.., Some(2)).await?.len(), 2);
+            assert_eq!(objects.get_all_keys_in(.., None).await?.len(), 5);
+            assert_eq!(objects.get_first_in(..).await?, Some(val::<B>(0)));
+            assert_eq!(objects.get_first_key_in(..).await?, Some(Key::from(0)));
+            let cursor = objects.cursor().range(..)?.open().await?;
+            assert_eq!(cursor.key(), Some(Key::from(0)));
+
+            // KeyRange can be used wherever a range is accepted
+            assert_eq!(objects.count_in(KeyRange::only(3)).await?, 1);
+            assert_eq!(objects.count_in(KeyRange::greater_than(3)).await?, 1);
+            assert_eq!(objects.count_in(KeyRange::at_most(3)).await?, 4);
+            assert_eq!(objects.count_in(KeyRange::all()).await?, 5);
+            objects.delete_range(KeyRange::less_than(2)).await?;
+            assert_eq!(objects.count().await?, 3);
+            objects.delete_range(..).await?;
+            assert_eq!(objects.count().await?, 0);
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+    let range = KeyRange::at_least("b");
+    assert!(range.includes(&Key::from("c")).unwrap());
+    assert!(!range.includes(&Key::from("a")).unwrap());
+    assert!(!range.includes(&Key::from(1)).unwrap());
+    assert!(KeyRange::all().includes(&Key::from(1)).unwrap());
+    assert!(matches!(
+        KeyRange::only(1).includes(&Key::from(f64::NAN)),
+        Err(Error::InvalidKey(_))
+    ));
+    assert!(matches!(
+        KeyRange::new(Bound::Included(Key::from(2)), Bound::Excluded(Key::from(1)))
+            .includes(&Key::from(1)),
+        Err(Error::InvalidKey(_))
+    ));
+}
+
+pub async fn prefix_ranges<B: TestBackend>() {
+    let factory = B::factory();
+    factory.delete_database("prefix_ranges").await.unwrap();
+
+    let db = factory
+        .open::<Infallible>("prefix_ranges", 1, async move |evt| {
+            evt.build_object_store("objects").create()?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+    db.transaction(&["objects"])
+        .rw()
+        .run::<_, Infallible>(async move |t| {
+            let objects = t.object_store("objects")?;
+            let keys = [
+                Key::from(1),
+                Key::from("user:4"),
+                Key::from("user:42"),
+                Key::from("user:42:a"),
+                Key::from("user:42:\u{FFFF}"),
+                Key::from("user:43"),
+                Key::from("\u{FFFF}"),
+                Key::from(vec![1u8, 255]),
+                Key::from(vec![1u8, 255, 0]),
+                Key::from(vec![2u8]),
+                Key::from((1, "a")),
+                Key::from((1, "a", 3)),
+                Key::from((1, "a", Key::from(vec![Key::from(1)]))),
+                Key::from((1, "a\0")),
+                Key::from((1, "b")),
+                Key::from((2,)),
+            ];
+            for key in &keys {
+                objects.put_kv(key, &B::null()).await?;
+            }
+            let count = async |range| objects.count_in(range).await;
+
+            assert_eq!(count(KeyRange::prefix("user:42")).await?, 3);
+            assert_eq!(count(KeyRange::prefix("user:42:")).await?, 2);
+            assert_eq!(count(KeyRange::prefix("\u{FFFF}")).await?, 1);
+            assert_eq!(count(KeyRange::prefix("")).await?, 6);
+            assert_eq!(count(KeyRange::prefix(vec![1u8, 255])).await?, 2);
+            assert_eq!(count(KeyRange::prefix(Vec::<u8>::new())).await?, 3);
+            assert_eq!(count(KeyRange::prefix((1, "a"))).await?, 3);
+            assert_eq!(count(KeyRange::prefix(vec![Key::from(1)])).await?, 5);
+            assert_eq!(count(KeyRange::prefix(Vec::<Key>::new())).await?, 6);
+            assert_eq!(count(KeyRange::prefix(1)).await?, 1);
+
+            let in_prefix = objects
+                .get_all_keys_in(KeyRange::prefix("user:42"), None)
+                .await?;
+            assert_eq!(
+                in_prefix,
+                [
+                    Key::from("user:42"),
+                    Key::from("user:42:a"),
+                    Key::from("user:42:\u{FFFF}"),
+                ]
+            );
+
+            objects.delete_range(KeyRange::prefix((1,))).await?;
+            assert_eq!(objects.count().await?, 11);
+            Ok(())
+        })
+        .await
+        .unwrap();
+}
+
+pub async fn cursor_streams<B: TestBackend>() {
+    let factory = B::factory();
+    factory.delete_database("cursor_streams").await.unwrap();
+
+    let db = factory
+        .open::<Infallible>("cursor_streams", 1, async move |evt| {
+            evt.build_object_store("objects").create()?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+    db.transaction(&["objects"])
+        .rw()
+        .run::<_, Infallible>(async move |t| {
+            let objects = t.object_store("objects")?;
+            for i in 0..10 {
+                objects.put_kv(i, &val::<B>(i * 10)).await?;
+            }
+
+            let all = objects.cursor().stream().try_collect::<Vec<_>>().await?;
+            assert_eq!(all.len(), 10);
+            assert_eq!(all[3].key, Key::from(3));
+            assert_eq!(all[3].primary_key, Key::from(3));
+            assert_eq!(all[3].value, val::<B>(30));
+
+            let values = objects
+                .cursor()
+                .range(Key::from(2)..)?
+                .direction(CursorDirection::Prev)
+                .stream()
+                .try_filter(|e| std::future::ready(number::<B>(&e.value).unwrap() % 20.0 == 0.0))
+                .map_ok(|e| e.value)
+                .take(3)
+                .try_collect::<Vec<_>>()
+                .await?;
+            assert_eq!(values, [80, 60, 40].map(val::<B>));
+
+            // Streams keep working while other requests are made in the same transaction
+            let cursor = objects.cursor().open().await?;
+            let mut stream = std::pin::pin!(cursor.into_stream());
+            while let Some(entry) = stream.next().await {
+                objects.delete(&entry?.key).await?;
+            }
+            assert_eq!(objects.count().await?, 0);
+
+            // An empty range yields an empty stream
+            let mut stream = std::pin::pin!(objects.cursor().stream());
+            assert!(stream.next().await.is_none());
+            Ok(())
+        })
+        .await
+        .unwrap();
+}
+
+pub async fn key_cursors<B: TestBackend>() {
+    let factory = B::factory();
+    factory.delete_database("key_cursors").await.unwrap();
+
+    let db = factory
+        .open::<Infallible>("key_cursors", 1, async move |evt| {
+            let objects = evt.build_object_store("objects").create()?;
+            objects.build_index("by_value", "").create()?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+    db.transaction(&["objects"])
+        .rw()
+        .run::<_, Infallible>(async move |t| {
+            let objects = t.object_store("objects")?;
+            for i in 0..5 {
+                objects.put_kv(i, &val::<B>(10 - i)).await?;
+            }
+
+            let mut cursor = objects.cursor().open_key().await?;
+            assert_eq!(cursor.key(), Some(Key::from(0)));
+            assert_eq!(cursor.primary_key(), Some(Key::from(0)));
+            cursor.advance_until(3).await?;
+            assert_eq!(cursor.key(), Some(Key::from(3)));
+            cursor.advance(2).await?;
+            assert_eq!(cursor.key(), None);
+            assert!(matches!(
+                cursor.advance(1).await,
+                Err(Error::CursorCompleted)
+            ));
+
+            let by_value = objects.index("by_value")?;
+            let entries = by_value
+                .cursor()
+                .open_key()
+                .await?
+                .into_stream()
+                .try_collect::<Vec<_>>()
+                .await?;
+            assert_eq!(
+                entries.iter().map(|e| e.key.clone()).collect::<Vec<_>>(),
+                [6, 7, 8, 9, 10].map(Key::from)
+            );
+            assert_eq!(
+                entries
+                    .iter()
+                    .map(|e| e.primary_key.clone())
+                    .collect::<Vec<_>>(),
+                [4, 3, 2, 1, 0].map(Key::from)
+            );
+            Ok(())
+        })
+        .await
+        .unwrap();
+}
+
+pub async fn cursor_directions<B: TestBackend>() {
+    let factory = B::factory();
+    factory.delete_database("cursor_directions").await.unwrap();
+
+    let db = factory
+        .open::<()>("cursor_directions", 1, async |evt| {
+            let users = evt.build_object_store("users").key_path("id").create()?;
+            users.build_index("name", "name").create()?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+    db.transaction(&["users"])
+        .rw()
+        .run::<_, ()>(async |t| {
+            let users = t.object_store("users")?;
+            users
+                .add_many([
+                    user::<B>(1, "b", &[]),
+                    user::<B>(2, "a", &[]),
+                    user::<B>(3, "b", &[]),
+                    user::<B>(4, "c", &[]),
+                    user::<B>(5, "a", &[]),
+                ])
+                .await?;
+            let index = users.index("name")?;
+
+            let primary_keys = async |direction| {
+                index
+                    .cursor()
+                    .direction(direction)
+                    .open_key()
+                    .await?
+                    .into_stream()
+                    .map_ok(|e| e.primary_key)
+                    .try_collect::<Vec<_>>()
+                    .await
+            };
+            let keys = |ks: &[u32]| ks.iter().map(|k| Key::from(*k)).collect::<Vec<_>>();
+            assert_eq!(
+                primary_keys(CursorDirection::Next).await?,
+                keys(&[2, 5, 1, 3, 4])
+            );
+            assert_eq!(
+                primary_keys(CursorDirection::Prev).await?,
+                keys(&[4, 3, 1, 5, 2])
+            );
+            assert_eq!(
+                primary_keys(CursorDirection::NextUnique).await?,
+                keys(&[2, 1, 4])
+            );
+            assert_eq!(
+                primary_keys(CursorDirection::PrevUnique).await?,
+                keys(&[4, 1, 2])
+            );
+
+            let mut cursor = users
+                .cursor()
+                .direction(CursorDirection::Prev)
+                .range(Key::from(2)..Key::from(5))?
+                .open()
+                .await?;
+            assert_eq!(cursor.key(), Some(Key::from(4)));
+            cursor.advance(2).await?;
+            assert_eq!(
+                cursor.value().as_ref().map(json::<B>),
+                Some(json::<B>(&user::<B>(2, "a", &[])))
+            );
+            cursor.advance(1).await?;
+            assert_eq!(cursor.key(), None);
+            assert!(matches!(
+                cursor.advance(1).await,
+                Err(Error::CursorCompleted)
+            ));
+
+            let mut cursor = index.cursor().open().await?;
+            cursor.advance_until("b").await?;
+            assert_eq!(cursor.primary_key(), Some(Key::from(1)));
+            cursor.advance_until_primary_key("b", 2).await?;
+            assert_eq!(cursor.primary_key(), Some(Key::from(3)));
+            cursor.update(&user::<B>(3, "d", &[])).await?;
+            cursor.advance(1).await?;
+            assert_eq!(cursor.key(), Some(Key::from("c")));
+            cursor.delete().await?;
+            assert_eq!(index.get_all_keys(None).await?, keys(&[2, 5, 1, 3]));
+            Ok(())
+        })
+        .await
+        .unwrap();
+}
+
+pub async fn update_all_with_cursor<B: TestBackend>() {
+    let factory = B::factory();
+    factory.delete_database("update_all").await.unwrap();
+
+    let db = factory
+        .open::<()>("update_all", 1, async |evt| {
+            let store = evt.build_object_store("users").key_path("id").create()?;
+            store.build_index("name", "name").unique().create()?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+    let renamed = db
+        .transaction(&["users"])
+        .rw()
+        .run::<_, ()>(async |t| {
+            let store = t.object_store("users")?;
+            for user in [user::<B>(1, "alice", &[]), user::<B>(2, "bob", &[])] {
+                store.add(&user).await?;
+            }
+            let mut cursor = store.cursor().open().await?;
+            while let Some(value) = cursor.value() {
+                let id = B::field(&value, "id").unwrap().to_key().unwrap();
+                let Some(Key::String(name)) = B::field(&value, "name").unwrap().to_key() else {
+                    panic!("The name is not a string");
+                };
+                let renamed = B::object(vec![
+                    ("id", B::Value::from_key(&id)),
+                    ("name", val::<B>(name.to_uppercase())),
+                    ("tags", B::array(Vec::new())),
+                ]);
+                cursor.update(&renamed).await?;
+                cursor.advance(1).await?;
+            }
+            store.get_all_in(.., None).await
+        })
+        .await
+        .unwrap();
+    assert_eq!(
+        renamed.iter().map(json::<B>).collect::<Vec<_>>(),
+        [user::<B>(1, "ALICE", &[]), user::<B>(2, "BOB", &[])]
+            .iter()
+            .map(json::<B>)
+            .collect::<Vec<_>>()
+    );
+}
+
+pub async fn run_waits_for_completion<B: TestBackend>() {
+    let factory = B::factory();
+    factory.delete_database("run_waits").await.unwrap();
+
+    let db = factory
+        .open::<Infallible>("run_waits", 1, async move |evt| {
+            evt.build_object_store("objects").create()?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+    let other = factory.open_latest_version("run_waits").await.unwrap();
+
+    // Once `run` returned, the changes are visible to other connections
+    db.transaction(&["objects"])
+        .rw()
+        .run::<_, Infallible>(async move |t| {
+            let objects = t.object_store("objects")?;
+            objects.put_kv(1, &val::<B>("saved")).await?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+    other
+        .transaction(&["objects"])
+        .run::<_, Infallible>(async move |t| {
+            let objects = t.object_store("objects")?;
+            assert_eq!(objects.get(1).await?, Some(val::<B>("saved")));
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+    // An explicit abort returns the result of the closure
+    let res = db
+        .transaction(&["objects"])
+        .rw()
+        .run::<_, Infallible>(async move |t| {
+            t.object_store("objects")?.clear().await?;
+            t.abort()?;
+            Ok(42)
+        })
+        .await
+        .unwrap();
+    assert_eq!(res, 42);
+
+    // `run_no_wait` returns without waiting for the completion
+    let res = db
+        .transaction(&["objects"])
+        .rw()
+        .run_no_wait::<_, Infallible>(async move |t| {
+            let objects = t.object_store("objects")?;
+            objects.put_kv(2, &val::<B>("saved")).await?;
+            objects.count().await
+        })
+        .await
+        .unwrap();
+    assert_eq!(res, 2);
+    other.close();
+}
+
+pub async fn transaction_durability<B: TestBackend>() {
+    let factory = B::factory();
+    factory.delete_database("durability").await.unwrap();
+
+    let db = factory
+        .open::<Infallible>("durability", 1, async move |evt| {
+            evt.build_object_store("objects").create()?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+    for (i, durability) in [Durability::Default, Durability::Strict, Durability::Relaxed]
+        .into_iter()
+        .enumerate()
+    {
+        db.transaction(&["objects"])
+            .rw()
+            .durability(durability)
+            .run::<_, Infallible>(async move |t| {
+                let objects = t.object_store("objects")?;
+                objects.put_kv(i as u32, &val::<B>("value")).await?;
+                Ok(())
+            })
+            .await
+            .unwrap();
+    }
+
+    db.transaction(&["objects"])
+        .durability(Durability::Relaxed)
+        .run::<_, Infallible>(async move |t| {
+            assert_eq!(t.object_store("objects")?.count().await?, 3);
+            Ok(())
+        })
+        .await
+        .unwrap();
+}
+
+pub async fn run_local_futures<B: TestBackend>() {
+    let factory = B::factory();
+    factory.delete_database("run_local").await.unwrap();
+
+    let db = factory
+        .open::<Infallible>("run_local", 1, async move |evt| {
+            evt.build_object_store("data").create()?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+    async fn checksum(data: &str) -> u32 {
+        futures::future::ready(data.bytes().map(u32::from).sum()).await
+    }
+
+    let (tx, rx) = futures_channel::oneshot::channel::<u32>();
+    tx.send(42).unwrap();
+    let (_never_tx, never_rx) = futures_channel::oneshot::channel::<u32>();
+    db.transaction(&["data"])
+        .rw()
+        .run::<_, Infallible>(async move |t| {
+            let data = t.object_store("data")?;
+            let sum = t.run_local(checksum("foo"))?;
+            data.put_kv("foo", &val::<B>(sum)).await?;
+            assert_eq!(t.run_local(rx)?, Ok(42));
+            assert!(matches!(
+                t.run_local(never_rx),
+                Err(Error::LocalFutureNotReady)
+            ));
+            let mut yields = 0;
+            let yielding = futures::future::poll_fn(|cx| {
+                yields += 1;
+                if yields < 3 {
+                    cx.waker().wake_by_ref();
+                    return Poll::Pending;
+                }
+                Poll::Ready(yields)
+            });
+            assert_eq!(t.run_local(yielding)?, 3);
+            let always_yielding = futures::future::poll_fn(|cx| {
+                cx.waker().wake_by_ref();
+                Poll::<()>::Pending
+            });
+            assert!(matches!(
+                t.run_local(always_yielding),
+                Err(Error::LocalFutureNotReady)
+            ));
+            // The transaction is still usable
+            assert_eq!(data.get("foo").await?, Some(val::<B>(324)));
+            Ok(())
+        })
+        .await
+        .unwrap();
+}
+
+pub async fn batch_writes<B: TestBackend>() {
+    let factory = B::factory();
+    factory.delete_database("batch_writes").await.unwrap();
+
+    let db = factory
+        .open::<Infallible>("batch_writes", 1, async move |evt| {
+            evt.build_object_store("auto").auto_increment().create()?;
+            evt.build_object_store("kv").create()?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+    db.transaction(&["auto", "kv"])
+        .rw()
+        .run::<_, Infallible>(async move |t| {
+            let auto = t.object_store("auto")?;
+            let values = (0..1000).map(val::<B>).collect::<Vec<_>>();
+            let keys = auto.put_many(&values).await?;
+            assert_eq!(keys.len(), 1000);
+            assert_eq!(keys[999], Key::from(1000));
+            assert_eq!(auto.count().await?, 1000);
+            assert!(auto.add_many(Vec::<B::Value>::new()).await?.is_empty());
+
+            let kv = t.object_store("kv")?;
+            kv.put_kv_many((0..10).map(|i| (i, val::<B>(i * 2))))
+                .await?;
+            assert_eq!(kv.get(3).await?, Some(val::<B>(6)));
+
+            // The first failure is reported with its position, the others still go through
+            let err = kv
+                .add_kv_many([(10, 0), (11, 0), (3, 0), (12, 0)].map(|(k, v)| (k, val::<B>(v))))
+                .await
+                .unwrap_err();
+            assert!(
+                matches!(&err, Error::Batch { index: 2, error } if matches!(**error, Error::AlreadyExists(_))),
+                "{err:?}"
+            );
+            assert_eq!(err.context().unwrap().store(), Some("kv"));
+            assert_eq!(kv.count().await?, 13);
+
+            // Synchronous failures also stop the batch
+            let err = kv
+                .put_kv_many([(Key::from(20), val::<B>(0)), (Key::from(f64::NAN), val::<B>(0))])
+                .await
+                .unwrap_err();
+            assert!(
+                matches!(&err, Error::Batch { index: 1, error } if matches!(**error, Error::InvalidKey(_))),
+                "{err:?}"
+            );
+
+            kv.delete_many(0..10).await?;
+            assert_eq!(kv.count().await?, 4);
+            Ok(())
+        })
+        .await
+        .unwrap();
+}
+
+pub async fn read_modify_write<B: TestBackend>() {
+    let factory = B::factory();
+    factory.delete_database("read_modify_write").await.unwrap();
+
+    let db = factory
+        .open::<Infallible>("read_modify_write", 1, async move |evt| {
+            evt.build_object_store("counters").create()?;
+            evt.build_object_store("users")
+                .key_path("id")
+                .auto_increment()
+                .create()?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+    db.transaction(&["counters"])
+        .rw()
+        .run::<_, Infallible>(async move |t| {
+            let counters = t.object_store("counters")?;
+            let key = Key::from("hits");
+            let incr = |v: Option<B::Value>| {
+                Some(val::<B>(v.and_then(|v| number::<B>(&v)).unwrap_or(0.) + 1.))
+            };
+            assert_eq!(
+                counters.update_with(&key, incr).await?,
+                (None, Some(val::<B>(1)))
+            );
+            assert_eq!(
+                counters.update_with(&key, incr).await?,
+                (Some(val::<B>(1)), Some(val::<B>(2)))
+            );
+            assert_eq!(
+                counters.update_with(&key, |_| None).await?,
+                (Some(val::<B>(2)), None)
+            );
+            assert_eq!(counters.count().await?, 0);
+
+            // Objects are compared by contents
+            let state = B::object(vec![("cursor", val::<B>(12))]);
+            let expected = B::object(vec![("cursor", val::<B>(12))]);
+            let (previous, new) = counters.compare_and_swap(&key, None, Some(&state)).await?;
+            assert!(previous.is_none() && new.is_some());
+            let (previous, new) = counters
+                .compare_and_swap(&key, Some(&expected), Some(&val::<B>(13)))
+                .await?;
+            assert!(previous.is_some());
+            assert_eq!(new, Some(val::<B>(13)));
+            let (previous, new) = counters
+                .compare_and_swap(&key, Some(&expected), None)
+                .await?;
+            assert_eq!((previous, new), (Some(val::<B>(13)), Some(val::<B>(13))));
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+    // With in-line keys, the new value must keep the key of the record
+    db.transaction(&["users"])
+        .rw()
+        .run::<_, Infallible>(async move |t| {
+            let users = t.object_store("users")?;
+            users.put(&user::<B>(1, "Alice", &[])).await?;
+            let (_, new) = users
+                .update_with(1, |_| Some(user::<B>(1, "Bob", &[])))
+                .await?;
+            assert_eq!(
+                new.as_ref().map(json::<B>),
+                Some(json::<B>(&user::<B>(1, "Bob", &[])))
+            );
+
+            let err = users
+                .update_with(1, |_| Some(user::<B>(2, "Carol", &[])))
+                .await
+                .unwrap_err();
+            assert!(matches!(err, Error::InvalidKey(_)), "{err:?}");
+            assert_eq!(err.context().unwrap().key(), Some(&Key::from(1)));
+            let without_id = B::object(vec![("name", val::<B>("Dave"))]);
+            let err = users
+                .update_with(1, |_| Some(without_id))
+                .await
+                .unwrap_err();
+            assert!(matches!(err, Error::InvalidKey(_)), "{err:?}");
+            let res = users
+                .compare_and_swap(1, None, Some(&user::<B>(3, "Eve", &[])))
+                .await;
+            assert!(res.is_ok(), "A mismatch is not an error: {res:?}");
+
+            assert_eq!(users.count().await?, 1);
+            assert_eq!(
+                users.get(1).await?.as_ref().map(json::<B>),
+                Some(json::<B>(&user::<B>(1, "Bob", &[])))
+            );
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+    db.transaction(&["counters"])
+        .run::<_, Infallible>(async move |t| {
+            let counters = t.object_store("counters")?;
+            let err = counters.update_with("hits", |_| None).await.unwrap_err();
+            assert!(matches!(err, Error::ReadOnly(_)), "{err:?}");
+            assert_eq!(
+                err.context().unwrap().operation(),
+                Some("ObjectStore::update_with")
             );
             Ok(())
         })
@@ -0,0 +1,227 @@
+use indexed_db::{memory::Value, Error, ExportFormat, Key};
+
+use crate::common::{user, TestBackend};
+
+/// Generate one test per scenario of this suite, that runs it on the native backend `$backend`
+///
+/// These scenarios check values and errors that only the native backends have, so they do not run on
+/// the browser. Each of them gets a new factory, so they can also check which databases exist.
+macro_rules! native_tests {
+    ($backend:ty) => {
+        native_tests!(
+            @tests $backend:
+            version_change_upgrades,
+            upgrades_close_other_connections,
+            export_keeps_schema_and_special_values,
+        );
+    };
+    (@tests $backend:ty: $($name:ident),* $(,)?) => {
+        $(
+            #[test]
+            fn $name() {
+                futures::executor::block_on(crate::common_native::$name::<$backend>());
+            }
+        )*
+    };
+}
+
+pub async fn version_change_upgrades<B: TestBackend<Value = Value>>() {
+    let factory = B::factory();
+    let db = factory
+        .open::<()>("upgrades", 1, async |evt| {
+            assert_eq!(evt.old_version(), 0);
+            evt.build_object_store("users").key_path("id").create()?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+    db.transaction(&["users"])
+        .rw()
+        .run::<_, ()>(async |t| {
+            t.object_store("users")?
+                .put_many([user::<B>(1, "Alice", &[]), user::<B>(2, "Bob", &[])])
+                .await?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+    db.close();
+
+    // A failed upgrade rolls back everything
+    factory
+        .open::<()>("upgrades", 2, async |evt| {
+            evt.delete_object_store("users")?;
+            evt.build_object_store("other").create()?;
+            Err(Error::User(()))
+        })
+        .await
+        .unwrap_err();
+
+    let db = factory
+        .open::<()>("upgrades", 3, async |evt| {
+            assert_eq!(evt.old_version(), 1);
+            assert_eq!(evt.database().object_store_names(), vec!["users"]);
+            let users = evt.transaction().object_store("users")?;
+            users.build_index("name", "name").unique().create()?;
+            assert_eq!(
+                users.index("name")?.get(&Key::from("Bob")).await?,
+                Some(user::<B>(2, "Bob", &[]))
+            );
+            Ok(())
+        })
+        .await
+        .unwrap();
+    assert_eq!(db.version(), 3);
+
+    assert!(matches!(
+        factory.open::<()>("upgrades", 2, async |_| Ok(())).await,
+        Err(Error::VersionTooOld(_))
+    ));
+    db.transaction(&["users"])
+        .run::<_, ()>(async |t| {
+            let users = t.object_store("users")?;
+            assert_eq!(users.index_names(), vec!["name"]);
+            assert!(matches!(
+                users.build_index("other", "other").create(),
+                Err(Error::InvalidCall(_))
+            ));
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+    factory.delete_database("upgrades").await.unwrap();
+    assert!(factory.databases().await.unwrap().is_empty());
+    assert!(matches!(
+        db.transaction(&["users"])
+            .run::<_, ()>(async |_| Ok(()))
+            .await,
+        Err(Error::DatabaseIsClosed(_))
+    ));
+}
+
+pub async fn upgrades_close_other_connections<B: TestBackend<Value = Value>>() {
+    let factory = B::factory();
+    let stale = factory
+        .open::<()>("stale", 1, async |evt| {
+            evt.build_object_store("users").key_path("id").create()?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+    // `stale` stays open while another connection upgrades the database
+    let db = factory
+        .open::<()>("stale", 2, async |evt| {
+            evt.delete_object_store("users")?;
+            evt.build_object_store("users").create()?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+    assert_eq!(db.version(), 2);
+
+    let outcome = stale
+        .transaction(&["users"])
+        .rw()
+        .run::<_, ()>(async |t| {
+            t.object_store("users")?
+                .add(&user::<B>(1, "Alice", &[]))
+                .await?;
+            Ok(())
+        })
+        .await;
+    assert!(
+        matches!(outcome, Err(Error::DatabaseIsClosed(_))),
+        "{outcome:?}"
+    );
+
+    // The new connection is unaffected, and sees none of the stale writes
+    db.transaction(&["users"])
+        .rw()
+        .run::<_, ()>(async |t| {
+            let users = t.object_store("users")?;
+            assert_eq!(users.count().await?, 0);
+            users.add_kv(1, &user::<B>(1, "Alice", &[])).await?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+}
+
+pub async fn export_keeps_schema_and_special_values<B: TestBackend<Value = Value>>() {
+    let factory = B::factory();
+    let db = factory
+        .open::<()>("export", 1, async |evt| {
+            let users = evt.build_object_store("users").key_path("id").create()?;
+            users.build_index("by_tag", "tags").multi_entry().create()?;
+            evt.build_object_store("raw").create()?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+    db.transaction(&["users", "raw"])
+        .rw()
+        .run::<_, ()>(async |t| {
+            t.object_store("users")?
+                .add(&user::<B>(1, "Jane \"J\"\n", &["a"]))
+                .await?;
+            let raw = t.object_store("raw")?;
+            raw.add_kv(&Key::Date(1.5e12), &Value::Date(f64::NAN))
+                .await?;
+            raw.add_kv(
+                &Key::Binary(vec![0, 1, 2, 255]),
+                &Value::Array(vec![
+                    Value::Undefined,
+                    Value::Number(f64::NEG_INFINITY),
+                    Value::Number(1e-9),
+                    Value::Array(vec![Value::Binary(vec![0xFB, 0xFF])]),
+                ]),
+            )
+            .await?;
+            raw.add_kv(
+                &Key::Array(vec![Key::from(1), Key::from("a")]),
+                &Value::object([("$type", "Date")]),
+            )
+            .await?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+    let mut ndjson = Vec::new();
+    db.export(&mut ndjson, ExportFormat::Ndjson).await.unwrap();
+    assert_eq!(
+        String::from_utf8(ndjson).unwrap(),
+        concat!(
+            r#"{"type":"database","name":"export","version":1}"#,
+            "\n",
+            r#"{"type":"store","name":"raw","key_path":null,"auto_increment":false,"indexes":[]}"#,
+            "\n",
+            r#"{"type":"store","name":"users","key_path":"id","auto_increment":false,"indexes":[{"name":"by_tag","key_path":"tags","unique":false,"multi_entry":true}]}"#,
+            "\n",
+            r#"{"type":"record","store":"raw","key":{"$type":"Date","value":1500000000000},"value":{"$type":"Date","value":null}}"#,
+            "\n",
+            r#"{"type":"record","store":"raw","key":{"$type":"ArrayBuffer","value":"AAEC/w=="},"value":[{"$type":"undefined"},{"$type":"Number","value":"-Infinity"},1e-9,[{"$type":"ArrayBuffer","value":"+/8="}]]}"#,
+            "\n",
+            r#"{"type":"record","store":"raw","key":[1,"a"],"value":{"$type":"Object","value":{"$type":"Date"}}}"#,
+            "\n",
+            r#"{"type":"record","store":"users","key":1,"value":{"id":1,"name":"Jane \"J\"\n","tags":["a"]}}"#,
+            "\n",
+        )
+    );
+
+    let mut json = Vec::new();
+    db.export(&mut json, ExportFormat::Json).await.unwrap();
+    let json = String::from_utf8(json).unwrap();
+    assert!(json.starts_with(r#"{"name":"export","version":1,"stores":[{"name":"raw","key_path":null,"auto_increment":false,"indexes":[],"records":[{"key":{"$type":"Date","value":1500000000000},"value":{"$type":"Date","value":null}},{"key":"#));
+    assert!(json.ends_with(
+        r#"{"key":1,"value":{"id":1,"name":"Jane \"J\"\n","tags":["a"]}}]}]}
+"#
+    ));
+
+    let empty = factory.open_latest_version("empty").await.unwrap();
+    let mut json = Vec::new();
+    empty.export(&mut json, ExportFormat::Json).await.unwrap();
+    assert_eq!(json, b"{\"name\":\"empty\",\"version\":1,\"stores\":[]}\n");
+}
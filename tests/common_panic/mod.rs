@@ -1,5 +1,5 @@
 use anyhow::Context;
-use indexed_db::Factory;
+use indexed_db::{set_panic_on_inactive_transaction, Factory};
 use wasm_bindgen_test::wasm_bindgen_test;
 use web_sys::js_sys::JsString;
 
@@ -8,6 +8,7 @@ use web_sys::js_sys::JsString;
 async fn other_awaits_panic() {
     // tracing_wasm::set_as_global_default();
     // std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+    set_panic_on_inactive_transaction(true);
 
     let factory = Factory::get().unwrap();
 
@@ -40,6 +41,7 @@ async fn other_awaits_panic() {
 async fn await_in_versionchange_panics() {
     // tracing_wasm::set_as_global_default();
     // std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+    set_panic_on_inactive_transaction(true);
 
     let factory = Factory::get().unwrap();
 
@@ -0,0 +1,543 @@
+use std::{convert::Infallible, time::Duration};
+
+use futures::StreamExt;
+use indexed_db::{backend::WebSys, Error, ExportFormat, Factory, Key};
+use wasm_bindgen_test::wasm_bindgen_test;
+use web_sys::{
+    js_sys::{global, Array, Date, Float32Array, Function, JsString, Object, Reflect, Uint8Array},
+    wasm_bindgen::{JsCast, JsValue},
+    WorkerGlobalScope,
+};
+
+use crate::common::TestBackend;
+
+impl TestBackend for WebSys {
+    fn factory() -> Factory {
+        Factory::get().unwrap()
+    }
+
+    fn object(fields: Vec<(&str, JsValue)>) -> JsValue {
+        let object = Object::new();
+        for (name, value) in fields {
+            Reflect::set(&object, &JsValue::from(name), &value).unwrap();
+        }
+        object.into()
+    }
+
+    fn array(items: Vec<JsValue>) -> JsValue {
+        items.into_iter().collect::<Array>().into()
+    }
+
+    fn null() -> JsValue {
+        JsValue::NULL
+    }
+
+    fn field(value: &JsValue, name: &str) -> Option<JsValue> {
+        Reflect::get(value, &JsValue::from(name))
+            .ok()
+            .filter(|field| !field.is_undefined())
+    }
+}
+
+/// Returns the duration in milliseconds, with the result
+async fn time_it<R>(cb: impl std::future::Future<Output = R>) -> (f64, R) {
+    let now = {
+        let performance = if let Some(window) = web_sys::window() {
+            window.performance()
+        } else if let Ok(worker_scope) = global().dyn_into::<WorkerGlobalScope>() {
+            worker_scope.performance()
+        } else {
+            None
+        }
+        .expect("No `performance` available (not in a browser environment?)");
+        move || performance.now()
+    };
+    let start_ms = now();
+    let ret = cb.await;
+    let elapsed_ms = now() - start_ms;
+    (elapsed_ms, ret)
+}
+
+/// Let the browser run its pending tasks, eg. fire the events of finished transactions
+async fn yield_to_event_loop() {
+    let set_timeout = Reflect::get(&global(), &JsString::from("setTimeout"))
+        .unwrap()
+        .dyn_into::<Function>()
+        .unwrap();
+    let timeout = web_sys::js_sys::Promise::new(&mut |resolve, _| {
+        set_timeout
+            .call2(&JsValue::UNDEFINED, &resolve, &JsValue::from(10))
+            .unwrap();
+    });
+    wasm_bindgen_futures::JsFuture::from(timeout).await.unwrap();
+}
+
+#[wasm_bindgen_test]
+async fn close_and_delete_before_reopen() {
+    // tracing_wasm::set_as_global_default();
+    // std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+
+    const DATABASE_NAME: &str = "close_and_delete_before_reopen";
+    const ITERATIONS: usize = 10;
+
+    let (delete_duration_ms, _) = time_it(async {
+        for _ in 0..ITERATIONS {
+            let factory = Factory::get().unwrap();
+
+            factory.delete_database(DATABASE_NAME).await.unwrap();
+
+            let _db = factory
+                .open::<()>(DATABASE_NAME, 1, async move |_| Ok(()))
+                .await
+                .unwrap();
+
+            // Here the database wrapper got dropped which should trigger database close.
+        }
+    })
+    .await;
+
+    // Deleting the database should be almost instantaneous in theory.
+    // However this operation will hang as long as the database is still opened,
+    // which can last for 10s of seconds if our code forget to close is (in
+    // which case the close will only occur when the underlying javascript
+    // object got garbage collected...).
+    assert!(
+        // 1s per iteration should be plenty under normal circumstances
+        delete_duration_ms < 1000f64 * ITERATIONS as f64,
+        "Deleting the database took too long: {}ms",
+        delete_duration_ms
+    );
+}
+
+#[wasm_bindgen_test]
+async fn factory_cmp() {
+    let factory = Factory::get().unwrap();
+    assert_eq!(
+        factory
+            .cmp(&JsValue::from_str("foo"), &JsValue::from_str("bar"))
+            .unwrap(),
+        std::cmp::Ordering::Greater
+    );
+    assert!(matches!(
+        factory.cmp(&JsValue::TRUE, &JsValue::FALSE),
+        Err(Error::InvalidKey(_)),
+    ));
+}
+
+#[wasm_bindgen_test]
+async fn blocked_open_and_delete() {
+    let factory = Factory::get().unwrap();
+
+    factory.delete_database("blocked").await.unwrap();
+    let db = factory
+        .open::<Infallible>("blocked", 1, async move |_| Ok(()))
+        .await
+        .unwrap();
+
+    // Upgrading while `db` is still open is blocked
+    let mut blocked_versions = Vec::new();
+    let res = factory
+        .build_open("blocked", 2)
+        .on_blocked(|old, new| blocked_versions.push((old, new)))
+        .blocked_timeout(Duration::from_millis(100))
+        .open::<Infallible>(async move |_| Ok(()))
+        .await;
+    assert!(
+        matches!(res, Err(Error::Blocked)),
+        "Unexpected result: {res:?}"
+    );
+    assert_eq!(blocked_versions, vec![(1, Some(2))]);
+
+    // And so is deleting
+    let mut blocked_versions = Vec::new();
+    let res = factory
+        .build_delete_database("blocked")
+        .on_blocked(|old, new| blocked_versions.push((old, new)))
+        .blocked_timeout(Duration::from_millis(100))
+        .delete()
+        .await;
+    assert!(
+        matches!(res, Err(Error::Blocked)),
+        "Unexpected result: {res:?}"
+    );
+    assert_eq!(blocked_versions, vec![(1, None)]);
+
+    // Once the connection is closed, everything proceeds as usual
+    db.close();
+    let db = factory
+        .open::<Infallible>("blocked", 2, async move |_| Ok(()))
+        .await
+        .unwrap();
+    assert_eq!(db.version(), 2);
+}
+
+#[wasm_bindgen_test]
+async fn version_change_events() {
+    let factory = Factory::get().unwrap();
+
+    factory
+        .delete_database("version_change_events")
+        .await
+        .unwrap();
+    let db = factory
+        .open::<Infallible>("version_change_events", 1, async move |_| Ok(()))
+        .await
+        .unwrap();
+    let mut changes = db.on_version_change();
+
+    // Another connection upgrading the database notifies us, and we close ourselves to let it proceed
+    let upgrade = factory.open::<Infallible>("version_change_events", 2, async move |_| Ok(()));
+    let react = async move {
+        let change = changes.next().await.unwrap();
+        assert_eq!(change.old_version, 1);
+        assert_eq!(change.new_version, Some(2));
+        db.close();
+    };
+    let (upgraded, ()) = futures::join!(upgrade, react);
+    assert_eq!(upgraded.unwrap().version(), 2);
+}
+
+#[wasm_bindgen_test]
+async fn abort_and_error_events() {
+    let factory = Factory::get().unwrap();
+
+    factory
+        .delete_database("abort_and_error_events")
+        .await
+        .unwrap();
+    let db = factory
+        .open::<Infallible>("abort_and_error_events", 1, async move |evt| {
+            evt.build_object_store("objects").create()?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+    let mut aborts = db.on_abort();
+    let mut errors = db.on_error();
+
+    // A failed request bubbles up as an error, and returning it aborts the transaction
+    let res = db
+        .transaction(&["objects"])
+        .rw()
+        .run::<_, Infallible>(async move |t| {
+            let objects = t.object_store("objects")?;
+            objects.add_kv("key", &JsString::from("a")).await?;
+            objects.add_kv("key", &JsString::from("b")).await?;
+            Ok(())
+        })
+        .await;
+    assert!(res.is_err());
+    assert!(errors.next().await.is_some());
+    assert!(matches!(aborts.next().await, Some(Error::Aborted(_))));
+}
+
+#[wasm_bindgen_test]
+fn key_ordering_matches_indexeddb() {
+    let factory = Factory::get().unwrap();
+    let keys = [
+        Key::from(-0.0),
+        Key::from(0),
+        Key::from(f64::NEG_INFINITY),
+        Key::from(12.5),
+        Key::Date(0.0),
+        Key::Date(-1000.0),
+        Key::from(""),
+        Key::from("a"),
+        Key::from("\u{1F600}"),
+        Key::from("\u{FF61}"),
+        Key::from(Vec::<u8>::new()),
+        Key::from(vec![0u8, 255]),
+        Key::from(vec![1u8]),
+        Key::from(Vec::<Key>::new()),
+        Key::from((1, "b")),
+        Key::from((1, "b", 0)),
+        Key::from(("a", 1)),
+    ];
+    for lhs in &keys {
+        assert_eq!(Key::from_js(&lhs.to_js()).as_ref(), Some(lhs));
+        for rhs in &keys {
+            assert_eq!(
+                lhs.cmp(rhs),
+                factory.cmp(&lhs.to_js(), &rhs.to_js()).unwrap(),
+                "comparing {lhs:?} with {rhs:?}",
+            );
+        }
+    }
+
+    assert_eq!(
+        Key::from_js(
+            &Uint8Array::from(&[1u8, 2, 3] as &[u8])
+                .subarray(1, 3)
+                .into()
+        ),
+        Some(Key::from(vec![2u8, 3])),
+    );
+    assert_eq!(Key::from_js(&JsValue::from_f64(f64::NAN)), None);
+    assert_eq!(Key::from_js(&JsValue::TRUE), None);
+}
+
+#[wasm_bindgen_test]
+async fn explicit_commit_and_abort() {
+    let factory = Factory::get().unwrap();
+    factory.delete_database("explicit_finish").await.unwrap();
+
+    let db = factory
+        .open::<Infallible>("explicit_finish", 1, async move |evt| {
+            evt.build_object_store("objects").create()?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+    db.transaction(&["objects"])
+        .rw()
+        .run::<_, Infallible>(async move |t| {
+            let objects = t.object_store("objects")?;
+            objects.put_kv(1, &JsValue::from("committed")).await?;
+            t.commit()?;
+            assert!(matches!(
+                objects.put_kv(2, &JsValue::from("too late")).await,
+                Err(Error::TransactionFinished(_))
+            ));
+            assert!(matches!(t.commit(), Err(Error::TransactionFinished(_))));
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+    db.transaction(&["objects"])
+        .rw()
+        .run::<_, Infallible>(async move |t| {
+            let objects = t.object_store("objects")?;
+            objects.put_kv(3, &JsValue::from("aborted")).await?;
+            let in_flight = objects.put_kv(4, &JsValue::from("aborted"));
+            t.abort()?;
+            assert!(matches!(
+                in_flight.await,
+                Err(Error::TransactionFinished(_))
+            ));
+            assert!(matches!(
+                objects.get(1).await,
+                Err(Error::TransactionFinished(_))
+            ));
+            assert!(matches!(
+                t.object_store("objects"),
+                Err(Error::TransactionFinished(_))
+            ));
+            assert!(matches!(t.abort(), Err(Error::TransactionFinished(_))));
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+    db.transaction(&["objects"])
+        .run::<_, Infallible>(async move |t| {
+            let objects = t.object_store("objects")?;
+            assert_eq!(objects.get_all_keys(None).await?, [Key::from(1)]);
+            Ok(())
+        })
+        .await
+        .unwrap();
+}
+
+#[wasm_bindgen_test]
+async fn finish_events_after_run_returned() {
+    let factory = Factory::get().unwrap();
+    factory.delete_database("finish_events").await.unwrap();
+
+    let db = factory
+        .open::<Infallible>("finish_events", 1, async move |evt| {
+            evt.build_object_store("objects").create()?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+    // The transaction aborts after `run` returned the error, with nothing waiting for the `abort` event
+    let res = db
+        .transaction(&["objects"])
+        .rw()
+        .run::<(), _>(async move |t| {
+            t.object_store("objects")?
+                .put_kv(1, &JsValue::from("aborted"))
+                .await?;
+            Err(Error::User("rollback"))
+        })
+        .await;
+    assert!(matches!(res, Err(Error::User("rollback"))));
+    yield_to_event_loop().await;
+
+    // Same with `run_no_wait`, for the `complete` event
+    db.transaction(&["objects"])
+        .rw()
+        .run_no_wait::<_, Infallible>(async move |t| {
+            t.object_store("objects")?
+                .put_kv(2, &JsValue::from("saved"))
+                .await?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+    yield_to_event_loop().await;
+
+    db.transaction(&["objects"])
+        .run::<_, Infallible>(async move |t| {
+            let objects = t.object_store("objects")?;
+            assert_eq!(objects.get_all_keys(None).await?, [Key::from(2)]);
+            Ok(())
+        })
+        .await
+        .unwrap();
+}
+
+#[wasm_bindgen_test]
+async fn other_awaits_error() {
+    let factory = Factory::get().unwrap();
+    factory.delete_database("other_awaits_error").await.unwrap();
+
+    let db = factory
+        .open::<Infallible>("other_awaits_error", 1, async move |evt| {
+            evt.build_object_store("data").auto_increment().create()?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+    let (tx, rx) = futures_channel::oneshot::channel::<()>();
+    let res = db
+        .transaction(&["data"])
+        .rw()
+        .run::<_, Infallible>(async move |t| {
+            t.object_store("data")?.add(&JsString::from("foo")).await?;
+            let _ = rx.await;
+            t.object_store("data")?.add(&JsString::from("bar")).await?;
+            Ok(())
+        })
+        .await;
+    assert!(
+        matches!(res, Err(Error::TransactionInactive(_))),
+        "Unexpected result: {res:?}"
+    );
+    let _ = tx.send(());
+
+    // The transaction was aborted, and the database is still usable
+    db.transaction(&["data"])
+        .run::<_, Infallible>(async move |t| {
+            assert_eq!(t.object_store("data")?.count().await?, 0);
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+    // Same thing during a version change
+    let (tx, rx) = futures_channel::oneshot::channel::<()>();
+    db.close();
+    let res = factory
+        .open::<Infallible>("other_awaits_error", 2, async move |evt| {
+            evt.build_object_store("other").create()?;
+            let _ = rx.await;
+            Ok(())
+        })
+        .await;
+    assert!(
+        matches!(res, Err(Error::TransactionInactive(_))),
+        "Unexpected result: {:?}",
+        res.map(|_| ())
+    );
+    let _ = tx.send(());
+}
+
+#[wasm_bindgen_test]
+async fn error_context() {
+    let factory = Factory::get().unwrap();
+    factory.delete_database("error_context").await.unwrap();
+
+    let db = factory
+        .open::<Infallible>("error_context", 1, async move |evt| {
+            evt.build_object_store("objects").create()?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+    db.transaction(&["objects"])
+        .rw()
+        .run::<_, Infallible>(async move |t| {
+            let err = t.object_store("missing").unwrap_err();
+            assert!(matches!(err, Error::DoesNotExist(_)), "{err:?}");
+            let context = err.context().unwrap();
+            assert_eq!(context.operation(), Some("Transaction::object_store"));
+            assert_eq!(context.store(), Some("missing"));
+
+            let objects = t.object_store("objects")?;
+            let err = objects
+                .add_kv(1, &Function::new_no_args("").into())
+                .await
+                .unwrap_err();
+            assert!(matches!(err, Error::FailedClone(_)), "{err:?}");
+            let context = err.context().unwrap();
+            assert_eq!(context.operation(), Some("ObjectStore::add_kv"));
+            assert_eq!(context.store(), Some("objects"));
+            assert_eq!(context.index(), None);
+            assert_eq!(context.key(), Some(&Key::from(1)));
+            assert!(context.message().is_some());
+            assert!(
+                err.to_string().contains("on store `objects`"),
+                "Unexpected message: {err}"
+            );
+            Ok(())
+        })
+        .await
+        .unwrap();
+}
+
+#[wasm_bindgen_test]
+async fn database_export() {
+    let factory = Factory::get().unwrap();
+    factory.delete_database("database_export").await.unwrap();
+
+    let db = factory
+        .open::<Infallible>("database_export", 1, async move |evt| {
+            evt.build_object_store("data").create()?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+    db.transaction(&["data"])
+        .rw()
+        .run::<_, Infallible>(async move |t| {
+            let data = t.object_store("data")?;
+            let value = Object::new();
+            Reflect::set(&value, &JsValue::from("at"), &Date::new(&1e12.into())).unwrap();
+            Reflect::set(
+                &value,
+                &JsValue::from("samples"),
+                &Float32Array::from(&[1.5f32][..]),
+            )
+            .unwrap();
+            let nested = Array::of2(&JsValue::from(1), &Array::of1(&JsValue::NULL));
+            Reflect::set(&value, &JsValue::from("nested"), &nested).unwrap();
+            data.add_kv("a", &value).await?;
+            data.add_kv(&b"k"[..], &JsValue::from(f64::NAN)).await?;
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+    let mut ndjson = Vec::new();
+    db.export(&mut ndjson, ExportFormat::Ndjson).await.unwrap();
+    assert_eq!(
+        String::from_utf8(ndjson).unwrap(),
+        concat!(
+            r#"{"type":"database","name":"database_export","version":1}"#,
+            "\n",
+            r#"{"type":"store","name":"data","key_path":null,"auto_increment":false,"indexes":[]}"#,
+            "\n",
+            r#"{"type":"record","store":"data","key":"a","value":{"at":{"$type":"Date","value":1000000000000},"samples":{"$type":"Float32Array","value":"AADAPw=="},"nested":[1,[null]]}}"#,
+            "\n",
+            r#"{"type":"record","store":"data","key":{"$type":"ArrayBuffer","value":"aw=="},"value":{"$type":"Number","value":"NaN"}}"#,
+            "\n",
+        )
+    );
+}
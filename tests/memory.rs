@@ -0,0 +1,33 @@
+#![cfg(feature = "memory")]
+
+use indexed_db::memory::{Factory, Memory, Value};
+
+#[macro_use]
+mod common;
+#[macro_use]
+mod common_native;
+
+impl common::TestBackend for Memory {
+    fn factory() -> Factory {
+        Factory::in_memory()
+    }
+
+    fn object(fields: Vec<(&str, Value)>) -> Value {
+        Value::object(fields)
+    }
+
+    fn array(items: Vec<Value>) -> Value {
+        Value::Array(items)
+    }
+
+    fn null() -> Value {
+        Value::Null
+    }
+
+    fn field(value: &Value, name: &str) -> Option<Value> {
+        value.get(name).cloned()
+    }
+}
+
+backend_tests!(Memory);
+native_tests!(Memory);
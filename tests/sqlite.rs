@@ -0,0 +1,376 @@
+#![cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
+
+use futures::{executor::block_on, TryStreamExt};
+use indexed_db::{
+    sqlite::{Factory, Sqlite, Value},
+    Error, ExportFormat, Key,
+};
+
+#[macro_use]
+mod common;
+#[macro_use]
+mod common_native;
+
+use common::user;
+
+impl common::TestBackend for Sqlite {
+    fn factory() -> Factory {
+        Factory::in_memory().unwrap()
+    }
+
+    fn object(fields: Vec<(&str, Value)>) -> Value {
+        Value::object(fields)
+    }
+
+    fn array(items: Vec<Value>) -> Value {
+        Value::Array(items)
+    }
+
+    fn null() -> Value {
+        Value::Null
+    }
+
+    fn field(value: &Value, name: &str) -> Option<Value> {
+        value.get(name).cloned()
+    }
+}
+
+backend_tests!(Sqlite);
+native_tests!(Sqlite);
+
+/// A path for an SQLite file that does not exist yet, removed once the returned guard is dropped
+fn temp_file(name: &str) -> (std::path::PathBuf, impl Drop) {
+    struct Remove(std::path::PathBuf);
+    impl Drop for Remove {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+    let path =
+        std::env::temp_dir().join(format!("indexed-db-{name}-{}.sqlite", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    (path.clone(), Remove(path))
+}
+
+#[test]
+fn persistence_across_factories() {
+    block_on(async {
+        let (path, _guard) = temp_file("persistence");
+        {
+            let factory = Factory::from_file(&path).unwrap();
+            let db = factory
+                .open::<()>("db", 2, async |evt| {
+                    let users = evt
+                        .build_object_store("users")
+                        .key_path("id")
+                        .auto_increment()
+                        .create()?;
+                    users.build_index("tags", "tags").multi_entry().create()?;
+                    Ok(())
+                })
+                .await
+                .unwrap();
+            db.transaction(&["users"])
+                .rw()
+                .run::<_, ()>(async |t| {
+                    let users = t.object_store("users")?;
+                    users
+                        .add(&Value::object([("tags", vec![Value::from("a")])]))
+                        .await?;
+                    users.put(&user::<Sqlite>(7, "Bob", &["a", "b"])).await?;
+                    Ok(())
+                })
+                .await
+                .unwrap();
+            // Not committed, so not persisted
+            db.transaction(&["users"])
+                .rw()
+                .run::<_, ()>(async |t| {
+                    t.object_store("users")?.clear().await?;
+                    Err::<(), _>(Error::User(()))
+                })
+                .await
+                .unwrap_err();
+        }
+
+        let factory = Factory::from_file(&path).unwrap();
+        let databases = factory.databases().await.unwrap();
+        assert_eq!(databases.len(), 1);
+        assert_eq!(
+            (databases[0].name.as_str(), databases[0].version),
+            ("db", 2)
+        );
+        let db = factory.open_latest_version("db").await.unwrap();
+        assert_eq!(db.version(), 2);
+        db.transaction(&["users"])
+            .rw()
+            .run::<_, ()>(async |t| {
+                let users = t.object_store("users")?;
+                assert_eq!(users.key_path()?, Some("id".into()));
+                assert!(users.auto_increment());
+                assert_eq!(
+                    users.get_all_keys(None).await?,
+                    vec![Key::from(1), Key::from(7)]
+                );
+                assert_eq!(
+                    users.get(&Key::from(7)).await?,
+                    Some(user::<Sqlite>(7, "Bob", &["a", "b"]))
+                );
+                // The key generator kept its state
+                assert_eq!(
+                    users.add(&Value::object([("name", "next")])).await?,
+                    Key::from(8)
+                );
+                let tags = users.index("tags")?;
+                assert!(tags.is_multi_entry());
+                assert_eq!(
+                    tags.get_all_keys_in(Key::from("a")..=Key::from("a"), None)
+                        .await?,
+                    vec![Key::from(1), Key::from(7)]
+                );
+                Ok(())
+            })
+            .await
+            .unwrap();
+    });
+}
+
+#[test]
+fn key_encoding_preserves_ordering() {
+    block_on(async {
+        let factory = Factory::in_memory().unwrap();
+        let db = factory
+            .open::<()>("db", 1, async |evt| {
+                let data = evt.build_object_store("data").create()?;
+                data.build_index("key", "key").create()?;
+                Ok(())
+            })
+            .await
+            .unwrap();
+
+        let mut keys = vec![
+            Key::Number(f64::NEG_INFINITY),
+            Key::from(-1e300),
+            Key::from(-1),
+            Key::from(-0.5),
+            Key::from(0),
+            Key::from(1e-300),
+            Key::from(1),
+            Key::Number(f64::INFINITY),
+            Key::Date(-1.0),
+            Key::Date(1e12),
+            Key::from(""),
+            Key::from("\0"),
+            Key::from("\0\0"),
+            Key::from("a"),
+            Key::from("a\0"),
+            Key::from("ab"),
+            Key::from("\u{FF61}"),
+            Key::from("\u{1F600}"),
+            Key::Binary(vec![]),
+            Key::Binary(vec![0]),
+            Key::Binary(vec![0, 0]),
+            Key::Binary(vec![0, 1]),
+            Key::Binary(vec![1]),
+            Key::Binary(vec![255, 255]),
+            Key::Array(vec![]),
+            Key::Array(vec![Key::Array(vec![])]),
+            Key::Array(vec![Key::from(1)]),
+            Key::Array(vec![Key::from(1), Key::from("a")]),
+            Key::Array(vec![Key::from("a")]),
+            Key::Array(vec![Key::Binary(vec![0])]),
+            Key::Array(vec![Key::Array(vec![Key::from(1)])]),
+        ];
+        let mut sorted = keys.clone();
+        sorted.sort();
+        keys.reverse();
+
+        db.transaction(&["data"])
+            .rw()
+            .run::<_, ()>(async |t| {
+                let data = t.object_store("data")?;
+                for (i, key) in keys.iter().enumerate() {
+                    let value =
+                        Value::object([("key", Value::from(key)), ("i", Value::from(i as u32))]);
+                    data.add_kv(key, &value).await?;
+                }
+                assert_eq!(data.get_all_keys(None).await?, sorted);
+                // `-0` is the same key as `0`
+                assert!(data.contains(&Key::Number(-0.0)).await?);
+
+                let index = data.index("key")?;
+                let index_keys = index
+                    .cursor()
+                    .open_key()
+                    .await?
+                    .into_stream()
+                    .map_ok(|e| e.key)
+                    .try_collect::<Vec<_>>()
+                    .await?;
+                assert_eq!(index_keys, sorted);
+
+                let binaries = Key::Binary(vec![])..Key::Array(vec![]);
+                assert_eq!(data.count_in(binaries.clone()).await?, 6);
+                assert_eq!(index.count_in(binaries).await?, 6);
+                assert_eq!(
+                    data.get_all_keys_in(Key::Array(vec![Key::from(1)]).., Some(2))
+                        .await?,
+                    vec![
+                        Key::Array(vec![Key::from(1)]),
+                        Key::Array(vec![Key::from(1), Key::from("a")]),
+                    ]
+                );
+                let prefixed = Key::from("a")..Key::from("a\u{FFFF}");
+                assert_eq!(data.count_in(prefixed).await?, 3);
+                Ok(())
+            })
+            .await
+            .unwrap();
+    });
+}
+
+#[test]
+fn failed_requests_leave_no_changes() {
+    block_on(async {
+        let (path, _guard) = temp_file("failed-requests");
+        let factory = Factory::from_file(&path).unwrap();
+
+        // A database whose creation failed does not exist
+        factory
+            .open::<()>("db", 1, async |evt| {
+                evt.build_object_store("users").create()?;
+                Err(Error::User(()))
+            })
+            .await
+            .unwrap_err();
+        assert!(factory.databases().await.unwrap().is_empty());
+
+        let db = factory
+            .open::<()>("db", 1, async |evt| {
+                let users = evt.build_object_store("users").key_path("id").create()?;
+                users.build_index("name", "name").unique().create()?;
+                users.build_index("tags", "tags").multi_entry().create()?;
+                Ok(())
+            })
+            .await
+            .unwrap();
+        db.transaction(&["users"])
+            .rw()
+            .run::<_, ()>(async |t| {
+                let users = t.object_store("users")?;
+                users.add(&user::<Sqlite>(1, "Alice", &["a"])).await?;
+                users.add(&user::<Sqlite>(2, "Bob", &["b"])).await?;
+                // The failed put keeps the previous record and its index entries
+                let err = users
+                    .put(&user::<Sqlite>(2, "Alice", &["c"]))
+                    .await
+                    .unwrap_err();
+                assert!(matches!(err, Error::AlreadyExists(_)));
+                assert_eq!(
+                    users.get(&Key::from(2)).await?,
+                    Some(user::<Sqlite>(2, "Bob", &["b"]))
+                );
+                let tags = users.index("tags")?;
+                assert_eq!(
+                    tags.get_all_keys(None).await?,
+                    vec![Key::from(1), Key::from(2)]
+                );
+                assert_eq!(tags.count_in(Key::from("c")..=Key::from("c")).await?, 0);
+                Ok(())
+            })
+            .await
+            .unwrap();
+        db.close();
+
+        let db = factory
+            .open::<()>("db", 2, async |evt| {
+                let users = evt.transaction().object_store("users")?;
+                // This shares its tags with Bob, so a unique index on the tags cannot be created
+                users
+                    .add(&Value::object([
+                        ("id", Value::from(3)),
+                        ("tags", Value::from(vec![Value::from("b")])),
+                    ]))
+                    .await?;
+                let err = users
+                    .build_index("tag", "tags")
+                    .unique()
+                    .create()
+                    .unwrap_err();
+                assert!(matches!(err, Error::AlreadyExists(_)));
+                assert_eq!(users.index_names(), vec!["name", "tags"]);
+                users.build_index("tag", "tags").create()?;
+                Ok(())
+            })
+            .await
+            .unwrap();
+        db.transaction(&["users"])
+            .run::<_, ()>(async |t| {
+                let users = t.object_store("users")?;
+                assert_eq!(users.index_names(), vec!["name", "tag", "tags"]);
+                assert_eq!(users.index("tag")?.count_in(..).await?, 3);
+                Ok(())
+            })
+            .await
+            .unwrap();
+    });
+}
+
+#[test]
+fn export_reads_persisted_records() {
+    block_on(async {
+        let (path, _guard) = temp_file("export");
+        {
+            let factory = Factory::from_file(&path).unwrap();
+            let db = factory
+                .open::<()>("db", 3, async |evt| {
+                    let logs = evt
+                        .build_object_store("logs")
+                        .compound_key_path(&["day", "seq"])
+                        .create()?;
+                    logs.build_index("level", "level").unique().create()?;
+                    evt.build_object_store("blobs").auto_increment().create()?;
+                    Ok(())
+                })
+                .await
+                .unwrap();
+            db.transaction(&["logs", "blobs"])
+                .rw()
+                .run::<_, ()>(async |t| {
+                    t.object_store("logs")?
+                        .add(&Value::object([
+                            ("day", Value::Date(864e5)),
+                            ("seq", Value::from(2)),
+                            ("level", Value::from("warn")),
+                        ]))
+                        .await?;
+                    t.object_store("blobs")?
+                        .add(&Value::from(vec![1u8, 2, 3]))
+                        .await?;
+                    Ok(())
+                })
+                .await
+                .unwrap();
+        }
+
+        let factory = Factory::from_file(&path).unwrap();
+        let db = factory.open_latest_version("db").await.unwrap();
+        let mut ndjson = Vec::new();
+        db.export(&mut ndjson, ExportFormat::Ndjson).await.unwrap();
+        assert_eq!(
+            String::from_utf8(ndjson).unwrap(),
+            concat!(
+                r#"{"type":"database","name":"db","version":3}"#,
+                "\n",
+                r#"{"type":"store","name":"blobs","key_path":null,"auto_increment":true,"indexes":[]}"#,
+                "\n",
+                r#"{"type":"store","name":"logs","key_path":["day","seq"],"auto_increment":false,"indexes":[{"name":"level","key_path":"level","unique":true,"multi_entry":false}]}"#,
+                "\n",
+                r#"{"type":"record","store":"blobs","key":1,"value":{"$type":"ArrayBuffer","value":"AQID"}}"#,
+                "\n",
+                r#"{"type":"record","store":"logs","key":[{"$type":"Date","value":86400000},2],"value":{"day":{"$type":"Date","value":86400000},"level":"warn","seq":2}}"#,
+                "\n",
+            )
+        );
+    });
+}
@@ -1,3 +1,10 @@
+#![cfg_attr(not(target_arch = "wasm32"), allow(dead_code, unused_macros))]
+
 wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_worker);
 
+#[macro_use]
 mod common;
+mod common_web_sys;
+
+#[cfg(target_arch = "wasm32")]
+backend_tests!(indexed_db::backend::WebSys);